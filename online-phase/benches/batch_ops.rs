@@ -3,9 +3,10 @@
 use std::time::{Duration, Instant};
 
 use ark_mpc::{
-    algebra::{AuthenticatedScalarResult, Scalar as GenericScalar},
+    algebra::{AuthenticatedPointResult, AuthenticatedScalarResult, Scalar as GenericScalar},
+    random_point,
     test_helpers::{execute_mock_mpc, TestCurve},
-    PARTY0,
+    ResultHandle, ResultValue, PARTY0,
 };
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use futures::future;
@@ -67,9 +68,133 @@ fn bench_batch_mul(c: &mut Criterion) {
     }
 }
 
+/// Execute a batch multiplication of shared points by shared scalars and
+/// return the time taken
+async fn perform_batch_point_mul(n: usize) -> Duration {
+    let mut rng = thread_rng();
+    let scalars = (0..n).map(|_| Scalar::random(&mut rng)).collect_vec();
+    let points = (0..n).map(|_| random_point()).collect_vec();
+
+    let (duration1, duration2) = execute_mock_mpc(|fabric| {
+        let scalars = scalars.clone();
+        let points = points.clone();
+        async move {
+            let start = Instant::now();
+            let scalars = fabric.batch_share_scalar(scalars, PARTY0);
+            let points = fabric.batch_share_point(points, PARTY0);
+
+            let res = AuthenticatedPointResult::batch_mul(&scalars, &points);
+            let res_open = AuthenticatedPointResult::open_authenticated_batch(&res);
+
+            let _ = black_box(future::join_all(res_open).await);
+
+            start.elapsed()
+        }
+    })
+    .await;
+
+    Duration::max(duration1, duration2)
+}
+
+/// Benchmark batch multiplication of shared points by shared scalars, which
+/// multiplies shares by the generator through the fabric's generator table
+fn bench_batch_point_mul(c: &mut Criterion) {
+    let runtime =
+        RuntimeBuilder::new_multi_thread().worker_threads(3).enable_all().build().unwrap();
+
+    let mut group = c.benchmark_group("batch-ops");
+
+    for batch_size in [10, 100, 1000].into_iter() {
+        let id = BenchmarkId::new("batch-point-mul", batch_size);
+        group.throughput(Throughput::Elements(batch_size));
+        group.bench_function(id, |b| {
+            let mut async_bencher = b.to_async(&runtime);
+            async_bencher.iter_custom(|n_iters| async move {
+                let mut total_time = Duration::default();
+                for _ in 0..n_iters {
+                    total_time += perform_batch_point_mul(batch_size as usize).await;
+                }
+
+                total_time
+            });
+        });
+    }
+}
+
+/// The number of gates in the chain of wide gates
+const CHAIN_LENGTH: usize = 10;
+
+/// Execute a chain of gates, each consuming the batch output by the last, and
+/// return the time taken
+///
+/// The chain is rooted at a batch exchanged with the peer, so the whole chain
+/// is queued, and each intermediate handle dropped, before any gate executes.
+/// With result GC enabled each gate is then the last to consume its input
+async fn perform_wide_gate_chain(batch: Vec<Scalar>, result_gc: bool) -> Duration {
+    let (duration1, duration2) = execute_mock_mpc(|fabric| {
+        let batch = batch.clone();
+        async move {
+            if result_gc {
+                fabric.enable_result_gc();
+            }
+
+            let start = Instant::now();
+            let local: ResultHandle<TestCurve, Vec<Scalar>> =
+                fabric.new_gate_op(vec![], move |_| ResultValue::ScalarBatch(batch));
+            let mut res = fabric.exchange_value(local);
+            for _ in 0..CHAIN_LENGTH {
+                res = fabric.new_gate_op(vec![res.id()], |mut args| {
+                    let mut batch: Vec<Scalar> = args.next().unwrap().into();
+                    batch.iter_mut().for_each(|x| *x += Scalar::one());
+                    ResultValue::ScalarBatch(batch)
+                });
+            }
+
+            let _ = black_box(res.await);
+            start.elapsed()
+        }
+    })
+    .await;
+
+    Duration::max(duration1, duration2)
+}
+
+/// Benchmark a chain of gates over a wide batch, with and without result GC
+///
+/// With GC enabled the executor moves each batch into the gate consuming it
+/// rather than cloning it
+fn bench_wide_gate_chain(c: &mut Criterion) {
+    let runtime =
+        RuntimeBuilder::new_multi_thread().worker_threads(3).enable_all().build().unwrap();
+
+    let mut group = c.benchmark_group("batch-ops");
+
+    for batch_size in [1000, 100_000].into_iter() {
+        for result_gc in [false, true] {
+            let name = if result_gc { "wide-gate-chain-gc" } else { "wide-gate-chain" };
+            let id = BenchmarkId::new(name, batch_size);
+            group.throughput(Throughput::Elements(batch_size * CHAIN_LENGTH as u64));
+            group.bench_function(id, |b| {
+                let mut async_bencher = b.to_async(&runtime);
+                async_bencher.iter_custom(|n_iters| async move {
+                    let mut total_time = Duration::default();
+                    for _ in 0..n_iters {
+                        let mut rng = thread_rng();
+                        let batch = (0..batch_size).map(|_| Scalar::random(&mut rng)).collect_vec();
+
+                        total_time += perform_wide_gate_chain(batch, result_gc).await;
+                    }
+
+                    total_time
+                });
+            });
+        }
+    }
+}
+
 criterion_group!(
     name = batch_ops;
     config = Criterion::default().sample_size(10);
-    targets = bench_batch_mul
+    targets = bench_batch_mul, bench_batch_point_mul, bench_wide_gate_chain
 );
 criterion_main!(batch_ops);
@@ -30,9 +30,34 @@ pub fn bench_native_msm(c: &mut Criterion) {
     }
 }
 
+/// Measures the throughput of a native MSM computed on the rayon thread pool
+#[cfg(feature = "parallel")]
+pub fn bench_parallel_msm(c: &mut Criterion) {
+    let mut rng = thread_rng();
+
+    let mut group = c.benchmark_group("parallel_msm");
+    for n_elems in (0..MAX_POWER_OF_TWO).map(|i| 1 << i) {
+        group.throughput(Throughput::Elements(n_elems));
+        group.bench_function(BenchmarkId::from_parameter(n_elems), |b| {
+            let scalars = (0..n_elems).map(|_| Scalar::random(&mut rng)).collect_vec();
+            let points = (0..n_elems).map(|_| random_point()).collect_vec();
+            b.iter(|| {
+                black_box(CurvePoint::<TestCurve>::msm_parallel(&scalars, &points));
+            })
+        });
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
 criterion_group!(
     name = native_msm;
     config = Criterion::default().sample_size(10);
     targets = bench_native_msm
 );
+#[cfg(feature = "parallel")]
+criterion_group!(
+    name = native_msm;
+    config = Criterion::default().sample_size(10);
+    targets = bench_native_msm, bench_parallel_msm
+);
 criterion_main!(native_msm);
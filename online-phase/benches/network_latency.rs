@@ -0,0 +1,133 @@
+//! Benchmarks comparing the per-round latency of the TCP and QUIC transports
+
+use std::{
+    net::{SocketAddr, TcpListener, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use ark_mpc::{
+    algebra::AuthenticatedScalarResult,
+    network::{MpcNetwork, QuicTwoPartyNet, TcpMpcNetwork},
+    offline_prep::PartyIDBeaverSource,
+    test_helpers::TestCurve,
+    MpcFabric, PARTY0, PARTY1,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use futures::Future;
+use tokio::runtime::Builder as RuntimeBuilder;
+
+/// A party's fabric and a value shared in it
+type PartySetup = (MpcFabric<TestCurve>, AuthenticatedScalarResult<TestCurve>);
+
+/// Connect a pair of fabrics with the given connection method and share a
+/// value between them
+///
+/// Each party connects in its own task, a QUIC listener does not finish
+/// connecting until its peer sends on the stream
+async fn setup_parties<N, F, Fut>(connect: F) -> (PartySetup, PartySetup)
+where
+    N: 'static + MpcNetwork<TestCurve>,
+    F: Fn(u64) -> Fut,
+    Fut: 'static + Future<Output = N> + Send,
+{
+    let build = |network: Fut| async move {
+        let network = network.await;
+        let party_id = network.party_id();
+        let fabric = MpcFabric::new(network, PartyIDBeaverSource::new(party_id));
+        let value = fabric.share_scalar(1u8, PARTY0);
+
+        (fabric, value)
+    };
+
+    let party0 = tokio::spawn(build(connect(PARTY0)));
+    let party1 = tokio::spawn(build(connect(PARTY1)));
+    (party0.await.unwrap(), party1.await.unwrap())
+}
+
+/// Get the local and peer addresses of the given party
+fn party_addrs(party_id: u64, addrs: (SocketAddr, SocketAddr)) -> (SocketAddr, SocketAddr) {
+    if party_id == PARTY0 {
+        addrs
+    } else {
+        (addrs.1, addrs.0)
+    }
+}
+
+/// Connect a pair of parties over localhost TCP
+async fn setup_tcp() -> (PartySetup, PartySetup) {
+    let free_addr = || TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let addrs = (free_addr(), free_addr());
+
+    setup_parties(|party_id| async move {
+        let (local_addr, peer_addr) = party_addrs(party_id, addrs);
+        let mut net = TcpMpcNetwork::new(party_id, local_addr, peer_addr);
+        net.connect().await.unwrap();
+        net
+    })
+    .await
+}
+
+/// Connect a pair of parties over localhost QUIC
+async fn setup_quic() -> (PartySetup, PartySetup) {
+    let free_addr = || UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let addrs = (free_addr(), free_addr());
+
+    setup_parties(|party_id| async move {
+        let (local_addr, peer_addr) = party_addrs(party_id, addrs);
+        let mut net = QuicTwoPartyNet::new(party_id, local_addr, peer_addr);
+        net.connect().await.unwrap();
+        net
+    })
+    .await
+}
+
+/// Run `n_rounds` sequential openings of the given value
+async fn run_rounds(value: AuthenticatedScalarResult<TestCurve>, n_rounds: u64) -> Duration {
+    let start_time = Instant::now();
+    for _ in 0..n_rounds {
+        black_box(value.open().await);
+    }
+
+    start_time.elapsed()
+}
+
+/// Measure the latency of a single round of communication over each transport
+pub fn bench_round_latency(c: &mut Criterion) {
+    let runtime =
+        RuntimeBuilder::new_multi_thread().worker_threads(3).enable_all().build().unwrap();
+
+    let mut group = c.benchmark_group("round-latency");
+    for transport in ["tcp", "quic"] {
+        let ((fabric0, value0), (fabric1, value1)) = runtime.block_on(async {
+            if transport == "tcp" {
+                setup_tcp().await
+            } else {
+                setup_quic().await
+            }
+        });
+
+        group.bench_function(transport, |b| {
+            b.to_async(&runtime).iter_custom(|n_iters| {
+                let (value0, value1) = (value0.clone(), value1.clone());
+                async move {
+                    let party0 = tokio::spawn(run_rounds(value0, n_iters));
+                    let party1 = tokio::spawn(run_rounds(value1, n_iters));
+
+                    // Take the maximum amount of time for either party to finish
+                    let (elapsed0, elapsed1) = (party0.await.unwrap(), party1.await.unwrap());
+                    Duration::max(elapsed0, elapsed1)
+                }
+            })
+        });
+
+        fabric0.shutdown();
+        fabric1.shutdown();
+    }
+}
+
+criterion_group!(
+    name = network_latency;
+    config = Criterion::default().sample_size(10);
+    targets = bench_round_latency
+);
+criterion_main!(network_latency);
@@ -0,0 +1,59 @@
+//! Benchmarks batched scalar arithmetic against element-wise arithmetic
+
+use ark_mpc::{algebra::Scalar as GenericScalar, test_helpers::TestCurve};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use itertools::Itertools;
+use rand::thread_rng;
+
+/// A scalar with curve generics defined
+type Scalar = GenericScalar<TestCurve>;
+
+/// The number of elements in each operand
+const N: usize = 1 << 20;
+
+/// Sample a batch of `N` random scalars
+fn random_batch() -> Vec<Scalar> {
+    let mut rng = thread_rng();
+    (0..N).map(|_| Scalar::random(&mut rng)).collect_vec()
+}
+
+/// Benchmark element-wise and batched addition
+fn bench_batch_add(c: &mut Criterion) {
+    let (a, b) = (random_batch(), random_batch());
+
+    let mut group = c.benchmark_group("scalar-batch-arithmetic");
+    group.throughput(Throughput::Elements(N as u64));
+    group.bench_function(BenchmarkId::new("add-elementwise", N), |bench| {
+        bench.iter(|| {
+            let res = a.iter().zip(b.iter()).map(|(x, y)| x + y).collect_vec();
+            black_box(res)
+        })
+    });
+    group.bench_function(BenchmarkId::new("add-batch", N), |bench| {
+        bench.iter(|| black_box(Scalar::batch_add(&a, &b)))
+    });
+}
+
+/// Benchmark element-wise and batched multiplication
+fn bench_batch_mul(c: &mut Criterion) {
+    let (a, b) = (random_batch(), random_batch());
+
+    let mut group = c.benchmark_group("scalar-batch-arithmetic");
+    group.throughput(Throughput::Elements(N as u64));
+    group.bench_function(BenchmarkId::new("mul-elementwise", N), |bench| {
+        bench.iter(|| {
+            let res = a.iter().zip(b.iter()).map(|(x, y)| x * y).collect_vec();
+            black_box(res)
+        })
+    });
+    group.bench_function(BenchmarkId::new("mul-batch", N), |bench| {
+        bench.iter(|| black_box(Scalar::batch_mul_public(&a, &b)))
+    });
+}
+
+criterion_group!(
+    name = scalar_batch_arithmetic;
+    config = Criterion::default().sample_size(10);
+    targets = bench_batch_add, bench_batch_mul
+);
+criterion_main!(scalar_batch_arithmetic);
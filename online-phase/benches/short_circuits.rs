@@ -0,0 +1,123 @@
+//! Benchmarks many short circuits run back-to-back on a single fabric,
+//! reporting the number of heap allocations made per circuit
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use ark_mpc::{
+    algebra::Scalar,
+    test_helpers::{mock_fabric, TestCurve},
+    MpcFabric,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::thread_rng;
+use tokio::runtime::Builder as RuntimeBuilder;
+
+// -----------
+// | Helpers |
+// -----------
+
+/// The number of gates in each short circuit
+const CIRCUIT_SIZE: usize = 10;
+
+/// A global allocator that counts the allocations made through it while
+/// counting is enabled
+struct CountingAllocator;
+
+/// Whether allocations are counted, disabled while the benchmarks are timed
+static COUNTING: AtomicBool = AtomicBool::new(false);
+/// The number of allocations made while counting was enabled
+static N_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.load(Ordering::Relaxed) {
+            N_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Run a short circuit of additions and multiplications to completion
+async fn run_short_circuit(fabric: &MpcFabric<TestCurve>, seed: Scalar<TestCurve>) {
+    let mut res = fabric.allocate_scalar(seed);
+    for _ in 0..CIRCUIT_SIZE / 2 {
+        res = &res + &res;
+        res = &res * &res;
+    }
+
+    black_box(res.await);
+}
+
+/// Run the given number of short circuits on a fresh fabric once, outside of
+/// any timed benchmark, and print the allocations made per circuit
+async fn report_allocations(n_circuits: usize) {
+    let fabric = mock_fabric();
+    let seed = Scalar::random(&mut thread_rng());
+
+    N_ALLOCATIONS.store(0, Ordering::Relaxed);
+    COUNTING.store(true, Ordering::Relaxed);
+    for _ in 0..n_circuits {
+        run_short_circuit(&fabric, seed).await;
+    }
+    COUNTING.store(false, Ordering::Relaxed);
+    fabric.shutdown();
+
+    let per_circuit = N_ALLOCATIONS.load(Ordering::Relaxed) as f64 / n_circuits as f64;
+    println!("{n_circuits} circuits: {per_circuit:.1} allocations per circuit");
+}
+
+// --------------
+// | Benchmarks |
+// --------------
+
+/// Benchmark running short circuits back-to-back, printing the allocations
+/// made per circuit before timing them
+fn bench_short_circuits(c: &mut Criterion) {
+    let runtime =
+        RuntimeBuilder::new_multi_thread().worker_threads(3).enable_all().build().unwrap();
+
+    let mut group = c.benchmark_group("short-circuits");
+    for n_circuits in [100, 1000].into_iter() {
+        runtime.block_on(report_allocations(n_circuits));
+
+        group.throughput(Throughput::Elements((n_circuits * CIRCUIT_SIZE) as u64));
+        group.bench_function(BenchmarkId::from_parameter(n_circuits), |b| {
+            let mut async_bencher = b.to_async(&runtime);
+            async_bencher.iter_custom(|n_iters| async move {
+                let mut total_time = Duration::default();
+                for _ in 0..n_iters {
+                    let fabric = mock_fabric();
+                    let seed = Scalar::random(&mut thread_rng());
+
+                    let start = Instant::now();
+                    for _ in 0..n_circuits {
+                        run_short_circuit(&fabric, seed).await;
+                    }
+
+                    total_time += start.elapsed();
+                    fabric.shutdown();
+                }
+
+                total_time
+            });
+        });
+    }
+}
+
+criterion_group!(
+    name = short_circuits;
+    config = Criterion::default().sample_size(10);
+    targets = bench_short_circuits
+);
+criterion_main!(short_circuits);
@@ -5,8 +5,8 @@ use rand::{distributions::uniform::SampleRange, thread_rng};
 
 #[tokio::main]
 async fn main() {
-    // Run the following circuit with the `stats` feature enabled and
-    // the stats will be dumped at the end of execution
+    // Run the following circuit with the `stats` feature enabled and read back
+    // the executor's stats once the result is available
     let mut rng = thread_rng();
     let depth = (0usize..=1000).sample_single(&mut rng);
 
@@ -23,7 +23,8 @@ async fn main() {
             res = &party0_value + &res * &party1_value;
         }
 
-        res.open().await
+        res.open().await;
+        println!("Executor stats: {:?}", fabric.stats_snapshot());
     })
     .await;
 }
@@ -9,9 +9,11 @@ use std::{
     task::{Context, Poll},
 };
 
-use ark_ec::CurveGroup;
-use futures::{Future, FutureExt};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{BigInteger, PrimeField};
+use futures::{future, Future, FutureExt};
 use itertools::{izip, Itertools};
+use subtle::ConstantTimeEq;
 
 use crate::{
     algebra::{macros::*, scalar::*},
@@ -23,7 +25,7 @@ use crate::{
 };
 
 use super::{
-    curve::{BatchCurvePointResult, CurvePoint, CurvePointResult},
+    curve::{BatchCurvePointResult, CurvePoint, CurvePointResult, MSM_CHUNK_SIZE},
     PointShare,
 };
 
@@ -70,6 +72,7 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
 
         let n = values.len();
         let fabric = values[0].fabric();
+        fabric.assert_same_fabric(values);
         let my_results = values.iter().map(|val| val.id()).collect_vec();
 
         // Party zero sends first then receives
@@ -128,11 +131,7 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
 
         // Check that the MAC check shares add up to the additive identity in
         // the curve group
-        if my_mac_share + peer_mac_share != CurvePoint::identity() {
-            return false;
-        }
-
-        true
+        (my_mac_share + peer_mac_share).ct_eq(&CurvePoint::identity()).into()
     }
 
     /// Open the value and check the MAC
@@ -194,6 +193,7 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
 
         let n = values.len();
         let fabric = values[0].fabric();
+        fabric.assert_same_fabric(values);
         let mac_key = fabric.mac_key();
 
         // Open the values
@@ -283,6 +283,39 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
     }
 }
 
+impl<C: CurveGroup> AuthenticatedPointResult<C>
+where
+    C::BaseField: PrimeField,
+    C::ScalarField: Unpin,
+{
+    /// Open a batch of values, check their MACs, and return the affine
+    /// coordinates of the opened points
+    ///
+    /// The coordinates are reduced from the base field into the scalar field by
+    /// interpreting their canonical big-endian encoding modulo the scalar field
+    /// order. The identity is mapped to `(0, 0)`
+    #[allow(clippy::type_complexity)]
+    pub fn open_batch_affine(
+        values: &[Self],
+    ) -> impl Future<Output = Result<Vec<(Scalar<C>, Scalar<C>)>, MpcError>> {
+        let openings = Self::open_authenticated_batch(values);
+        future::try_join_all(openings)
+            .map(|res| res.map(|points| points.iter().map(Self::affine_coords).collect_vec()))
+    }
+
+    /// Reduce the affine coordinates of a point into the scalar field
+    fn affine_coords(point: &CurvePoint<C>) -> (Scalar<C>, Scalar<C>) {
+        let affine = point.to_affine();
+        match affine.xy() {
+            Some((x, y)) => (
+                Scalar::from_be_bytes_mod_order(&x.into_bigint().to_bytes_be()),
+                Scalar::from_be_bytes_mod_order(&y.into_bigint().to_bytes_be()),
+            ),
+            None => (Scalar::zero(), Scalar::zero()),
+        }
+    }
+}
+
 /// The value that results from opening an `AuthenticatedPointResult` and
 /// checking its MAC. This encapsulates both the underlying value and the result
 /// of the MAC check
@@ -323,7 +356,8 @@ where
 }
 
 impl<C: CurveGroup> Sum for AuthenticatedPointResult<C> {
-    // Assumes the iterator is non-empty
+    /// Assumes the iterator is non-empty, prefer `MpcFabric::sum` where the
+    /// iterator may be empty
     fn sum<I: Iterator<Item = Self>>(mut iter: I) -> Self {
         let first =
             iter.next().expect("AuthenticatedPointResult<C>::sum requires a non-empty iterator");
@@ -361,6 +395,7 @@ impl<C: CurveGroup> Add<&CurvePointResult<C>> for &AuthenticatedPointResult<C> {
     type Output = AuthenticatedPointResult<C>;
 
     fn add(self, other: &CurvePointResult<C>) -> AuthenticatedPointResult<C> {
+        self.assert_same_fabric(other);
         let fabric = self.fabric();
         let party_id = fabric.party_id();
         let mac_key = fabric.mac_key();
@@ -381,6 +416,7 @@ impl<C: CurveGroup> Add<&AuthenticatedPointResult<C>> for &AuthenticatedPointRes
     type Output = AuthenticatedPointResult<C>;
 
     fn add(self, other: &AuthenticatedPointResult<C>) -> AuthenticatedPointResult<C> {
+        self.assert_same_fabric(other);
         self.fabric.new_gate_op(vec![self.id(), other.id()], |mut args| {
             let lhs: PointShare<C> = args.next().unwrap().into();
             let rhs: PointShare<C> = args.next().unwrap().into();
@@ -404,6 +440,8 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let mut all_ids = Vec::with_capacity(2 * n);
         for (a, b) in a.iter().zip(b.iter()) {
@@ -437,6 +475,8 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
         let party_id = fabric.party_id();
         let mac_key = fabric.mac_key();
 
@@ -486,6 +526,7 @@ impl<C: CurveGroup> Sub<&CurvePointResult<C>> for &AuthenticatedPointResult<C> {
     type Output = AuthenticatedPointResult<C>;
 
     fn sub(self, other: &CurvePointResult<C>) -> AuthenticatedPointResult<C> {
+        self.assert_same_fabric(other);
         let fabric = self.fabric();
         let party_id = fabric.party_id();
         let mac_key = fabric.mac_key();
@@ -505,6 +546,7 @@ impl<C: CurveGroup> Sub<&AuthenticatedPointResult<C>> for &AuthenticatedPointRes
     type Output = AuthenticatedPointResult<C>;
 
     fn sub(self, other: &AuthenticatedPointResult<C>) -> AuthenticatedPointResult<C> {
+        self.assert_same_fabric(other);
         self.fabric.new_gate_op(vec![self.id(), other.id()], |mut args| {
             let lhs: PointShare<C> = args.next().unwrap().into();
             let rhs: PointShare<C> = args.next().unwrap().into();
@@ -528,6 +570,8 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let mut all_ids = Vec::with_capacity(2 * n);
         for (a, b) in a.iter().zip(b.iter()) {
@@ -561,6 +605,8 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
         let party_id = fabric.party_id();
         let mac_key = fabric.mac_key();
 
@@ -608,6 +654,7 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
         let all_ids = a.iter().flat_map(|p| p.ids()).collect_vec();
 
         fabric.new_batch_gate_op(all_ids, n, move |args| {
@@ -626,7 +673,14 @@ impl<C: CurveGroup> Mul<&Scalar<C>> for &AuthenticatedPointResult<C> {
     type Output = AuthenticatedPointResult<C>;
 
     fn mul(self, other: &Scalar<C>) -> AuthenticatedPointResult<C> {
+        // Multiplying by zero or one needs no gate
         let rhs = *other;
+        if rhs == Scalar::zero() {
+            return self.fabric().curve_identity_authenticated();
+        } else if rhs == Scalar::one() {
+            return self.clone();
+        }
+
         self.fabric().new_gate_op(self.ids(), move |mut args| {
             let share: PointShare<C> = args.next().unwrap().into();
             ResultValue::PointShare(share * rhs)
@@ -640,6 +694,7 @@ impl<C: CurveGroup> Mul<&ScalarResult<C>> for &AuthenticatedPointResult<C> {
     type Output = AuthenticatedPointResult<C>;
 
     fn mul(self, other: &ScalarResult<C>) -> AuthenticatedPointResult<C> {
+        self.assert_same_fabric(other);
         self.fabric().new_gate_op(vec![self.id(), other.id()], move |mut args| {
             let share: PointShare<C> = args.next().unwrap().into();
             let rhs: Scalar<C> = args.next().unwrap().into();
@@ -656,8 +711,10 @@ impl<C: CurveGroup> Mul<&AuthenticatedScalarResult<C>> for &AuthenticatedPointRe
 
     // Beaver trick
     fn mul(self, rhs: &AuthenticatedScalarResult<C>) -> AuthenticatedPointResult<C> {
+        self.assert_same_fabric(rhs);
         // Sample a beaver triple
         let generator = CurvePoint::generator();
+        self.fabric().record_mults(1);
         let (a, b, c) = self.fabric().next_triple();
 
         // Open the values d = [rhs - a] and e = [lhs - bG] for curve group generator G
@@ -690,8 +747,11 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         // Sample a set of beaver triples for the multiplications
+        fabric.record_mults(n);
         let (beaver_a, beaver_b, beaver_c) = fabric.next_triple_batch(n);
         let beaver_b_gen = AuthenticatedPointResult::batch_mul_generator(&beaver_b);
 
@@ -709,7 +769,6 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
 
         let de_db_G = AuthenticatedPointResult::batch_add_public(&dbG, &deG);
         let ae_c_G = AuthenticatedPointResult::batch_add(&aeG, &cG);
-
         AuthenticatedPointResult::batch_add(&de_db_G, &ae_c_G)
     }
 
@@ -726,6 +785,8 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let mut all_ids = Vec::with_capacity(2 * n);
         for (a, b) in a.iter().zip(b.iter()) {
@@ -760,20 +821,17 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
         let all_ids = a.iter().flat_map(|v| v.ids()).collect_vec();
 
         // Multiply the shares in a batch gate
+        let table = fabric.generator_table();
         fabric.new_batch_gate_op(
             all_ids,
             n, // output_arity
             move |args| {
-                let scalars = args.into_iter().map(ScalarShare::from).collect_vec();
-                let generator = CurvePoint::generator();
-
-                scalars
-                    .into_iter()
-                    .map(|x| x * generator)
-                    .map(ResultValue::PointShare)
+                args.map(ScalarShare::from)
+                    .map(|x| ResultValue::PointShare(table.mul_share(&x)))
                     .collect_vec()
             },
         )
@@ -795,6 +853,8 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
 
         // Create a gate to sum the points
         let fabric = scalars[0].fabric();
+        fabric.assert_same_fabric(scalars);
+        fabric.assert_same_fabric(points);
         let all_ids = mul_out.iter().flat_map(|p| p.ids()).collect_vec();
 
         fabric.new_gate_op(all_ids, move |mut args| {
@@ -805,16 +865,108 @@ impl<C: CurveGroup> AuthenticatedPointResult<C> {
         })
     }
 
+    /// Compute `sum(a_i * P_i + b_i * Q_i)`, i.e. the sum of two
+    /// multiscalar multiplications
+    ///
+    /// The Beaver multiplications of both MSMs are batched so that all masked
+    /// values are opened in a single round
+    #[allow(non_snake_case)]
+    pub fn double_msm(
+        a: &[AuthenticatedScalarResult<C>],
+        P: &[AuthenticatedPointResult<C>],
+        b: &[AuthenticatedScalarResult<C>],
+        Q: &[AuthenticatedPointResult<C>],
+    ) -> AuthenticatedPointResult<C> {
+        assert_eq!(a.len(), P.len(), "double_msm requires equal length vectors");
+        assert_eq!(b.len(), Q.len(), "double_msm requires equal length vectors");
+
+        let scalars = [a, b].concat();
+        let points = [P, Q].concat();
+        Self::msm(&scalars, &points)
+    }
+
+    /// Multiply a public point by a shared scalar given in its bit
+    /// decomposition, i.e. compute `sum(bit_i * 2^i * point)`
+    ///
+    /// The doublings of `point` are public, so each term is a local
+    /// multiplication of a shared bit by a public point and no triples are
+    /// consumed
+    pub fn mul_by_bits(
+        point: &CurvePoint<C>,
+        bits: &[AuthenticatedScalarResult<C>],
+    ) -> AuthenticatedPointResult<C> {
+        assert!(!bits.is_empty(), "mul_by_bits requires a non-empty bit vector");
+
+        let fabric = bits[0].fabric();
+        fabric.assert_same_fabric(bits);
+        let all_ids = bits.iter().flat_map(|b| b.ids()).collect_vec();
+
+        // Precompute the doublings `2^i * point`
+        let mut doublings = Vec::with_capacity(bits.len());
+        let mut curr = *point;
+        for _ in 0..bits.len() {
+            doublings.push(curr);
+            curr = curr + curr;
+        }
+
+        fabric.new_gate_op(all_ids, move |args| {
+            let share = args
+                .map(ScalarShare::from)
+                .zip(doublings)
+                .map(|(bit, double)| bit * double)
+                .reduce(|acc, term| acc + term)
+                .unwrap();
+
+            ResultValue::PointShare(share)
+        })
+    }
+
     /// Multiscalar multiplication on iterator types
+    ///
+    /// The inputs are consumed in chunks of `MSM_CHUNK_SIZE`, so at most one
+    /// chunk of each is held in memory at a time
     pub fn msm_iter<S, P>(scalars: S, points: P) -> AuthenticatedPointResult<C>
     where
         S: IntoIterator<Item = AuthenticatedScalarResult<C>>,
         P: IntoIterator<Item = AuthenticatedPointResult<C>>,
     {
-        let scalars = scalars.into_iter().collect::<Vec<_>>();
-        let points = points.into_iter().collect::<Vec<_>>();
+        Self::msm_iter_chunked(scalars, points, MSM_CHUNK_SIZE)
+    }
 
-        Self::msm(&scalars, &points)
+    /// Multiscalar multiplication on iterator types, consuming the inputs in
+    /// chunks of the given size
+    ///
+    /// Each chunk is multiplied in its own batch, opening the masks for that
+    /// chunk only, and the partial sums are accumulated into the result
+    pub fn msm_iter_chunked<S, P>(
+        scalars: S,
+        points: P,
+        chunk_size: usize,
+    ) -> AuthenticatedPointResult<C>
+    where
+        S: IntoIterator<Item = AuthenticatedScalarResult<C>>,
+        P: IntoIterator<Item = AuthenticatedPointResult<C>>,
+    {
+        assert!(chunk_size > 0, "msm chunk size must be non-zero");
+        let mut scalars = scalars.into_iter();
+        let mut points = points.into_iter();
+
+        let mut res: Option<AuthenticatedPointResult<C>> = None;
+        loop {
+            let scalar_chunk = scalars.by_ref().take(chunk_size).collect_vec();
+            let point_chunk = points.by_ref().take(chunk_size).collect_vec();
+            if scalar_chunk.is_empty() && point_chunk.is_empty() {
+                break;
+            }
+
+            let chunk_res = Self::msm(&scalar_chunk, &point_chunk);
+            res = Some(match res {
+                Some(acc) => acc + chunk_res,
+                None => chunk_res,
+            });
+        }
+
+        res.expect("multiscalar_mul requires non-empty vectors")
     }
 }
 
@@ -864,13 +1016,15 @@ pub mod test_helpers {
 
 #[cfg(test)]
 mod test {
-    use itertools::Itertools;
-    use rand::thread_rng;
+    use ark_ec::AffineRepr;
+    use itertools::{izip, Itertools};
+    use num_bigint::BigUint;
+    use rand::{thread_rng, Rng};
 
     use crate::{
         algebra::{AuthenticatedPointResult, CurvePoint, Scalar},
         random_point,
-        test_helpers::{execute_mock_mpc, open_await_all_points, TestCurve},
+        test_helpers::{execute_mock_mpc, open_await_all, open_await_all_points, TestCurve},
         PARTY0, PARTY1,
     };
 
@@ -1152,6 +1306,24 @@ mod test {
         assert_eq!(res.unwrap(), expected)
     }
 
+    /// Tests that multiplying by a constant zero or one allocates no gate
+    #[tokio::test]
+    async fn test_mul_constant_zero_one() {
+        let p = random_point();
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let p_shared = fabric.share_point(p, PARTY0);
+            let n_gates = fabric.num_gates();
+            let zero = &p_shared * Scalar::zero();
+            let one = &p_shared * Scalar::one();
+            let n_new_gates = fabric.num_gates() - n_gates;
+
+            (n_new_gates, open_await_all_points(&[zero, one]).await)
+        })
+        .await;
+
+        assert_eq!(res, (0, vec![CurvePoint::identity(), p]));
+    }
+
     /// Tests multiplication with a public (allocated but not shared) scalar
     #[tokio::test]
     async fn test_mul_public_scalar() {
@@ -1265,6 +1437,51 @@ mod test {
         assert_eq!(res, expected_res)
     }
 
+    // -----------
+    // | Opening |
+    // -----------
+
+    /// Tests opening a batch of points to their affine coordinates
+    #[tokio::test]
+    async fn test_open_batch_affine() {
+        const N: usize = 10;
+        let points = (0..N).map(|_| random_point()).collect_vec();
+
+        let (res, _) = execute_mock_mpc(|fabric| {
+            let points = points.clone();
+            async move {
+                let shared_points = fabric.batch_share_point(points, PARTY0 /* sender */);
+
+                let affine = AuthenticatedPointResult::open_batch_affine(&shared_points).await;
+                let opened = open_await_all_points(&shared_points).await;
+                let expected =
+                    opened.iter().map(AuthenticatedPointResult::affine_coords).collect_vec();
+
+                (affine.unwrap(), expected)
+            }
+        })
+        .await;
+
+        let (affine, expected) = res;
+        assert_eq!(affine, expected);
+    }
+
+    /// Tests that the affine coordinates of an opened point reduce correctly
+    /// into the scalar field
+    #[test]
+    fn test_affine_coords() {
+        let point = random_point::<TestCurve>();
+        let (x, y) = point.to_affine().xy().map(|(x, y)| (*x, *y)).unwrap();
+
+        let (x_scalar, y_scalar) = AuthenticatedPointResult::affine_coords(&point);
+        assert_eq!(x_scalar, Scalar::from_biguint(&BigUint::from(x)));
+        assert_eq!(y_scalar, Scalar::from_biguint(&BigUint::from(y)));
+
+        let identity = CurvePoint::<TestCurve>::identity();
+        let (x_id, y_id) = AuthenticatedPointResult::affine_coords(&identity);
+        assert_eq!((x_id, y_id), (Scalar::zero(), Scalar::zero()));
+    }
+
     // ------------------------------
     // | Multiscalar Multiplication |
     // ------------------------------
@@ -1293,4 +1510,115 @@ mod test {
 
         assert_eq!(res, expected_res)
     }
+
+    /// Tests that a chunked iterator MSM matches the unchunked MSM
+    #[tokio::test]
+    async fn test_msm_iter_chunked() {
+        const N: usize = 50;
+        let mut rng = thread_rng();
+        let a = (0..N).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let b = (0..N).map(|_| random_point()).collect_vec();
+
+        let (res, _) = execute_mock_mpc(|fabric| {
+            let a = a.clone();
+            let b = b.clone();
+            async move {
+                let a_shared = fabric.batch_share_scalar(a, PARTY0);
+                let b_shared = fabric.batch_share_point(b, PARTY1);
+
+                let unchunked = AuthenticatedPointResult::msm(&a_shared, &b_shared);
+                let chunked = AuthenticatedPointResult::msm_iter_chunked(
+                    a_shared.clone(),
+                    b_shared.clone(),
+                    7, // chunk_size
+                );
+                let streamed = AuthenticatedPointResult::msm_iter(a_shared, b_shared);
+
+                (
+                    unchunked.open_authenticated().await.unwrap(),
+                    chunked.open_authenticated().await.unwrap(),
+                    streamed.open_authenticated().await.unwrap(),
+                )
+            }
+        })
+        .await;
+
+        let (unchunked, chunked, streamed) = res;
+        assert_eq!(chunked, unchunked);
+        assert_eq!(streamed, unchunked);
+    }
+
+    /// Tests the sum of two multiscalar multiplications
+    #[tokio::test]
+    #[allow(non_snake_case)]
+    async fn test_double_msm() {
+        const N: usize = 50;
+        let mut rng = thread_rng();
+        let a = (0..N).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let P = (0..N).map(|_| random_point()).collect_vec();
+        let b = (0..N).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let Q = (0..N).map(|_| random_point()).collect_vec();
+
+        let (res, _) = execute_mock_mpc(|fabric| {
+            let (a, P, b, Q) = (a.clone(), P.clone(), b.clone(), Q.clone());
+            async move {
+                let a_shared = fabric.batch_share_scalar(a, PARTY0);
+                let P_shared = fabric.batch_share_point(P, PARTY1);
+                let b_shared = fabric.batch_share_scalar(b, PARTY1);
+                let Q_shared = fabric.batch_share_point(Q, PARTY0);
+
+                let res = AuthenticatedPointResult::double_msm(
+                    &a_shared, &P_shared, &b_shared, &Q_shared,
+                );
+                let res = res.open_authenticated().await.unwrap();
+
+                // Compute the expected value in the clear over the opened inputs
+                let a = open_await_all(&a_shared).await;
+                let P = open_await_all_points(&P_shared).await;
+                let b = open_await_all(&b_shared).await;
+                let Q = open_await_all_points(&Q_shared).await;
+                let expected: CurvePoint<TestCurve> =
+                    izip!(a, P, b, Q).map(|(a_i, P_i, b_i, Q_i)| a_i * P_i + b_i * Q_i).sum();
+
+                (res, expected)
+            }
+        })
+        .await;
+
+        let (res, expected) = res;
+        assert_eq!(res, expected)
+    }
+
+    /// Tests multiplying a public point by a shared scalar in bit form
+    #[tokio::test]
+    async fn test_mul_by_bits() {
+        const N: usize = 64;
+        let mut rng = thread_rng();
+        let bits = (0..N).map(|_| Scalar::from(rng.gen_bool(0.5))).collect_vec();
+        let point = random_point();
+
+        let (res, _) = execute_mock_mpc(|fabric| {
+            let bits = bits.clone();
+            async move {
+                let shared_bits = fabric.batch_share_scalar(bits, PARTY0);
+
+                let triples_before = fabric.num_triples_consumed();
+                let res = AuthenticatedPointResult::mul_by_bits(&point, &shared_bits);
+                let triples_used = fabric.num_triples_consumed() - triples_before;
+                let res = res.open_authenticated().await.unwrap();
+
+                // Reconstruct the scalar from the opened bits
+                let opened_bits = open_await_all(&shared_bits).await;
+                let scalar: Scalar<TestCurve> =
+                    opened_bits.into_iter().rev().fold(Scalar::zero(), |acc, bit| acc + acc + bit);
+
+                (res, scalar * point, triples_used)
+            }
+        })
+        .await;
+
+        let (res, expected, triples_used) = res;
+        assert_eq!(res, expected);
+        assert_eq!(triples_used, 0);
+    }
 }
@@ -2,6 +2,7 @@
 //! us to bring curve arithmetic into the execution graph
 
 use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
     iter::Sum,
     mem::size_of,
     ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
@@ -20,7 +21,11 @@ use ark_ff::PrimeField;
 
 use ark_serialize::SerializationError;
 use itertools::Itertools;
+use num_bigint::BigUint;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use serde::{de::Error as DeError, Deserialize, Serialize};
+use subtle::{Choice, ConstantTimeEq};
 
 use crate::{
     algebra::{macros::*, scalar::*, PointShare, ToBytes},
@@ -31,7 +36,7 @@ use super::authenticated_curve::AuthenticatedPointResult;
 
 /// The number of points and scalars to pull from an iterated MSM when
 /// performing a multi-scalar multiplication
-const MSM_CHUNK_SIZE: usize = 1 << 16;
+pub(crate) const MSM_CHUNK_SIZE: usize = 1 << 16;
 /// The threshold at which we call out to the Arkworks MSM implementation
 ///
 /// MSM sizes below this threshold are computed serially as the parallelism
@@ -43,7 +48,10 @@ pub const HASH_TO_CURVE_SECURITY: usize = 16; // 128 bit security
 
 /// A wrapper around the inner point that allows us to define foreign traits on
 /// the point
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+///
+/// Equality and hashing are over the affine point, so equal points in
+/// different projective representations hash identically
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct CurvePoint<C: CurveGroup>(pub(crate) C);
 impl<C: CurveGroup> Unpin for CurvePoint<C> {}
 
@@ -108,7 +116,15 @@ impl<C: CurveGroup> CurvePoint<C> {
     }
 
     /// Deserialize a point from a byte buffer
+    ///
+    /// Rejects buffers of the wrong length and encodings of points not in the
+    /// prime order subgroup. A `TryFrom<&[u8]>` impl would conflict with the
+    /// blanket impl over `From<C>`, so this is the checked conversion
     pub fn from_bytes(bytes: &[u8]) -> Result<CurvePoint<C>, SerializationError> {
+        if bytes.len() != C::generator().compressed_size() {
+            return Err(SerializationError::InvalidData);
+        }
+
         let point = C::deserialize_compressed(bytes)?;
         Ok(CurvePoint(point))
     }
@@ -126,6 +142,29 @@ where
     }
 }
 
+/// Formats the point as its affine coordinates in `0x`-prefixed hex
+impl<C: CurveGroup> Display for CurvePoint<C>
+where
+    C::BaseField: PrimeField,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.to_affine().xy() {
+            Some((x, y)) => {
+                let (x, y): (BigUint, BigUint) = ((*x).into(), (*y).into());
+                write!(f, "({x:#x}, {y:#x})")
+            },
+            None => write!(f, "identity"),
+        }
+    }
+}
+
+/// Compares the compressed encodings of the points in constant time
+impl<C: CurveGroup> ConstantTimeEq for CurvePoint<C> {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.to_bytes().ct_eq(&other.to_bytes())
+    }
+}
+
 impl<C: CurveGroup> CurvePoint<C>
 where
     C::Config: SWUConfig,
@@ -218,6 +257,7 @@ impl<C: CurveGroup> Add<&CurvePointResult<C>> for &CurvePointResult<C> {
     type Output = CurvePointResult<C>;
 
     fn add(self, rhs: &CurvePointResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         self.fabric.new_gate_op(vec![self.id, rhs.id], |mut args| {
             let lhs: CurvePoint<C> = args.next().unwrap().into();
             let rhs: CurvePoint<C> = args.next().unwrap().into();
@@ -251,6 +291,8 @@ impl<C: CurveGroup> CurvePointResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let lhs = a.iter().map(|r| r.id);
         let rhs = b.iter().map(|r| r.id);
@@ -292,6 +334,7 @@ impl<C: CurveGroup> Sub<&CurvePointResult<C>> for &CurvePointResult<C> {
     type Output = CurvePointResult<C>;
 
     fn sub(self, rhs: &CurvePointResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         self.fabric.new_gate_op(vec![self.id, rhs.id], |mut args| {
             let lhs: CurvePoint<C> = args.next().unwrap().into();
             let rhs: CurvePoint<C> = args.next().unwrap().into();
@@ -336,6 +379,8 @@ impl<C: CurveGroup> CurvePointResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let lhs = a.iter().map(|v| v.id);
         let rhs = b.iter().map(|v| v.id);
@@ -390,6 +435,7 @@ impl<C: CurveGroup> CurvePointResult<C> {
     pub fn batch_neg(a: &[CurvePointResult<C>]) -> Vec<CurvePointResult<C>> {
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
         let all_ids = a.iter().map(|r| r.id).collect_vec();
 
         fabric.new_batch_gate_op(all_ids, n /* output_arity */, |args| {
@@ -442,6 +488,7 @@ impl<C: CurveGroup> Mul<&ScalarResult<C>> for &CurvePointResult<C> {
     type Output = CurvePointResult<C>;
 
     fn mul(self, rhs: &ScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         self.fabric.new_gate_op(vec![self.id, rhs.id], |mut args| {
             let lhs: CurvePoint<C> = args.next().unwrap().into();
             let rhs: Scalar<C> = args.next().unwrap().into();
@@ -461,6 +508,8 @@ impl<C: CurveGroup> CurvePointResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let lhs = a.iter().map(|r| r.id);
         let rhs = b.iter().map(|r| r.id);
@@ -492,6 +541,8 @@ impl<C: CurveGroup> CurvePointResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let mut all_ids = Vec::with_capacity(n * 2);
         for (a, b) in a.iter().zip(b.iter()) {
@@ -536,7 +587,8 @@ impl<C: CurveGroup> Sum for CurvePoint<C> {
 }
 
 impl<C: CurveGroup> Sum for CurvePointResult<C> {
-    /// Assumes the iterator is non-empty
+    /// Assumes the iterator is non-empty, prefer `MpcFabric::sum` where the
+    /// iterator may be empty
     fn sum<I: Iterator<Item = Self>>(mut iter: I) -> Self {
         let first = iter.next().expect("empty iterator");
         iter.fold(first, |acc, x| acc + x)
@@ -559,6 +611,26 @@ impl<C: CurveGroup> CurvePoint<C> {
         C::msm(&affine_points, &stripped_scalars).map(CurvePoint).unwrap()
     }
 
+    /// Compute the multiscalar multiplication of the given scalars and points,
+    /// splitting the MSM into one chunk per thread of the rayon thread pool
+    /// and summing the partial results
+    #[cfg(feature = "parallel")]
+    pub fn msm_parallel(scalars: &[Scalar<C>], points: &[CurvePoint<C>]) -> CurvePoint<C> {
+        assert_eq!(scalars.len(), points.len(), "msm cannot compute on vectors of unequal length");
+
+        let n = scalars.len();
+        if n < MSM_SIZE_THRESHOLD {
+            return Self::msm(scalars, points);
+        }
+
+        let chunk_size = n.div_ceil(rayon::current_num_threads()).max(MSM_SIZE_THRESHOLD);
+        scalars
+            .par_chunks(chunk_size)
+            .zip(points.par_chunks(chunk_size))
+            .map(|(scalars, points)| Self::msm(scalars, points))
+            .reduce(CurvePoint::identity, |acc, x| acc + x)
+    }
+
     /// Compute the multiscalar multiplication of the given scalars and points
     /// represented as streaming iterators
     pub fn msm_iter<I, J>(scalars: I, points: J) -> CurvePoint<C>
@@ -592,6 +664,7 @@ impl<C: CurveGroup> CurvePoint<C> {
         assert_eq!(scalars.len(), points.len(), "msm cannot compute on vectors of unequal length");
 
         let fabric = scalars[0].fabric();
+        fabric.assert_same_fabric(scalars);
         let scalar_ids = scalars.iter().map(|s| s.id()).collect_vec();
 
         // Clone `points` so that the gate closure may capture it
@@ -623,6 +696,7 @@ impl<C: CurveGroup> CurvePoint<C> {
 
         let n = scalars.len();
         let fabric = scalars[0].fabric();
+        fabric.assert_same_fabric(scalars);
         let scalar_ids = scalars.iter().flat_map(|s| s.ids()).collect_vec();
 
         // Clone points to let the gate closure take ownership
@@ -667,6 +741,8 @@ impl<C: CurveGroup> CurvePointResult<C> {
 
         let n = scalars.len();
         let fabric = scalars[0].fabric();
+        fabric.assert_same_fabric(scalars);
+        fabric.assert_same_fabric(points);
 
         let lhs = scalars.iter().map(|s| s.id());
         let rhs = points.iter().map(|p| p.id);
@@ -706,6 +782,8 @@ impl<C: CurveGroup> CurvePointResult<C> {
 
         let n = scalars.len();
         let fabric = scalars[0].fabric();
+        fabric.assert_same_fabric(scalars);
+        fabric.assert_same_fabric(points);
 
         let mut all_ids = Vec::with_capacity(n * 2);
         for (a, b) in scalars.iter().zip(points.iter()) {
@@ -751,6 +829,8 @@ impl<C: CurveGroup> CurvePointResult<C> {
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashSet;
+
     use rand::thread_rng;
 
     use crate::{test_helpers::mock_fabric, test_helpers::TestCurve};
@@ -822,4 +902,82 @@ mod test {
         assert_eq!(res, expected_res);
         fabric.shutdown();
     }
+
+    /// Tests parsing points from compressed byte buffers
+    #[test]
+    fn test_point_from_bytes() {
+        let point = random_point();
+        let bytes = point.to_bytes();
+        assert_eq!(TestCurvePoint::from_bytes(&bytes).unwrap(), point);
+
+        // Wrong length
+        assert!(TestCurvePoint::from_bytes(&bytes[1..]).is_err());
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert!(TestCurvePoint::from_bytes(&padded).is_err());
+
+        // An x-coordinate larger than the base field's modulus
+        let malformed = vec![u8::MAX; bytes.len()];
+        assert!(TestCurvePoint::from_bytes(&malformed).is_err());
+    }
+
+    /// Tests that the parallel MSM matches the serial MSM
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_msm_parallel() {
+        let mut rng = thread_rng();
+        for n in [0, 5, 100, 1000] {
+            let scalars = (0..n).map(|_| Scalar::random(&mut rng)).collect_vec();
+            let points = (0..n).map(|_| random_point()).collect_vec();
+
+            let expected = TestCurvePoint::msm(&scalars, &points);
+            assert_eq!(TestCurvePoint::msm_parallel(&scalars, &points), expected);
+        }
+    }
+
+    /// Tests formatting the generator and the identity
+    #[test]
+    fn test_point_display() {
+        // The generator of the BN254 G1 group is (1, 2)
+        assert_eq!(TestCurvePoint::generator().to_string(), "(0x1, 0x2)");
+        assert_eq!(TestCurvePoint::identity().to_string(), "identity");
+    }
+
+    /// Tests that equal points in different projective representations
+    /// collide in a hash set
+    #[test]
+    fn test_point_hash() {
+        let p = random_point();
+        let doubled = p + p;
+        let scaled = p * Scalar::from(2u8);
+
+        let mut rng = thread_rng();
+        let q = random_point();
+        let identity = q - q;
+
+        let set: HashSet<TestCurvePoint> =
+            [doubled, scaled, p, identity, TestCurvePoint::identity(), p + q - q]
+                .into_iter()
+                .collect();
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&TestCurvePoint::identity()));
+
+        let scalar = Scalar::<TestCurve>::random(&mut rng);
+        let scalars: HashSet<_> = [scalar, scalar + Scalar::zero()].into_iter().collect();
+        assert_eq!(scalars.len(), 1);
+    }
+
+    /// Tests that constant-time equality agrees with `==` across projective
+    /// representations of the same point
+    #[test]
+    fn test_point_ct_eq() {
+        let p = random_point();
+        let q = random_point();
+        let identity = q - q;
+
+        assert!(bool::from((p + p).ct_eq(&(p * Scalar::from(2u8)))));
+        assert!(bool::from(identity.ct_eq(&TestCurvePoint::identity())));
+        assert!(!bool::from(p.ct_eq(&q)));
+        assert!(!bool::from(p.ct_eq(&TestCurvePoint::identity())));
+    }
 }
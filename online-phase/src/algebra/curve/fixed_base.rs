@@ -0,0 +1,104 @@
+//! Defines a precomputed table of multiples of a fixed base point, which
+//! speeds up repeated multiplications of the same point by different scalars
+
+use ark_ec::CurveGroup;
+use ark_ff::{BigInteger, PrimeField};
+use itertools::Itertools;
+
+use crate::algebra::{PointShare, Scalar, ScalarShare};
+
+use super::CurvePoint;
+
+/// The number of scalar bits covered by each window of the table
+const WINDOW_BITS: usize = 4;
+/// The number of multiples of the base stored for each window
+const WINDOW_SIZE: usize = 1 << WINDOW_BITS;
+
+/// A table of the multiples of a fixed base point over fixed-width windows of
+/// the scalar
+///
+/// Window `i` holds `j * 2^(4i) * base` for each 4-bit digit `j`, so a
+/// multiplication is one table lookup and one addition per window rather
+/// than a full double-and-add
+#[derive(Clone, Debug)]
+pub struct FixedBaseTable<C: CurveGroup> {
+    /// The multiples of the base in each window, each of length `WINDOW_SIZE`
+    windows: Vec<Vec<CurvePoint<C>>>,
+}
+
+impl<C: CurveGroup> FixedBaseTable<C> {
+    /// Precompute the table for the given base
+    pub fn new(base: CurvePoint<C>) -> Self {
+        let n_bits = C::ScalarField::MODULUS_BIT_SIZE as usize;
+        let n_windows = n_bits.div_ceil(WINDOW_BITS);
+
+        let mut window_base = base;
+        let windows = (0..n_windows)
+            .map(|_| {
+                let window = (0..WINDOW_SIZE)
+                    .scan(CurvePoint::identity(), |acc, _| {
+                        let multiple = *acc;
+                        *acc += window_base;
+                        Some(multiple)
+                    })
+                    .collect_vec();
+
+                window_base = window[WINDOW_SIZE - 1] + window_base;
+                window
+            })
+            .collect_vec();
+
+        Self { windows }
+    }
+
+    /// Multiply the base by the given scalar
+    pub fn mul(&self, scalar: &Scalar<C>) -> CurvePoint<C> {
+        let bits = scalar.inner().into_bigint().to_bits_le();
+        bits.chunks(WINDOW_BITS)
+            .zip(self.windows.iter())
+            .map(|(digit_bits, window)| {
+                let digit = digit_bits.iter().rev().fold(0, |acc, bit| (acc << 1) | *bit as usize);
+                window[digit]
+            })
+            .sum()
+    }
+
+    /// Multiply the base by each of the given scalars
+    pub fn batch_mul(&self, scalars: &[Scalar<C>]) -> Vec<CurvePoint<C>> {
+        scalars.iter().map(|scalar| self.mul(scalar)).collect_vec()
+    }
+
+    /// Multiply the base by a share of a scalar and its MAC
+    pub fn mul_share(&self, share: &ScalarShare<C>) -> PointShare<C> {
+        PointShare::new(self.mul(&share.share()), self.mul(&share.mac()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use itertools::Itertools;
+    use rand::thread_rng;
+
+    use crate::{
+        algebra::{CurvePoint, Scalar},
+        random_point,
+        test_helpers::TestCurve,
+    };
+
+    use super::FixedBaseTable;
+
+    /// Tests that table multiplications match generic scalar multiplications
+    #[test]
+    fn test_fixed_base_mul() {
+        let mut rng = thread_rng();
+        let base = random_point();
+        let table = FixedBaseTable::new(base);
+
+        let mut scalars = (0..10).map(|_| Scalar::<TestCurve>::random(&mut rng)).collect_vec();
+        scalars.extend([Scalar::zero(), Scalar::one(), -Scalar::one()]);
+
+        let expected = scalars.iter().map(|scalar| base * scalar).collect_vec();
+        assert_eq!(table.batch_mul(&scalars), expected);
+        assert_eq!(table.mul(&Scalar::zero()), CurvePoint::identity());
+    }
+}
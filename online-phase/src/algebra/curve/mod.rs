@@ -4,10 +4,12 @@
 
 mod authenticated_curve;
 mod curve;
+mod fixed_base;
 mod share;
 
 pub use authenticated_curve::*;
 pub use curve::*;
+pub use fixed_base::*;
 pub use share::*;
 
 #[cfg(feature = "test_helpers")]
@@ -83,6 +83,8 @@ impl<C: CurveGroup> AuthenticatedDensePoly<C> {
     /// TODO: Opt for a more efficient implementation that allocates fewer
     /// gates, i.e. by awaiting all results then creating the evaluation
     pub fn eval(&self, x: &ScalarResult<C>) -> AuthenticatedScalarResult<C> {
+        self.coeffs[0].assert_same_fabric(x);
+
         // Compute the powers of x from 0 to n
         let n = self.degree();
         let powers_of_x = self.fabric().new_batch_gate_op(vec![x.id()], n + 1, move |mut args| {
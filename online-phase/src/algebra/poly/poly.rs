@@ -70,6 +70,9 @@ impl<C: CurveGroup> DensePolynomialResult<C> {
     /// Evaluate the polynomial at a given point
     pub fn eval(&self, point: ScalarResult<C>) -> ScalarResult<C> {
         let fabric = self.fabric();
+        fabric.assert_same_fabric(&self.coeffs);
+        point.assert_same_fabric(&self.coeffs[0]);
+
         let mut deps = Vec::with_capacity(self.coeffs.len() + 1);
         deps.push(point.id());
         deps.extend(self.coeffs.iter().map(|coeff| coeff.id()));
@@ -111,6 +114,7 @@ impl<C: CurveGroup> DensePolynomialResult<C> {
     ///
     /// Done using the extended Euclidean algorithm
     pub fn mul_inverse_mod_t(&self, t: usize) -> Self {
+        self.fabric().assert_same_fabric(&self.coeffs);
         let ids = self.coeffs.iter().map(|c| c.id()).collect_vec();
         let n_result_coeffs = t;
 
@@ -349,6 +353,9 @@ impl<C: CurveGroup> Div<&DensePolynomialResult<C>> for &DensePolynomialResult<C>
             return DensePolynomialResult::zero(fabric);
         }
 
+        fabric.assert_same_fabric(&self.coeffs);
+        fabric.assert_same_fabric(&rhs.coeffs);
+
         let n_lhs_coeffs = self.coeffs.len();
 
         let mut deps = self.coeffs.iter().map(|coeff| coeff.id()).collect_vec();
@@ -1,28 +1,37 @@
 //! Defines the authenticated (malicious secure) variant of the MPC scalar type
 
 use std::{
-    iter::{self, Sum},
+    collections::HashSet,
+    iter::{self, Product, Sum},
     ops::{Add, Div, Mul, Neg, Sub},
     pin::Pin,
+    slice,
     task::{Context, Poll},
 };
 
 use ark_ec::CurveGroup;
 use ark_ff::FftField;
 use ark_poly::EvaluationDomain;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
 use futures::{Future, FutureExt};
 use itertools::{izip, Itertools};
+use sha3::{Digest, Sha3_256};
+use subtle::{Choice, ConstantTimeEq};
 
 use crate::{
     algebra::{macros::*, AuthenticatedPointResult, CurvePoint, CurvePointResult, ScalarResult},
     commitment::{HashCommitment, HashCommitmentResult},
     error::MpcError,
     fabric::{ResultId, ResultValue},
-    network::NetworkPayload,
-    ResultHandle, PARTY0,
+    gadgets::{is_zero_batch, mod_power_of_two_batch, truncate_batch, INTEGER_BITS},
+    network::{NetworkPayload, PartyId},
+    MpcFabric, ResultHandle,
 };
 
-use super::{scalar::Scalar, BatchScalarResult, ScalarShare};
+use super::{
+    scalar::{n_bytes_field, Scalar},
+    BatchScalarResult, ScalarShare,
+};
 
 // -----------------------------
 // | AuthenticatedScalarResult |
@@ -52,6 +61,11 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
     /// This follows the protocol detailed in:
     ///     https://dl.acm.org/doi/pdf/10.1145/72981.72995
     /// Which gives a two round implementation
+    ///
+    /// Each inversion consumes one shared value and one triple. An inverse pair
+    /// from the offline phase would not save the triple: masking `x` by `r`
+    /// is a shared multiplication regardless, and `r` alone unmasks the
+    /// opened inverse
     pub fn batch_inverse(
         values: &[AuthenticatedScalarResult<C>],
     ) -> Vec<AuthenticatedScalarResult<C>> {
@@ -59,6 +73,7 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
         assert!(n > 0, "cannot invert empty batch of scalars");
 
         let fabric = values[0].fabric();
+        fabric.assert_same_fabric(values);
 
         // For the following steps, let the input values be x_i for i=1..n
 
@@ -100,29 +115,170 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
     }
 }
 
+/// The number of bytes of the MAC key share's hash that an exported share
+/// records
+const MAC_KEY_FINGERPRINT_LEN: usize = 8;
+
+/// Fingerprint a MAC key share, so that an exported share records the key it
+/// was authenticated under without revealing it
+fn mac_key_fingerprint<C: CurveGroup>(mac_key: Scalar<C>) -> [u8; MAC_KEY_FINGERPRINT_LEN] {
+    let digest = Sha3_256::digest(mac_key.to_bytes_be());
+    digest[..MAC_KEY_FINGERPRINT_LEN].try_into().unwrap()
+}
+
+/// Share export implementations
+impl<C: CurveGroup> AuthenticatedScalarResult<C> {
+    /// Export the local party's share of the value and of its MAC, e.g. to
+    /// checkpoint a long computation
+    ///
+    /// The bytes are the canonical encodings of the share and the MAC share,
+    /// in that order, followed by a fingerprint of the MAC key share
+    pub async fn to_share_bytes(&self) -> Vec<u8>
+    where
+        C::ScalarField: Unpin,
+    {
+        let share: ScalarShare<C> = self.clone().await;
+
+        let mut bytes =
+            Vec::with_capacity(2 * n_bytes_field::<C::ScalarField>() + MAC_KEY_FINGERPRINT_LEN);
+        for value in [share.share(), share.mac()] {
+            value.inner().serialize_uncompressed(&mut bytes).expect("writing to a vec cannot fail");
+        }
+        bytes.extend_from_slice(&mac_key_fingerprint(self.fabric().mac_key()));
+
+        bytes
+    }
+
+    /// Import a share exported by `to_share_bytes` into the given fabric
+    ///
+    /// The share remains valid in a fresh fabric so long as the fabric's
+    /// MAC key share is the one the share was authenticated under, and the
+    /// peer imports its own share of the value. A share exported under a
+    /// different MAC key share is rejected with `InvalidData`
+    pub fn from_share_bytes(
+        bytes: &[u8],
+        fabric: &MpcFabric<C>,
+    ) -> Result<Self, SerializationError> {
+        let n_bytes = n_bytes_field::<C::ScalarField>();
+        if bytes.len() != 2 * n_bytes + MAC_KEY_FINGERPRINT_LEN {
+            return Err(SerializationError::InvalidData);
+        }
+
+        let (values, fingerprint) = bytes.split_at(2 * n_bytes);
+        if fingerprint != mac_key_fingerprint(fabric.mac_key()) {
+            return Err(SerializationError::InvalidData);
+        }
+
+        let (share_bytes, mac_bytes) = values.split_at(n_bytes);
+        let share = C::ScalarField::deserialize_uncompressed(share_bytes)?;
+        let mac = C::ScalarField::deserialize_uncompressed(mac_bytes)?;
+
+        Ok(fabric.allocate_scalar_share(ScalarShare::new(Scalar::new(share), Scalar::new(mac))))
+    }
+}
+
 /// Opening implementations
 impl<C: CurveGroup> AuthenticatedScalarResult<C> {
     /// Open the value without checking its MAC
     pub fn open(&self) -> ScalarResult<C> {
-        let (val0, val1) = if self.party_id() == PARTY0 {
-            let party0_value = self.fabric().new_network_op(self.ids(), |mut args| {
-                let share: ScalarShare<C> = args.next().unwrap().into();
-                NetworkPayload::Scalar(share.share())
-            });
-            let party1_value: ScalarResult<C> = self.fabric().receive_value();
+        // Each party broadcasts its share in turn, in order of party ID
+        let fabric = self.fabric();
+        let shares: Vec<ScalarResult<C>> = fabric
+            .party_ids()
+            .map(|party_id| {
+                if party_id == self.party_id() {
+                    fabric.new_network_op(self.ids(), |mut args| {
+                        let share: ScalarShare<C> = args.next().unwrap().into();
+                        NetworkPayload::Scalar(share.share())
+                    })
+                } else {
+                    fabric.receive_value()
+                }
+            })
+            .collect();
 
-            (party0_value, party1_value)
-        } else {
-            let party0_value: ScalarResult<C> = self.fabric().receive_value();
-            let party1_value = self.fabric().new_network_op(self.ids(), |mut args| {
-                let share: ScalarShare<C> = args.next().unwrap().into();
-                NetworkPayload::Scalar(share.share())
-            });
+        let mut shares = shares.into_iter();
+        let first = shares.next().unwrap();
+        shares.fold(first, |acc, share| acc + share)
+    }
 
-            (party0_value, party1_value)
-        };
+    /// Open the underlying share without any MAC bookkeeping
+    ///
+    /// This is NOT safe in the presence of a malicious adversary on its own, it
+    /// is intended for semi-honest subprotocols, e.g. opening masks within a
+    /// larger computation whose outputs are MAC checked. Unlike
+    /// `open_authenticated`, no MAC check gates are allocated
+    pub fn partial_open(&self) -> ScalarResult<C> {
+        self.open()
+    }
+
+    /// Reconstruct the value directly from both parties' local shares, without
+    /// a network round trip
+    ///
+    /// This is only possible in a single-process test harness whose fabrics
+    /// publish their shares to one another, e.g. one built by
+    /// `test_helpers::mock_fabric_pair`. Every party's share must already be
+    /// computed, e.g. by awaiting the value on each fabric. It skips the MAC
+    /// check entirely and must NEVER be used outside of tests
+    #[cfg(feature = "test_helpers")]
+    pub fn cheating_open(&self) -> Scalar<C> {
+        let fabric = self.fabric();
+        let shares = fabric
+            .shared_shares()
+            .expect("cheating_open requires a fabric that publishes its shares")
+            .lock()
+            .expect("shares poisoned");
+
+        fabric
+            .party_ids()
+            .map(|party_id| {
+                let share = shares.get(&(party_id, self.id())).unwrap_or_else(|| {
+                    panic!("party {party_id}'s share of result {} not yet computed", self.id())
+                });
+                share.share()
+            })
+            .sum()
+    }
+
+    /// Reveal the value to the given party only, without checking its MAC
+    ///
+    /// On the receiving party the result resolves to the underlying value, on
+    /// every other party it resolves to zero
+    pub fn open_to(&self, party_id: PartyId) -> ScalarResult<C> {
+        // Every other party broadcasts its share in turn, in order of party ID
+        let fabric = self.fabric();
+        let mut sent = None;
+        let mut peer_shares = Vec::new();
+        for sender in fabric.party_ids().filter(|id| *id != party_id) {
+            if sender == fabric.party_id() {
+                let share: ScalarResult<C> = fabric.new_network_op(self.ids(), |mut args| {
+                    let share: ScalarShare<C> = args.next().unwrap().into();
+                    NetworkPayload::Scalar(share.share())
+                });
+                sent = Some(share);
+            } else {
+                let share: ScalarResult<C> = fabric.receive_value();
+                peer_shares.push(share);
+            }
+        }
 
-        val0 + val1
+        match sent {
+            // Allocate a gate in place of the receiver's output gate so that the
+            // parties' result IDs remain aligned
+            Some(sent) => {
+                fabric.new_gate_op(vec![sent.id()], |_args| ResultValue::Scalar(Scalar::zero()))
+            },
+            None => {
+                let mut ids = vec![self.id()];
+                ids.extend(peer_shares.iter().map(|share| share.id()));
+                fabric.new_gate_op(ids, |mut args| {
+                    let my_share: ScalarShare<C> = args.next().unwrap().into();
+                    let peer_sum: Scalar<C> = args.map(Scalar::from).sum();
+
+                    ResultValue::Scalar(my_share.share() + peer_sum)
+                })
+            },
+        }
     }
 
     /// Open a batch of values without checking their MACs
@@ -133,41 +289,36 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
 
         let n = values.len();
         let fabric = values[0].fabric();
+        fabric.assert_same_fabric(values);
         let my_results = values.iter().map(|val| val.id()).collect_vec();
 
-        // Party zero sends first then receives
-        let (party0_vals, party1_vals) = if values[0].fabric().party_id() == PARTY0 {
-            // Send the local shares
-            let party0_vals: BatchScalarResult<C> = fabric.new_network_op(my_results, |args| {
-                let shares: Vec<Scalar<C>> =
-                    args.map(ScalarShare::from).map(|s| s.share()).collect();
-                NetworkPayload::ScalarBatch(shares)
-            });
-            let party1_vals: BatchScalarResult<C> = fabric.receive_value();
-
-            (party0_vals, party1_vals)
-        } else {
-            let party0_vals: BatchScalarResult<C> = fabric.receive_value();
-            let party1_vals: BatchScalarResult<C> = fabric.new_network_op(my_results, |args| {
-                let shares: Vec<Scalar<C>> =
-                    args.map(ScalarShare::from).map(|s| s.share()).collect();
-                NetworkPayload::ScalarBatch(shares)
-            });
-
-            (party0_vals, party1_vals)
-        };
+        // Each party broadcasts its shares in turn, in order of party ID
+        let party_vals: Vec<BatchScalarResult<C>> = fabric
+            .party_ids()
+            .map(|party_id| {
+                if party_id == fabric.party_id() {
+                    fabric.new_network_op(my_results.clone(), |args| {
+                        let shares: Vec<Scalar<C>> =
+                            args.map(ScalarShare::from).map(|s| s.share()).collect();
+                        NetworkPayload::ScalarBatch(shares)
+                    })
+                } else {
+                    fabric.receive_value()
+                }
+            })
+            .collect_vec();
 
         // Create the new values by combining the additive shares
-        fabric.new_batch_gate_op(vec![party0_vals.id, party1_vals.id], n, move |mut args| {
-            let party0_vals: Vec<Scalar<C>> = args.next().unwrap().into();
-            let party1_vals: Vec<Scalar<C>> = args.next().unwrap().into();
-
-            let mut results = Vec::with_capacity(n);
-            for i in 0..n {
-                results.push(ResultValue::Scalar(party0_vals[i] + party1_vals[i]));
+        let ids = party_vals.iter().map(|vals| vals.id()).collect_vec();
+        fabric.new_batch_gate_op(ids, n, move |args| {
+            let mut results = vec![Scalar::zero(); n];
+            for party_vals in args.map(Vec::<Scalar<C>>::from) {
+                for (res, val) in results.iter_mut().zip(party_vals) {
+                    *res += val;
+                }
             }
 
-            results
+            results.into_iter().map(ResultValue::Scalar).collect()
         })
     }
 
@@ -190,11 +341,7 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
         }
 
         // Sum of the commitments should be zero
-        if peer_mac_share + my_mac_share != Scalar::zero() {
-            return false;
-        }
-
-        true
+        (peer_mac_share + my_mac_share).ct_eq(&Scalar::zero()).into()
     }
 
     /// Verify a batch of MAC checks
@@ -214,9 +361,41 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
             return false;
         }
 
-        // Build a commitment from the gate inputs
+        // Check every share rather than short circuiting on the first failure
         izip!(my_mac_shares, peer_mac_shares)
-            .all(|(my_share, peer_share)| my_share + peer_share == Scalar::zero())
+            .fold(Choice::from(1), |valid, (my_share, peer_share)| {
+                valid & (my_share + peer_share).ct_eq(&Scalar::zero())
+            })
+            .into()
+    }
+
+    /// Compute the local share of a random linear combination of the MAC
+    /// checks of a batch of opened values
+    ///
+    /// The coefficients are powers of a challenge derived by hashing the opened
+    /// values, so both parties compute the same combination without an extra
+    /// round of communication. A party that corrupts an opening changes the
+    /// challenge along with it, so it cannot pick the coefficients it cancels
+    /// against
+    pub(crate) fn combined_mac_check(
+        mac_key: Scalar<C>,
+        values: &[Scalar<C>],
+        shares: &[ScalarShare<C>],
+    ) -> Scalar<C> {
+        let mut hasher = Sha3_256::new();
+        for value in values.iter() {
+            hasher.update(value.to_bytes_be());
+        }
+        let challenge = Scalar::from_be_bytes_mod_order(hasher.finalize().as_slice());
+
+        let mut coeff = challenge;
+        let mut res = Scalar::zero();
+        for (value, share) in izip!(values, shares) {
+            res += coeff * (mac_key * value - share.mac());
+            coeff *= challenge;
+        }
+
+        res
     }
 
     /// Open the value and check its MAC
@@ -227,6 +406,85 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
     pub fn open_authenticated(&self) -> AuthenticatedScalarOpenResult<C> {
         // Both parties open the underlying value
         let recovered_value = self.open();
+        let mac_check = self.check_opening_mac(&recovered_value);
+
+        AuthenticatedScalarOpenResult { value: recovered_value, mac_check }
+    }
+
+    /// Open the value, deferring its MAC check until the next call to
+    /// `MpcFabric::flush_mac_checks`
+    ///
+    /// The returned future resolves only after the fabric flushes, at which
+    /// point the MACs of all deferred openings are checked together in a
+    /// single commitment round
+    pub fn open_authenticated_deferred(&self) -> AuthenticatedScalarOpenResult<C> {
+        let value = self.open();
+        let mac_check = self.fabric().defer_mac_check(&value, self);
+
+        AuthenticatedScalarOpenResult { value, mac_check }
+    }
+
+    /// Open the value by first committing to the local share, then exchanging
+    /// the shares and verifying the peer's commitment, finally checking the
+    /// MAC of the recovered value
+    ///
+    /// The resulting future resolves to an `MpcError::AuthenticationError` if
+    /// either the peer's share does not match its commitment, or the MAC check
+    /// fails
+    pub fn commit_and_open(&self) -> AuthenticatedScalarOpenResult<C> {
+        let my_share: ScalarResult<C> = self.fabric().new_gate_op(self.ids(), |mut args| {
+            let share: ScalarShare<C> = args.next().unwrap().into();
+            ResultValue::Scalar(share.share())
+        });
+
+        let my_comm = HashCommitmentResult::commit(my_share.clone());
+        self.open_committed(my_share, my_comm)
+    }
+
+    /// Open the value given a commitment to the local share
+    ///
+    /// Separated from `commit_and_open` so that tests may commit to a value
+    /// other than the share that is sent
+    fn open_committed(
+        &self,
+        my_share: ScalarResult<C>,
+        my_comm: HashCommitmentResult<C, Scalar<C>>,
+    ) -> AuthenticatedScalarOpenResult<C> {
+        let fabric = self.fabric();
+        let peer_commit = fabric.exchange_value(my_comm.commitment);
+
+        // Only send the share once the peer has committed to theirs
+        let share_after_commit: ScalarResult<C> = fabric
+            .new_gate_op(vec![my_share.id(), peer_commit.id()], |mut args| args.next().unwrap());
+        let peer_share = fabric.exchange_value(share_after_commit);
+        let peer_blinder = fabric.exchange_value(fabric.allocate_scalar(my_comm.blinder));
+
+        // Verify the peer's opening against their commitment
+        let commitment_check: ScalarResult<C> = fabric.new_gate_op(
+            vec![peer_share.id(), peer_blinder.id(), peer_commit.id()],
+            |mut args| {
+                let peer_share: Scalar<C> = args.next().unwrap().into();
+                let blinder: Scalar<C> = args.next().unwrap().into();
+                let commitment: Scalar<C> = args.next().unwrap().into();
+
+                let comm = HashCommitment { values: vec![peer_share], blinder, commitment };
+                ResultValue::Scalar(Scalar::from(comm.verify()))
+            },
+        );
+
+        // Recover the value and check its MAC, both checks must pass
+        let recovered_value = &my_share + &peer_share;
+        let mac_check = self.check_opening_mac(&recovered_value);
+
+        AuthenticatedScalarOpenResult {
+            value: recovered_value,
+            mac_check: commitment_check * mac_check,
+        }
+    }
+
+    /// Run the MAC check protocol on an opened value, returning a result that
+    /// is one if the check passes and zero otherwise
+    fn check_opening_mac(&self, recovered_value: &ScalarResult<C>) -> ScalarResult<C> {
         let fabric = self.fabric();
 
         // Add a gate to compute the MAC check value: `key_share * opened_value -
@@ -242,36 +500,89 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
 
         // Compute a commitment to this value and share it with the peer
         let my_comm = HashCommitmentResult::commit(mac_check_value);
-        let peer_commit = self.fabric().exchange_value(my_comm.commitment);
+        let peer_commit = fabric.exchange_value(my_comm.commitment);
 
-        // Once the parties have exchanged their commitments, they can open them, they
-        // have already exchanged the underlying values and their commitments so
-        // all that is left is the blinder
-        let peer_mac_check = self.fabric().exchange_value(my_comm.values[0].clone());
-
-        let blinder_result: ScalarResult<C> = self.fabric().allocate_scalar(my_comm.blinder);
-        let peer_blinder = self.fabric().exchange_value(blinder_result);
+        // Once the peer has committed, open the commitment by sending the MAC check
+        // value and its blinder together in a single message
+        let blinder = my_comm.blinder;
+        let opening: ResultHandle<C, Vec<Scalar<C>>> =
+            fabric.new_gate_op(vec![my_comm.values[0].id, peer_commit.id], move |mut args| {
+                let value: Scalar<C> = args.next().unwrap().into();
+                ResultValue::ScalarBatch(vec![value, blinder])
+            });
+        let peer_opening = fabric.exchange_value(opening);
 
         // Check the commitment and the MAC result
-        let commitment_check: ScalarResult<C> = self.fabric().new_gate_op(
-            vec![my_comm.values[0].id, peer_mac_check.id, peer_blinder.id, peer_commit.id],
+        fabric.new_gate_op(
+            vec![my_comm.values[0].id, peer_opening.id, peer_commit.id],
             |mut args| {
                 let my_comm_value: Scalar<C> = args.next().unwrap().into();
-                let peer_value: Scalar<C> = args.next().unwrap().into();
-                let blinder: Scalar<C> = args.next().unwrap().into();
+                let peer_opening: Vec<Scalar<C>> = args.next().unwrap().into();
                 let commitment: Scalar<C> = args.next().unwrap().into();
 
-                // Build a commitment from the gate inputs
-                ResultValue::Scalar(Scalar::from(Self::verify_mac_check(
-                    my_comm_value,
-                    peer_value,
-                    commitment,
-                    blinder,
-                )))
+                // Build a commitment from the gate inputs, a malformed opening fails the
+                // check
+                let valid = match (peer_opening.first(), peer_opening.get(1)) {
+                    (Some(&peer_value), Some(&peer_blinder)) => {
+                        Self::verify_mac_check(my_comm_value, peer_value, commitment, peer_blinder)
+                    },
+                    _ => false,
+                };
+
+                ResultValue::Scalar(Scalar::from(valid))
             },
-        );
+        )
+    }
+
+    /// Open the value only if the shared bit `cond` opens to one
+    ///
+    /// The condition is opened and MAC checked. The value is multiplied by the
+    /// condition before it is opened, so when the condition is zero nothing
+    /// about the value is revealed and the future resolves to `None`
+    pub fn open_if(&self, cond: &Self) -> impl Future<Output = Result<Option<Scalar<C>>, MpcError>>
+    where
+        C::ScalarField: Unpin,
+    {
+        let cond_open = cond.open_authenticated();
+        let masked_open = (cond * self).open_authenticated();
+
+        async move {
+            let cond = cond_open.await?;
+            let masked = masked_open.await?;
+
+            if cond == Scalar::one() {
+                Ok(Some(masked))
+            } else if cond == Scalar::zero() {
+                Ok(None)
+            } else {
+                Err(MpcError::ArithmeticError("open_if condition is not a bit".to_string()))
+            }
+        }
+    }
+
+    /// Open the value, check its MAC, then check the opened value against a
+    /// caller-provided predicate
+    ///
+    /// This catches values that are correctly authenticated but semantically
+    /// wrong, e.g. an out of range share produced by a bug upstream
+    pub fn open_validated(
+        &self,
+        predicate: impl Fn(&Scalar<C>) -> bool,
+    ) -> impl Future<Output = Result<Scalar<C>, MpcError>>
+    where
+        C::ScalarField: Unpin,
+    {
+        let open = self.open_authenticated();
+        async move {
+            let value = open.await?;
+            if !predicate(&value) {
+                return Err(MpcError::ValidationError(format!(
+                    "opened value {value} failed validation"
+                )));
+            }
 
-        AuthenticatedScalarOpenResult { value: recovered_value, mac_check: commitment_check }
+            Ok(value)
+        }
     }
 
     /// Open a batch of values and check their MACs
@@ -282,6 +593,7 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
 
         let n = values.len();
         let fabric = &values[0].fabric();
+        fabric.assert_same_fabric(values);
         let mac_key = fabric.mac_key();
 
         // Both parties open the underlying values
@@ -394,6 +706,11 @@ impl<C: CurveGroup> Add<&Scalar<C>> for &AuthenticatedScalarResult<C> {
     type Output = AuthenticatedScalarResult<C>;
 
     fn add(self, rhs: &Scalar<C>) -> Self::Output {
+        // Adding zero leaves both parties' shares and MACs unchanged, so needs no gate
+        if *rhs == Scalar::zero() {
+            return self.clone();
+        }
+
         // Both parties add the public value to their modifier, and the MACs do not
         // change when adding a public value
         let fabric = self.fabric();
@@ -417,6 +734,7 @@ impl<C: CurveGroup> Add<&ScalarResult<C>> for &AuthenticatedScalarResult<C> {
 
     #[allow(clippy::suspicious_arithmetic_impl)]
     fn add(self, rhs: &ScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         // As above, only party 0 adds the public value to their share, but both parties
         // track this with the modifier
         //
@@ -441,6 +759,7 @@ impl<C: CurveGroup> Add<&AuthenticatedScalarResult<C>> for &AuthenticatedScalarR
     type Output = AuthenticatedScalarResult<C>;
 
     fn add(self, rhs: &AuthenticatedScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         self.fabric().new_gate_op(vec![self.id(), rhs.id()], |mut args| {
             let lhs: ScalarShare<C> = args.next().unwrap().into();
             let rhs: ScalarShare<C> = args.next().unwrap().into();
@@ -462,6 +781,8 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         // Collect ids chunking the result into the order in which they will be added
         let mut all_ids = Vec::with_capacity(2 * n);
@@ -497,6 +818,8 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
         let n = a.len();
         assert_eq!(n, b.len(), "Cannot add batches of different sizes");
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let mut all_ids = Vec::with_capacity(n);
         for (a, b) in a.iter().zip(b.iter()) {
@@ -536,6 +859,7 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
         let all_ids = a.iter().map(|v| v.id()).collect_vec();
 
         // Add the underlying values
@@ -561,10 +885,12 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
 }
 
 impl<C: CurveGroup> Sum for AuthenticatedScalarResult<C> {
-    /// Assumes the iterator is non-empty
+    /// Assumes the iterator is non-empty, prefer `MpcFabric::sum` where the
+    /// iterator may be empty
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
         let values = iter.collect_vec();
         let fabric = values[0].fabric();
+        fabric.assert_same_fabric(&values);
 
         // Add the underlying values
         let ids = values.iter().map(|v| v.id()).collect_vec();
@@ -609,6 +935,7 @@ impl<C: CurveGroup> Sub<&ScalarResult<C>> for &AuthenticatedScalarResult<C> {
     type Output = AuthenticatedScalarResult<C>;
 
     fn sub(self, rhs: &ScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         let fabric = self.fabric();
         let party_id = fabric.party_id();
         let mac_key = fabric.mac_key();
@@ -628,6 +955,7 @@ impl<C: CurveGroup> Sub<&AuthenticatedScalarResult<C>> for &ScalarResult<C> {
     type Output = AuthenticatedScalarResult<C>;
 
     fn sub(self, rhs: &AuthenticatedScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         let fabric = rhs.fabric();
         let party_id = fabric.party_id();
         let mac_key = fabric.mac_key();
@@ -647,6 +975,7 @@ impl<C: CurveGroup> Sub<&AuthenticatedScalarResult<C>> for &AuthenticatedScalarR
     type Output = AuthenticatedScalarResult<C>;
 
     fn sub(self, rhs: &AuthenticatedScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         self.fabric.new_gate_op(vec![self.id(), rhs.id()], |mut args| {
             let lhs: ScalarShare<C> = args.next().unwrap().into();
             let rhs: ScalarShare<C> = args.next().unwrap().into();
@@ -666,6 +995,8 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
         let n = a.len();
         assert_eq!(n, b.len(), "Cannot add batches of different sizes");
         let fabric = &a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let mut all_ids = Vec::with_capacity(n);
         for (a, b) in a.iter().zip(b.iter()) {
@@ -697,6 +1028,8 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let mut all_ids = Vec::with_capacity(n);
         for (a, b) in a.iter().zip(b.iter()) {
@@ -751,6 +1084,7 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
         let all_ids = a.iter().map(|v| v.id()).collect_vec();
 
         fabric.new_batch_gate_op(
@@ -771,7 +1105,14 @@ impl<C: CurveGroup> Mul<&Scalar<C>> for &AuthenticatedScalarResult<C> {
     type Output = AuthenticatedScalarResult<C>;
 
     fn mul(self, rhs: &Scalar<C>) -> Self::Output {
+        // Multiplying by zero or one needs no gate
         let rhs = *rhs;
+        if rhs == Scalar::zero() {
+            return self.fabric().zero_authenticated();
+        } else if rhs == Scalar::one() {
+            return self.clone();
+        }
+
         self.fabric().new_gate_op(self.ids(), move |mut args| {
             let share: ScalarShare<C> = args.next().unwrap().into();
             ResultValue::ScalarShare(share * rhs)
@@ -785,6 +1126,7 @@ impl<C: CurveGroup> Mul<&ScalarResult<C>> for &AuthenticatedScalarResult<C> {
     type Output = AuthenticatedScalarResult<C>;
 
     fn mul(self, rhs: &ScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         self.fabric().new_gate_op(vec![self.id(), rhs.id()], |mut args| {
             let lhs: ScalarShare<C> = args.next().unwrap().into();
             let rhs: Scalar<C> = args.next().unwrap().into();
@@ -802,7 +1144,9 @@ impl<C: CurveGroup> Mul<&AuthenticatedScalarResult<C>> for &AuthenticatedScalarR
     // Use the Beaver trick
     #[allow(clippy::suspicious_arithmetic_impl)]
     fn mul(self, rhs: &AuthenticatedScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         // Sample a beaver triplet
+        self.fabric().record_mults(1);
         let (a, b, c) = self.fabric().next_triple();
 
         // Mask the left and right hand sides and open them
@@ -857,6 +1201,9 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
+        fabric.record_mults(n);
         let (beaver_a, beaver_b, beaver_c) = fabric.next_triple_batch(n);
 
         // Open the values d = [lhs - a] and e = [rhs - b]
@@ -878,6 +1225,19 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
         AuthenticatedScalarResult::batch_add(&de_plus_db, &ea_plus_c)
     }
 
+    /// Compute the inner product of two batches of values
+    ///
+    /// The multiplications are batched so that their triples are requested
+    /// from the beaver source at once, and the products are summed in a
+    /// single gate
+    pub fn inner_product(
+        a: &[AuthenticatedScalarResult<C>],
+        b: &[AuthenticatedScalarResult<C>],
+    ) -> AuthenticatedScalarResult<C> {
+        assert!(!a.is_empty(), "inner_product requires non-empty vectors");
+        Self::batch_mul(a, b).into_iter().sum()
+    }
+
     /// Multiply a batch of `AuthenticatedScalarResult`s by a batch of
     /// `ScalarResult`s
     pub fn batch_mul_public(
@@ -891,6 +1251,8 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let mut all_ids = Vec::with_capacity(2 * n);
         for (a, b) in a.iter().zip(b.iter()) {
@@ -927,6 +1289,7 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
 
         let n = a.len();
         let fabric = a[0].fabric();
+        fabric.assert_same_fabric(a);
 
         let b = b.to_vec();
         let ids = a.iter().map(|a| a.id()).collect_vec();
@@ -948,11 +1311,39 @@ impl<C: CurveGroup> AuthenticatedScalarResult<C> {
     }
 }
 
+impl<C: CurveGroup> Product for AuthenticatedScalarResult<C> {
+    /// Assumes the iterator is non-empty, prefer `MpcFabric::product` where
+    /// the iterator may be empty
+    ///
+    /// Multiplies the values in a balanced binary tree, batching each level of
+    /// the tree into a single `batch_mul`. This takes `O(log n)` rounds of
+    /// communication rather than the `O(n)` rounds of a sequential fold
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        let mut level = iter.collect_vec();
+        assert!(
+            !level.is_empty(),
+            "AuthenticatedScalarResult::product requires a non-empty iterator"
+        );
+
+        while level.len() > 1 {
+            // Carry an odd element up to the next level unmultiplied
+            let carry = if level.len() % 2 == 1 { level.pop() } else { None };
+
+            let (lhs, rhs): (Vec<_>, Vec<_>) = level.into_iter().tuples().unzip();
+            level = AuthenticatedScalarResult::batch_mul(&lhs, &rhs);
+            level.extend(carry);
+        }
+
+        level.pop().unwrap()
+    }
+}
+
 // === Division === //
 #[allow(clippy::suspicious_arithmetic_impl)]
 impl<C: CurveGroup> Div<&ScalarResult<C>> for &AuthenticatedScalarResult<C> {
     type Output = AuthenticatedScalarResult<C>;
     fn div(self, rhs: &ScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         let rhs_inv = rhs.inverse();
         self * rhs_inv
     }
@@ -963,6 +1354,7 @@ impl_borrow_variants!(AuthenticatedScalarResult<C>, Div, div, /, ScalarResult<C>
 impl<C: CurveGroup> Div<&AuthenticatedScalarResult<C>> for &AuthenticatedScalarResult<C> {
     type Output = AuthenticatedScalarResult<C>;
     fn div(self, rhs: &AuthenticatedScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         let rhs_inv = rhs.inverse();
         self * rhs_inv
     }
@@ -984,6 +1376,14 @@ impl<C: CurveGroup> Mul<&AuthenticatedScalarResult<C>> for &CurvePoint<C> {
 
     fn mul(self, rhs: &AuthenticatedScalarResult<C>) -> Self::Output {
         let lhs = *self;
+        if lhs == CurvePoint::generator() {
+            let table = rhs.fabric().generator_table();
+            return rhs.fabric().new_gate_op(vec![rhs.id()], move |mut args| {
+                let scalar: ScalarShare<C> = args.next().unwrap().into();
+                ResultValue::PointShare(table.mul_share(&scalar))
+            });
+        }
+
         rhs.fabric().new_gate_op(vec![rhs.id()], move |mut args| {
             let scalar: ScalarShare<C> = args.next().unwrap().into();
             ResultValue::PointShare(lhs * scalar)
@@ -996,6 +1396,7 @@ impl<C: CurveGroup> Mul<&AuthenticatedScalarResult<C>> for &CurvePointResult<C>
     type Output = AuthenticatedPointResult<C>;
 
     fn mul(self, rhs: &AuthenticatedScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         self.fabric().new_gate_op(vec![self.id(), rhs.id()], |mut args| {
             let point: CurvePoint<C> = args.next().unwrap().into();
             let scalar: ScalarShare<C> = args.next().unwrap().into();
@@ -1007,6 +1408,252 @@ impl<C: CurveGroup> Mul<&AuthenticatedScalarResult<C>> for &CurvePointResult<C>
 impl_borrow_variants!(CurvePointResult<C>, Mul, mul, *, AuthenticatedScalarResult<C>, Output=AuthenticatedPointResult<C>, C: CurveGroup);
 impl_commutative!(CurvePointResult<C>, Mul, mul, *, AuthenticatedScalarResult<C>, Output=AuthenticatedPointResult<C>, C: CurveGroup);
 
+// === Conditional Swap === //
+
+impl<C: CurveGroup> AuthenticatedScalarResult<C> {
+    /// Swap `a` and `b` if `cond` is one, i.e. return `(a, b)` when `cond == 0`
+    /// and `(b, a)` when `cond == 1`
+    ///
+    /// `cond` must be a share of a bit, the swap is computed with a single
+    /// multiplication as `d = cond * (b - a)`, `(a + d, b - d)`
+    pub fn conditional_swap(cond: &Self, a: Self, b: Self) -> (Self, Self) {
+        let d = cond * (&b - &a);
+        (a + &d, b - d)
+    }
+}
+
+// === Bit Operations === //
+
+impl<C: CurveGroup> AuthenticatedScalarResult<C> {
+    /// Compute the number of set bits in a shared bit vector
+    ///
+    /// Assumes that each input is a share of a bit; the weight is computed as
+    /// the sum of the inputs in a single gate, so a non-bit input is simply
+    /// added into the count. No network round is required
+    pub fn hamming_weight(bits: &[Self]) -> Self {
+        assert!(!bits.is_empty(), "hamming_weight requires a non-empty bit vector");
+        bits.iter().cloned().sum()
+    }
+}
+
+// === Integer Gadgets === //
+
+impl<C: CurveGroup> AuthenticatedScalarResult<C> {
+    /// Compute a shared bit that is one iff the value lies in `[0, 2^num_bits)`
+    ///
+    /// The value is interpreted as a signed integer `x` with
+    /// `|x| < 2^INTEGER_BITS`; a field element outside of this bound yields an
+    /// arbitrary bit. The value is truncated by `num_bits` through its bit
+    /// decomposition modulo `2^num_bits`, and the quotient is tested for zero
+    pub fn range_check(&self, num_bits: usize) -> Self {
+        assert!(num_bits <= INTEGER_BITS, "range_check supports at most {INTEGER_BITS} bits");
+
+        // The quotient lies in `[-2^(INTEGER_BITS - num_bits), 2^(INTEGER_BITS -
+        // num_bits))`
+        let quotient = truncate_batch(slice::from_ref(self), num_bits, INTEGER_BITS);
+        is_zero_batch(&quotient, INTEGER_BITS - num_bits + 1).pop().unwrap()
+    }
+
+    /// Check that the value lies in `[0, 2^num_bits)`, revealing only whether
+    /// it does
+    ///
+    /// The indicator bit of `range_check` is opened and MAC checked, an out of
+    /// range value resolves to a `ValidationError`
+    pub fn assert_range(&self, num_bits: usize) -> impl Future<Output = Result<(), MpcError>>
+    where
+        C::ScalarField: Unpin,
+    {
+        let in_range = self.range_check(num_bits).open_authenticated();
+        async move {
+            if in_range.await? == Scalar::one() {
+                Ok(())
+            } else {
+                Err(MpcError::ValidationError(format!("value is not in [0, 2^{num_bits})")))
+            }
+        }
+    }
+
+    /// Compute a shared one-hot vector of length `len`, holding one at
+    /// position `index` and zero elsewhere
+    ///
+    /// Each position is `is_zero(index - i)`. The zero tests share a single
+    /// masked opening and their ORs are batched level by level, so the vector
+    /// takes as many rounds as one zero test. The index must lie in `[0, len)`
+    pub fn one_hot(index: &Self, len: usize) -> Vec<Self> {
+        assert!(len > 0, "one_hot requires a non-zero length");
+
+        let offsets = (0..len).map(|i| index - Scalar::from(i)).collect_vec();
+        is_zero_batch(&offsets, index_bits(len))
+    }
+
+    /// Divide the value by `2^shift`, rounding towards negative infinity
+    ///
+    /// The value is interpreted as a signed integer `x` with
+    /// `|x| < 2^INTEGER_BITS`; a field element outside of this bound yields an
+    /// arbitrary result. The value is opened under a mask whose low `shift`
+    /// bits are shared bits from the offline phase, which gives `x mod
+    /// 2^shift` after correcting for a borrow out of the low bits; this
+    /// residue is subtracted so that the division is exact. Shifting by at
+    /// least `INTEGER_BITS` gives zero for non-negative values and minus one
+    /// for negative values, and a shift of zero opens nothing
+    pub fn truncate(&self, shift: usize) -> Self {
+        let shift = shift.min(INTEGER_BITS);
+        truncate_batch(slice::from_ref(self), shift, INTEGER_BITS).pop().unwrap()
+    }
+
+    /// Reduce the value modulo `2^k`, giving a residue in `[0, 2^k)`
+    ///
+    /// The value is interpreted as a signed integer `x` with
+    /// `|x| < 2^INTEGER_BITS`, so negative values wrap to `2^k + x` for `k`
+    /// wide enough. The residue is recomposed from the low `k` shared bits of
+    /// the mask under which the value is opened, corrected by the low `k` bits
+    /// of the opening and the borrow between them. `k` may exceed
+    /// `INTEGER_BITS`, but the masked opening must still fit in the scalar
+    /// field, i.e. `k + STATISTICAL_SECURITY + 3` may be at most the field's
+    /// bit length
+    pub fn mod_power_of_two(&self, k: usize) -> Self {
+        let bits = k.max(INTEGER_BITS);
+        mod_power_of_two_batch(slice::from_ref(self), k, bits).pop().unwrap()
+    }
+
+    /// Multiply two shared fixed-point values with `precision_bits` fractional
+    /// bits
+    ///
+    /// A fixed-point value `v` is encoded as the integer `round(v *
+    /// 2^precision_bits)` with `|x| < 2^INTEGER_BITS`. The product of two
+    /// encodings is truncated by `precision_bits`, rounding towards negative
+    /// infinity, so the result is within `2^-precision_bits` of the true
+    /// product. The truncation opens the product under a mask with
+    /// `STATISTICAL_SECURITY` bits of slack over its `2 * INTEGER_BITS` bit
+    /// bound, so the opening is within statistical distance
+    /// `2^-STATISTICAL_SECURITY` of uniform; this needs a scalar field of at
+    /// least `2 * INTEGER_BITS + STATISTICAL_SECURITY + 3` bits. The result
+    /// must itself fit in `INTEGER_BITS` bits to be used in further gadgets
+    pub fn mul_fixed_point(&self, other: &Self, precision_bits: u32) -> Self {
+        let precision_bits = precision_bits as usize;
+        assert!(
+            precision_bits <= INTEGER_BITS,
+            "mul_fixed_point supports at most {INTEGER_BITS} precision bits"
+        );
+
+        let product = self * other;
+        truncate_batch(&[product], precision_bits, 2 * INTEGER_BITS).pop().unwrap()
+    }
+
+    /// Obliviously read the element of a shared array at a shared index
+    ///
+    /// The selected element is the inner product of the array with the
+    /// `one_hot` vector of the index. This costs `O(n)` zero tests and
+    /// multiplications for an array of length `n`; the zero tests run
+    /// concurrently, so the rounds grow only with the bit length of `n`. The
+    /// index must lie in `[0, n)`
+    pub fn select_index(array: &[Self], index: &Self) -> Self {
+        assert!(!array.is_empty(), "select_index requires a non-empty array");
+
+        let selector = Self::one_hot(index, array.len());
+        Self::inner_product(&selector, array)
+    }
+
+    /// Obliviously write a shared value into a shared array at a shared index
+    ///
+    /// Each element is updated as `array[i] + one_hot[i] * (value - array[i])`,
+    /// so every element is rewritten and the written position stays hidden.
+    /// The `n` multiplications are batched into one round after the `one_hot`
+    /// vector. The index must lie in `[0, n)`
+    pub fn write_index(array: &mut [Self], index: &Self, value: &Self) {
+        assert!(!array.is_empty(), "write_index requires a non-empty array");
+
+        let selector = Self::one_hot(index, array.len());
+        let diffs = array.iter().map(|x| value - x).collect_vec();
+        let updates = Self::batch_mul(&selector, &diffs);
+
+        for (x, update) in array.iter_mut().zip(updates) {
+            *x = &*x + update;
+        }
+    }
+}
+
+/// The number of bits `b` such that the difference of two indices into an
+/// array of length `len` is less than `2^b` in absolute value
+fn index_bits(len: usize) -> usize {
+    (usize::BITS - (len - 1).leading_zeros()).max(1) as usize
+}
+
+// === Interpolation === //
+
+impl<C: CurveGroup> AuthenticatedScalarResult<C> {
+    /// Evaluate the unique polynomial passing through the public x-coordinates
+    /// `points` and the shared y-coordinates `values` at `eval_at`
+    ///
+    /// The barycentric weights of the Lagrange basis depend only on public
+    /// values, so they are computed locally in a single gate along with the
+    /// basis at `eval_at`; the result is then a public linear combination of
+    /// the shared values and consumes no Beaver triples
+    ///
+    /// The x-coordinates must be distinct, otherwise no polynomial is defined
+    /// by the points and the result is meaningless. They are only known once
+    /// the gate runs, so they cannot be checked here; see `interpolate_public`
+    /// for x-coordinates known at the call site
+    pub fn interpolate(
+        points: &[ScalarResult<C>],
+        values: &[Self],
+        eval_at: &ScalarResult<C>,
+    ) -> Self {
+        assert_eq!(points.len(), values.len(), "interpolate requires equal length inputs");
+        assert!(!points.is_empty(), "interpolate requires at least one point");
+
+        let n = points.len();
+        let fabric = eval_at.fabric();
+        fabric.assert_same_fabric(points);
+
+        // Compute the barycentric weights and the Lagrange basis evaluated at
+        // `eval_at` in one gate over the public inputs
+        let mut ids = points.iter().map(|x| x.id()).collect_vec();
+        ids.push(eval_at.id());
+        let basis: Vec<ScalarResult<C>> = fabric.new_batch_gate_op(ids, n, move |args| {
+            let mut args = args.map(Scalar::from).collect_vec();
+            let x = args.pop().unwrap();
+            let xs = args;
+
+            let mut weights = (0..n)
+                .map(|i| (0..n).filter(|&j| j != i).map(|j| xs[i] - xs[j]).product::<Scalar<C>>())
+                .collect_vec();
+            Scalar::batch_inverse(&mut weights);
+
+            (0..n)
+                .map(|i| {
+                    let numerator =
+                        (0..n).filter(|&j| j != i).map(|j| x - xs[j]).product::<Scalar<C>>();
+                    ResultValue::Scalar(numerator * weights[i])
+                })
+                .collect_vec()
+        });
+
+        Self::batch_mul_public(values, &basis).into_iter().sum()
+    }
+
+    /// Interpolate as in `interpolate`, through x-coordinates known at the
+    /// call site
+    ///
+    /// Returns an error if the x-coordinates are not distinct, in which case
+    /// no polynomial is defined by the points
+    pub fn interpolate_public(
+        points: &[Scalar<C>],
+        values: &[Self],
+        eval_at: &ScalarResult<C>,
+    ) -> Result<Self, MpcError> {
+        let mut seen = HashSet::with_capacity(points.len());
+        if let Some(dup) = points.iter().find(|x| !seen.insert(**x)) {
+            return Err(MpcError::ArithmeticError(format!(
+                "interpolate requires distinct x-coordinates, {dup} repeats"
+            )));
+        }
+
+        let points = eval_at.fabric().allocate_scalars(points.to_vec());
+        Ok(Self::interpolate(&points, values, eval_at))
+    }
+}
+
 // === FFT and IFFT === //
 impl<C: CurveGroup> AuthenticatedScalarResult<C>
 where
@@ -1058,6 +1705,7 @@ where
         let n = domain.size();
 
         let fabric = x[0].fabric();
+        fabric.assert_same_fabric(x);
         let ids = x.iter().map(|v| v.id()).collect_vec();
 
         fabric.new_batch_gate_op(ids, n, move |args| {
@@ -1112,75 +1760,469 @@ pub mod test_helpers {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
     use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
     use futures::future;
     use itertools::Itertools;
+    use num_bigint::BigUint;
     use rand::{thread_rng, Rng, RngCore};
 
     use crate::{
-        algebra::{poly_test_helpers::TestPolyField, scalar::Scalar, AuthenticatedScalarResult},
-        test_helpers::{execute_mock_mpc, open_await_all, TestCurve},
+        algebra::{
+            poly_test_helpers::TestPolyField, scalar::Scalar, AuthenticatedPointResult,
+            AuthenticatedScalarResult, CurvePoint, ScalarResult, ScalarShare,
+        },
+        commitment::HashCommitmentResult,
+        error::MpcError,
+        fabric::ResultValue,
+        offline_prep::{
+            PartyIDBeaverSource, PreprocessingPhase, SeededBeaverSource, StatsBeaverSource,
+        },
+        test_helpers::{
+            execute_mock_mpc, execute_mock_mpc_n_party, execute_mock_mpc_with_beaver_source,
+            mock_fabric, open_await_all, TestCurve,
+        },
         PARTY0, PARTY1,
     };
 
-    // ------------
-    // | Addition |
-    // ------------
+    #[cfg(feature = "test_helpers")]
+    use crate::test_helpers::mock_fabric_pair;
 
-    /// Tests addition with a constant value
+    /// A beaver source that counts the single and batched triple requests made
+    /// to it
+    struct TripleRequestCounter {
+        /// The underlying source
+        source: PartyIDBeaverSource,
+        /// The number of single and batched triple requests
+        counts: Arc<Mutex<(usize, usize)>>,
+    }
+
+    impl TripleRequestCounter {
+        /// Wrap a `PartyIDBeaverSource` for the given party
+        fn new(party_id: u64) -> Self {
+            Self { source: PartyIDBeaverSource::new(party_id), counts: Arc::default() }
+        }
+    }
+
+    impl PreprocessingPhase<TestCurve> for TripleRequestCounter {
+        fn get_mac_key_share(&self) -> Scalar<TestCurve> {
+            self.source.get_mac_key_share()
+        }
+
+        fn next_local_input_mask(&mut self) -> (Scalar<TestCurve>, ScalarShare<TestCurve>) {
+            self.source.next_local_input_mask()
+        }
+
+        fn next_counterparty_input_mask(&mut self) -> ScalarShare<TestCurve> {
+            self.source.next_counterparty_input_mask()
+        }
+
+        fn next_shared_bit(&mut self) -> ScalarShare<TestCurve> {
+            self.source.next_shared_bit()
+        }
+
+        fn next_shared_value(&mut self) -> ScalarShare<TestCurve> {
+            self.source.next_shared_value()
+        }
+
+        fn next_shared_inverse_pair(&mut self) -> (ScalarShare<TestCurve>, ScalarShare<TestCurve>) {
+            self.source.next_shared_inverse_pair()
+        }
+
+        fn next_triplet(
+            &mut self,
+        ) -> (ScalarShare<TestCurve>, ScalarShare<TestCurve>, ScalarShare<TestCurve>) {
+            self.counts.lock().unwrap().0 += 1;
+            self.source.next_triplet()
+        }
+
+        fn next_triplet_batch(
+            &mut self,
+            num_triplets: usize,
+        ) -> (Vec<ScalarShare<TestCurve>>, Vec<ScalarShare<TestCurve>>, Vec<ScalarShare<TestCurve>>)
+        {
+            self.counts.lock().unwrap().1 += 1;
+            self.source.next_triplet_batch(num_triplets)
+        }
+    }
+
+    // -----------
+    // | Opening |
+    // -----------
+
+    /// Tests that a partial open recovers the same value as a full open
     #[tokio::test]
-    async fn test_add_constant() {
+    async fn test_partial_open() {
         let mut rng = thread_rng();
-        let a = Scalar::random(&mut rng);
-        let b = Scalar::random(&mut rng);
+        let value = Scalar::<TestCurve>::random(&mut rng);
 
         let (res, _) = execute_mock_mpc(|fabric| async move {
-            let a_shared = fabric.share_scalar(a, PARTY0);
-            let res = &a_shared + b;
+            let shared_value = fabric.share_scalar(value, PARTY0 /* sender */);
 
-            res.open_authenticated().await
+            let partial = shared_value.partial_open().await;
+            let full = shared_value.open_authenticated().await.unwrap();
+            (partial, full)
         })
         .await;
 
-        assert_eq!(res.unwrap(), a + b)
+        let (partial, full) = res;
+        assert_eq!(partial, value);
+        assert_eq!(partial, full);
     }
 
-    /// Tests batch addition with constant values
+    /// Tests that a cheating open matches the networked open
     #[tokio::test]
-    async fn test_batch_add_constant() {
-        const N: usize = 100;
+    #[cfg(feature = "test_helpers")]
+    async fn test_cheating_open() {
         let mut rng = thread_rng();
+        let value = Scalar::<TestCurve>::random(&mut rng);
 
-        let a = (0..N).map(|_| Scalar::random(&mut rng)).collect_vec();
-        let b = (0..N).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let (fabric0, fabric1) = mock_fabric_pair();
+        let shared0 = fabric0.share_scalar(value, PARTY0 /* sender */);
+        let shared1 = fabric1.share_scalar(value, PARTY0 /* sender */);
 
-        let expected_res = a.iter().zip(b.iter()).map(|(a, b)| a + b).collect_vec();
+        future::join(shared0.clone(), shared1.clone()).await;
+        let cheating_res = shared0.cheating_open();
+        let (open0, open1) = future::join(shared0.open(), shared1.open()).await;
 
-        let (res, _) = execute_mock_mpc(|fabric| {
-            let a = a.clone();
-            let b = b.clone();
+        fabric0.shutdown();
+        fabric1.shutdown();
 
-            async move {
-                let a_shared = fabric.batch_share_scalar(a, PARTY0 /* sender */);
-                let res = AuthenticatedScalarResult::batch_add_constant(&a_shared, &b);
-                open_await_all(&res).await
-            }
+        assert_eq!(cheating_res, value);
+        assert_eq!(cheating_res, open0);
+        assert_eq!(cheating_res, open1);
+    }
+
+    /// Tests that multiplying by a public zero or one allocates no gate
+    #[tokio::test]
+    async fn test_mul_public_zero_one() {
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let value = fabric.share_scalar(5u8, PARTY0 /* sender */);
+            let n_gates = fabric.num_gates();
+            let zero = &value * Scalar::zero();
+            let one = &value * Scalar::one();
+            let n_new_gates = fabric.num_gates() - n_gates;
+
+            let opened = open_await_all(&[zero, one]).await;
+            (n_new_gates, opened)
         })
         .await;
 
-        assert_eq!(res, expected_res)
+        let (n_new_gates, opened) = res;
+        assert_eq!(n_new_gates, 0);
+        assert_eq!(opened, vec![Scalar::zero(), Scalar::from(5u8)]);
     }
 
-    /// Tests addition with a public value    
+    /// Tests that adding or subtracting a public zero allocates no gate and
+    /// opens to the original value
     #[tokio::test]
-    async fn test_add_public() {
+    async fn test_add_public_zero() {
         let mut rng = thread_rng();
-        let a = Scalar::random(&mut rng);
-        let b = Scalar::random(&mut rng);
+        let value = Scalar::<TestCurve>::random(&mut rng);
 
         let (res, _) = execute_mock_mpc(|fabric| async move {
-            let a_shared = fabric.share_scalar(a, PARTY0);
-            let b = fabric.allocate_scalar(b);
+            let shared = fabric.share_scalar(value, PARTY1 /* sender */);
+            let n_gates = fabric.num_gates();
+            let sum = &shared + Scalar::zero();
+            let commuted = Scalar::zero() + &shared;
+            let diff = &shared - Scalar::zero();
+            let n_new_gates = fabric.num_gates() - n_gates;
+
+            let opened = future::try_join_all(
+                [shared, sum, commuted, diff].iter().map(|v| v.open_authenticated()),
+            )
+            .await;
+            (n_new_gates, opened)
+        })
+        .await;
+
+        let (n_new_gates, opened) = res;
+        assert_eq!(n_new_gates, 0);
+        assert_eq!(opened.unwrap(), vec![value; 4]);
+    }
+
+    /// Tests exporting shares and importing them into fresh fabrics, after
+    /// which the value still opens correctly
+    #[tokio::test]
+    async fn test_share_export() {
+        let mut rng = thread_rng();
+        let value = Scalar::<TestCurve>::random(&mut rng);
+
+        let (bytes0, bytes1) = execute_mock_mpc(|fabric| async move {
+            let shared = fabric.share_scalar(value, PARTY0 /* sender */);
+            let doubled = &shared + &shared;
+            doubled.to_share_bytes().await
+        })
+        .await;
+
+        let (res, _) = execute_mock_mpc(|fabric| {
+            let bytes = if fabric.party_id() == PARTY0 { bytes0.clone() } else { bytes1.clone() };
+            let peer_bytes =
+                if fabric.party_id() == PARTY0 { bytes1.clone() } else { bytes0.clone() };
+            async move {
+                let truncated = AuthenticatedScalarResult::from_share_bytes(&bytes[1..], &fabric);
+                let wrong_key = AuthenticatedScalarResult::from_share_bytes(&peer_bytes, &fabric);
+                let imported =
+                    AuthenticatedScalarResult::from_share_bytes(&bytes, &fabric).unwrap();
+                (truncated.is_err(), wrong_key.is_err(), imported.open_authenticated().await)
+            }
+        })
+        .await;
+
+        let (truncated_rejected, wrong_key_rejected, opened) = res;
+        assert!(truncated_rejected);
+        assert!(wrong_key_rejected);
+        assert_eq!(opened.unwrap(), value + value);
+    }
+
+    /// Tests the number of messages exchanged in an authenticated opening
+    ///
+    /// Each party sends its share, then its commitment to the MAC check value,
+    /// then the MAC check value and blinder together
+    #[tokio::test]
+    async fn test_open_authenticated_messages() {
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let value = fabric.random_shared_scalars(1).pop().unwrap();
+
+            let received_before = fabric.network_stats().messages_received();
+            let opened = value.open_authenticated().await;
+            let received = fabric.network_stats().messages_received() - received_before;
+
+            (opened.is_ok(), received)
+        })
+        .await;
+
+        let (opened, received) = res;
+        assert!(opened);
+        assert_eq!(received, 3);
+    }
+
+    /// Tests revealing a value to a single party
+    #[tokio::test]
+    async fn test_open_to() {
+        let mut rng = thread_rng();
+        let value = Scalar::<TestCurve>::random(&mut rng);
+
+        let (party0_res, party1_res) = execute_mock_mpc(|fabric| async move {
+            let shared_value = fabric.share_scalar(value, PARTY1 /* sender */);
+            shared_value.open_to(PARTY0).await
+        })
+        .await;
+
+        assert_eq!(party0_res, value);
+        assert_eq!(party1_res, Scalar::zero());
+    }
+
+    /// Tests revealing a value to a single party between three parties
+    #[tokio::test]
+    async fn test_open_to_three_party() {
+        let mut rng = thread_rng();
+        let value = Scalar::<TestCurve>::random(&mut rng);
+
+        let res = execute_mock_mpc_n_party(3, |fabric| async move {
+            let shared_value = fabric.share_scalar(value, PARTY0 /* sender */);
+            shared_value.open_to(PARTY1).await
+        })
+        .await;
+
+        assert_eq!(res, vec![Scalar::zero(), value, Scalar::zero()]);
+    }
+
+    /// Tests conditionally opening a value when the condition is true
+    #[tokio::test]
+    async fn test_open_if_true() {
+        let mut rng = thread_rng();
+        let value = Scalar::<TestCurve>::random(&mut rng);
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let shared_value = fabric.share_scalar(value, PARTY0 /* sender */);
+            let cond = fabric.share_scalar(1u8, PARTY1 /* sender */);
+
+            shared_value.open_if(&cond).await
+        })
+        .await;
+
+        assert_eq!(res.unwrap(), Some(value))
+    }
+
+    /// Tests conditionally opening a value when the condition is false
+    #[tokio::test]
+    async fn test_open_if_false() {
+        let mut rng = thread_rng();
+        let value = Scalar::<TestCurve>::random(&mut rng);
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let shared_value = fabric.share_scalar(value, PARTY0 /* sender */);
+            let cond = fabric.share_scalar(0u8, PARTY1 /* sender */);
+
+            shared_value.open_if(&cond).await
+        })
+        .await;
+
+        assert_eq!(res.unwrap(), None)
+    }
+
+    /// Tests that a validated open rejects a value that passes the MAC check
+    /// but fails the predicate
+    #[tokio::test]
+    async fn test_open_validated() {
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let in_range = fabric.share_scalar(5u8, PARTY0 /* sender */);
+            let out_of_range = fabric.share_scalar(200u8, PARTY1 /* sender */);
+
+            let max = BigUint::from(100u8);
+            let range_check = |x: &Scalar<TestCurve>| x.to_biguint() < max;
+
+            (
+                in_range.open_validated(range_check).await,
+                out_of_range.open_validated(range_check).await,
+            )
+        })
+        .await;
+
+        let (in_range, out_of_range) = res;
+        assert_eq!(in_range.unwrap(), Scalar::from(5u8));
+        assert!(matches!(out_of_range, Err(MpcError::ValidationError(_))));
+    }
+
+    /// Tests opening a value through the commit-and-open protocol
+    #[tokio::test]
+    async fn test_commit_and_open() {
+        let mut rng = thread_rng();
+        let value = Scalar::<TestCurve>::random(&mut rng);
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let shared_value = fabric.share_scalar(value, PARTY0 /* sender */);
+            shared_value.commit_and_open().await
+        })
+        .await;
+
+        assert_eq!(res.unwrap(), value)
+    }
+
+    /// Tests that commit-and-open fails when the peer's share does not match
+    /// its commitment
+    #[tokio::test]
+    async fn test_commit_and_open_tampered_commitment() {
+        let mut rng = thread_rng();
+        let value = Scalar::<TestCurve>::random(&mut rng);
+
+        let (party0_res, _) = execute_mock_mpc(|fabric| async move {
+            let shared_value = fabric.share_scalar(value, PARTY0 /* sender */);
+            let my_share: ScalarResult<TestCurve> =
+                fabric.new_gate_op(shared_value.ids(), |mut args| {
+                    let share: ScalarShare<TestCurve> = args.next().unwrap().into();
+                    ResultValue::Scalar(share.share())
+                });
+            let tampered_share = &my_share + Scalar::one();
+
+            // Party 1 commits to a value other than the share it sends
+            let committed =
+                if fabric.party_id() == PARTY0 { my_share.clone() } else { tampered_share };
+            let my_comm = HashCommitmentResult::commit(committed);
+
+            shared_value.open_committed(my_share, my_comm).await
+        })
+        .await;
+
+        assert!(matches!(party0_res, Err(MpcError::AuthenticationError)))
+    }
+
+    /// Tests opening a shared value between three parties
+    #[tokio::test]
+    async fn test_open_three_party() {
+        let mut rng = thread_rng();
+        let value = Scalar::<TestCurve>::random(&mut rng);
+
+        let res = execute_mock_mpc_n_party(3, |fabric| async move {
+            assert_eq!(fabric.num_parties(), 3);
+            let shared_value = fabric.share_scalar(value, PARTY0 /* sender */);
+            shared_value.open().await
+        })
+        .await;
+
+        assert_eq!(res, vec![value; 3])
+    }
+
+    /// Tests opening a batch of values shared between three parties
+    #[tokio::test]
+    async fn test_open_batch_three_party() {
+        let res = execute_mock_mpc_n_party(3, |fabric| async move {
+            // Each party holds its party ID as a share of each value
+            let id = Scalar::from(fabric.party_id());
+            let shares = (1..=5u8)
+                .map(|i| ScalarShare::new(id * Scalar::from(i), Scalar::zero()))
+                .collect_vec();
+            let shared_values = fabric.allocate_scalar_shares(shares);
+
+            future::join_all(AuthenticatedScalarResult::open_batch(&shared_values)).await
+        })
+        .await;
+
+        // The shares sum to 0 + 1 + 2 = 3 times each value
+        let expected = (1..=5u8).map(|i| Scalar::from(3u8 * i)).collect_vec();
+        assert_eq!(res, vec![expected; 3])
+    }
+
+    // ------------
+    // | Addition |
+    // ------------
+
+    /// Tests addition with a constant value
+    #[tokio::test]
+    async fn test_add_constant() {
+        let mut rng = thread_rng();
+        let a = Scalar::random(&mut rng);
+        let b = Scalar::random(&mut rng);
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let a_shared = fabric.share_scalar(a, PARTY0);
+            let res = &a_shared + b;
+
+            res.open_authenticated().await
+        })
+        .await;
+
+        assert_eq!(res.unwrap(), a + b)
+    }
+
+    /// Tests batch addition with constant values
+    #[tokio::test]
+    async fn test_batch_add_constant() {
+        const N: usize = 100;
+        let mut rng = thread_rng();
+
+        let a = (0..N).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let b = (0..N).map(|_| Scalar::random(&mut rng)).collect_vec();
+
+        let expected_res = a.iter().zip(b.iter()).map(|(a, b)| a + b).collect_vec();
+
+        let (res, _) = execute_mock_mpc(|fabric| {
+            let a = a.clone();
+            let b = b.clone();
+
+            async move {
+                let a_shared = fabric.batch_share_scalar(a, PARTY0 /* sender */);
+                let res = AuthenticatedScalarResult::batch_add_constant(&a_shared, &b);
+                open_await_all(&res).await
+            }
+        })
+        .await;
+
+        assert_eq!(res, expected_res)
+    }
+
+    /// Tests addition with a public value    
+    #[tokio::test]
+    async fn test_add_public() {
+        let mut rng = thread_rng();
+        let a = Scalar::random(&mut rng);
+        let b = Scalar::random(&mut rng);
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let a_shared = fabric.share_scalar(a, PARTY0);
+            let b = fabric.allocate_scalar(b);
             let res = &a_shared + b;
 
             res.open_authenticated().await
@@ -1593,6 +2635,118 @@ mod tests {
         assert_eq!(res, expected_res)
     }
 
+    /// Tests the inner product of two shared value batches
+    #[tokio::test]
+    async fn test_inner_product() {
+        const N: usize = 10;
+        let mut rng = thread_rng();
+        let a = (0..N).map(|_| Scalar::<TestCurve>::random(&mut rng)).collect_vec();
+        let b = (0..N).map(|_| Scalar::<TestCurve>::random(&mut rng)).collect_vec();
+
+        let expected_res: Scalar<TestCurve> = a.iter().zip(b.iter()).map(|(a, b)| a * b).sum();
+
+        let (res, _) = execute_mock_mpc(|fabric| {
+            let a = a.clone();
+            let b = b.clone();
+            async move {
+                let shared_a = fabric.batch_share_scalar(a, PARTY0 /* sender */);
+                let shared_b = fabric.batch_share_scalar(b, PARTY1 /* sender */);
+
+                let res = AuthenticatedScalarResult::inner_product(&shared_a, &shared_b);
+                res.open_authenticated().await
+            }
+        })
+        .await;
+
+        assert_eq!(res.unwrap(), expected_res)
+    }
+
+    /// Tests that each batched multiplication requests its triples from the
+    /// beaver source in a single batch
+    #[tokio::test]
+    async fn test_batched_triple_requests() {
+        const N: usize = 10;
+        let source0 = TripleRequestCounter::new(PARTY0);
+        let source1 = TripleRequestCounter::new(PARTY1);
+        let (counts0, counts1) = (source0.counts.clone(), source1.counts.clone());
+
+        execute_mock_mpc_with_beaver_source(
+            |fabric| async move {
+                let a = fabric.random_shared_scalars(N);
+                let b = fabric.random_shared_scalars(N);
+                let generator = CurvePoint::generator();
+                let points = a.iter().map(|x| x * generator).collect_vec();
+
+                let products = AuthenticatedScalarResult::batch_mul(&a, &b);
+                let inner = AuthenticatedScalarResult::inner_product(&a, &b);
+                let msm = AuthenticatedPointResult::msm(&b, &points);
+
+                future::join_all(products.iter().chain([&inner]).map(|x| x.open())).await;
+                msm.open().await;
+            },
+            source0,
+            source1,
+        )
+        .await;
+
+        for counts in [counts0, counts1] {
+            let (single, batch) = *counts.lock().unwrap();
+            assert_eq!(single, 0);
+            assert_eq!(batch, 3);
+        }
+    }
+
+    /// Tests the product of a batch of shared values
+    #[tokio::test]
+    async fn test_product() {
+        const N: usize = 16;
+        let mut rng = thread_rng();
+        let values = (0..N).map(|_| Scalar::<TestCurve>::random(&mut rng)).collect_vec();
+        let expected_res = values.iter().fold(Scalar::one(), |acc, x| acc * x);
+
+        let (res, _) = execute_mock_mpc(|fabric| {
+            let values = values.clone();
+            async move {
+                let shared_values = fabric.batch_share_scalar(values, PARTY0 /* sender */);
+
+                let res: AuthenticatedScalarResult<TestCurve> = shared_values.into_iter().product();
+                res.open_authenticated().await
+            }
+        })
+        .await;
+
+        assert_eq!(res.unwrap(), expected_res)
+    }
+
+    /// Tests that the product of `N` values allocates exactly `log2(N)` levels
+    /// of batched multiplication, i.e. `log2(N)` rounds of communication
+    #[tokio::test]
+    async fn test_product_rounds() {
+        const N: usize = 16;
+        const LOG_N: usize = 4;
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let values =
+                fabric.batch_share_scalar(vec![Scalar::one(); N], PARTY0 /* sender */);
+
+            // Measure the number of gates allocated for a single batched multiplication
+            let start = fabric.num_gates();
+            let _ = AuthenticatedScalarResult::batch_mul(&values[..1], &values[1..2]);
+            let gates_per_round = fabric.num_gates() - start;
+
+            let start = fabric.num_gates();
+            let prod: AuthenticatedScalarResult<TestCurve> = values.into_iter().product();
+            let n_gates = fabric.num_gates() - start;
+
+            prod.open().await;
+            (n_gates, gates_per_round)
+        })
+        .await;
+
+        let (n_gates, gates_per_round) = res;
+        assert_eq!(n_gates, LOG_N * gates_per_round)
+    }
+
     // ------------
     // | Division |
     // ------------
@@ -1714,6 +2868,33 @@ mod tests {
         assert_eq!(res.unwrap(), expected_res)
     }
 
+    /// Tests the preprocessed values consumed by a batch of inversions
+    #[tokio::test]
+    async fn test_inverse_preprocessing() {
+        const N: usize = 10;
+        let source0 = StatsBeaverSource::new(PartyIDBeaverSource::new(PARTY0));
+        let source1 = StatsBeaverSource::new(PartyIDBeaverSource::new(PARTY1));
+        let (counters0, counters1) = (source0.counters(), source1.counters());
+
+        execute_mock_mpc_with_beaver_source(
+            |fabric| async move {
+                let values = fabric.batch_share_scalar(vec![Scalar::from(2u8); N], PARTY0);
+                let inverses = AuthenticatedScalarResult::batch_inverse(&values);
+                open_await_all(&inverses).await
+            },
+            source0,
+            source1,
+        )
+        .await;
+
+        for counters in [counters0, counters1] {
+            let stats = counters.snapshot();
+            assert_eq!(stats.shared_values, N);
+            assert_eq!(stats.triples, N);
+            assert_eq!(stats.inverse_pairs, 0);
+        }
+    }
+
     // ------------------------------
     // | Misc Arithmetic Operations |
     // ------------------------------
@@ -1738,6 +2919,398 @@ mod tests {
         assert_eq!(res, expected_res)
     }
 
+    /// Tests a conditional swap with the condition unset
+    #[tokio::test]
+    async fn test_conditional_swap_no_swap() {
+        let mut rng = thread_rng();
+        let a = Scalar::<TestCurve>::random(&mut rng);
+        let b = Scalar::<TestCurve>::random(&mut rng);
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let cond = fabric.share_scalar(0u8, PARTY0 /* sender */);
+            let a = fabric.share_scalar(a, PARTY0 /* sender */);
+            let b = fabric.share_scalar(b, PARTY1 /* sender */);
+
+            let (x, y) = AuthenticatedScalarResult::conditional_swap(&cond, a, b);
+            (x.open_authenticated().await.unwrap(), y.open_authenticated().await.unwrap())
+        })
+        .await;
+
+        assert_eq!(res, (a, b))
+    }
+
+    /// Tests a conditional swap with the condition set
+    #[tokio::test]
+    async fn test_conditional_swap_swap() {
+        let mut rng = thread_rng();
+        let a = Scalar::<TestCurve>::random(&mut rng);
+        let b = Scalar::<TestCurve>::random(&mut rng);
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let cond = fabric.share_scalar(1u8, PARTY0 /* sender */);
+            let a = fabric.share_scalar(a, PARTY0 /* sender */);
+            let b = fabric.share_scalar(b, PARTY1 /* sender */);
+
+            let (x, y) = AuthenticatedScalarResult::conditional_swap(&cond, a, b);
+            (x.open_authenticated().await.unwrap(), y.open_authenticated().await.unwrap())
+        })
+        .await;
+
+        assert_eq!(res, (b, a))
+    }
+
+    /// Tests the Hamming weight of a shared bit vector
+    #[tokio::test]
+    async fn test_hamming_weight() {
+        let bits = vec![1u8, 0, 1, 1, 0, 0, 1, 0, 1, 1];
+        let expected = Scalar::from(bits.iter().filter(|&&b| b == 1).count());
+
+        let (res, _) = execute_mock_mpc(|fabric| {
+            let bits = bits.clone();
+            async move {
+                let shared_bits = fabric.batch_share_scalar(bits, PARTY0 /* sender */);
+                let weight = AuthenticatedScalarResult::hamming_weight(&shared_bits);
+
+                weight.open_authenticated().await
+            }
+        })
+        .await;
+
+        assert_eq!(res.unwrap(), expected)
+    }
+
+    /// Encode a signed integer as a scalar
+    fn signed_scalar(x: i64) -> Scalar<TestCurve> {
+        let abs = Scalar::from(x.unsigned_abs());
+        if x < 0 {
+            -abs
+        } else {
+            abs
+        }
+    }
+
+    /// Decode a scalar as a signed integer
+    fn decode_signed(x: Scalar<TestCurve>) -> i128 {
+        match x.to_u128() {
+            Some(x) => x as i128,
+            None => -((-x).to_u128().unwrap() as i128),
+        }
+    }
+
+    /// Tests truncating positive and negative values by various shifts
+    #[tokio::test]
+    async fn test_truncate() {
+        let values = [0i64, 1, 1000, -1, -1000, (1 << 40) + 5, i64::MIN];
+        let shifts = [0usize, 1, 3, 8, 20, 64, 100];
+
+        let cases = values.into_iter().cartesian_product(shifts).collect_vec();
+        let expected = cases
+            .iter()
+            .map(|(x, shift)| signed_scalar((*x as i128 >> shift.min(&127)) as i64))
+            .collect_vec();
+
+        let seed = thread_rng().next_u64();
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| {
+                let cases = cases.clone();
+                async move {
+                    let values = cases.iter().map(|(x, _)| signed_scalar(*x)).collect_vec();
+                    let shared = fabric.batch_share_scalar(values, PARTY0 /* sender */);
+                    let truncated = shared
+                        .iter()
+                        .zip(cases.iter())
+                        .map(|(x, (_, shift))| x.truncate(*shift))
+                        .collect_vec();
+
+                    open_await_all(&truncated).await
+                }
+            },
+            SeededBeaverSource::new(PARTY0, seed),
+            SeededBeaverSource::new(PARTY1, seed),
+        )
+        .await;
+
+        assert_eq!(res, expected)
+    }
+
+    /// Tests reducing values modulo powers of two
+    #[tokio::test]
+    async fn test_mod_power_of_two() {
+        const X: u64 = 0x1234_5678_9abc_def0;
+        let cases = [
+            (signed_scalar(X as i64), 8, Scalar::from(0xf0u64)),
+            (signed_scalar(X as i64), 20, Scalar::from(X & 0xfffff)),
+            (signed_scalar(X as i64), 0, Scalar::zero()),
+            (signed_scalar(-1), 8, Scalar::from(0xffu64)),
+            (signed_scalar(-5), 100, Scalar::from(1u128 << 100) - Scalar::from(5u64)),
+        ];
+
+        let seed = thread_rng().next_u64();
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| async move {
+                let values = cases.iter().map(|(x, ..)| *x).collect_vec();
+                let shared = fabric.batch_share_scalar(values, PARTY0 /* sender */);
+                let reduced = shared
+                    .iter()
+                    .zip(cases.iter())
+                    .map(|(x, (_, k, _))| x.mod_power_of_two(*k))
+                    .collect_vec();
+
+                open_await_all(&reduced).await
+            },
+            SeededBeaverSource::new(PARTY0, seed),
+            SeededBeaverSource::new(PARTY1, seed),
+        )
+        .await;
+
+        let expected = cases.iter().map(|(.., expected)| *expected).collect_vec();
+        assert_eq!(res, expected)
+    }
+
+    /// Tests multiplying fixed-point values
+    #[tokio::test]
+    async fn test_mul_fixed_point() {
+        const PRECISION: u32 = 16;
+        let scale = (1u64 << PRECISION) as f64;
+        let cases = [(3.25, -1.5), (-0.7, -12.125), (1234.5678, 0.001)];
+
+        let encode = |v: f64| signed_scalar((v * scale).round() as i64);
+        let lhs = cases.iter().map(|(a, _)| encode(*a)).collect_vec();
+        let rhs = cases.iter().map(|(_, b)| encode(*b)).collect_vec();
+
+        let seed = thread_rng().next_u64();
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| {
+                let lhs = lhs.clone();
+                let rhs = rhs.clone();
+                async move {
+                    let lhs = fabric.batch_share_scalar(lhs, PARTY0 /* sender */);
+                    let rhs = fabric.batch_share_scalar(rhs, PARTY1 /* sender */);
+                    let products = lhs
+                        .iter()
+                        .zip(rhs.iter())
+                        .map(|(a, b)| a.mul_fixed_point(b, PRECISION))
+                        .collect_vec();
+
+                    open_await_all(&products).await
+                }
+            },
+            SeededBeaverSource::new(PARTY0, seed),
+            SeededBeaverSource::new(PARTY1, seed),
+        )
+        .await;
+
+        for ((a, b), product) in cases.into_iter().zip(res) {
+            let decoded = decode_signed(product) as f64 / scale;
+            // Allow for the rounding of each encoding as well as the truncation
+            let tolerance = (a.abs() + b.abs() + 2.) / scale;
+            assert!((decoded - a * b).abs() <= tolerance, "{decoded} != {a} * {b}");
+        }
+    }
+
+    /// Tests the range check on values in and out of range
+    #[tokio::test]
+    async fn test_range_check() {
+        const NUM_BITS: usize = 8;
+        let cases = [(0, true), (1, true), (200, true), (255, true), (256, false), (-1, false)];
+        let cases = cases.into_iter().chain([(1 << 40, false), (-(1 << 40), false)]).collect_vec();
+
+        let values = cases.iter().map(|(x, _)| signed_scalar(*x)).collect_vec();
+        let expected = cases.iter().map(|(_, in_range)| Scalar::from(*in_range)).collect_vec();
+
+        // Sample random masks rather than the constant bits of the default source
+        let seed = thread_rng().next_u64();
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| {
+                let values = values.clone();
+                async move {
+                    let shared = fabric.batch_share_scalar(values, PARTY0 /* sender */);
+                    let checks = shared.iter().map(|x| x.range_check(NUM_BITS)).collect_vec();
+
+                    open_await_all(&checks).await
+                }
+            },
+            SeededBeaverSource::new(PARTY0, seed),
+            SeededBeaverSource::new(PARTY1, seed),
+        )
+        .await;
+
+        assert_eq!(res, expected)
+    }
+
+    /// Tests asserting a range through a MAC-checked open of the indicator
+    #[tokio::test]
+    async fn test_assert_range() {
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let in_range = fabric.share_scalar(1000u64, PARTY0 /* sender */);
+            let out_of_range = fabric.share_scalar(1u64 << 20, PARTY0 /* sender */);
+
+            (in_range.assert_range(16).await, out_of_range.assert_range(16).await)
+        })
+        .await;
+
+        assert_eq!(res.0, Ok(()));
+        assert!(matches!(res.1, Err(MpcError::ValidationError(_))));
+    }
+
+    /// Tests that the one-hot vector of several indices has a single one at
+    /// the index
+    #[tokio::test]
+    async fn test_one_hot() {
+        const N: usize = 6;
+        let indices = [0usize, 2, N - 1];
+
+        let seed = thread_rng().next_u64();
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| async move {
+                let shared_indices =
+                    fabric.batch_share_scalar(indices.to_vec(), PARTY0 /* sender */);
+
+                let mut vectors = Vec::new();
+                for index in shared_indices.iter() {
+                    let one_hot = AuthenticatedScalarResult::one_hot(index, N);
+                    let sum: AuthenticatedScalarResult<TestCurve> = one_hot.iter().cloned().sum();
+
+                    let sum = sum.open_authenticated().await.unwrap();
+                    vectors.push((sum, open_await_all(&one_hot).await));
+                }
+
+                vectors
+            },
+            SeededBeaverSource::new(PARTY0, seed),
+            SeededBeaverSource::new(PARTY1, seed),
+        )
+        .await;
+
+        for (index, (sum, one_hot)) in indices.into_iter().zip(res) {
+            assert_eq!(sum, Scalar::one());
+            let expected = (0..N).map(|i| Scalar::from(i == index)).collect_vec();
+            assert_eq!(one_hot, expected);
+        }
+    }
+
+    /// Tests reading a shared array at several shared indices
+    #[tokio::test]
+    async fn test_select_index() {
+        const N: usize = 8;
+        let mut rng = thread_rng();
+        let array = (0..N).map(|_| Scalar::<TestCurve>::random(&mut rng)).collect_vec();
+        let indices = [0usize, 3, N - 1];
+        let expected = indices.iter().map(|i| array[*i]).collect_vec();
+
+        let seed = rng.next_u64();
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| {
+                let array = array.clone();
+                async move {
+                    let shared_array = fabric.batch_share_scalar(array, PARTY0 /* sender */);
+                    let shared_indices =
+                        fabric.batch_share_scalar(indices.to_vec(), PARTY1 /* sender */);
+                    let reads = shared_indices
+                        .iter()
+                        .map(|i| AuthenticatedScalarResult::select_index(&shared_array, i))
+                        .collect_vec();
+
+                    open_await_all(&reads).await
+                }
+            },
+            SeededBeaverSource::new(PARTY0, seed),
+            SeededBeaverSource::new(PARTY1, seed),
+        )
+        .await;
+
+        assert_eq!(res, expected)
+    }
+
+    /// Tests writing to a shared array at several shared indices and reading
+    /// the writes back
+    #[tokio::test]
+    async fn test_write_index() {
+        const N: usize = 6;
+        let mut rng = thread_rng();
+        let array = (0..N).map(|_| Scalar::<TestCurve>::random(&mut rng)).collect_vec();
+        let writes = [(1usize, Scalar::random(&mut rng)), (4, Scalar::random(&mut rng))];
+        let writes = writes.into_iter().chain([(1, Scalar::random(&mut rng))]).collect_vec();
+
+        let mut expected = array.clone();
+        for (index, value) in writes.iter() {
+            expected[*index] = *value;
+        }
+
+        let seed = rng.next_u64();
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| {
+                let array = array.clone();
+                let writes = writes.clone();
+                async move {
+                    let mut shared_array =
+                        fabric.batch_share_scalar(array, PARTY0 /* sender */);
+                    for (index, value) in writes {
+                        let index = fabric.share_scalar(index, PARTY1 /* sender */);
+                        let value = fabric.share_scalar(value, PARTY1 /* sender */);
+                        AuthenticatedScalarResult::write_index(&mut shared_array, &index, &value);
+                    }
+
+                    let index = fabric.share_scalar(4usize, PARTY0 /* sender */);
+                    let read = AuthenticatedScalarResult::select_index(&shared_array, &index);
+                    (open_await_all(&shared_array).await, read.open_authenticated().await.unwrap())
+                }
+            },
+            SeededBeaverSource::new(PARTY0, seed),
+            SeededBeaverSource::new(PARTY1, seed),
+        )
+        .await;
+
+        assert_eq!(res.1, expected[4]);
+        assert_eq!(res.0, expected);
+    }
+
+    /// Tests interpolating a polynomial through shared values at public points
+    #[tokio::test]
+    async fn test_interpolate() {
+        const N: usize = 5;
+        let mut rng = thread_rng();
+
+        // A random polynomial of degree `N - 1`
+        let coeffs = (0..N).map(|_| Scalar::<TestCurve>::random(&mut rng)).collect_vec();
+        let eval =
+            |x: Scalar<TestCurve>| coeffs.iter().rev().fold(Scalar::zero(), |acc, c| acc * x + c);
+
+        let xs = (1..=N as u64).map(Scalar::from).collect_vec();
+        let ys = xs.iter().map(|x| eval(*x)).collect_vec();
+        let eval_point = Scalar::random(&mut rng);
+        let expected = eval(eval_point);
+
+        let (res, _) = execute_mock_mpc(|fabric| {
+            let xs = xs.clone();
+            let ys = ys.clone();
+            async move {
+                let values = fabric.batch_share_scalar(ys, PARTY0 /* sender */);
+                let points = fabric.allocate_scalars(xs);
+                let eval_at = fabric.allocate_scalar(eval_point);
+
+                let res = AuthenticatedScalarResult::interpolate(&points, &values, &eval_at);
+                res.open_authenticated().await
+            }
+        })
+        .await;
+
+        assert_eq!(res.unwrap(), expected)
+    }
+
+    /// Tests that interpolating through a repeated x-coordinate is rejected
+    #[tokio::test]
+    async fn test_interpolate_duplicate_points() {
+        let fabric = mock_fabric();
+        let xs = [1u8, 2, 1].map(Scalar::from);
+        let values = (0..xs.len()).map(|_| fabric.one_authenticated()).collect_vec();
+        let eval_at = fabric.allocate_scalar(3u8);
+
+        let res = AuthenticatedScalarResult::interpolate_public(&xs, &values, &eval_at);
+        assert!(matches!(res, Err(MpcError::ArithmeticError(_))));
+        fabric.shutdown();
+    }
+
     #[tokio::test]
     async fn test_fft() {
         let mut rng = thread_rng();
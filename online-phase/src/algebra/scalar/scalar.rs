@@ -6,20 +6,21 @@
 
 use std::{
     cmp::Ordering,
-    fmt::{Display, Formatter, Result as FmtResult},
+    fmt::{Display, Formatter, LowerHex, Result as FmtResult},
     iter::{Product, Sum},
     ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign},
 };
 
 use ark_ec::CurveGroup;
 use ark_ff::{batch_inversion, Field, One, PrimeField, Zero};
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
 use ark_std::UniformRand;
 use itertools::Itertools;
 use num_bigint::BigUint;
 use num_traits::Num;
 use rand::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
+use subtle::{Choice, ConstantTimeEq};
 
 use crate::algebra::{macros::*, ToBytes};
 
@@ -99,6 +100,49 @@ impl<C: CurveGroup> Scalar<C> {
         }
     }
 
+    /// Add two batches of scalars element-wise
+    #[cfg(feature = "batch_arithmetic")]
+    pub fn batch_add(a: &[Self], b: &[Self]) -> Vec<Self> {
+        Self::batch_binary_op(a, b, |x, y| x + y)
+    }
+
+    /// Multiply two batches of public scalars element-wise
+    #[cfg(feature = "batch_arithmetic")]
+    pub fn batch_mul_public(a: &[Self], b: &[Self]) -> Vec<Self> {
+        Self::batch_binary_op(a, b, |x, y| x * y)
+    }
+
+    /// Apply a binary operation to two batches of scalars element-wise
+    ///
+    /// The loop is unrolled over chunks of `BATCH_UNROLL` elements, operating
+    /// on the underlying field elements directly, so that the compiler may
+    /// interleave the independent operations within a chunk
+    #[cfg(feature = "batch_arithmetic")]
+    fn batch_binary_op<F>(a: &[Self], b: &[Self], op: F) -> Vec<Self>
+    where
+        F: Fn(C::ScalarField, C::ScalarField) -> C::ScalarField,
+    {
+        /// The number of elements operated on per iteration
+        const BATCH_UNROLL: usize = 4;
+        assert_eq!(a.len(), b.len(), "batch operands must be of equal length");
+
+        let mut res = Vec::with_capacity(a.len());
+        let a_chunks = a.chunks_exact(BATCH_UNROLL);
+        let b_chunks = b.chunks_exact(BATCH_UNROLL);
+        let (a_rem, b_rem) = (a_chunks.remainder(), b_chunks.remainder());
+        for (x, y) in a_chunks.zip(b_chunks) {
+            res.extend([
+                Scalar(op(x[0].0, y[0].0)),
+                Scalar(op(x[1].0, y[1].0)),
+                Scalar(op(x[2].0, y[2].0)),
+                Scalar(op(x[3].0, y[3].0)),
+            ]);
+        }
+
+        res.extend(a_rem.iter().zip(b_rem).map(|(x, y)| Scalar(op(x.0, y.0))));
+        res
+    }
+
     /// Compute the exponentiation of the given scalar
     pub fn pow(&self, exp: u64) -> Self {
         Scalar::new(self.0.pow([exp]))
@@ -131,6 +175,12 @@ impl<C: CurveGroup> Scalar<C> {
         self.0.into()
     }
 
+    /// Convert the underlying value to a `u128`, or `None` if it does not fit
+    /// in 128 bits
+    pub fn to_u128(&self) -> Option<u128> {
+        u128::try_from(self.to_biguint()).ok()
+    }
+
     /// Convert from a `BigUint`
     pub fn from_biguint(val: &BigUint) -> Self {
         let le_bytes = val.to_bytes_le();
@@ -184,6 +234,15 @@ impl<C: CurveGroup> Display for Scalar<C> {
     }
 }
 
+/// Formats the canonical value in hex, the alternate form (`{:#x}`) adds the
+/// `0x` prefix used for Starknet felts
+impl<C: CurveGroup> LowerHex for Scalar<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        LowerHex::fmt(&self.to_biguint(), f)
+    }
+}
+
+/// Scalars serialize as their canonical little-endian encoding
 impl<C: CurveGroup> Serialize for Scalar<C> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let mut bytes = Vec::with_capacity(n_bytes_field::<C::ScalarField>());
@@ -192,9 +251,19 @@ impl<C: CurveGroup> Serialize for Scalar<C> {
     }
 }
 
+/// Deserialization rejects encodings of the wrong length and encodings of
+/// values not reduced modulo the field's modulus
 impl<'de, C: CurveGroup> Deserialize<'de> for Scalar<C> {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         let bytes = <Vec<u8>>::deserialize(deserializer)?;
+        let n_bytes = n_bytes_field::<C::ScalarField>();
+        if bytes.len() != n_bytes {
+            return Err(serde::de::Error::invalid_length(
+                bytes.len(),
+                &"a canonical field encoding",
+            ));
+        }
+
         let inner = C::ScalarField::deserialize_uncompressed(bytes.as_slice())
             .map_err(serde::de::Error::custom)?;
         Ok(Scalar(inner))
@@ -343,6 +412,23 @@ impl<C: CurveGroup> From<Scalar<C>> for BigUint {
     }
 }
 
+/// Parses the big-endian encoding output by `to_bytes_be`, rejecting
+/// encodings of the wrong length and encodings of values not reduced modulo
+/// the field's modulus
+impl<C: CurveGroup> TryFrom<&[u8]> for Scalar<C> {
+    type Error = SerializationError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() != n_bytes_field::<C::ScalarField>() {
+            return Err(SerializationError::InvalidData);
+        }
+
+        let le_bytes = bytes.iter().rev().copied().collect_vec();
+        let inner = C::ScalarField::deserialize_uncompressed(le_bytes.as_slice())?;
+        Ok(Scalar(inner))
+    }
+}
+
 // -------------------
 // | Iterator Traits |
 // -------------------
@@ -359,6 +445,13 @@ impl<C: CurveGroup> Product for Scalar<C> {
     }
 }
 
+/// Compares the canonical encodings of the scalars in constant time
+impl<C: CurveGroup> ConstantTimeEq for Scalar<C> {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.to_bytes_be().ct_eq(&other.to_bytes_be())
+    }
+}
+
 impl<C: CurveGroup> PartialOrd for Scalar<C> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -374,14 +467,20 @@ impl<C: CurveGroup> Ord for Scalar<C> {
 #[cfg(test)]
 mod test {
     use crate::{
-        algebra::{poly_test_helpers::TestPolyField, scalar::Scalar, ScalarResult},
+        algebra::{
+            poly_test_helpers::TestPolyField,
+            scalar::{n_bytes_field, Scalar},
+            ScalarResult,
+        },
         test_helpers::{execute_mock_mpc, mock_fabric, TestCurve},
     };
-    use ark_ff::Field;
+    use ark_ff::{BigInteger, Field, PrimeField};
     use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
     use futures::future;
     use itertools::Itertools;
+    use num_bigint::BigUint;
     use rand::{thread_rng, Rng, RngCore};
+    use subtle::ConstantTimeEq;
 
     /// Tests serialization and deserialization of scalars
     #[test]
@@ -394,6 +493,101 @@ mod test {
         assert_eq!(scalar, deserialized);
     }
 
+    /// Tests parsing scalars from big-endian byte buffers
+    #[test]
+    fn test_scalar_try_from_bytes() {
+        let mut rng = thread_rng();
+        let scalar = Scalar::<TestCurve>::random(&mut rng);
+        let bytes = scalar.to_bytes_be();
+        assert_eq!(Scalar::try_from(bytes.as_slice()).unwrap(), scalar);
+
+        // Wrong length
+        assert!(Scalar::<TestCurve>::try_from(&bytes[1..]).is_err());
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert!(Scalar::<TestCurve>::try_from(padded.as_slice()).is_err());
+
+        // Not reduced modulo the field's modulus
+        let modulus = TestPolyField::MODULUS.to_bytes_be();
+        assert!(Scalar::<TestCurve>::try_from(modulus.as_slice()).is_err());
+    }
+
+    /// Tests converting scalars to and from `u128`s
+    #[test]
+    fn test_u128_round_trip() {
+        let mut rng = thread_rng();
+        for value in [0, 1, rng.gen(), u128::MAX] {
+            assert_eq!(Scalar::<TestCurve>::from(value).to_u128(), Some(value));
+        }
+
+        let too_large = Scalar::<TestCurve>::from(u128::MAX) + Scalar::one();
+        assert_eq!(too_large.to_u128(), None);
+        assert_eq!((-Scalar::<TestCurve>::one()).to_u128(), None);
+    }
+
+    /// Tests batched addition and multiplication against element-wise ops
+    #[test]
+    #[cfg(feature = "batch_arithmetic")]
+    fn test_batch_arithmetic() {
+        let mut rng = thread_rng();
+
+        // Use a length that is not a multiple of the unrolling width
+        const N: usize = 103;
+        let a = (0..N).map(|_| Scalar::<TestCurve>::random(&mut rng)).collect_vec();
+        let b = (0..N).map(|_| Scalar::<TestCurve>::random(&mut rng)).collect_vec();
+
+        let expected_sum = a.iter().zip(b.iter()).map(|(x, y)| x + y).collect_vec();
+        let expected_prod = a.iter().zip(b.iter()).map(|(x, y)| x * y).collect_vec();
+        assert_eq!(Scalar::batch_add(&a, &b), expected_sum);
+        assert_eq!(Scalar::batch_mul_public(&a, &b), expected_prod);
+        assert!(Scalar::<TestCurve>::batch_add(&[], &[]).is_empty());
+    }
+
+    /// Tests that constant-time equality agrees with `==`
+    #[test]
+    fn test_scalar_ct_eq() {
+        let mut rng = thread_rng();
+        let a = Scalar::<TestCurve>::random(&mut rng);
+        let b = a + Scalar::one();
+
+        assert!(bool::from(a.ct_eq(&(b - Scalar::one()))));
+        assert!(!bool::from(a.ct_eq(&b)));
+        assert!(bool::from((a - a).ct_eq(&Scalar::zero())));
+    }
+
+    /// Tests the decimal and hex formatting of a scalar
+    #[test]
+    fn test_scalar_formatting() {
+        let scalar = Scalar::<TestCurve>::from(4660u64);
+        assert_eq!(scalar.to_string(), "4660");
+        assert_eq!(format!("{scalar:x}"), "1234");
+        assert_eq!(format!("{scalar:#x}"), "0x1234");
+
+        let modulus = BigUint::from(TestPolyField::MODULUS);
+        assert_eq!(format!("{:x}", -Scalar::<TestCurve>::one()), format!("{:x}", modulus - 1u8));
+    }
+
+    /// Tests deserializing scalars from known encodings
+    #[test]
+    fn test_scalar_deserialization() {
+        // The little-endian encoding of 258
+        let mut bytes = vec![0u8; n_bytes_field::<TestPolyField>()];
+        bytes[..2].copy_from_slice(&[2, 1]);
+        let encoding = serde_json::to_vec(&bytes).unwrap();
+        let scalar: Scalar<TestCurve> = serde_json::from_slice(&encoding).unwrap();
+        assert_eq!(scalar, Scalar::from(258u64));
+
+        // The modulus is not a canonical encoding
+        let modulus = TestPolyField::MODULUS.to_bytes_le();
+        let encoding = serde_json::to_vec(&modulus).unwrap();
+        assert!(serde_json::from_slice::<Scalar<TestCurve>>(&encoding).is_err());
+
+        // Nor is an encoding with trailing bytes
+        bytes.push(0);
+        let encoding = serde_json::to_vec(&bytes).unwrap();
+        assert!(serde_json::from_slice::<Scalar<TestCurve>>(&encoding).is_err());
+    }
+
     /// Tests addition of raw scalars in a circuit
     #[tokio::test]
     async fn test_scalar_add() {
@@ -2,7 +2,7 @@
 //! `Scalar`
 
 use std::{
-    iter::Product,
+    iter::{Product, Sum},
     ops::{Add, Mul, Neg, Sub},
 };
 
@@ -50,6 +50,7 @@ impl<C: CurveGroup> ScalarResult<C> {
     pub fn batch_inverse(values: &[ScalarResult<C>]) -> Vec<ScalarResult<C>> {
         let n = values.len();
         let fabric = &values[0].fabric;
+        fabric.assert_same_fabric(values);
         let ids = values.iter().map(|v| v.id).collect_vec();
 
         fabric.new_batch_gate_op(ids, n /* output_arity */, move |args| {
@@ -81,6 +82,7 @@ impl<C: CurveGroup> Add<&ScalarResult<C>> for &ScalarResult<C> {
     type Output = ScalarResult<C>;
 
     fn add(self, rhs: &ScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         self.fabric.new_gate_op(vec![self.id, rhs.id], |mut args| {
             let lhs: Scalar<C> = args.next().unwrap().into();
             let rhs: Scalar<C> = args.next().unwrap().into();
@@ -97,6 +99,8 @@ impl<C: CurveGroup> ScalarResult<C> {
 
         let n = a.len();
         let fabric = &a[0].fabric;
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let lhs = a.iter().map(|v| v.id);
         let rhs = b.iter().map(|v| v.id);
@@ -121,6 +125,7 @@ impl<C: CurveGroup> ScalarResult<C> {
 
         let n = a.len();
         let fabric = &a[0].fabric;
+        fabric.assert_same_fabric(a);
         let b = b.to_vec();
 
         let ids = a.iter().map(|v| v.id).collect_vec();
@@ -168,6 +173,7 @@ impl<C: CurveGroup> Sub<&ScalarResult<C>> for &ScalarResult<C> {
     type Output = ScalarResult<C>;
 
     fn sub(self, rhs: &ScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         self.fabric.new_gate_op(vec![self.id, rhs.id], |mut args| {
             let lhs: Scalar<C> = args.next().unwrap().into();
             let rhs: Scalar<C> = args.next().unwrap().into();
@@ -184,6 +190,8 @@ impl<C: CurveGroup> ScalarResult<C> {
 
         let n = a.len();
         let fabric = &a[0].fabric;
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let lhs = a.iter().map(|v| v.id);
         let rhs = b.iter().map(|v| v.id);
@@ -207,6 +215,7 @@ impl<C: CurveGroup> ScalarResult<C> {
 
         let n = a.len();
         let fabric = &a[0].fabric;
+        fabric.assert_same_fabric(a);
         let b = b.to_vec();
 
         let ids = a.iter().map(|v| v.id).collect_vec();
@@ -242,6 +251,7 @@ impl<C: CurveGroup> Mul<&ScalarResult<C>> for &ScalarResult<C> {
     type Output = ScalarResult<C>;
 
     fn mul(self, rhs: &ScalarResult<C>) -> Self::Output {
+        self.assert_same_fabric(rhs);
         self.fabric.new_gate_op(vec![self.id, rhs.id], |mut args| {
             let lhs: Scalar<C> = args.next().unwrap().into();
             let rhs: Scalar<C> = args.next().unwrap().into();
@@ -259,6 +269,8 @@ impl<C: CurveGroup> ScalarResult<C> {
 
         let n = a.len();
         let fabric = &a[0].fabric;
+        fabric.assert_same_fabric(a);
+        fabric.assert_same_fabric(b);
 
         let lhs = a.iter().map(|v| v.id);
         let rhs = b.iter().map(|v| v.id);
@@ -283,6 +295,7 @@ impl<C: CurveGroup> ScalarResult<C> {
 
         let n = a.len();
         let fabric = &a[0].fabric;
+        fabric.assert_same_fabric(a);
         let b = b.to_vec();
 
         let ids = a.iter().map(|v| v.id).collect_vec();
@@ -315,6 +328,7 @@ impl<C: CurveGroup> ScalarResult<C> {
     pub fn batch_neg(a: &[ScalarResult<C>]) -> Vec<ScalarResult<C>> {
         let n = a.len();
         let fabric = &a[0].fabric;
+        fabric.assert_same_fabric(a);
         let ids = a.iter().map(|v| v.id).collect_vec();
         fabric.new_batch_gate_op(ids, n /* output_arity */, move |args| {
             args.into_iter().map(Scalar::from).map(|x| -x).map(ResultValue::Scalar).collect_vec()
@@ -322,13 +336,34 @@ impl<C: CurveGroup> ScalarResult<C> {
     }
 }
 
+impl<C: CurveGroup> Sum for ScalarResult<C> {
+    /// Assumes the iterator is non-empty, prefer `MpcFabric::sum` where the
+    /// iterator may be empty
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        let values: Vec<Self> = iter.collect_vec();
+        assert!(!values.is_empty(), "Cannot compute sum of empty iterator");
+
+        let ids = values.iter().map(|v| v.id()).collect_vec();
+        let fabric = values[0].fabric();
+        fabric.assert_same_fabric(&values);
+
+        fabric.new_gate_op(ids, move |args| {
+            let res = args.map(Scalar::from).sum();
+            ResultValue::Scalar(res)
+        })
+    }
+}
+
 impl<C: CurveGroup> Product for ScalarResult<C> {
+    /// Assumes the iterator is non-empty, prefer `MpcFabric::product` where
+    /// the iterator may be empty
     fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
         let values: Vec<Self> = iter.collect_vec();
         assert!(!values.is_empty(), "Cannot compute product of empty iterator");
 
         let ids = values.iter().map(|v| v.id()).collect_vec();
         let fabric = values[0].fabric();
+        fabric.assert_same_fabric(&values);
 
         fabric.new_gate_op(ids, move |args| {
             let res = args.map(Scalar::from).product();
@@ -359,6 +394,7 @@ where
         let n = domain.size();
 
         let fabric = x[0].fabric();
+        fabric.assert_same_fabric(x);
         let ids = x.iter().map(|v| v.id).collect_vec();
 
         fabric.new_batch_gate_op(ids, n /* output_arity */, move |args| {
@@ -388,6 +424,7 @@ where
         let n = domain.size();
 
         let fabric = x[0].fabric();
+        fabric.assert_same_fabric(x);
         let ids = x.iter().map(|v| v.id).collect_vec();
 
         fabric.new_batch_gate_op(ids, n /* output_arity */, move |args| {
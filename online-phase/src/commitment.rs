@@ -3,8 +3,8 @@
 
 use ark_ec::CurveGroup;
 use itertools::Itertools;
-use rand::thread_rng;
 use sha3::{Digest, Sha3_256};
+use subtle::ConstantTimeEq;
 
 use crate::{
     algebra::{Scalar, ScalarResult, ToBytes},
@@ -39,7 +39,7 @@ impl<C: CurveGroup, T: From<ResultValue<C>> + ToBytes> HashCommitment<C, T> {
         let out_bytes = hasher.finalize();
         let out = Scalar::from_be_bytes_mod_order(out_bytes.as_slice());
 
-        out == self.commitment
+        out.ct_eq(&self.commitment).into()
     }
 }
 
@@ -63,9 +63,9 @@ impl<C: CurveGroup, T: From<ResultValue<C>> + ToBytes> HashCommitmentResult<C, T
     pub(crate) fn batch_commit(values: Vec<ResultHandle<C, T>>) -> HashCommitmentResult<C, T> {
         assert!(!values.is_empty(), "Cannot commit to an empty set of values");
         let fabric = &values[0].fabric;
+        fabric.assert_same_fabric(&values);
 
-        let mut rng = thread_rng();
-        let blinder = Scalar::random(&mut rng);
+        let blinder = fabric.random_blinder();
         let ids = values.iter().map(|v| v.id()).collect_vec();
 
         let comm = fabric.new_gate_op(ids, move |args| {
@@ -3,6 +3,8 @@ use std::{error::Error, fmt::Display};
 
 use quinn::{ConnectError, ConnectionError};
 
+use crate::fabric::ResultId;
+
 /// An application level error that results from an error deeper in the MPC
 /// stack
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -15,6 +17,30 @@ pub enum MpcError {
     VisibilityError(String),
     /// An error performing an arithmetic operation
     ArithmeticError(String),
+    /// The computation was cancelled before the result was available
+    Cancelled,
+    /// An opened value failed a caller-provided validation predicate
+    ValidationError(String),
+    /// An operation referenced an opcode that is not registered with the
+    /// fabric
+    UnknownOpcode(String),
+    /// A trace was replayed on a different number of inputs than it was
+    /// recorded with
+    TraceInputMismatch {
+        /// The number of inputs the trace was recorded with
+        expected: usize,
+        /// The number of inputs given to the replay
+        actual: usize,
+    },
+    /// An operation depends on the given result, which is either its own
+    /// output or has not yet been allocated, so the operation can never run
+    DependencyCycle(ResultId),
+    /// The peer missed too many heartbeats and is presumed dead, so pending
+    /// results that depend on it will never be produced
+    PeerUnreachable,
+    /// An error in the offline phase, e.g. the preprocessed values it serves
+    /// are exhausted
+    PreprocessingError(PreprocessingError),
 }
 
 impl Display for MpcError {
@@ -63,4 +89,27 @@ pub enum SetupError {
     NoIncomingConnection,
     /// An error setting up the QUIC server on the local node
     ServerSetupError,
+    /// An error establishing a TCP connection to the peer
+    TcpError(String),
+    /// An error authenticating the peer or agreeing on session keys in an
+    /// encrypted transport's handshake
+    HandshakeError(String),
+}
+
+/// An error reading or consuming values from the offline phase
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PreprocessingError {
+    /// An error reading or writing a file of preprocessed values
+    IoError(String),
+    /// A file of preprocessed values is malformed
+    InvalidFormat(String),
+    /// The source has no more values of the given kind
+    Exhausted(String),
+}
+
+impl Display for PreprocessingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
 }
+impl Error for PreprocessingError {}
@@ -7,16 +7,25 @@
 //! offline phase implementation to allocate values.
 
 mod executor;
+mod graph;
 mod network_sender;
 mod result;
+mod trace;
 
 use ark_ec::CurveGroup;
-pub use executor::ExecutorSizeHints;
-#[cfg(not(feature = "benchmarks"))]
-use executor::{single_threaded::SerialExecutor, ExecutorMessage};
 #[cfg(feature = "benchmarks")]
-pub use executor::{single_threaded::SerialExecutor, ExecutorMessage, GrowableBuffer};
-pub use result::{ResultHandle, ResultId, ResultValue};
+pub use executor::GrowableBuffer;
+pub use executor::{
+    single_threaded::SerialExecutor, ExecutorBackend, ExecutorJobQueue, ExecutorMessage,
+    ExecutorSizeHints, TraceHook,
+};
+#[cfg(feature = "stats")]
+pub use executor::{BufferStats, ExecutorStats, QueueLengthHistogram, QUEUE_LENGTH_BUCKETS};
+pub use network_sender::{HeartbeatConfig, NetworkStats};
+use result::ResultGuard;
+use result::ERR_MIXED_FABRICS;
+pub use result::{CancellableResult, OpResult, ResultHandle, ResultId, ResultValue, ResultWaiter};
+pub use trace::{CircuitTrace, Opcode, OpcodeFn, TracedOp};
 
 use futures::executor::block_on;
 use tracing::log;
@@ -24,32 +33,50 @@ use tracing::log;
 use crossbeam::queue::SegQueue;
 use kanal::Sender as KanalSender;
 use std::{
+    collections::HashMap,
     fmt::{Debug, Formatter, Result as FmtResult},
+    iter::{self, Product, Sum},
+    mem,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self as std_mpsc, RecvTimeoutError},
+        Arc, Mutex, OnceLock, RwLock, Weak,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+use tokio::{
+    runtime::Handle,
     sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc, Mutex,
+        broadcast::{self, Sender as BroadcastSender},
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        watch::{self, Sender as WatchSender},
     },
 };
-use tokio::sync::broadcast::{self, Sender as BroadcastSender};
 
 use itertools::Itertools;
+use rand::thread_rng;
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
 
 use crate::{
     algebra::{
         AuthenticatedPointResult, AuthenticatedScalarResult, CurvePoint, CurvePointResult,
-        PointShare, Scalar, ScalarResult, ScalarShare,
+        FixedBaseTable, PointShare, Scalar, ScalarResult, ScalarShare,
     },
+    commitment::{HashCommitment, HashCommitmentResult},
+    error::{MpcError, PreprocessingError},
     network::{MpcNetwork, NetworkOutbound, NetworkPayload, PartyId},
-    offline_prep::PreprocessingPhase,
+    offline_prep::{AsyncPreprocessingPhase, PreprocessingPhase},
     PARTY0,
 };
 
 #[cfg(feature = "multithreaded_executor")]
 use self::executor::multi_threaded::ParallelExecutor;
 use self::{
-    executor::ExecutorJobQueue,
+    graph::{render_dot, GraphOp},
     network_sender::NetworkSender,
-    result::{OpResult, ResultWaiter},
+    trace::OpcodeRegistry,
 };
 
 /// The result id that is hardcoded to zero
@@ -67,7 +94,11 @@ const RESULT_SHARED_IDENTITY: ResultId = 5;
 
 /// The number of constant results allocated in the fabric, i.e. those defined
 /// above
-const N_CONSTANT_RESULTS: usize = 6;
+pub(crate) const N_CONSTANT_RESULTS: usize = 6;
+
+/// Error message emitted when the task serving an asynchronous triple source
+/// has stopped
+const ERR_ASYNC_TRIPLES_STOPPED: &str = "asynchronous triple source stopped";
 
 /// A type alias for the identifier used for a gate
 pub type OperationId = usize;
@@ -97,6 +128,16 @@ impl<C: CurveGroup> Operation<C> {
     pub fn result_ids(&self) -> Vec<ResultId> {
         (self.result_id..self.result_id + self.output_arity).collect_vec()
     }
+
+    /// Get the IDs of the operation's arguments
+    pub fn args(&self) -> &[ResultId] {
+        &self.args
+    }
+
+    /// Get the kind of the operation
+    pub fn kind(&self) -> OperationKind {
+        self.op_type.kind()
+    }
 }
 
 impl<C: CurveGroup> Debug for Operation<C> {
@@ -131,6 +172,39 @@ pub enum OperationType<C: CurveGroup> {
         #[allow(clippy::type_complexity)]
         function: Box<dyn FnOnce(BoxedResultIter<C>) -> NetworkPayload<C> + Send + Sync>,
     },
+    /// A gate operation whose function is registered with the fabric under an
+    /// opcode, so that the operation may be traced and replayed
+    Registered {
+        /// The opcode under which the function is registered
+        opcode: Opcode,
+        /// The registered function
+        function: OpcodeFn<C>,
+    },
+}
+
+impl<C: CurveGroup> OperationType<C> {
+    /// Get the kind of the operation
+    pub fn kind(&self) -> OperationKind {
+        match self {
+            OperationType::Gate { .. } => OperationKind::Gate,
+            OperationType::GateBatch { .. } => OperationKind::GateBatch,
+            OperationType::Network { .. } => OperationKind::Network,
+            OperationType::Registered { .. } => OperationKind::Registered,
+        }
+    }
+}
+
+/// The kind of an operation, without its function
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OperationKind {
+    /// A gate operation
+    Gate,
+    /// A gate operation with output arity greater than one
+    GateBatch,
+    /// A network operation
+    Network,
+    /// A gate operation registered under an opcode
+    Registered,
 }
 
 /// A clone implementation, never concretely called but used as a Marker type to
@@ -147,10 +221,64 @@ impl<C: CurveGroup> Debug for OperationType<C> {
             OperationType::Gate { .. } => write!(f, "Gate"),
             OperationType::GateBatch { .. } => write!(f, "GateBatch"),
             OperationType::Network { .. } => write!(f, "Network"),
+            OperationType::Registered { opcode, .. } => write!(f, "Registered({opcode})"),
         }
     }
 }
 
+/// A value type with an additive identity wire in the fabric
+///
+/// Used to give the empty sum a value, as a `Sum` implementation cannot
+/// allocate a wire without a handle to the fabric
+pub trait AdditiveIdentity<C: CurveGroup>: Sized {
+    /// Get the additive identity wire from the fabric
+    fn additive_identity(fabric: &MpcFabric<C>) -> Self;
+}
+
+impl<C: CurveGroup> AdditiveIdentity<C> for ScalarResult<C> {
+    fn additive_identity(fabric: &MpcFabric<C>) -> Self {
+        fabric.zero()
+    }
+}
+
+impl<C: CurveGroup> AdditiveIdentity<C> for AuthenticatedScalarResult<C> {
+    fn additive_identity(fabric: &MpcFabric<C>) -> Self {
+        fabric.zero_authenticated()
+    }
+}
+
+impl<C: CurveGroup> AdditiveIdentity<C> for CurvePointResult<C> {
+    fn additive_identity(fabric: &MpcFabric<C>) -> Self {
+        fabric.curve_identity()
+    }
+}
+
+impl<C: CurveGroup> AdditiveIdentity<C> for AuthenticatedPointResult<C> {
+    fn additive_identity(fabric: &MpcFabric<C>) -> Self {
+        fabric.curve_identity_authenticated()
+    }
+}
+
+/// A value type with a multiplicative identity wire in the fabric
+///
+/// The counterpart of `AdditiveIdentity` for the empty product
+pub trait MultiplicativeIdentity<C: CurveGroup>: Sized {
+    /// Get the multiplicative identity wire from the fabric
+    fn multiplicative_identity(fabric: &MpcFabric<C>) -> Self;
+}
+
+impl<C: CurveGroup> MultiplicativeIdentity<C> for ScalarResult<C> {
+    fn multiplicative_identity(fabric: &MpcFabric<C>) -> Self {
+        fabric.one()
+    }
+}
+
+impl<C: CurveGroup> MultiplicativeIdentity<C> for AuthenticatedScalarResult<C> {
+    fn multiplicative_identity(fabric: &MpcFabric<C>) -> Self {
+        fabric.one_authenticated()
+    }
+}
+
 /// A fabric for the MPC protocol, defines a dependency injection layer that
 /// dynamically schedules circuit gate evaluations onto the network to be
 /// executed
@@ -196,6 +324,8 @@ impl<C: CurveGroup> Debug for MpcFabric<C> {
 pub struct FabricInner<C: CurveGroup> {
     /// The ID of the local party in the MPC execution
     party_id: u64,
+    /// The number of parties in the MPC execution
+    num_parties: usize,
     /// The next identifier to assign to a result
     next_result_id: Arc<AtomicUsize>,
     /// The next identifier to assign to an operation
@@ -206,6 +336,98 @@ pub struct FabricInner<C: CurveGroup> {
     outbound_queue: KanalSender<NetworkOutbound<C>>,
     /// The underlying shared randomness source
     offline_phase: Arc<Mutex<Box<dyn PreprocessingPhase<C>>>>,
+    /// The queue of requests to the asynchronous triple source, if one is
+    /// set; each request holds the result IDs reserved for a batch of triples
+    async_triples: Arc<Mutex<Option<UnboundedSender<Vec<ResultId>>>>>,
+    /// The number of Beaver triples consumed from the offline phase
+    n_triples_consumed: Arc<AtomicUsize>,
+    /// The number of Beaver triples the shared multiplications issued so far
+    /// expect to consume
+    n_triples_expected: Arc<AtomicUsize>,
+    /// Whether to validate that each multiplication consumes exactly one triple
+    validate_triples: Arc<AtomicBool>,
+    /// Whether to check each triple by sacrificing another before it is used
+    sacrifice_triples: Arc<AtomicBool>,
+    /// Whether the fabric's pending computation has been cancelled
+    cancelled: Arc<AtomicBool>,
+    /// The error that caused the fabric to cancel, if it was not cancelled by
+    /// the caller
+    cancel_reason: Arc<Mutex<Option<MpcError>>>,
+    /// The depth of each result produced by an operation or received from the
+    /// peer, other results have depth zero
+    depths: Arc<Mutex<HashMap<ResultId, usize>>>,
+    /// The depth of the most recently allocated network op
+    last_network_depth: Arc<AtomicUsize>,
+    /// The maximum depth of any result in the circuit
+    max_depth: Arc<AtomicUsize>,
+    /// The seeded source of local blinding randomness, if deterministic
+    /// execution is enabled
+    seeded_rng: Arc<Mutex<Option<ChaCha20Rng>>>,
+    /// The functions registered for traceable operations
+    opcodes: Arc<RwLock<OpcodeRegistry<C>>>,
+    /// The registered operations recorded since tracing began, `None` if the
+    /// fabric is not tracing
+    trace: Arc<Mutex<Option<Vec<TracedOp>>>>,
+    /// Whether the fabric is recording the dependency graph, checked before
+    /// locking the graph so that allocation does not contend on the lock
+    recording_graph: Arc<AtomicBool>,
+    /// The operations recorded for the dependency graph, `None` if the fabric
+    /// is not recording the graph
+    op_graph: Arc<Mutex<Option<Vec<GraphOp>>>>,
+    /// The IDs of the interned public scalars, `None` if the fabric is not
+    /// interning constants
+    #[allow(clippy::type_complexity)]
+    interned_scalars: Arc<Mutex<Option<HashMap<Scalar<C>, ResultId>>>>,
+    /// The precomputed table of multiples of the curve generator, built on
+    /// first use
+    generator_table: Arc<OnceLock<Arc<FixedBaseTable<C>>>>,
+    /// Whether result garbage collection is enabled
+    result_gc: Arc<AtomicBool>,
+    /// The number of results held by the executor since GC was enabled
+    n_live_results: Arc<AtomicUsize>,
+    /// The MAC checks deferred until the next flush
+    pending_mac_checks: Arc<Mutex<PendingMacChecks<C>>>,
+    /// The counters of the traffic over the network
+    network_stats: Arc<NetworkStats>,
+    /// The window in nanoseconds the network sender waits to coalesce
+    /// outbound messages
+    coalescing_window: Arc<AtomicU64>,
+    /// The heartbeat configuration of the network sender, `None` if
+    /// heartbeats are disabled
+    heartbeat: Arc<WatchSender<Option<HeartbeatConfig>>>,
+    /// The most recent snapshot of the executor's stats
+    #[cfg(feature = "stats")]
+    stats: Arc<Mutex<ExecutorStats>>,
+    /// The shares published by every fabric of a single-process test harness,
+    /// set when this fabric publishes its own
+    #[cfg(feature = "test_helpers")]
+    shared_shares: Arc<OnceLock<SharedShares<C>>>,
+}
+
+/// The shares of authenticated scalars computed by each fabric of a
+/// single-process test harness, keyed by party and result ID
+#[cfg(feature = "test_helpers")]
+pub(crate) type SharedShares<C> = Arc<Mutex<HashMap<(PartyId, ResultId), ScalarShare<C>>>>;
+
+/// The MAC checks of opened values that have been deferred until the next
+/// flush
+struct PendingMacChecks<C: CurveGroup> {
+    /// The ID reserved for the result of the combined check, and the GC guard
+    /// shared by every handle to it
+    check: Option<(ResultId, Option<Arc<ResultGuard<C>>>)>,
+    /// The IDs of the opened values
+    values: Vec<ResultId>,
+    /// The IDs of the shares the values were opened from
+    shares: Vec<ResultId>,
+    /// The GC guards of the values and shares, keeping them alive until the
+    /// flush consumes them
+    guards: Vec<Arc<ResultGuard<C>>>,
+}
+
+impl<C: CurveGroup> Default for PendingMacChecks<C> {
+    fn default() -> Self {
+        Self { check: None, values: Vec::new(), shares: Vec::new(), guards: Vec::new() }
+    }
 }
 
 impl<C: CurveGroup> Debug for FabricInner<C> {
@@ -256,11 +478,39 @@ impl<C: CurveGroup> FabricInner<C> {
 
         Self {
             party_id,
+            num_parties: 2,
             next_result_id,
             next_op_id,
             execution_queue,
             outbound_queue,
             offline_phase: Arc::new(Mutex::new(Box::new(offline_phase))),
+            async_triples: Arc::default(),
+            n_triples_consumed: Arc::new(AtomicUsize::new(0)),
+            n_triples_expected: Arc::new(AtomicUsize::new(0)),
+            validate_triples: Arc::new(AtomicBool::new(false)),
+            sacrifice_triples: Arc::new(AtomicBool::new(false)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            cancel_reason: Arc::default(),
+            depths: Arc::default(),
+            last_network_depth: Arc::new(AtomicUsize::new(0)),
+            max_depth: Arc::new(AtomicUsize::new(0)),
+            seeded_rng: Arc::new(Mutex::new(None)),
+            opcodes: Arc::default(),
+            trace: Arc::new(Mutex::new(None)),
+            recording_graph: Arc::new(AtomicBool::new(false)),
+            op_graph: Arc::new(Mutex::new(None)),
+            interned_scalars: Arc::new(Mutex::new(None)),
+            generator_table: Arc::default(),
+            result_gc: Arc::new(AtomicBool::new(false)),
+            n_live_results: Arc::new(AtomicUsize::new(0)),
+            pending_mac_checks: Arc::default(),
+            network_stats: Arc::default(),
+            coalescing_window: Arc::default(),
+            heartbeat: Arc::new(watch::channel(None).0),
+            #[cfg(feature = "stats")]
+            stats: Arc::default(),
+            #[cfg(feature = "test_helpers")]
+            shared_shares: Arc::default(),
         }
     }
 
@@ -274,6 +524,26 @@ impl<C: CurveGroup> FabricInner<C> {
         self.execution_queue.push(ExecutorMessage::Shutdown)
     }
 
+    /// Shutdown the executor once it has finished the work already submitted
+    pub(crate) fn shutdown_drain(&self) {
+        self.execution_queue.push(ExecutorMessage::ShutdownDrain(self.cancelled.clone()))
+    }
+
+    /// Cancel all pending computation in the fabric
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+        self.execution_queue.push(ExecutorMessage::Cancel)
+    }
+
+    /// Cancel all pending computation in the fabric because of the given
+    /// error, which cancellable awaiters resolve to
+    pub(crate) fn cancel_with_error(&self, err: MpcError) {
+        // Set the reason before the flag so that awaiters observing the flag
+        // see the reason
+        self.cancel_reason.lock().expect("cancel reason poisoned").get_or_insert(err);
+        self.cancel();
+    }
+
     /// -----------
     /// | Getters |
     /// -----------
@@ -358,8 +628,18 @@ impl<C: CurveGroup> FabricInner<C> {
     /// The peer will already send the value with the corresponding ID, so all
     /// that is needed is to allocate a slot in the result buffer for the
     /// receipt
+    ///
+    /// The peer sends the value in the same round as the network op allocated
+    /// just before it, e.g. the local half of an exchange, so the value takes
+    /// that op's depth
     pub(crate) fn receive_value(&self) -> ResultId {
-        self.new_result_id()
+        let id = self.new_result_id();
+        let depth = self.last_network_depth.load(Ordering::Acquire);
+        if depth > 0 {
+            self.depths.lock().expect("depth map poisoned").insert(id, depth);
+        }
+
+        id
     }
 
     // --------------
@@ -380,6 +660,46 @@ impl<C: CurveGroup> FabricInner<C> {
         // Allocate IDs for the results
         assert!(output_arity > 0, "output arity must be greater than 0");
         let ids = self.new_result_id_batch(output_arity);
+        self.push_op(args, ids, op_type)
+    }
+
+    /// Allocate a new in-flight gate operation whose result is written to an
+    /// ID reserved earlier via `new_result_id`
+    ///
+    /// This allows a handle to the result to be given out before the operation
+    /// that computes it is known
+    pub(crate) fn new_op_with_result_id(
+        &self,
+        args: Vec<ResultId>,
+        result_id: ResultId,
+        op_type: OperationType<C>,
+    ) -> ResultId {
+        assert!(matches!(op_type, OperationType::Gate { .. }), "reserved results require a gate");
+        self.push_op(args, vec![result_id], op_type)[0]
+    }
+
+    /// Build an operation producing the given contiguous result IDs and
+    /// forward it to the executor
+    fn push_op(
+        &self,
+        args: Vec<ResultId>,
+        ids: Vec<ResultId>,
+        op_type: OperationType<C>,
+    ) -> Vec<ResultId> {
+        // A cancelled fabric computes nothing further, so no gate may consume
+        // the unset results it hands out after cancellation
+        if self.cancelled.load(Ordering::Acquire) {
+            return ids;
+        }
+
+        #[cfg(debug_assertions)]
+        self.check_for_cycle(&args, &ids);
+        let op_type = self.record_depth(&args, &ids, op_type);
+
+        let output_arity = ids.len();
+        if let OperationType::Registered { opcode, .. } = &op_type {
+            self.record_trace(opcode, &args, &ids);
+        }
 
         // Build the operation
         let op = Operation {
@@ -390,11 +710,84 @@ impl<C: CurveGroup> FabricInner<C> {
             inflight_args: 0,
             op_type,
         };
+        self.record_graph(&op, &ids);
 
         // Forward the op to the executor
         self.execution_queue.push(ExecutorMessage::Op(op));
         ids
     }
+
+    /// Record the depth of an operation's results, i.e. the number of network
+    /// ops on the longest path to them
+    ///
+    /// A network op is wrapped to count the round it belongs to once it is
+    /// executed, so that rounds are counted the same way by every executor
+    fn record_depth(
+        &self,
+        args: &[ResultId],
+        ids: &[ResultId],
+        op_type: OperationType<C>,
+    ) -> OperationType<C> {
+        let is_network_op = matches!(op_type, OperationType::Network { .. });
+        let mut depths = self.depths.lock().expect("depth map poisoned");
+        let max_dep = args.iter().filter_map(|id| depths.get(id)).max().copied().unwrap_or(0);
+        let depth = max_dep + usize::from(is_network_op);
+        if depth > 0 {
+            depths.extend(ids.iter().map(|id| (*id, depth)));
+        }
+        drop(depths);
+        self.max_depth.fetch_max(depth, Ordering::AcqRel);
+
+        match op_type {
+            OperationType::Network { function } => {
+                self.last_network_depth.store(depth, Ordering::Release);
+                let rounds = self.network_stats.rounds.clone();
+                OperationType::Network {
+                    function: Box::new(move |args| {
+                        rounds.fetch_max(depth, Ordering::AcqRel);
+                        function(args)
+                    }),
+                }
+            },
+            op_type => op_type,
+        }
+    }
+
+    /// Check that an operation does not depend on its own output or on a
+    /// result that has not been allocated yet
+    ///
+    /// Either would leave the operation waiting on itself and silently stall
+    /// the executor. The high level API cannot build such a circuit, so the
+    /// check only runs in debug builds to catch misuse of the low level API
+    #[cfg(debug_assertions)]
+    fn check_for_cycle(&self, args: &[ResultId], ids: &[ResultId]) {
+        let next_id = self.next_result_id.load(Ordering::Acquire);
+        if let Some(arg) = args.iter().find(|arg| ids.contains(arg) || **arg >= next_id) {
+            panic!("invalid circuit: {}", crate::error::MpcError::DependencyCycle(*arg));
+        }
+    }
+
+    /// Record a registered operation in the trace, if the fabric is tracing
+    fn record_trace(&self, opcode: &Opcode, args: &[ResultId], ids: &[ResultId]) {
+        if let Some(trace) = self.trace.lock().expect("trace poisoned").as_mut() {
+            let op =
+                TracedOp { opcode: opcode.clone(), args: args.to_vec(), outputs: ids.to_vec() };
+            trace.push(op);
+        }
+    }
+
+    /// Record an operation in the dependency graph, if the fabric is
+    /// recording the graph
+    fn record_graph(&self, op: &Operation<C>, ids: &[ResultId]) {
+        if !self.recording_graph.load(Ordering::Acquire) {
+            return;
+        }
+
+        if let Some(graph) = self.op_graph.lock().expect("op graph poisoned").as_mut() {
+            let args = op.args.clone();
+            graph.push(GraphOp { id: op.id, kind: op.kind(), args, outputs: ids.to_vec() });
+        }
+    }
 }
 
 impl<C: CurveGroup> MpcFabric<C> {
@@ -414,17 +807,49 @@ impl<C: CurveGroup> MpcFabric<C> {
         network: N,
         offline_phase: S,
     ) -> Self {
-        // Build an executor queue and a fabric around it
+        Self::spawn_executor(network, offline_phase, |fabric| {
+            let executor_queue = fabric.execution_queue.clone();
+            let outbound_queue = fabric.outbound_queue.clone();
+
+            #[cfg(not(feature = "multithreaded_executor"))]
+            let executor = SerialExecutor::new(size_hints, executor_queue, outbound_queue);
+            #[cfg(all(feature = "stats", not(feature = "multithreaded_executor")))]
+            let executor = executor.with_stats_sink(fabric.stats.clone());
+            #[cfg(feature = "multithreaded_executor")]
+            let executor = ParallelExecutor::new(size_hints, executor_queue, outbound_queue);
+
+            executor
+        })
+    }
+
+    /// Constructor that runs the fabric on a custom executor backend
+    ///
+    /// The builder receives the job queue the backend should consume and the
+    /// queue onto which it should push outbound network messages
+    pub fn with_executor<N, S, E, F>(network: N, offline_phase: S, build_executor: F) -> Self
+    where
+        N: 'static + MpcNetwork<C>,
+        S: 'static + PreprocessingPhase<C>,
+        E: ExecutorBackend<C>,
+        F: FnOnce(ExecutorJobQueue<C>, KanalSender<NetworkOutbound<C>>) -> E,
+    {
+        Self::spawn_executor(network, offline_phase, |fabric| {
+            build_executor(fabric.execution_queue.clone(), fabric.outbound_queue.clone())
+        })
+    }
+
+    /// Build a fabric and run the executor built from it on its own thread
+    fn spawn_executor<N, S, E, F>(network: N, offline_phase: S, build_executor: F) -> Self
+    where
+        N: 'static + MpcNetwork<C>,
+        S: 'static + PreprocessingPhase<C>,
+        E: ExecutorBackend<C>,
+        F: FnOnce(&FabricInner<C>) -> E,
+    {
         let executor_queue = Arc::new(SegQueue::new());
-        let self_ = Self::new_with_executor(network, offline_phase, executor_queue.clone());
-
-        // Spawn the executor
-        let outbound_queue = self_.inner.outbound_queue.clone();
-        #[cfg(not(feature = "multithreaded_executor"))]
-        let executor = SerialExecutor::new(size_hints, executor_queue, outbound_queue);
-        #[cfg(feature = "multithreaded_executor")]
-        let executor = ParallelExecutor::new(size_hints, executor_queue, outbound_queue);
-        // tokio::task::spawn_blocking(move || executor.run());
+        let self_ = Self::new_with_executor(network, offline_phase, executor_queue);
+
+        let executor = build_executor(&self_.inner);
         std::thread::spawn(move || executor.run());
 
         self_
@@ -444,47 +869,451 @@ impl<C: CurveGroup> MpcFabric<C> {
         // Build a fabric
         let party_id = network.party_id();
         let mac_key = offline_phase.get_mac_key_share();
-        let fabric = FabricInner::new(
+        let mut fabric = FabricInner::new(
             party_id,
             mac_key,
             executor_queue.clone(),
             outbound_sender.to_sync(),
             offline_phase,
         );
+        fabric.num_parties = network.num_parties();
 
         // Start a network sender and operator executor
-        let network_sender = NetworkSender::new(
-            outbound_receiver,
-            executor_queue.clone(),
-            network,
-            shutdown_receiver,
-        );
+        let network_sender =
+            NetworkSender::new(outbound_receiver, network, shutdown_receiver, &fabric);
         tokio::task::spawn_blocking(move || block_on(network_sender.run()));
 
         // Create the fabric and fill in the MAC key after
         Self { inner: Arc::new(fabric.clone()), shutdown: shutdown_sender, mac_key }
     }
 
+    /// Exchange heartbeats with the peer, cancelling the fabric with
+    /// `MpcError::PeerUnreachable` if the peer misses too many of them
+    ///
+    /// Without heartbeats, results that depend on a dead peer never resolve.
+    /// With them, results awaited through `ResultHandle::cancellable` fail
+    /// once no message has arrived from the peer for `max_missed` heartbeat
+    /// intervals. Every party must enable heartbeats with the same config,
+    /// otherwise an idle peer is indistinguishable from a dead one
+    pub fn with_heartbeat(self, config: HeartbeatConfig) -> Self {
+        self.inner.heartbeat.send_replace(Some(config));
+        self
+    }
+
+    /// Fetch Beaver triples from the given asynchronous source rather than the
+    /// offline phase
+    ///
+    /// Triples are requested from the source in order by a background task
+    /// spawned on the given runtime. Their results are reserved when a
+    /// multiplication is allocated and filled in once the source resolves, so
+    /// a slow source only delays the gates that depend on its triples. If the
+    /// source fails, the fabric is cancelled with its error
+    pub fn with_async_triples<S: 'static + AsyncPreprocessingPhase<C>>(
+        self,
+        source: S,
+        runtime: &Handle,
+    ) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        runtime.spawn(fetch_async_triples(Arc::downgrade(&self.inner), source, receiver));
+
+        *self.inner.async_triples.lock().expect("async triples poisoned") = Some(sender);
+        self
+    }
+
+    /// Draw all local blinding randomness from a `ChaCha20Rng` seeded with the
+    /// given seed, so that a failing circuit may be replayed deterministically
+    ///
+    /// This covers the blinders of hash commitments and the local masks the
+    /// fabric samples. It never affects randomness that the protocol's
+    /// security rests on: input masks and Beaver triples come from the offline
+    /// phase, and coin flip contributions are always sampled from the OS. A
+    /// seeded fabric's commitments are predictable to anyone who knows the
+    /// seed, so this should only be used in tests and debugging
+    pub fn with_seed(self, seed: u64) -> Self {
+        let rng = ChaCha20Rng::seed_from_u64(seed);
+        *self.inner.seeded_rng.lock().expect("rng poisoned") = Some(rng);
+        self
+    }
+
+    /// Sample a scalar of local blinding randomness, drawn from the seeded rng
+    /// if one is set
+    pub(crate) fn random_blinder(&self) -> Scalar<C> {
+        match self.inner.seeded_rng.lock().expect("rng poisoned").as_mut() {
+            Some(rng) => Scalar::random(rng),
+            None => Scalar::random(&mut thread_rng()),
+        }
+    }
+
     /// Get the party ID of the local party
     pub fn party_id(&self) -> PartyId {
         self.inner.party_id
     }
 
+    /// Whether the given fabric is this fabric or a clone of it
+    pub fn same_fabric(&self, other: &MpcFabric<C>) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// Assert that every result in a batch was allocated in this fabric
+    pub(crate) fn assert_same_fabric<T: From<ResultValue<C>>>(
+        &self,
+        results: &[ResultHandle<C, T>],
+    ) {
+        let same = results.iter().all(|result| self.same_fabric(result.fabric()));
+        assert!(same, "{ERR_MIXED_FABRICS}");
+    }
+
+    /// Get the number of parties in the MPC, party IDs range over
+    /// `0..num_parties`
+    pub fn num_parties(&self) -> usize {
+        self.inner.num_parties
+    }
+
+    /// Get the IDs of all parties in the MPC
+    pub fn party_ids(&self) -> Range<PartyId> {
+        0..self.num_parties() as PartyId
+    }
+
     /// Get a copy of the local party's mac key share
     pub fn mac_key(&self) -> Scalar<C> {
         self.mac_key
     }
 
+    /// Get the fabric's precomputed table of multiples of the curve generator
+    ///
+    /// The table is built on first use and shared by every clone of the
+    /// fabric. Multiplications of shared values by the generator use it
+    /// automatically
+    pub fn generator_table(&self) -> Arc<FixedBaseTable<C>> {
+        self.inner
+            .generator_table
+            .get_or_init(|| Arc::new(FixedBaseTable::new(CurvePoint::generator())))
+            .clone()
+    }
+
     /// Get the total number of ops that have been allocated in the fabric
     pub fn num_gates(&self) -> usize {
         self.inner.next_op_id.load(Ordering::Acquire)
     }
 
+    /// Get the current depth of the circuit, i.e. the maximum number of
+    /// network ops on any path through the circuit allocated so far
+    ///
+    /// This gives an estimate of the round complexity of a circuit before it
+    /// is run. A value received from the peer takes the depth of the local
+    /// network op allocated just before it
+    pub fn circuit_depth(&self) -> usize {
+        self.inner.max_depth.load(Ordering::Acquire)
+    }
+
+    /// Set a hook that the executor invokes with each result an operation
+    /// produces, along with the kind of the operation
+    ///
+    /// This allows callers to build custom profilers or open a `tracing` span
+    /// per gate. The hook runs on the executor's thread, so it should be cheap.
+    /// When no hook is set the executor skips the call entirely. Only the
+    /// serial executor supports trace hooks
+    pub fn set_trace_hook<F>(&self, hook: F)
+    where
+        F: 'static + Fn(&OpResult<C>, OperationKind) + Send + Sync,
+    {
+        let hook = TraceHook::new(hook);
+        self.inner.execution_queue.push(ExecutorMessage::SetTraceHook(Some(hook)));
+    }
+
+    /// Remove the trace hook, if one is set
+    pub fn clear_trace_hook(&self) {
+        self.inner.execution_queue.push(ExecutorMessage::SetTraceHook(None));
+    }
+
+    /// Publish the share of every authenticated scalar this fabric computes
+    /// into `shares`, so that the other fabrics in a single-process test
+    /// harness may read them
+    ///
+    /// This is implemented as a trace hook, so it replaces any hook already
+    /// set and is only supported by the serial executor
+    #[cfg(feature = "test_helpers")]
+    pub(crate) fn publish_shares(&self, shares: SharedShares<C>) {
+        let party_id = self.party_id();
+        let sink = shares.clone();
+        self.set_trace_hook(move |res, _| {
+            if let ResultValue::ScalarShare(share) = &res.value {
+                sink.lock().expect("shares poisoned").insert((party_id, res.id), *share);
+            }
+        });
+
+        assert!(self.inner.shared_shares.set(shares).is_ok(), "shares already published");
+    }
+
+    /// The shares published by every fabric of this fabric's test harness,
+    /// `None` if this fabric does not publish its shares
+    #[cfg(feature = "test_helpers")]
+    pub(crate) fn shared_shares(&self) -> Option<&SharedShares<C>> {
+        self.inner.shared_shares.get()
+    }
+
+    /// Set whether the executor runs ready network ops ahead of ready local
+    /// ops
+    ///
+    /// Network ops gate the depth of a circuit, so sending them early lets the
+    /// peer make progress sooner and reduces end-to-end latency on circuits
+    /// with long chains of dependent rounds. It may hurt throughput on wide
+    /// circuits, so it is disabled by default. Only the serial executor
+    /// supports prioritization
+    pub fn set_network_priority(&self, enabled: bool) {
+        self.inner.execution_queue.push(ExecutorMessage::SetNetworkPriority(enabled));
+    }
+
+    /// Set the window the network sender waits after an outbound message for
+    /// further messages to coalesce into the same transmission
+    ///
+    /// Messages already queued when one is sent are always coalesced with it,
+    /// a non-zero window additionally groups the messages of a wide round that
+    /// the executor produces over a short span, at the cost of delaying each
+    /// transmission by up to the window
+    pub fn set_coalescing_window(&self, window: Duration) {
+        let nanos = u64::try_from(window.as_nanos()).unwrap_or(u64::MAX);
+        self.inner.coalescing_window.store(nanos, Ordering::Release);
+    }
+
+    /// Set whether the fabric interns public scalars, so that allocating a
+    /// value equal to one allocated earlier returns a handle to the same
+    /// result
+    ///
+    /// This reduces buffer pressure in circuits that allocate the same
+    /// constants many times. It is opt-in because interned handles share a
+    /// result, so callers that key state on result IDs must not assume each
+    /// allocation is distinct. Every allocation still reserves one result ID,
+    /// whether or not its value was interned before, so the parties' result
+    /// IDs stay aligned even if they intern different values. Interned
+    /// results are never reclaimed by the result GC, and disabling interning
+    /// forgets them
+    pub fn set_intern_constants(&self, enabled: bool) {
+        let mut interned = self.inner.interned_scalars.lock().expect("interned poisoned");
+        if enabled {
+            interned.get_or_insert_with(HashMap::new);
+        } else {
+            *interned = None;
+        }
+    }
+
+    /// Whether the fabric interns public scalars
+    fn interns_constants(&self) -> bool {
+        self.inner.interned_scalars.lock().expect("interned poisoned").is_some()
+    }
+
+    /// Enable garbage collection of results
+    ///
+    /// Once enabled, the executor reclaims a result after every handle to it
+    /// has been dropped and every operation consuming it has executed. This
+    /// bounds the memory of long-running fabrics that evaluate many circuits.
+    ///
+    /// GC is opt-in because it breaks the invariant that a result ID remains
+    /// valid for the lifetime of the fabric: a raw `ResultId` must not be used
+    /// as an operation argument after all handles to it are dropped. Results
+    /// allocated before GC is enabled are never reclaimed.
+    ///
+    /// GC is only implemented by the serial executor. A fabric running on the
+    /// parallel executor or a custom `ExecutorBackend` ignores this call and
+    /// never reclaims results
+    pub fn enable_result_gc(&self) {
+        if !self.inner.result_gc.swap(true, Ordering::AcqRel) {
+            let live_results = self.inner.n_live_results.clone();
+            self.inner.execution_queue.push(ExecutorMessage::EnableResultGc(live_results));
+        }
+    }
+
+    /// Whether result garbage collection is enabled
+    pub fn result_gc_enabled(&self) -> bool {
+        self.inner.result_gc.load(Ordering::Acquire)
+    }
+
+    /// Get the number of results held by the executor since result GC was
+    /// enabled
+    pub fn num_live_results(&self) -> usize {
+        self.inner.n_live_results.load(Ordering::Acquire)
+    }
+
+    /// Get a snapshot of the executor's stats
+    ///
+    /// The executor publishes its stats each time it drains its work queue, so
+    /// the snapshot may lag behind operations that are still being processed
+    #[cfg(feature = "stats")]
+    pub fn stats_snapshot(&self) -> ExecutorStats {
+        self.inner.stats.lock().expect("stats poisoned").clone()
+    }
+
+    /// Get the counters of the traffic this party has sent and received over
+    /// the network
+    pub fn network_stats(&self) -> &NetworkStats {
+        &self.inner.network_stats
+    }
+
+    /// Get the number of Beaver triples consumed from the offline phase
+    pub fn num_triples_consumed(&self) -> usize {
+        self.inner.n_triples_consumed.load(Ordering::Acquire)
+    }
+
+    /// Enable or disable triple consumption validation
+    ///
+    /// When enabled, `check_triple_consumption` and `shutdown` panic if the
+    /// fabric has consumed any number of Beaver triples other than one per
+    /// shared multiplication issued, which would desync the parties'
+    /// preprocessing. This is intended as an invariant check in tests
+    pub fn set_validate_triples(&self, enabled: bool) {
+        self.inner.validate_triples.store(enabled, Ordering::Release);
+    }
+
+    /// Enable or disable the triple sacrifice check
+    ///
+    /// When enabled, each triple is checked against a second triple before it
+    /// is used, as in SPDZ. A triple whose product does not match, or whose
+    /// check fails to authenticate, has its MACs corrupted so that any opening
+    /// derived from it fails with an `AuthenticationError`. This doubles the
+    /// number of triples consumed and delays each multiplication by the rounds
+    /// of the check. Every party must enable the check together
+    pub fn set_sacrifice_triples(&self, enabled: bool) {
+        self.inner.sacrifice_triples.store(enabled, Ordering::Release);
+    }
+
+    /// Whether the triple sacrifice check is enabled
+    fn sacrifices_triples(&self) -> bool {
+        self.inner.sacrifice_triples.load(Ordering::Acquire)
+    }
+
+    /// Record that `n_mults` shared multiplications were issued, each of which
+    /// expects a Beaver triple
+    ///
+    /// With the triple sacrifice check enabled, each multiplication expects two
+    /// triples. Callers record the multiplications before drawing their
+    /// triples
+    pub(crate) fn record_mults(&self, n_mults: usize) {
+        let expected = if self.sacrifices_triples() { 2 * n_mults } else { n_mults };
+        self.inner.n_triples_expected.fetch_add(expected, Ordering::AcqRel);
+    }
+
+    /// Check that the fabric has consumed exactly the Beaver triples expected
+    /// by the shared multiplications issued so far, if triple validation is
+    /// enabled
+    ///
+    /// This may be called as a checkpoint while building a circuit, and runs
+    /// on `shutdown`. A multiplication being built concurrently on another
+    /// task may not have drawn its triples yet, so the check should be run
+    /// once such tasks are done. A cancelled fabric is not checked, as it
+    /// stops drawing triples
+    pub fn check_triple_consumption(&self) {
+        if !self.inner.validate_triples.load(Ordering::Acquire) || self.is_cancelled() {
+            return;
+        }
+
+        let consumed = self.num_triples_consumed();
+        let expected = self.inner.n_triples_expected.load(Ordering::Acquire);
+        assert_eq!(
+            consumed, expected,
+            "triple validation failed: multiplications expected {expected} triples but \
+             {consumed} were consumed"
+        );
+    }
+
+    /// Signal the network sender to stop
+    fn stop_network_sender(&self) {
+        // The sender stops on its own if the peer becomes unreachable, in which
+        // case there is no receiver for the signal
+        if self.shutdown.send(()).is_err() {
+            log::debug!("network sender already stopped");
+        }
+    }
+
     /// Shutdown the fabric and the threads it has spawned
     pub fn shutdown(self) {
         log::debug!("shutting down fabric");
         self.inner.shutdown();
-        self.shutdown.send(()).expect("error sending shutdown signal");
+        self.stop_network_sender();
+        self.check_triple_consumption();
+    }
+
+    /// Shutdown the fabric once the executor has finished the work already
+    /// submitted to it
+    ///
+    /// Operations that are ready, or become ready from queued results, are
+    /// executed and their awaiters resolve. Results that are still waiting on
+    /// the network will never be produced, so the fabric is marked cancelled
+    /// and handles awaited through `ResultHandle::cancellable` resolve to
+    /// `MpcError::Cancelled` rather than hanging
+    pub fn shutdown_drain(self) {
+        log::debug!("draining and shutting down fabric");
+        self.inner.shutdown_drain();
+        self.stop_network_sender();
+    }
+
+    /// Shutdown the fabric once it is idle, cancelling any computation that is
+    /// still pending after the given timeout
+    ///
+    /// The fabric shuts down as soon as the executor is idle, i.e. no
+    /// operation is waiting on its inputs and no task is waiting on a result.
+    /// As with `shutdown_drain`, the fabric is then marked cancelled, so
+    /// results first awaited after that observe the shutdown rather than
+    /// hanging. Pending results awaited through `ResultHandle::cancellable`
+    /// resolve to `MpcError::Cancelled` once the timeout elapses, rather than
+    /// hanging on a network dependency that never resolves
+    ///
+    /// Returns a handle to the thread that shuts the fabric down, which exits
+    /// once the fabric is shut down
+    pub fn shutdown_with_timeout(self, timeout: Duration) -> JoinHandle<()> {
+        let deadline = Instant::now() + timeout;
+        let (idle_send, idle_recv) = std_mpsc::channel();
+        let cancelled = self.inner.cancelled.clone();
+        self.inner.execution_queue.push(ExecutorMessage::ShutdownWhenIdle(cancelled, idle_send));
+
+        std::thread::spawn(move || {
+            match idle_recv.recv_timeout(timeout) {
+                // The executor has already shut down
+                Ok(()) => self.stop_network_sender(),
+                Err(RecvTimeoutError::Timeout) => {
+                    self.cancel();
+                    self.shutdown();
+                },
+                // The executor stopped for another reason, or its backend dropped
+                // the signal without waiting for idleness, in which case fall back
+                // to cancelling at the deadline
+                Err(RecvTimeoutError::Disconnected) => {
+                    if !self.is_cancelled() {
+                        std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+                        self.cancel();
+                    }
+                    self.shutdown();
+                },
+            }
+        })
+    }
+
+    /// Cancel all pending computation in the fabric
+    ///
+    /// The executor stops processing and drains its queue, results awaited
+    /// through `ResultHandle::cancellable` that are not yet available resolve
+    /// to `MpcError::Cancelled`, and results awaited directly panic
+    pub fn cancel(&self) {
+        log::debug!("cancelling fabric");
+        self.inner.cancel();
+    }
+
+    /// Whether the fabric's pending computation has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// The error that pending results resolve to if the fabric has been
+    /// cancelled
+    ///
+    /// This is `MpcError::Cancelled` unless the fabric cancelled itself
+    /// because of an error, such as an unreachable peer
+    pub fn cancellation_error(&self) -> Option<MpcError> {
+        if !self.is_cancelled() {
+            return None;
+        }
+
+        let reason = self.inner.cancel_reason.lock().expect("cancel reason poisoned");
+        Some(reason.clone().unwrap_or(MpcError::Cancelled))
     }
 
     /// Register a waiter on a result
@@ -580,6 +1409,10 @@ impl<C: CurveGroup> MpcFabric<C> {
         vals: Vec<T>,
         sender: PartyId,
     ) -> Vec<AuthenticatedScalarResult<C>> {
+        if vals.is_empty() {
+            return Vec::new();
+        }
+
         let n = vals.len();
         let mut offline = self.inner.offline_phase.lock().expect("offline phase poisoned");
         let (masked_vals, mask_shares) = if self.party_id() == sender {
@@ -599,6 +1432,19 @@ impl<C: CurveGroup> MpcFabric<C> {
         AuthenticatedScalarResult::batch_add_public(&shares, &masked_vals)
     }
 
+    /// Share the coefficients of a dealer's polynomial with the counterparty
+    ///
+    /// All coefficients are shared in a single batched round, so evaluating
+    /// the shared polynomial at a public point is a local linear combination.
+    /// The non-dealing party's `coeffs` are ignored beyond their length
+    pub fn share_polynomial_coeffs(
+        &self,
+        party_id: PartyId,
+        coeffs: &[Scalar<C>],
+    ) -> Vec<AuthenticatedScalarResult<C>> {
+        self.batch_share_scalar(coeffs.to_vec(), party_id)
+    }
+
     /// Share a `CurvePoint` value with the counterparty
     pub fn share_point(&self, val: CurvePoint<C>, sender: PartyId) -> AuthenticatedPointResult<C> {
         let mut offline = self.inner.offline_phase.lock().expect("offline phase poisoned");
@@ -624,6 +1470,10 @@ impl<C: CurveGroup> MpcFabric<C> {
         vals: Vec<CurvePoint<C>>,
         sender: PartyId,
     ) -> Vec<AuthenticatedPointResult<C>> {
+        if vals.is_empty() {
+            return Vec::new();
+        }
+
         let n = vals.len();
         let mut offline = self.inner.offline_phase.lock().expect("offline phase poisoned");
         let (masked_vals, mask_shares) = if self.party_id() == sender {
@@ -649,13 +1499,36 @@ impl<C: CurveGroup> MpcFabric<C> {
     }
 
     /// Allocate a public value in the fabric
+    ///
+    /// If the fabric interns constants, see `set_intern_constants`, a value
+    /// equal to one allocated earlier returns a handle to the earlier result
     pub fn allocate_scalar<T: Into<Scalar<C>>>(&self, value: T) -> ScalarResult<C> {
-        let id = self.inner.allocate_value(ResultValue::Scalar(value.into()));
+        let value = value.into();
+        if let Some(interned) =
+            self.inner.interned_scalars.lock().expect("interned poisoned").as_mut()
+        {
+            // Reserve an ID on a hit as well, so that the result IDs allocated do not
+            // depend on which values were interned before
+            let reserved = self.inner.new_result_id();
+            let id = *interned.entry(value).or_insert_with(|| {
+                let result = OpResult { id: reserved, value: ResultValue::Scalar(value) };
+                self.inner.execution_queue.push(ExecutorMessage::Result(result));
+                reserved
+            });
+
+            return ResultHandle::new_unguarded(id, self.clone());
+        }
+
+        let id = self.inner.allocate_value(ResultValue::Scalar(value));
         ResultHandle::new(id, self.clone())
     }
 
     /// Allocate a batch of scalars in the fabric
     pub fn allocate_scalars<T: Into<Scalar<C>>>(&self, values: Vec<T>) -> Vec<ScalarResult<C>> {
+        if self.interns_constants() {
+            return values.into_iter().map(|value| self.allocate_scalar(value)).collect_vec();
+        }
+
         let result_values =
             values.into_iter().map(|value| ResultValue::Scalar(value.into())).collect_vec();
 
@@ -803,6 +1676,10 @@ impl<C: CurveGroup> MpcFabric<C> {
         T: 'static + From<ResultValue<C>> + Into<ResultValue<C>> + Send + Sync,
         Vec<T>: Into<NetworkPayload<C>> + From<ResultValue<C>>,
     {
+        if values.is_empty() {
+            return Vec::new();
+        }
+
         let n = values.len();
         let res = self.share_plaintext(values, sender);
 
@@ -813,6 +1690,42 @@ impl<C: CurveGroup> MpcFabric<C> {
         })
     }
 
+    // --------------
+    // | Arithmetic |
+    // --------------
+
+    /// Sum the values of an iterator, which may be empty
+    ///
+    /// The `Sum` implementations of the fabric's result types require a
+    /// non-empty iterator; here the empty sum is the fabric's identity wire
+    pub fn sum<T, I>(&self, iter: I) -> T
+    where
+        T: Sum + AdditiveIdentity<C>,
+        I: IntoIterator<Item = T>,
+    {
+        let mut iter = iter.into_iter().peekable();
+        match iter.peek() {
+            Some(_) => iter.sum(),
+            None => T::additive_identity(self),
+        }
+    }
+
+    /// Multiply the values of an iterator, which may be empty
+    ///
+    /// As with `sum`, the empty product is the fabric's identity wire rather
+    /// than a panic
+    pub fn product<T, I>(&self, iter: I) -> T
+    where
+        T: Product + MultiplicativeIdentity<C>,
+        I: IntoIterator<Item = T>,
+    {
+        let mut iter = iter.into_iter().peekable();
+        match iter.peek() {
+            Some(_) => iter.product(),
+            None => T::multiplicative_identity(self),
+        }
+    }
+
     // -------------------
     // | Gate Definition |
     // -------------------
@@ -869,27 +1782,234 @@ impl<C: CurveGroup> MpcFabric<C> {
         ResultHandle::new(id, self.clone())
     }
 
-    // -----------------
-    // | Offline Phase |
-    // -----------------
-
-    /// Sample the next beaver triplet with MACs from the beaver source
-    pub fn next_triple(
-        &self,
-    ) -> (AuthenticatedScalarResult<C>, AuthenticatedScalarResult<C>, AuthenticatedScalarResult<C>)
+    /// Register a function under an opcode, so that operations using it may
+    /// be traced and replayed
+    ///
+    /// The function must be deterministic for replays to reproduce the
+    /// recorded circuit
+    pub fn register_opcode<F>(&self, opcode: &str, function: F)
+    where
+        F: 'static + for<'a> Fn(BoxedResultIter<'a, C>) -> Vec<ResultValue<C>> + Send + Sync,
     {
-        let (a, b, c) =
-            self.inner.offline_phase.lock().expect("beaver source poisoned").next_triplet();
+        let mut opcodes = self.inner.opcodes.write().expect("opcode registry poisoned");
+        opcodes.register(opcode.to_string(), Arc::new(function));
+    }
+
+    /// Whether a function is registered under the given opcode
+    pub fn has_opcode(&self, opcode: &str) -> bool {
+        self.inner.opcodes.read().expect("opcode registry poisoned").get(opcode).is_some()
+    }
+
+    /// Construct a new operation evaluating the function registered under the
+    /// given opcode, returning a handle to each of its `output_arity` results
+    ///
+    /// Panics if the opcode is not registered. The registered function must
+    /// produce exactly `output_arity` values, the executor panics rather than
+    /// drop or invent results otherwise
+    pub fn new_registered_op<T>(
+        &self,
+        opcode: &str,
+        args: Vec<ResultId>,
+        output_arity: usize,
+    ) -> Vec<ResultHandle<C, T>>
+    where
+        T: From<ResultValue<C>>,
+    {
+        let function = self
+            .inner
+            .opcodes
+            .read()
+            .expect("opcode registry poisoned")
+            .get(opcode)
+            .unwrap_or_else(|| panic!("opcode {opcode} is not registered"));
+
+        let op_type = OperationType::Registered { opcode: opcode.to_string(), function };
+        let ids = self.inner.new_op(args, output_arity, op_type);
+        ids.into_iter().map(|id| ResultHandle::new(id, self.clone())).collect_vec()
+    }
+
+    /// Begin recording the registered operations allocated in the fabric,
+    /// discarding any trace in progress
+    pub fn start_trace(&self) {
+        *self.inner.trace.lock().expect("trace poisoned") = Some(Vec::new());
+    }
+
+    /// Stop recording and return the trace of the registered operations
+    /// allocated since `start_trace`, or `None` if the fabric was not tracing
+    pub fn finish_trace(&self) -> Option<CircuitTrace> {
+        let ops = self.inner.trace.lock().expect("trace poisoned").take()?;
+        Some(CircuitTrace::from_ops(ops))
+    }
+
+    /// Set whether the fabric records the operation dependency graph for
+    /// `to_dot`
+    ///
+    /// Only operations allocated while recording appear in the graph. Disabling
+    /// recording discards the graph
+    pub fn set_record_graph(&self, enabled: bool) {
+        let mut graph = self.inner.op_graph.lock().expect("op graph poisoned");
+        *graph = enabled.then(Vec::new);
+        self.inner.recording_graph.store(enabled, Ordering::Release);
+    }
+
+    /// Render the operation dependency graph recorded since
+    /// `set_record_graph` was enabled in Graphviz DOT format
+    ///
+    /// Nodes are operations labeled with their kind and edges are labeled with
+    /// the ID of the result passed between operations
+    pub fn to_dot(&self) -> String {
+        let graph = self.inner.op_graph.lock().expect("op graph poisoned");
+        render_dot(graph.as_deref().unwrap_or_default())
+    }
+
+    // --------------
+    // | MAC Checks |
+    // --------------
+
+    /// Defer the MAC check of an opened value until the next call to
+    /// `flush_mac_checks`
+    ///
+    /// Returns a handle to the result of the combined check that will cover
+    /// the value
+    pub(crate) fn defer_mac_check(
+        &self,
+        value: &ScalarResult<C>,
+        share: &AuthenticatedScalarResult<C>,
+    ) -> ScalarResult<C> {
+        let mut pending =
+            self.inner.pending_mac_checks.lock().expect("pending MAC checks poisoned");
+        pending.values.push(value.id());
+        pending.shares.push(share.id());
+        pending.guards.extend(value.gc_guard().into_iter().chain(share.gc_guard()));
+
+        // Every handle to the check shares the guard of the first
+        if let Some((check_id, guard)) = pending.check.as_ref() {
+            return ResultHandle::with_gc_guard(*check_id, self.clone(), guard.clone());
+        }
+
+        let check: ScalarResult<C> = ResultHandle::new(self.inner.new_result_id(), self.clone());
+        pending.check = Some((check.id(), check.gc_guard()));
+        check
+    }
+
+    /// Check the MACs of all values opened via
+    /// `AuthenticatedScalarResult::open_authenticated_deferred` since the last
+    /// flush
+    ///
+    /// Rather than exchanging a commitment per opened value, the parties commit
+    /// to a random linear combination of the MAC checks and verify it in a
+    /// single commitment round. If the combined check fails, every deferred
+    /// opening covered by the flush resolves to an `AuthenticationError`.
+    ///
+    /// Both parties must flush at the same point in the circuit
+    pub fn flush_mac_checks(&self) {
+        let pending = mem::take(
+            &mut *self.inner.pending_mac_checks.lock().expect("pending MAC checks poisoned"),
+        );
+        let check_id = match pending.check {
+            Some((id, _)) => id,
+            None => return,
+        };
+
+        // Compute the local share of the combined MAC check
+        let n = pending.values.len();
+        let mac_key = self.mac_key();
+        let mut args = pending.values;
+        args.extend(pending.shares);
+        let my_check: ScalarResult<C> = self.new_gate_op(args, move |mut args| {
+            let values: Vec<Scalar<C>> = args.by_ref().take(n).map(Scalar::from).collect();
+            let shares: Vec<ScalarShare<C>> = args.map(ScalarShare::from).collect();
+
+            ResultValue::Scalar(AuthenticatedScalarResult::combined_mac_check(
+                mac_key, &values, &shares,
+            ))
+        });
+
+        // Commit to the combined check and exchange it once the peer has committed
+        let my_comm = HashCommitmentResult::commit(my_check.clone());
+        let peer_comm = self.exchange_value(my_comm.commitment);
+
+        let check_after_commit: ScalarResult<C> =
+            self.new_gate_op(vec![my_check.id(), peer_comm.id()], |mut args| args.next().unwrap());
+        let peer_check = self.exchange_value(check_after_commit);
+        let peer_blinder = self.exchange_value(self.allocate_scalar(my_comm.blinder));
+
+        // Write the result of the check to the ID handed out to the deferred openings
+        let function = Box::new(|mut args: BoxedResultIter<C>| {
+            let my_check: Scalar<C> = args.next().unwrap().into();
+            let peer_check: Scalar<C> = args.next().unwrap().into();
+            let blinder: Scalar<C> = args.next().unwrap().into();
+            let commitment: Scalar<C> = args.next().unwrap().into();
+
+            ResultValue::Scalar(Scalar::from(AuthenticatedScalarResult::verify_mac_check(
+                my_check, peer_check, commitment, blinder,
+            )))
+        });
+        self.inner.new_op_with_result_id(
+            vec![my_check.id(), peer_check.id(), peer_blinder.id(), peer_comm.id()],
+            check_id,
+            OperationType::Gate { function },
+        );
+    }
+
+    // -----------------
+    // | Offline Phase |
+    // -----------------
+
+    /// Sample the next beaver triplet with MACs from the beaver source
+    ///
+    /// If the beaver source is exhausted, the fabric is cancelled with the
+    /// source's error and the returned triplet is never computed, awaiting any
+    /// result derived from it with `cancellable` resolves to the error
+    pub fn next_triple(
+        &self,
+    ) -> (AuthenticatedScalarResult<C>, AuthenticatedScalarResult<C>, AuthenticatedScalarResult<C>)
+    {
+        self.try_next_triple().unwrap_or_else(|err| {
+            self.inner.cancel_with_error(err);
+            let (mut a, mut b, mut c) = self.unset_triple_batch(1);
+            (a.pop().unwrap(), b.pop().unwrap(), c.pop().unwrap())
+        })
+    }
+
+    /// Sample the next beaver triplet with MACs from the beaver source, or
+    /// an error if the beaver source is exhausted
+    #[allow(clippy::type_complexity)]
+    pub fn try_next_triple(
+        &self,
+    ) -> Result<
+        (AuthenticatedScalarResult<C>, AuthenticatedScalarResult<C>, AuthenticatedScalarResult<C>),
+        MpcError,
+    > {
+        // The sacrifice check and asynchronous sources operate on batches
+        let has_async_triples =
+            self.inner.async_triples.lock().expect("async triples poisoned").is_some();
+        if self.sacrifices_triples() || has_async_triples {
+            let (mut a, mut b, mut c) = self.try_next_triple_batch(1)?;
+            return Ok((a.pop().unwrap(), b.pop().unwrap(), c.pop().unwrap()));
+        }
+
+        let (a, b, c) = self
+            .inner
+            .offline_phase
+            .lock()
+            .expect("beaver source poisoned")
+            .try_next_triplet()
+            .map_err(MpcError::PreprocessingError)?;
+        self.inner.n_triples_consumed.fetch_add(1, Ordering::AcqRel);
 
         let mut abc = self.allocate_scalar_shares(vec![a, b, c]);
         let c_val = abc.pop().unwrap();
         let b_val = abc.pop().unwrap();
         let a_val = abc.pop().unwrap();
 
-        (a_val, b_val, c_val)
+        Ok((a_val, b_val, c_val))
     }
 
     /// Sample the next batch of beaver triples as `AuthenticatedScalar`s
+    ///
+    /// If the beaver source cannot supply the batch, the fabric is cancelled
+    /// as in `next_triple`
     #[allow(clippy::type_complexity)]
     pub fn next_triple_batch(
         &self,
@@ -899,8 +2019,237 @@ impl<C: CurveGroup> MpcFabric<C> {
         Vec<AuthenticatedScalarResult<C>>,
         Vec<AuthenticatedScalarResult<C>>,
     ) {
-        let (a_vals, b_vals, c_vals) =
-            self.inner.offline_phase.lock().expect("beaver source poisoned").next_triplet_batch(n);
+        self.try_next_triple_batch(n).unwrap_or_else(|err| {
+            self.inner.cancel_with_error(err);
+            self.unset_triple_batch(n)
+        })
+    }
+
+    /// Reserve handles to a batch of triples whose values are never set
+    ///
+    /// These stand in for the triples a cancelled fabric could not supply, so
+    /// that no gate ever computes on, or opens a value masked by, a triple the
+    /// parties do not hold
+    #[allow(clippy::type_complexity)]
+    fn unset_triple_batch(
+        &self,
+        n: usize,
+    ) -> (
+        Vec<AuthenticatedScalarResult<C>>,
+        Vec<AuthenticatedScalarResult<C>>,
+        Vec<AuthenticatedScalarResult<C>>,
+    ) {
+        let mut handles = self
+            .inner
+            .new_result_id_batch(3 * n)
+            .into_iter()
+            .map(|id| ResultHandle::new(id, self.clone()))
+            .collect_vec();
+
+        let c_vals = handles.split_off(2 * n);
+        let b_vals = handles.split_off(n);
+        (handles, b_vals, c_vals)
+    }
+
+    /// Sample the next batch of beaver triples as `AuthenticatedScalar`s, or
+    /// an error if the beaver source cannot supply the batch
+    #[allow(clippy::type_complexity)]
+    pub fn try_next_triple_batch(
+        &self,
+        n: usize,
+    ) -> Result<
+        (
+            Vec<AuthenticatedScalarResult<C>>,
+            Vec<AuthenticatedScalarResult<C>>,
+            Vec<AuthenticatedScalarResult<C>>,
+        ),
+        MpcError,
+    > {
+        if !self.sacrifices_triples() {
+            return self.fetch_triple_batch(n);
+        }
+
+        // Draw the triples to check and the triples to sacrifice in one batch
+        let (mut a_vals, mut b_vals, mut c_vals) = self.fetch_triple_batch(2 * n)?;
+        let sacrificed = (a_vals.split_off(n), b_vals.split_off(n), c_vals.split_off(n));
+        Ok(self.sacrifice_triple_batch((a_vals, b_vals, c_vals), sacrificed))
+    }
+
+    /// Fetch a batch of beaver triples from the asynchronous triple source if
+    /// one is set, otherwise from the offline phase
+    #[allow(clippy::type_complexity)]
+    fn fetch_triple_batch(
+        &self,
+        n: usize,
+    ) -> Result<
+        (
+            Vec<AuthenticatedScalarResult<C>>,
+            Vec<AuthenticatedScalarResult<C>>,
+            Vec<AuthenticatedScalarResult<C>>,
+        ),
+        MpcError,
+    > {
+        if let Some(triples) = self.request_async_triples(n) {
+            return triples;
+        }
+
+        let (a_vals, b_vals, c_vals) = self
+            .inner
+            .offline_phase
+            .lock()
+            .expect("beaver source poisoned")
+            .try_next_triplet_batch(n)
+            .map_err(MpcError::PreprocessingError)?;
+        self.inner.n_triples_consumed.fetch_add(n, Ordering::AcqRel);
+
+        Ok(self.allocate_triple_batch(a_vals, b_vals, c_vals))
+    }
+
+    /// Check a batch of triples `(a, b, c)` against a batch of sacrificed
+    /// triples `(f, g, h)`, returning the checked triples
+    ///
+    /// For a random public `t`, the parties open `rho = t * a - f` and
+    /// `sigma = b - g`, then open
+    /// `t * c - h - sigma * f - rho * g - sigma * rho`, which is zero iff
+    /// `t * (c - ab) = h - fg`. The checked triples are released only once
+    /// the check resolves, with their MACs corrupted if it fails
+    #[allow(clippy::type_complexity)]
+    fn sacrifice_triple_batch(
+        &self,
+        (a, b, c): (
+            Vec<AuthenticatedScalarResult<C>>,
+            Vec<AuthenticatedScalarResult<C>>,
+            Vec<AuthenticatedScalarResult<C>>,
+        ),
+        (f, g, h): (
+            Vec<AuthenticatedScalarResult<C>>,
+            Vec<AuthenticatedScalarResult<C>>,
+            Vec<AuthenticatedScalarResult<C>>,
+        ),
+    ) -> (
+        Vec<AuthenticatedScalarResult<C>>,
+        Vec<AuthenticatedScalarResult<C>>,
+        Vec<AuthenticatedScalarResult<C>>,
+    ) {
+        let n = a.len();
+        if n == 0 {
+            return (a, b, c);
+        }
+
+        let t = self.coin_flip().open_authenticated();
+        let t_repeat = vec![t.value.clone(); n];
+
+        // Open `rho = t * a - f` and `sigma = b - g`
+        let t_a = AuthenticatedScalarResult::batch_mul_public(&a, &t_repeat);
+        let rho = AuthenticatedScalarResult::batch_sub(&t_a, &f);
+        let sigma = AuthenticatedScalarResult::batch_sub(&b, &g);
+        let opened = AuthenticatedScalarResult::open_authenticated_batch(&[rho, sigma].concat());
+        let (rho_open, sigma_open): (Vec<_>, Vec<_>) = opened[..n]
+            .iter()
+            .zip(&opened[n..])
+            .map(|(r, s)| (r.value.clone(), s.value.clone()))
+            .unzip();
+
+        // Open `t * c - h - sigma * f - rho * g - sigma * rho`
+        let t_c = AuthenticatedScalarResult::batch_mul_public(&c, &t_repeat);
+        let sigma_f = AuthenticatedScalarResult::batch_mul_public(&f, &sigma_open);
+        let rho_g = AuthenticatedScalarResult::batch_mul_public(&g, &rho_open);
+        let sigma_rho = ScalarResult::batch_mul(&sigma_open, &rho_open);
+
+        let z = AuthenticatedScalarResult::batch_sub(&t_c, &h);
+        let z = AuthenticatedScalarResult::batch_sub(&z, &sigma_f);
+        let z = AuthenticatedScalarResult::batch_sub(&z, &rho_g);
+        let z = AuthenticatedScalarResult::batch_sub_public(&z, &sigma_rho);
+        let z_open = AuthenticatedScalarResult::open_authenticated_batch(&z);
+
+        // Release the checked triples once every opening has been checked
+        let mac_checks = iter::once(&t.mac_check)
+            .chain(opened.iter().map(|o| &o.mac_check))
+            .chain(z_open.iter().map(|o| &o.mac_check));
+        let n_checks = 1 + 3 * n;
+        let check_ids = mac_checks
+            .map(|check| check.id())
+            .chain(z_open.iter().map(|o| o.value.id()))
+            .chain(a.iter().chain(&b).chain(&c).map(|x| x.id()))
+            .collect_vec();
+
+        let mac_corruption = self.random_blinder();
+        let mut checked: Vec<AuthenticatedScalarResult<C>> =
+            self.new_batch_gate_op(check_ids, 3 * n, move |mut args| {
+                // Fold rather than short circuit so that the shares follow the checks
+                let macs_valid = args
+                    .by_ref()
+                    .take(n_checks)
+                    .fold(true, |valid, check| valid & (Scalar::from(check) == Scalar::one()));
+                let valid = args
+                    .by_ref()
+                    .take(n)
+                    .fold(macs_valid, |valid, z| valid & (Scalar::from(z) == Scalar::zero()));
+
+                args.map(ScalarShare::from)
+                    .map(|mut share| {
+                        if !valid {
+                            share.mac += mac_corruption;
+                        }
+                        ResultValue::ScalarShare(share)
+                    })
+                    .collect_vec()
+            });
+
+        let checked_c = checked.split_off(2 * n);
+        let checked_b = checked.split_off(n);
+        (checked, checked_b, checked_c)
+    }
+
+    /// Request a batch of beaver triples from the asynchronous triple source,
+    /// or `None` if no asynchronous source is set
+    ///
+    /// The triples' results are reserved here and filled in by the task that
+    /// awaits the source
+    #[allow(clippy::type_complexity)]
+    fn request_async_triples(
+        &self,
+        n: usize,
+    ) -> Option<
+        Result<
+            (
+                Vec<AuthenticatedScalarResult<C>>,
+                Vec<AuthenticatedScalarResult<C>>,
+                Vec<AuthenticatedScalarResult<C>>,
+            ),
+            MpcError,
+        >,
+    > {
+        let requests = self.inner.async_triples.lock().expect("async triples poisoned");
+        let requests = requests.as_ref()?;
+
+        let ids = self.inner.new_result_id_batch(3 * n);
+        if requests.send(ids.clone()).is_err() {
+            let err = PreprocessingError::IoError(ERR_ASYNC_TRIPLES_STOPPED.to_string());
+            return Some(Err(MpcError::PreprocessingError(err)));
+        }
+        self.inner.n_triples_consumed.fetch_add(n, Ordering::AcqRel);
+
+        let mut handles =
+            ids.into_iter().map(|id| ResultHandle::new(id, self.clone())).collect_vec();
+        let c_vals = handles.split_off(2 * n);
+        let b_vals = handles.split_off(n);
+        Some(Ok((handles, b_vals, c_vals)))
+    }
+
+    /// Allocate a batch of beaver triples from their shares
+    #[allow(clippy::type_complexity)]
+    fn allocate_triple_batch(
+        &self,
+        a_vals: Vec<ScalarShare<C>>,
+        b_vals: Vec<ScalarShare<C>>,
+        c_vals: Vec<ScalarShare<C>>,
+    ) -> (
+        Vec<AuthenticatedScalarResult<C>>,
+        Vec<AuthenticatedScalarResult<C>>,
+        Vec<AuthenticatedScalarResult<C>>,
+    ) {
+        let n = a_vals.len();
 
         // Concatenate and allocate all the values
         let vals = a_vals.into_iter().chain(b_vals).chain(c_vals).collect_vec();
@@ -957,6 +2306,79 @@ impl<C: CurveGroup> MpcFabric<C> {
         (left, right)
     }
 
+    /// Sample a random shared value and its square
+    pub fn random_square_pair(
+        &self,
+    ) -> (AuthenticatedScalarResult<C>, AuthenticatedScalarResult<C>) {
+        let (mut values, mut squares) = self.random_square_pairs(1);
+        (values.pop().unwrap(), squares.pop().unwrap())
+    }
+
+    /// Sample a batch of random shared values and their squares
+    ///
+    /// The pairs are taken from the offline phase if it supplies them,
+    /// otherwise each pair is derived from a beaver triplet with a single
+    /// opening. That opening is MAC checked, and if any check fails each
+    /// party corrupts its MAC shares of the squares, so that any later MAC
+    /// check on a square fails
+    pub fn random_square_pairs(
+        &self,
+        n: usize,
+    ) -> (Vec<AuthenticatedScalarResult<C>>, Vec<AuthenticatedScalarResult<C>>) {
+        let pairs = self
+            .inner
+            .offline_phase
+            .lock()
+            .expect("offline phase poisoned")
+            .next_shared_square_pair_batch(n);
+
+        match pairs {
+            Some((values, squares)) => {
+                let mut allocated =
+                    self.allocate_scalar_shares(values.into_iter().chain(squares).collect_vec());
+                let squares = allocated.split_off(n);
+                (allocated, squares)
+            },
+            None => {
+                // For a triplet `(a, b, c)`, `a^2 = c + (a - b) * a`. Opening `a - b`
+                // reveals nothing about `a` as `b` is discarded
+                // Each square is a shared multiplication of a value by itself
+                self.record_mults(n);
+                let (a, b, c) = self.next_triple_batch(n);
+                let masked = AuthenticatedScalarResult::batch_sub(&a, &b);
+                let d_open = AuthenticatedScalarResult::open_authenticated_batch(&masked);
+                let d = d_open.iter().map(|open| open.value.clone()).collect_vec();
+                let da = AuthenticatedScalarResult::batch_mul_public(&a, &d);
+                let squares = AuthenticatedScalarResult::batch_add(&c, &da);
+
+                let ids = d_open
+                    .iter()
+                    .map(|open| open.mac_check.id())
+                    .chain(squares.iter().map(|square| square.id()))
+                    .collect_vec();
+                let mac_corruption = self.random_blinder();
+                let squares = self.new_batch_gate_op(ids, n, move |mut args| {
+                    let valid = args
+                        .by_ref()
+                        .take(n)
+                        .fold(true, |valid, check| valid & (Scalar::from(check) == Scalar::one()));
+
+                    args.map(ScalarShare::from)
+                        .map(|mut square| {
+                            if !valid {
+                                square.mac += mac_corruption;
+                            }
+
+                            ResultValue::ScalarShare(square)
+                        })
+                        .collect_vec()
+                });
+
+                (a, squares)
+            },
+        }
+    }
+
     /// Sample a random shared bit from the offline phase
     pub fn random_shared_bit(&self) -> AuthenticatedScalarResult<C> {
         let bit =
@@ -976,11 +2398,240 @@ impl<C: CurveGroup> MpcFabric<C> {
 
         self.allocate_scalar_shares(bits)
     }
+
+    /// Jointly sample a random value that neither party can bias, using a
+    /// commit-then-reveal coin flip
+    ///
+    /// Each party commits to a random contribution, and only reveals it once
+    /// the peer's commitment has been received. The result is the sum of the
+    /// contributions, allocated as an authenticated sharing of a public value.
+    ///
+    /// If the peer's reveal does not match its commitment, the local party
+    /// corrupts its MAC share of the result so that any MAC check on a value
+    /// derived from the coin fails with an `AuthenticationError`
+    pub fn coin_flip(&self) -> AuthenticatedScalarResult<C> {
+        let mut rng = thread_rng();
+        let contribution = self.allocate_scalar(Scalar::random(&mut rng));
+        let comm = HashCommitmentResult::commit(contribution.clone());
+
+        self.coin_flip_committed(comm, contribution)
+    }
+
+    /// Run the reveal phase of a coin flip, given a commitment to the local
+    /// contribution and the value to reveal
+    ///
+    /// Separated from `coin_flip` so that tests may reveal a value other than
+    /// the committed contribution
+    fn coin_flip_committed(
+        &self,
+        comm: HashCommitmentResult<C, Scalar<C>>,
+        reveal: ScalarResult<C>,
+    ) -> AuthenticatedScalarResult<C> {
+        let peer_comm = self.exchange_value(comm.commitment);
+
+        // Only reveal once the peer has committed
+        let reveal_after_commit: ScalarResult<C> =
+            self.new_gate_op(vec![reveal.id(), peer_comm.id()], |mut args| args.next().unwrap());
+        let peer_reveal = self.exchange_value(reveal_after_commit);
+        let peer_blinder = self.exchange_value(self.allocate_scalar(comm.blinder));
+
+        let party_id = self.party_id();
+        let mac_key = self.mac_key();
+        let mac_corruption = self.random_blinder();
+        self.new_gate_op(
+            vec![reveal.id(), peer_reveal.id(), peer_blinder.id(), peer_comm.id()],
+            move |mut args| {
+                let my_reveal: Scalar<C> = args.next().unwrap().into();
+                let peer_reveal: Scalar<C> = args.next().unwrap().into();
+                let blinder: Scalar<C> = args.next().unwrap().into();
+                let commitment: Scalar<C> = args.next().unwrap().into();
+
+                let peer_comm = HashCommitment { values: vec![peer_reveal], blinder, commitment };
+                let value = my_reveal + peer_reveal;
+
+                let share = if party_id == PARTY0 { value } else { Scalar::zero() };
+                let mut mac = mac_key * value;
+                if !peer_comm.verify() {
+                    mac += mac_corruption;
+                }
+
+                ResultValue::ScalarShare(ScalarShare::new(share, mac))
+            },
+        )
+    }
+
+    /// Generate a random shared bit using the square-root protocol rather than
+    /// trusting the offline phase's bit output
+    ///
+    /// See `random_shared_bits_verified` for a description of the protocol
+    pub fn random_shared_bit_verified(&self) -> AuthenticatedScalarResult<C> {
+        self.random_shared_bits_verified(1).pop().unwrap()
+    }
+
+    /// Generate a batch of random shared bits using the square-root protocol
+    ///
+    /// For each bit, the parties sample a shared random value `r`, open `r^2`
+    /// and compute a public square root `s`. Then `r / s` is `1` or `-1`, each
+    /// with probability 1/2, so `(r / s + 1) / 2` is a uniformly random bit.
+    ///
+    /// The openings of `r^2` are MAC checked. If any check fails, or any
+    /// opened `r^2` is zero and so has no invertible root, each party corrupts
+    /// its MAC shares of the whole batch, so that any MAC check on a value
+    /// derived from the bits fails with an `AuthenticationError`. A zero `r`
+    /// is sampled with negligible probability, so this only happens when a
+    /// party cheats
+    ///
+    /// When the offline phase supplies no square pairs, each `r^2` is derived
+    /// from a triplet by opening `a - b`, and soundness also depends on the
+    /// MAC check of that opening. A failed check corrupts the MAC of `r^2`, so
+    /// it is caught by the check on the opening of `r^2`, see
+    /// `random_square_pairs`
+    pub fn random_shared_bits_verified(&self, n: usize) -> Vec<AuthenticatedScalarResult<C>> {
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let (r, r_squared) = self.random_square_pairs(n);
+        let r_squared_open = AuthenticatedScalarResult::open_authenticated_batch(&r_squared);
+
+        let ids = r_squared_open
+            .iter()
+            .map(|open| open.mac_check.id())
+            .chain(r_squared_open.iter().map(|open| open.value.id()))
+            .chain(r.iter().map(|r| r.id()))
+            .collect_vec();
+
+        let party_id = self.party_id();
+        let mac_key = self.mac_key();
+        let mac_corruption = self.random_blinder();
+        self.new_batch_gate_op(ids, n, move |mut args| {
+            // Fold rather than short circuit so that the remaining args follow
+            let macs_valid = args
+                .by_ref()
+                .take(n)
+                .fold(true, |valid, check| valid & (Scalar::from(check) == Scalar::one()));
+
+            // Compute the public coefficient `1 / 2s` for each bit
+            let roots = args
+                .by_ref()
+                .take(n)
+                .map(|r2| Scalar::from(r2).sqrt().filter(|s| *s != Scalar::zero()))
+                .collect_vec();
+            let valid = macs_valid && roots.iter().all(Option::is_some);
+
+            let half = Scalar::from(2u8).inverse();
+            args.map(ScalarShare::from)
+                .zip(roots)
+                .map(|(r, root)| {
+                    let coeff = root.map(|s| (Scalar::from(2u8) * s).inverse());
+                    let mut bit =
+                        (r * coeff.unwrap_or_default()).add_public(half, mac_key, party_id);
+                    if !valid {
+                        bit.mac += mac_corruption;
+                    }
+
+                    ResultValue::ScalarShare(bit)
+                })
+                .collect_vec()
+        })
+    }
+}
+
+/// Serve requests for triples from an asynchronous source in order, filling in
+/// the results reserved for each batch
+///
+/// Exits once the fabric is dropped or the source fails
+async fn fetch_async_triples<C: CurveGroup, S: AsyncPreprocessingPhase<C>>(
+    fabric: Weak<FabricInner<C>>,
+    mut source: S,
+    mut requests: UnboundedReceiver<Vec<ResultId>>,
+) {
+    while let Some(ids) = requests.recv().await {
+        let triples = source.fetch_triplet_batch(ids.len() / 3).await;
+        let Some(fabric) = fabric.upgrade() else { return };
+
+        match triples {
+            Ok((a_vals, b_vals, c_vals)) => {
+                let values = a_vals.into_iter().chain(b_vals).chain(c_vals);
+                let results = ids
+                    .into_iter()
+                    .zip(values)
+                    .map(|(id, share)| OpResult { id, value: ResultValue::ScalarShare(share) })
+                    .collect_vec();
+                fabric.execution_queue.push(ExecutorMessage::ResultBatch(results));
+            },
+            Err(err) => {
+                fabric.cancel_with_error(MpcError::PreprocessingError(err));
+                return;
+            },
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{algebra::Scalar, test_helpers::execute_mock_mpc, PARTY0};
+    use std::{
+        pin::Pin,
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        task::{Context, Poll},
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use futures::{future, Sink, SinkExt, Stream, StreamExt};
+    use itertools::Itertools;
+    use rand::thread_rng;
+    use tokio::{runtime::Handle, sync::Semaphore};
+
+    use crate::{
+        algebra::{
+            AuthenticatedDensePoly, AuthenticatedPointResult, AuthenticatedScalarResult,
+            CurvePoint, Scalar, ScalarResult, ScalarShare,
+        },
+        commitment::HashCommitmentResult,
+        error::{MpcError, MpcNetworkError, PreprocessingError},
+        fabric::{
+            result::OpResult, ExecutorBackend, ExecutorJobQueue, ExecutorMessage,
+            ExecutorSizeHints, HeartbeatConfig, Operation, ResultWaiter, SerialExecutor,
+        },
+        gadgets,
+        network::{
+            MockNetwork, MpcNetwork, NetworkOutbound, NetworkPayload, NoRecvNetwork, PartyId,
+            UnboundedDuplexStream,
+        },
+        offline_prep::{
+            AsyncPreprocessingPhase, FileBeaverSource, PartyIDBeaverSource, PreprocessingPhase,
+            PreprocessingValues, SeededBeaverSource,
+        },
+        test_helpers::{
+            execute_mock_mpc, execute_mock_mpc_with_beaver_source, mock_fabric, open_await_all,
+            TestCurve,
+        },
+        MpcFabric, OperationKind, ResultValue, PARTY0, PARTY1,
+    };
+
+    #[cfg(feature = "stats")]
+    use crate::fabric::ExecutorStats;
+
+    /// Await the executor's stats once it has executed every gate allocated so
+    /// far
+    ///
+    /// The executor publishes its stats when it goes idle, so a snapshot taken
+    /// as soon as a result resolves may lag behind the circuit
+    #[cfg(feature = "stats")]
+    async fn await_idle_stats(fabric: &MpcFabric<TestCurve>) -> ExecutorStats {
+        let n_ops = fabric.num_gates();
+        let mut stats = fabric.stats_snapshot();
+        while stats.n_ops < n_ops {
+            tokio::task::yield_now().await;
+            stats = fabric.stats_snapshot();
+        }
+
+        stats
+    }
 
     /// Tests a linear circuit of very large depth
     #[tokio::test]
@@ -1000,4 +2651,1324 @@ mod test {
 
         assert_eq!(res, Scalar::from(DEPTH + 1));
     }
+
+    /// Tests that the parties' MAC key shares sum to the global MAC key
+    #[tokio::test]
+    async fn test_mac_key_shares() {
+        // The `PartyIDBeaverSource` assigns each party its ID as a MAC key share
+        let expected_key = Scalar::from(0u8) + Scalar::from(1u8);
+
+        let (party0_share, party1_share) =
+            execute_mock_mpc(|fabric| async move { fabric.mac_key() }).await;
+
+        assert_eq!(party0_share + party1_share, expected_key);
+    }
+
+    /// Tests sharing a polynomial's coefficients and evaluating it at a public
+    /// point
+    #[tokio::test]
+    async fn test_share_polynomial_coeffs() {
+        let mut rng = thread_rng();
+        let coeffs = (0..3).map(|_| Scalar::<TestCurve>::random(&mut rng)).collect_vec();
+        let x = Scalar::random(&mut rng);
+        let expected = coeffs[0] + coeffs[1] * x + coeffs[2] * x * x;
+
+        let (res, _) = execute_mock_mpc(|fabric| {
+            let coeffs = coeffs.clone();
+            async move {
+                let shared_coeffs = fabric.share_polynomial_coeffs(PARTY0, &coeffs);
+                let poly = AuthenticatedDensePoly::from_coeffs(shared_coeffs);
+
+                let eval = poly.eval(&fabric.allocate_scalar(x));
+                eval.open_authenticated().await
+            }
+        })
+        .await;
+
+        assert_eq!(res.unwrap(), expected);
+    }
+
+    /// Tests that a coin flip produces an authenticated random value that both
+    /// parties agree on
+    #[tokio::test]
+    async fn test_coin_flip() {
+        let (res0, res1) = execute_mock_mpc(|fabric| async move {
+            let coin1 = fabric.coin_flip().open_authenticated().await.unwrap();
+            let coin2 = fabric.coin_flip().open_authenticated().await.unwrap();
+
+            (coin1, coin2)
+        })
+        .await;
+
+        assert_eq!(res0, res1);
+        assert_ne!(res0.0, res0.1);
+    }
+
+    /// Tests that a party changing its reveal after committing is caught
+    #[tokio::test]
+    async fn test_coin_flip_changed_reveal() {
+        let (res0, res1) = execute_mock_mpc(|fabric| async move {
+            let contribution = fabric.allocate_scalar(Scalar::random(&mut thread_rng()));
+            let changed = &contribution + Scalar::one();
+            let comm = HashCommitmentResult::commit(contribution.clone());
+
+            // Party 1 reveals a value other than the one it committed to
+            let reveal = if fabric.party_id() == PARTY0 { contribution } else { changed };
+            let coin = fabric.coin_flip_committed(comm, reveal);
+
+            coin.open_authenticated().await
+        })
+        .await;
+
+        assert!(matches!(res0, Err(MpcError::AuthenticationError)));
+        assert!(matches!(res1, Err(MpcError::AuthenticationError)));
+    }
+
+    /// Tests cancelling a circuit whose network dependency never resolves
+    #[tokio::test]
+    async fn test_cancel_pending_result() {
+        let fabric = mock_fabric();
+
+        // The mock network never receives, so this result never resolves
+        let pending: ScalarResult<TestCurve> = fabric.receive_value();
+        let res = pending + Scalar::one();
+
+        fabric.clone().shutdown_with_timeout(Duration::from_millis(100));
+        let res = res.cancellable().await;
+
+        assert!(fabric.is_cancelled());
+        assert_eq!(res, Err(MpcError::Cancelled));
+    }
+
+    /// Tests that a timed shutdown returns as soon as the fabric is idle rather
+    /// than waiting out the timeout
+    #[tokio::test]
+    async fn test_shutdown_with_timeout_idle() {
+        let fabric = mock_fabric();
+        let res = fabric.allocate_scalar(1u8) + Scalar::one();
+        assert_eq!(res.await, Scalar::from(2u8));
+
+        let shutdown = fabric.clone().shutdown_with_timeout(Duration::from_secs(3600));
+        tokio::task::spawn_blocking(move || shutdown.join().unwrap()).await.unwrap();
+    }
+
+    /// Tests that awaiting a pending result directly wakes and panics with the
+    /// cancellation error rather than hanging
+    #[tokio::test]
+    async fn test_cancel_plain_await() {
+        let fabric = mock_fabric();
+        let pending: ScalarResult<TestCurve> = fabric.receive_value();
+        let task = tokio::spawn(pending);
+        tokio::task::yield_now().await;
+
+        fabric.inner.cancel_with_error(MpcError::PeerUnreachable);
+        let err = task.await.unwrap_err().into_panic();
+        let msg = err.downcast_ref::<String>().unwrap();
+
+        assert!(msg.contains(&MpcError::PeerUnreachable.to_string()));
+        fabric.shutdown();
+    }
+
+    /// Tests that running out of triples cancels the fabric with a
+    /// preprocessing error rather than panicking in a multiplication
+    #[tokio::test]
+    async fn test_triples_exhausted() {
+        let source =
+            FileBeaverSource::<TestCurve>::from_values(PreprocessingValues::new(Scalar::one()));
+        let fabric = MpcFabric::new(NoRecvNetwork::default(), source);
+        let exhausted =
+            MpcError::PreprocessingError(PreprocessingError::Exhausted("triples".to_string()));
+
+        assert_eq!(fabric.try_next_triple().err(), Some(exhausted.clone()));
+        assert!(!fabric.is_cancelled());
+
+        let a = fabric.allocate_scalar_share(ScalarShare::new(Scalar::one(), Scalar::one()));
+        let b = fabric.allocate_scalar_share(ScalarShare::new(Scalar::one(), Scalar::one()));
+        let res = (a * b).cancellable().await;
+
+        assert_eq!(res, Err(exhausted.clone()));
+        assert_eq!(fabric.cancellation_error(), Some(exhausted.clone()));
+
+        // The stand-in triple is never computed
+        let (triple_a, ..) = fabric.next_triple();
+        assert_eq!(triple_a.cancellable().await, Err(exhausted));
+        fabric.shutdown();
+    }
+
+    /// Tests that heartbeats keep an idle connection alive, and that awaiters
+    /// fail once the peer stops responding
+    ///
+    /// The network sender runs on a blocking task, which stops the paused clock
+    /// from advancing on its own, so the test steps the clock explicitly
+    #[tokio::test(start_paused = true)]
+    async fn test_heartbeat_peer_unreachable() {
+        let config =
+            HeartbeatConfig { heartbeat_interval: Duration::from_millis(20), max_missed: 10 };
+        let (stream0, stream1) = UnboundedDuplexStream::new_duplex_pair();
+        let build_fabric = |party_id, stream| {
+            let network = MockNetwork::new(party_id, stream);
+            MpcFabric::new(network, PartyIDBeaverSource::new(party_id)).with_heartbeat(config)
+        };
+        let fabric0 = build_fabric(PARTY0, stream0);
+        let fabric1 = build_fabric(PARTY1, stream1);
+
+        // Idle for twice the timeout before computing
+        for _ in 0..2 * config.max_missed {
+            tokio::time::advance(config.heartbeat_interval).await;
+        }
+
+        let run = |fabric: MpcFabric<TestCurve>| async move {
+            let a = fabric.share_scalar(2u8, PARTY0);
+            let b = fabric.share_scalar(3u8, PARTY1);
+            (a * b).open().cancellable().await
+        };
+        let (res0, res1) =
+            future::join(tokio::spawn(run(fabric0.clone())), tokio::spawn(run(fabric1.clone())))
+                .await;
+        assert_eq!(res0.unwrap(), Ok(Scalar::from(6u8)));
+        assert_eq!(res1.unwrap(), Ok(Scalar::from(6u8)));
+
+        // Party 1 stops responding
+        fabric1.shutdown();
+        let pending: ScalarResult<TestCurve> = fabric0.receive_value();
+        let res = tokio::select! {
+            res = pending.cancellable() => res,
+            _ = async {
+                loop {
+                    tokio::time::advance(config.heartbeat_interval).await;
+                }
+            } => unreachable!(),
+        };
+
+        assert_eq!(res, Err(MpcError::PeerUnreachable));
+        assert_eq!(fabric0.cancellation_error(), Some(MpcError::PeerUnreachable));
+        fabric0.shutdown();
+    }
+
+    /// Tests that a draining shutdown finishes queued work and resolves all
+    /// outstanding awaiters
+    #[tokio::test]
+    async fn test_shutdown_drain() {
+        const N: usize = 100;
+        let fabric = mock_fabric();
+
+        // Queue a chain of local gates, and a result that never arrives
+        let one = fabric.allocate_scalar(Scalar::from(1u8));
+        let mut local = fabric.allocate_scalar(Scalar::from(0u8));
+        for _ in 0..N {
+            local = &local + &one;
+        }
+        let pending: ScalarResult<TestCurve> = fabric.receive_value();
+        let pending = pending + Scalar::one();
+
+        // Await both before the shutdown so that their waiters are outstanding
+        let local_task = tokio::spawn(local.cancellable());
+        let pending_task = tokio::spawn(pending.cancellable());
+        tokio::task::yield_now().await;
+
+        fabric.shutdown_drain();
+        let local_res = local_task.await.unwrap();
+        let pending_res = pending_task.await.unwrap();
+
+        assert_eq!(local_res, Ok(Scalar::from(N as u64)));
+        assert_eq!(pending_res, Err(MpcError::Cancelled));
+    }
+
+    /// Tests that the square-root protocol always produces bits
+    #[tokio::test]
+    async fn test_random_shared_bits_verified() {
+        const N: usize = 100;
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let bits = fabric.random_shared_bits_verified(N);
+            open_await_all(&bits).await
+        })
+        .await;
+
+        assert!(res.into_iter().all(|b| b == Scalar::zero() || b == Scalar::one()));
+    }
+
+    /// Tests that a zero random value corrupts the verified bits, so that
+    /// opening them fails
+    #[tokio::test]
+    async fn test_random_shared_bits_verified_zero() {
+        const N: usize = 5;
+        const SEED: u64 = 42;
+
+        // Serve triples `(0, b, 0)`, so that the random values squared are zero
+        let file_source = |party_id| {
+            let mut seeded = SeededBeaverSource::new(party_id, SEED);
+            let mut values = PreprocessingValues::new(seeded.get_mac_key_share());
+            let zero = ScalarShare::new(Scalar::zero(), Scalar::zero());
+            values.triples = (0..N).map(|_| (zero, seeded.next_triplet().1, zero)).collect_vec();
+            FileBeaverSource::<TestCurve>::from_values(values)
+        };
+
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| async move {
+                let bits = fabric.random_shared_bits_verified(N);
+                AuthenticatedScalarResult::open_authenticated_batch(&bits)[0].clone().await
+            },
+            file_source(PARTY0),
+            file_source(PARTY1),
+        )
+        .await;
+
+        assert_eq!(res, Err(MpcError::AuthenticationError));
+    }
+
+    /// Tests that square pairs are computed from triples when the offline phase
+    /// does not supply them
+    #[tokio::test]
+    async fn test_square_pairs_from_triples() {
+        const N: usize = 5;
+        const SEED: u64 = 42;
+
+        // Copy triples into sources without square pairs
+        let file_source = |party_id| {
+            let mut seeded = SeededBeaverSource::new(party_id, SEED);
+            let mut values = PreprocessingValues::new(seeded.get_mac_key_share());
+            values.triples = (0..N).map(|_| seeded.next_triplet()).collect_vec();
+            FileBeaverSource::<TestCurve>::from_values(values)
+        };
+
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| async move {
+                let (values, squares) = fabric.random_square_pairs(N);
+                let values = open_await_all(&values).await;
+                let squares = open_await_all(&squares).await;
+
+                (values, squares, fabric.num_triples_consumed())
+            },
+            file_source(PARTY0),
+            file_source(PARTY1),
+        )
+        .await;
+
+        let (values, squares, n_triples) = res;
+        assert_eq!(n_triples, N);
+        for (value, square) in values.into_iter().zip(squares) {
+            assert_eq!(square, value * value);
+        }
+    }
+
+    /// Tests that a bad MAC on the opening used to derive a square from a
+    /// triple corrupts the square, so that opening it fails
+    #[tokio::test]
+    async fn test_square_pairs_from_triples_bad_mac() {
+        const N: usize = 5;
+        const SEED: u64 = 42;
+
+        // Corrupt the MAC of party 0's share of each `b`
+        let file_source = |party_id| {
+            let mut seeded = SeededBeaverSource::new(party_id, SEED);
+            let mut values = PreprocessingValues::new(seeded.get_mac_key_share());
+            values.triples = (0..N)
+                .map(|_| {
+                    let (a, mut b, c) = seeded.next_triplet();
+                    if party_id == PARTY0 {
+                        b.mac += Scalar::one();
+                    }
+
+                    (a, b, c)
+                })
+                .collect_vec();
+            FileBeaverSource::<TestCurve>::from_values(values)
+        };
+
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| async move {
+                let (_, squares) = fabric.random_square_pairs(N);
+                AuthenticatedScalarResult::open_authenticated_batch(&squares)[0].clone().await
+            },
+            file_source(PARTY0),
+            file_source(PARTY1),
+        )
+        .await;
+
+        assert_eq!(res, Err(MpcError::AuthenticationError));
+    }
+
+    /// Multiply two shared values with triples from a dealer that gives party 1
+    /// a share of `c + 1` with a valid MAC for its first triple
+    async fn multiply_with_corrupt_dealer(sacrifice: bool) -> Result<Scalar<TestCurve>, MpcError> {
+        let dealer = |party_id| {
+            let mut source = PartyIDBeaverSource::new(party_id);
+            let mac_key_share = PreprocessingPhase::<TestCurve>::get_mac_key_share(&source);
+            let mut values = PreprocessingValues::new(mac_key_share);
+            values.shared_values = source.next_shared_value_batch(2);
+            values.triples = (0..2).map(|_| source.next_triplet()).collect_vec();
+
+            // Party 1 holds the whole MAC key
+            if party_id == PARTY1 {
+                values.triples[0].2 =
+                    values.triples[0].2 + ScalarShare::new(Scalar::one(), Scalar::one());
+            }
+            FileBeaverSource::from_values(values)
+        };
+
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| async move {
+                fabric.set_sacrifice_triples(sacrifice);
+                let values = fabric.random_shared_scalars(2);
+                (&values[0] * &values[1]).open_authenticated().await
+            },
+            dealer(PARTY0),
+            dealer(PARTY1),
+        )
+        .await;
+
+        res
+    }
+
+    /// Tests that the triple sacrifice check catches a triple whose product is
+    /// wrong but whose MACs are valid
+    #[tokio::test]
+    async fn test_sacrifice_corrupt_triple() {
+        // The shared values are both one, the corrupted triple shifts the product
+        let unchecked = multiply_with_corrupt_dealer(false /* sacrifice */).await;
+        assert_eq!(unchecked, Ok(Scalar::from(2u8)));
+
+        let checked = multiply_with_corrupt_dealer(true /* sacrifice */).await;
+        assert_eq!(checked, Err(MpcError::AuthenticationError));
+    }
+
+    /// Tests that honest triples pass the sacrifice check and that each
+    /// multiplication then consumes two triples
+    #[tokio::test]
+    async fn test_sacrifice_honest_triples() {
+        const N: usize = 5;
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            fabric.set_sacrifice_triples(true);
+            fabric.set_validate_triples(true);
+
+            let a = fabric.batch_share_scalar(vec![Scalar::from(2u8); N], PARTY0 /* sender */);
+            let b = fabric.batch_share_scalar(vec![Scalar::from(3u8); N], PARTY1 /* sender */);
+            let products = AuthenticatedScalarResult::batch_mul(&a, &b);
+            let product = &a[0] * &b[0];
+
+            let opened =
+                future::join_all(products.iter().chain([&product]).map(|p| p.open_authenticated()))
+                    .await;
+            (opened, fabric.num_triples_consumed())
+        })
+        .await;
+
+        let (opened, n_triples) = res;
+        assert_eq!(n_triples, 2 * (N + 1));
+        assert!(opened.into_iter().all(|p| p == Ok(Scalar::from(6u8))));
+    }
+
+    /// An asynchronous triple source that holds each batch until it is released
+    struct SlowTripleSource {
+        /// The source of the triples
+        source: PartyIDBeaverSource,
+        /// The permits to release a batch of triples
+        release: Arc<Semaphore>,
+    }
+
+    #[async_trait]
+    impl AsyncPreprocessingPhase<TestCurve> for SlowTripleSource {
+        async fn fetch_triplet_batch(
+            &mut self,
+            num_triplets: usize,
+        ) -> Result<
+            (Vec<ScalarShare<TestCurve>>, Vec<ScalarShare<TestCurve>>, Vec<ScalarShare<TestCurve>>),
+            PreprocessingError,
+        > {
+            self.release.acquire().await.unwrap().forget();
+            self.source.try_next_triplet_batch(num_triplets)
+        }
+    }
+
+    /// Tests that gates which do not depend on a pending asynchronous triple
+    /// fetch still make progress
+    #[tokio::test]
+    async fn test_async_triples() {
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let release = Arc::new(Semaphore::new(0));
+            let source = PartyIDBeaverSource::new(fabric.party_id());
+            let fabric = fabric.with_async_triples(
+                SlowTripleSource { source, release: release.clone() },
+                &Handle::current(),
+            );
+
+            let a = fabric.share_scalar(2u8, PARTY0 /* sender */);
+            let b = fabric.share_scalar(3u8, PARTY1 /* sender */);
+            let product = &a * &b;
+
+            // The sum resolves while the product's triple is held
+            let sum = (&a + &b).open_authenticated().await.unwrap();
+            release.add_permits(1);
+            let product = product.open_authenticated().await.unwrap();
+
+            (sum, product)
+        })
+        .await;
+
+        assert_eq!(res, (Scalar::from(5u8), Scalar::from(6u8)));
+    }
+
+    /// Tests that a known circuit mixing gadgets consumes exactly one triple
+    /// per shared multiplication with triple validation enabled
+    #[tokio::test]
+    async fn test_triple_validation() {
+        const N: usize = 10;
+        // 1 scalar mul, N batched scalar muls, 1 point mul, N batched point muls,
+        // a product of N values which takes N - 1 muls, an inner product of N
+        // muls, an xor of 1 mul, and a batched or of N muls
+        const N_MULTS: usize = 1 + N + 1 + N + (N - 1) + N + 1 + N;
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            fabric.set_validate_triples(true);
+            let generator = CurvePoint::<TestCurve>::generator();
+
+            let a = fabric.batch_share_scalar(vec![Scalar::from(2u8); N], PARTY0);
+            let b = fabric.batch_share_scalar(vec![Scalar::from(3u8); N], PARTY1);
+            let bits = fabric.batch_share_scalar(vec![Scalar::one(); N], PARTY0);
+            let points = fabric.batch_share_point(vec![generator; N], PARTY0);
+
+            let _scalar_prod = &a[0] * &b[0];
+            let _scalar_prods = AuthenticatedScalarResult::batch_mul(&a, &b);
+            let _point_prod = &points[0] * &a[0];
+            let _point_prods = AuthenticatedPointResult::batch_mul(&a, &points);
+            let _product: AuthenticatedScalarResult<TestCurve> = b.iter().cloned().product();
+            let _inner_product = AuthenticatedScalarResult::inner_product(&a, &b);
+            let _xor = gadgets::bit_xor(&bits[0], &bits[1]);
+            let _or = gadgets::bit_or_batch(&bits, &bits);
+
+            fabric.check_triple_consumption();
+            fabric.num_triples_consumed()
+        })
+        .await;
+
+        assert_eq!(res, N_MULTS);
+    }
+
+    /// Tests that drawing a triple outside of a multiplication fails triple
+    /// validation
+    #[tokio::test]
+    #[should_panic(expected = "triple validation failed")]
+    async fn test_triple_validation_extra_draw() {
+        let fabric = mock_fabric();
+        fabric.set_validate_triples(true);
+
+        let a = fabric.allocate_scalar_share(ScalarShare::new(Scalar::one(), Scalar::one()));
+        let _prod = &a * &a;
+        let _extra = fabric.next_triple();
+        fabric.check_triple_consumption();
+    }
+
+    /// Tests the reported depth of a chain of shared multiplications
+    #[tokio::test]
+    async fn test_circuit_depth() {
+        const N: usize = 5;
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            // Each multiplication opens its masked inputs, adding one round
+            let values = fabric.random_shared_scalars(N + 1);
+            let product = values.iter().skip(1).fold(values[0].clone(), |acc, x| acc * x);
+            product.clone().await;
+            let depth_product = fabric.circuit_depth();
+
+            // The open adds a round, and the opened value depends on the share
+            // received from the peer in that round, so exchanging it adds another
+            let opened = product.open();
+            fabric.exchange_value(opened).await;
+
+            (depth_product, fabric.circuit_depth(), fabric.network_stats().num_rounds())
+        })
+        .await;
+
+        assert_eq!(res, (N, N + 2, N + 2));
+    }
+
+    /// Tests that allocating an interned constant many times allocates a single
+    /// result, while reserving the same result IDs as without interning
+    #[tokio::test]
+    async fn test_intern_constants() {
+        const N: usize = 1000;
+        let fabric = mock_fabric();
+        fabric.set_intern_constants(true);
+
+        let next_id = fabric.inner.next_result_id.load(Ordering::Acquire);
+        let values = (0..N).map(|_| fabric.allocate_scalar(7u8)).collect_vec();
+        let batch = fabric.allocate_scalars(vec![7u8, 8u8]);
+        assert!(values.iter().chain(&batch[..1]).all(|value| value.id == values[0].id));
+        assert_ne!(batch[1].id, values[0].id);
+        assert_eq!(fabric.inner.next_result_id.load(Ordering::Acquire), next_id + N + 2);
+
+        let sum = values.iter().skip(1).fold(values[0].clone(), |acc, value| acc + value);
+        assert_eq!(sum.await, Scalar::from(7 * N as u64));
+
+        // Without interning each allocation is a distinct result
+        fabric.set_intern_constants(false);
+        let (a, b) = (fabric.allocate_scalar(7u8), fabric.allocate_scalar(7u8));
+        assert_ne!(a.id, b.id);
+        fabric.shutdown();
+    }
+
+    /// Tests rendering the dependency graph of a small circuit
+    #[tokio::test]
+    async fn test_to_dot() {
+        let fabric = mock_fabric();
+        let a = fabric.allocate_scalar(1u8);
+        let b = fabric.allocate_scalar(2u8);
+
+        // Only operations allocated while recording appear in the graph
+        let _ignored = &a + &b;
+        fabric.set_record_graph(true);
+        let c = &a + &b;
+        let d = &c * &a;
+
+        let dot = fabric.to_dot();
+        assert!(dot.starts_with("digraph circuit {"));
+        assert_eq!(dot.matches("[label=\"gate\"]").count(), 2);
+        assert_eq!(dot.matches("shape=box").count(), 2);
+        assert_eq!(dot.matches(" -> ").count(), 4);
+        assert!(dot.contains(&format!("[label=\"{}\"]", c.id)));
+
+        assert_eq!(d.await, Scalar::from(3u8));
+        fabric.set_record_graph(false);
+        assert_eq!(fabric.to_dot(), "digraph circuit {\n}\n");
+        fabric.shutdown();
+    }
+
+    /// Tests that a batch of independent multiplications takes a single round
+    /// regardless of its size
+    #[tokio::test]
+    async fn test_batch_mul_rounds() {
+        for n in [1, 10, 100] {
+            let (res, _) = execute_mock_mpc(|fabric| async move {
+                let values = fabric.random_shared_scalars(2 * n);
+                let (lhs, rhs): (Vec<_>, Vec<_>) = values.iter().cloned().tuples().unzip();
+                let products = AuthenticatedScalarResult::batch_mul(&lhs, &rhs);
+                future::join_all(products.iter().cloned()).await;
+                let rounds = fabric.circuit_depth();
+
+                (rounds, open_await_all(&values).await, open_await_all(&products).await)
+            })
+            .await;
+
+            let (rounds, values, products) = res;
+            assert_eq!(rounds, 1);
+            for (ab, product) in values.chunks(2).zip(products) {
+                assert_eq!(product, ab[0] * ab[1]);
+            }
+        }
+    }
+
+    /// Tests that an operation mixing results from two fabrics is rejected
+    #[tokio::test]
+    #[should_panic(expected = "cannot operate on results allocated in different fabrics")]
+    async fn test_mixed_fabrics() {
+        let fabric1 = mock_fabric();
+        let fabric2 = mock_fabric();
+        assert!(fabric1.same_fabric(&fabric1.clone()));
+        assert!(!fabric1.same_fabric(&fabric2));
+
+        let a = fabric1.allocate_scalar(1u8);
+        let b = fabric2.allocate_scalar(2u8);
+        drop(a + b);
+    }
+
+    /// Tests that a batch operation mixing results from two fabrics is
+    /// rejected
+    #[tokio::test]
+    #[should_panic(expected = "cannot operate on results allocated in different fabrics")]
+    async fn test_mixed_fabrics_batch() {
+        let fabric1 = mock_fabric();
+        let fabric2 = mock_fabric();
+
+        let a = fabric1.allocate_scalars(vec![1u8, 2u8]);
+        let b = vec![fabric1.allocate_scalar(3u8), fabric2.allocate_scalar(4u8)];
+        ScalarResult::batch_add(&a, &b);
+    }
+
+    /// Tests that mixing in a result from another fabric is rejected even when
+    /// its ID is one this fabric has already allocated
+    #[tokio::test]
+    #[should_panic(expected = "cannot operate on results allocated in different fabrics")]
+    async fn test_mixed_fabrics_low_id() {
+        let fabric1 = mock_fabric();
+        let fabric2 = mock_fabric();
+
+        let mut values = fabric1.allocate_scalars(vec![1u8; 10]);
+        let foreign = fabric2.allocate_scalar(2u8);
+        let next_id = fabric1.inner.next_result_id.load(Ordering::Acquire);
+        assert!(foreign.id() < next_id);
+
+        values.push(foreign);
+        let _sum: ScalarResult<TestCurve> = values.into_iter().sum();
+    }
+
+    /// Tests that a gate taking a result the fabric has not yet allocated is
+    /// rejected
+    #[cfg(debug_assertions)]
+    #[tokio::test]
+    #[should_panic(expected = "DependencyCycle")]
+    async fn test_gate_unallocated_arg() {
+        let fabric = mock_fabric();
+
+        let next_id = fabric.inner.next_result_id.load(Ordering::Acquire);
+        let _res: ScalarResult<TestCurve> =
+            fabric.new_gate_op(vec![next_id + 10], |mut args| args.next().unwrap());
+    }
+
+    /// Tests summing empty, single-element, and many-element iterators through
+    /// the fabric
+    #[tokio::test]
+    async fn test_fabric_sum() {
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let mut sums = Vec::new();
+            for n in [0, 1, 5] {
+                let values = fabric.random_shared_scalars(n);
+                let points = values.iter().map(|v| v * CurvePoint::generator()).collect_vec();
+                let public = values.iter().map(|v| v.open()).collect_vec();
+
+                let shared_sum: AuthenticatedScalarResult<TestCurve> =
+                    fabric.sum(values.iter().cloned());
+                let point_sum: AuthenticatedPointResult<TestCurve> = fabric.sum(points);
+                let public_sum: ScalarResult<TestCurve> = fabric.sum(public);
+
+                let expected: Scalar<TestCurve> = open_await_all(&values).await.into_iter().sum();
+                sums.push((
+                    expected,
+                    shared_sum.open_authenticated().await.unwrap(),
+                    point_sum.open_authenticated().await.unwrap(),
+                    public_sum.await,
+                ));
+            }
+
+            sums
+        })
+        .await;
+
+        for (expected, shared_sum, point_sum, public_sum) in res {
+            assert_eq!(shared_sum, expected);
+            assert_eq!(point_sum, expected * CurvePoint::generator());
+            assert_eq!(public_sum, expected);
+        }
+    }
+
+    /// Tests multiplying empty, single-element, and many-element iterators
+    /// through the fabric
+    #[tokio::test]
+    async fn test_fabric_product() {
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let mut products = Vec::new();
+            for n in [0, 1, 5] {
+                let values = fabric.random_shared_scalars(n);
+                let public = values.iter().map(|v| v.open()).collect_vec();
+
+                let shared_product: AuthenticatedScalarResult<TestCurve> =
+                    fabric.product(values.iter().cloned());
+                let public_product: ScalarResult<TestCurve> = fabric.product(public);
+
+                let expected: Scalar<TestCurve> =
+                    open_await_all(&values).await.into_iter().product();
+                products.push((
+                    expected,
+                    shared_product.open_authenticated().await.unwrap(),
+                    public_product.await,
+                ));
+            }
+
+            products
+        })
+        .await;
+
+        for (expected, shared_product, public_product) in res {
+            assert_eq!(shared_product, expected);
+            assert_eq!(public_product, expected);
+        }
+    }
+
+    /// Tests that sharing and opening empty batches returns empty results
+    /// without exchanging any messages
+    #[tokio::test]
+    async fn test_empty_batches() {
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let scalars = fabric.batch_share_scalar(Vec::<Scalar<TestCurve>>::new(), PARTY0);
+            let points = fabric.batch_share_point(vec![], PARTY1);
+            let plaintext = fabric.batch_share_plaintext(Vec::<Scalar<TestCurve>>::new(), PARTY0);
+            let products = AuthenticatedScalarResult::<TestCurve>::batch_mul(&[], &[]);
+
+            let scalar_opens = AuthenticatedScalarResult::open_batch(&scalars);
+            let scalar_auth_opens = AuthenticatedScalarResult::open_authenticated_batch(&scalars);
+            let point_opens = AuthenticatedPointResult::open_batch(&points);
+            let point_auth_opens = AuthenticatedPointResult::open_authenticated_batch(&points);
+
+            let all_empty = scalars.is_empty()
+                && points.is_empty()
+                && plaintext.is_empty()
+                && products.is_empty()
+                && scalar_opens.is_empty()
+                && scalar_auth_opens.is_empty()
+                && point_opens.is_empty()
+                && point_auth_opens.is_empty();
+            (all_empty, fabric.network_stats().messages_sent())
+        })
+        .await;
+
+        let (all_empty, messages_sent) = res;
+        assert!(all_empty);
+        assert_eq!(messages_sent, 0);
+    }
+
+    /// Tests the network counters after running a chain of multiplications
+    #[tokio::test]
+    async fn test_network_stats() {
+        const N: usize = 5;
+
+        // Each party's final message has been sent once the peer's open completes
+        let (fabric0, fabric1) = execute_mock_mpc(|fabric| async move {
+            let values = fabric.random_shared_scalars(N + 1);
+            let product = values.iter().skip(1).fold(values[0].clone(), |acc, x| acc * x);
+            product.open().await;
+
+            fabric
+        })
+        .await;
+
+        // Each multiplication and the final open exchange at least one message in
+        // their own round
+        let (stats0, stats1) = (fabric0.network_stats(), fabric1.network_stats());
+        assert!(stats0.messages_sent() > N);
+        assert_eq!(stats0.messages_sent(), stats1.messages_received());
+        assert_eq!(stats0.num_rounds(), N + 1);
+        assert_eq!(stats1.num_rounds(), N + 1);
+
+        // Bytes are only counted with the `stats` feature
+        #[cfg(feature = "stats")]
+        {
+            assert_eq!(stats0.bytes_sent(), stats1.bytes_received());
+            assert!(stats0.bytes_sent() > stats0.messages_sent());
+        }
+    }
+
+    /// A network that counts the transmissions sent on it, and holds them
+    /// until its gate is opened
+    struct CountingNetwork {
+        /// The underlying network
+        network: MockNetwork<TestCurve>,
+        /// The number of transmissions sent
+        n_sends: Arc<AtomicUsize>,
+        /// Whether transmissions may be sent
+        gate: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl MpcNetwork<TestCurve> for CountingNetwork {
+        fn party_id(&self) -> PartyId {
+            self.network.party_id()
+        }
+
+        async fn close(&mut self) -> Result<(), MpcNetworkError> {
+            MpcNetwork::close(&mut self.network).await
+        }
+    }
+
+    impl Stream for CountingNetwork {
+        type Item = Result<NetworkOutbound<TestCurve>, MpcNetworkError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.network.poll_next_unpin(cx)
+        }
+    }
+
+    impl Sink<NetworkOutbound<TestCurve>> for CountingNetwork {
+        type Error = MpcNetworkError;
+
+        fn poll_ready(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            if !self.gate.load(Ordering::Acquire) {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            self.network.poll_ready_unpin(cx)
+        }
+
+        fn start_send(
+            mut self: Pin<&mut Self>,
+            item: NetworkOutbound<TestCurve>,
+        ) -> Result<(), Self::Error> {
+            self.n_sends.fetch_add(1, Ordering::Relaxed);
+            self.network.start_send_unpin(item)
+        }
+
+        fn poll_flush(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            self.network.poll_flush_unpin(cx)
+        }
+
+        fn poll_close(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            self.network.poll_close_unpin(cx)
+        }
+    }
+
+    /// Tests that the messages of a wide layer queued behind a pending
+    /// transmission are coalesced into a single transmission
+    #[tokio::test]
+    async fn test_coalesce_wide_layer() {
+        const WIDTH: usize = 50;
+
+        let (stream0, stream1) = UnboundedDuplexStream::new_duplex_pair();
+        let build_fabric = |party_id, stream| {
+            let n_sends = Arc::new(AtomicUsize::new(0));
+            let gate = Arc::new(AtomicBool::new(false));
+            let network = CountingNetwork {
+                network: MockNetwork::new(party_id, stream),
+                n_sends: n_sends.clone(),
+                gate: gate.clone(),
+            };
+
+            (MpcFabric::new(network, PartyIDBeaverSource::new(party_id)), n_sends, gate)
+        };
+
+        // Each multiplication sends one message per party. The first message of
+        // the layer is held at the network while the rest queue behind it, the
+        // messages of a batch are counted as sent before it is transmitted
+        let run = |fabric: MpcFabric<TestCurve>,
+                   n_sends: Arc<AtomicUsize>,
+                   gate: Arc<AtomicBool>| async move {
+            let values = fabric.random_shared_scalars(2 * WIDTH);
+            let products = values.chunks(2).map(|pair| &pair[0] * &pair[1]).collect_vec();
+
+            let network_stats = fabric.network_stats();
+            while network_stats.messages_sent() + fabric.inner.outbound_queue.len() < WIDTH {
+                tokio::task::yield_now().await;
+            }
+            gate.store(true, Ordering::Release);
+
+            future::join_all(products.iter().cloned()).await;
+            let layer_sends = n_sends.load(Ordering::Relaxed);
+            let layer_messages = network_stats.messages_sent();
+
+            let opened = future::join_all(products.iter().map(|p| p.open())).await;
+            fabric.shutdown();
+            (opened, layer_sends, layer_messages)
+        };
+        let (fabric0, n_sends0, gate0) = build_fabric(PARTY0, stream0);
+        let (fabric1, n_sends1, gate1) = build_fabric(PARTY1, stream1);
+        let (res0, res1) = future::join(
+            tokio::spawn(run(fabric0, n_sends0, gate0)),
+            tokio::spawn(run(fabric1, n_sends1, gate1)),
+        )
+        .await;
+
+        let (opened0, sends0, messages0) = res0.unwrap();
+        let (opened1, sends1, messages1) = res1.unwrap();
+        assert_eq!(opened0, opened1);
+        for (sends, messages) in [(sends0, messages0), (sends1, messages1)] {
+            assert_eq!(messages, WIDTH);
+            assert!(sends <= 2);
+        }
+    }
+
+    /// Tests reading the executor's stats after running a small circuit
+    #[cfg(feature = "stats")]
+    #[tokio::test]
+    async fn test_stats_snapshot() {
+        const N: usize = 5;
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let values = fabric.random_shared_scalars(N + 1);
+            let product = values.iter().skip(1).fold(values[0].clone(), |acc, x| acc * x);
+            product.open().await;
+
+            await_idle_stats(&fabric).await
+        })
+        .await;
+
+        assert!(res.n_ops > 0);
+        assert!(res.n_network_ops > 0);
+        assert_eq!(res.max_depth, N + 1);
+    }
+
+    /// Tests that a bursty workload spreads the queue length histogram across
+    /// multiple buckets
+    #[cfg(feature = "stats")]
+    #[tokio::test]
+    async fn test_queue_length_histogram() {
+        const N: usize = 1000;
+        let fabric = mock_fabric();
+
+        // Stall the executor on a slow gate while a burst of work queues behind it
+        let one = fabric.allocate_scalar(Scalar::from(1u8));
+        let _slow: ScalarResult<TestCurve> = fabric.new_gate_op(vec![one.id()], |mut args| {
+            std::thread::sleep(Duration::from_millis(50));
+            args.next().unwrap()
+        });
+        let mut res = one.clone();
+        for _ in 0..N {
+            res = &res + &one;
+        }
+        res.await;
+
+        let stats = await_idle_stats(&fabric).await;
+
+        let histogram = stats.queue_length_histogram;
+        let n_buckets = histogram.buckets().filter(|(_, count)| *count > 0).count();
+        assert!(n_buckets > 1);
+        assert!(histogram.buckets().any(|(range, count)| *range.start() > 64 && count > 0));
+        fabric.shutdown();
+    }
+
+    /// Tests that the executor reports buffer growth when a circuit outgrows
+    /// its size hint
+    #[cfg(feature = "stats")]
+    #[tokio::test]
+    async fn test_buffer_growth_stats() {
+        use crate::{test_helpers::execute_mock_mpc_with_size_hint, ExecutorSizeHints};
+
+        const N: usize = 100;
+        let size_hint = ExecutorSizeHints { n_ops: 2, n_results: 2 };
+
+        let (res, _) = execute_mock_mpc_with_size_hint(
+            |fabric| async move {
+                let values = fabric.random_shared_scalars(N);
+                let sum: AuthenticatedScalarResult<TestCurve> = values.into_iter().sum();
+                sum.open().await;
+
+                await_idle_stats(&fabric).await
+            },
+            size_hint,
+        )
+        .await;
+
+        assert!(res.operations_buffer.n_grows > 0);
+        assert!(res.results_buffer.n_grows > 0);
+        assert!(res.results_buffer.high_water >= N);
+    }
+
+    /// Tests flushing the MAC checks of a batch of deferred openings
+    #[tokio::test]
+    async fn test_flush_mac_checks() {
+        const N: usize = 100;
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let values = fabric.random_shared_scalars(N);
+            let expected = values.iter().map(|v| v.open()).collect_vec();
+            let opens = values.iter().map(|v| v.open_authenticated_deferred()).collect_vec();
+            fabric.flush_mac_checks();
+
+            let opened = future::join_all(opens).await;
+            let expected = future::join_all(expected).await;
+            opened.into_iter().collect::<Result<Vec<_>, _>>().map(|res| res == expected)
+        })
+        .await;
+
+        assert_eq!(res, Ok(true));
+    }
+
+    /// Tests that a flush fails every deferred opening when one MAC is invalid
+    #[tokio::test]
+    async fn test_flush_mac_checks_invalid_mac() {
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let valid = fabric.random_shared_scalars(10);
+
+            // Party 1 corrupts its MAC share of a sharing of one
+            let share = if fabric.party_id() == PARTY0 {
+                ScalarShare::new(Scalar::zero(), Scalar::zero())
+            } else {
+                ScalarShare::new(Scalar::one(), Scalar::from(2u8))
+            };
+            let invalid = fabric.allocate_scalar_share(share);
+
+            let valid_open = valid[0].open_authenticated_deferred();
+            let invalid_open = invalid.open_authenticated_deferred();
+            fabric.flush_mac_checks();
+
+            (valid_open.await, invalid_open.await)
+        })
+        .await;
+
+        assert_eq!(res, (Err(MpcError::AuthenticationError), Err(MpcError::AuthenticationError)));
+    }
+
+    /// Tests that a flush checks all deferred openings in a single commitment
+    /// round
+    #[cfg(feature = "stats")]
+    #[tokio::test]
+    async fn test_flush_mac_checks_single_round() {
+        const N: usize = 100;
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            let values = fabric.random_shared_scalars(N);
+            let opens = values.iter().map(|v| v.open_authenticated_deferred()).collect_vec();
+            fabric.flush_mac_checks();
+            future::join_all(opens).await;
+
+            await_idle_stats(&fabric).await
+        })
+        .await;
+
+        // One network op per opening, plus the commitment, the combined check,
+        // and the blinder
+        assert_eq!(res.n_network_ops, N + 3);
+    }
+
+    /// Tests that dropping one deferred opening before a flush does not
+    /// reclaim the inputs or the combined check shared with the others when
+    /// result GC is enabled
+    #[tokio::test]
+    async fn test_flush_mac_checks_gc() {
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            fabric.enable_result_gc();
+
+            let values = fabric.random_shared_scalars(2);
+            let dropped = values[0].open_authenticated_deferred();
+            let kept = values[1].open_authenticated_deferred();
+            drop((dropped, values));
+            fabric.flush_mac_checks();
+
+            // Let the executor reclaim whatever was released before awaiting
+            fabric.allocate_scalar(Scalar::one()).await;
+            kept.await.is_ok()
+        })
+        .await;
+
+        assert!(res);
+    }
+
+    /// Tests that the number of live results stays bounded over repeated
+    /// sub-circuits when result GC is enabled
+    #[tokio::test]
+    async fn test_result_gc() {
+        const N_ITERS: usize = 10;
+
+        let (res, _) = execute_mock_mpc(|fabric| async move {
+            fabric.enable_result_gc();
+
+            let mut live_results = Vec::with_capacity(N_ITERS);
+            let mut flush_ids = Vec::with_capacity(N_ITERS);
+            for _ in 0..N_ITERS {
+                let values = fabric.random_shared_scalars(2);
+                let product = &values[0] * &values[1];
+                product.open_authenticated().await.unwrap();
+                drop(values);
+
+                // The executor processes its queue in order, so once this value is
+                // available all the releases above have been processed
+                let flush = fabric.allocate_scalar(Scalar::one());
+                flush.clone().await;
+                live_results.push(fabric.num_live_results());
+                flush_ids.push(flush.id());
+            }
+
+            (live_results, flush_ids)
+        })
+        .await;
+
+        // Without GC the live results would grow by one iteration's worth of
+        // results each iteration. With GC only the handles still in scope and
+        // values the peer has sent ahead remain
+        let (live_results, flush_ids) = res;
+        let results_per_iter = flush_ids[1] - flush_ids[0];
+        assert!(live_results.iter().all(|n| *n < results_per_iter), "{live_results:?}");
+    }
+
+    /// Tests that two fabrics seeded identically sample the same commitment
+    /// blinders
+    #[tokio::test]
+    async fn test_seeded_blinders() {
+        const N: usize = 5;
+        let commit_n = |fabric: &MpcFabric<TestCurve>| {
+            (0..N)
+                .map(|_| HashCommitmentResult::commit(fabric.allocate_scalar(1u8)).blinder)
+                .collect_vec()
+        };
+
+        let blinders1 = commit_n(&mock_fabric().with_seed(42));
+        let blinders2 = commit_n(&mock_fabric().with_seed(42));
+        let blinders3 = commit_n(&mock_fabric().with_seed(43));
+
+        assert_eq!(blinders1, blinders2);
+        assert_ne!(blinders1, blinders3);
+    }
+
+    /// Run a circuit in which a network op and several local ops become ready
+    /// at once, returning the order in which they execute
+    async fn ready_op_order(prioritize_network: bool) -> Vec<&'static str> {
+        let fabric = mock_fabric();
+        fabric.set_network_priority(prioritize_network);
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        // Allocate the ops on an input that is not yet available
+        let input: ScalarResult<TestCurve> = fabric.receive_value();
+        let order_clone = order.clone();
+        let mut outputs: Vec<ScalarResult<TestCurve>> =
+            vec![fabric.new_network_op(vec![input.id()], move |mut args| {
+                order_clone.lock().unwrap().push("network");
+                NetworkPayload::Scalar(args.next().unwrap().into())
+            })];
+        for _ in 0..3 {
+            let order_clone = order.clone();
+            outputs.push(fabric.new_gate_op(vec![input.id()], move |mut args| {
+                order_clone.lock().unwrap().push("local");
+                args.next().unwrap()
+            }));
+        }
+
+        // Deliver the input, making all the ops ready together
+        let value = ResultValue::Scalar(Scalar::one());
+        fabric
+            .inner
+            .execution_queue
+            .push(ExecutorMessage::Result(OpResult { id: input.id(), value }));
+        future::join_all(outputs).await;
+
+        let order = order.lock().unwrap().clone();
+        order
+    }
+
+    /// Tests that ready network ops are executed ahead of ready local ops when
+    /// network priority is enabled
+    #[tokio::test]
+    async fn test_network_priority() {
+        let fifo_order = ready_op_order(false).await;
+        let priority_order = ready_op_order(true).await;
+
+        assert_ne!(fifo_order[0], "network");
+        assert_eq!(priority_order, vec!["network", "local", "local", "local"]);
+    }
+
+    /// Tests that an operation depending on its own output is rejected
+    #[cfg(debug_assertions)]
+    #[tokio::test]
+    #[should_panic(expected = "DependencyCycle")]
+    async fn test_dependency_cycle() {
+        let fabric = mock_fabric();
+
+        // Reference the ID the gate's own output will be assigned
+        let next_id = fabric.inner.next_result_id.load(Ordering::Acquire);
+        let _res: ScalarResult<TestCurve> =
+            fabric.new_gate_op(vec![next_id], |mut args| args.next().unwrap());
+    }
+
+    /// An executor backend that counts the operations it receives, then
+    /// defers to the serial executor
+    struct CountingExecutor {
+        /// The underlying executor
+        inner: SerialExecutor<TestCurve>,
+        /// The number of operations received
+        n_ops: Arc<AtomicUsize>,
+    }
+
+    impl ExecutorBackend<TestCurve> for CountingExecutor {
+        fn job_queue(&self) -> &ExecutorJobQueue<TestCurve> {
+            self.inner.job_queue()
+        }
+
+        fn handle_new_result(&mut self, result: OpResult<TestCurve>) {
+            self.inner.handle_new_result(result)
+        }
+
+        fn handle_new_operation(&mut self, op: Operation<TestCurve>) {
+            self.n_ops.fetch_add(1, Ordering::Relaxed);
+            self.inner.handle_new_operation(op)
+        }
+
+        fn handle_new_waiter(&mut self, waiter: ResultWaiter<TestCurve>) {
+            self.inner.handle_new_waiter(waiter)
+        }
+    }
+
+    /// Tests running the fabric on a custom executor backend
+    #[tokio::test]
+    async fn test_custom_executor() {
+        const N: usize = 10;
+        let n_ops = Arc::new(AtomicUsize::new(0));
+
+        let n_ops_clone = n_ops.clone();
+        let fabric = MpcFabric::with_executor(
+            NoRecvNetwork::default(),
+            PartyIDBeaverSource::default(),
+            move |job_queue, network_outbound| CountingExecutor {
+                inner: SerialExecutor::new(
+                    ExecutorSizeHints::default(),
+                    job_queue,
+                    network_outbound,
+                ),
+                n_ops: n_ops_clone,
+            },
+        );
+
+        // Compute `N` gates as a chain of additions
+        let one = fabric.allocate_scalar(Scalar::from(1u8));
+        let mut res = fabric.allocate_scalar(Scalar::from(0u8));
+        for _ in 0..N {
+            res = &res + &one;
+        }
+
+        assert_eq!(res.await, Scalar::from(N as u64));
+        assert_eq!(n_ops.load(Ordering::Relaxed), N);
+        fabric.shutdown();
+    }
+
+    /// Tests that the circuit depth is tracked as gates are allocated, and that
+    /// rounds are counted on a custom executor backend
+    #[tokio::test]
+    async fn test_circuit_depth_custom_executor() {
+        let fabric = MpcFabric::with_executor(
+            NoRecvNetwork::default(),
+            PartyIDBeaverSource::default(),
+            move |job_queue, network_outbound| CountingExecutor {
+                inner: SerialExecutor::new(
+                    ExecutorSizeHints::default(),
+                    job_queue,
+                    network_outbound,
+                ),
+                n_ops: Arc::default(),
+            },
+        );
+
+        // Two dependent sends with a local gate between them
+        let one = fabric.allocate_scalar(Scalar::from(1u8));
+        let sent = fabric.send_value(one.clone());
+        let sum = &sent + &one;
+        let res = fabric.send_value(sum);
+        assert_eq!(fabric.circuit_depth(), 2);
+
+        assert_eq!(res.await, Scalar::from(2u8));
+        assert_eq!(fabric.network_stats().num_rounds(), 2);
+        fabric.shutdown();
+    }
+
+    /// Tests that the trace hook observes each completed operation
+    #[tokio::test]
+    async fn test_trace_hook() {
+        const N: usize = 10;
+        let fabric = mock_fabric();
+        let completions = Arc::new(Mutex::new(Vec::new()));
+
+        let completions_clone = completions.clone();
+        fabric.set_trace_hook(move |res, kind| {
+            completions_clone.lock().unwrap().push((res.id, kind));
+        });
+
+        // Compute `N` gates as a chain of additions
+        let mut res = fabric.allocate_scalar(Scalar::from(0u8));
+        let one = fabric.allocate_scalar(Scalar::from(1u8));
+        for _ in 0..N {
+            res = &res + &one;
+        }
+        let out = res.clone().await;
+        assert_eq!(out, Scalar::from(N as u64));
+
+        let completions = completions.lock().unwrap();
+        assert_eq!(completions.len(), N);
+        assert!(completions.iter().all(|(_, kind)| *kind == OperationKind::Gate));
+        assert_eq!(completions.last().unwrap().0, res.id());
+        fabric.shutdown();
+    }
 }
@@ -7,19 +7,45 @@
 pub struct GrowableBuffer<T: Clone> {
     /// The underlying buffer
     buf: Vec<Option<T>>,
+    /// The number of times the buffer has grown past its allocation
+    #[cfg(feature = "stats")]
+    n_grows: usize,
 }
 
 #[allow(unsafe_code)]
 impl<T: Clone> GrowableBuffer<T> {
     /// Constructor, takes a size-hint to pre-allocate buffer slots
     pub fn new(size_hint: usize) -> Self {
-        Self { buf: vec![None; size_hint] }
+        Self {
+            buf: vec![None; size_hint],
+            #[cfg(feature = "stats")]
+            n_grows: 0,
+        }
     }
 
     /// Grow the underlying buffer
     fn grow(&mut self, access_idx: usize) {
         let new_size = usize::max(access_idx + 1, self.buf.len() * 2);
         self.buf.resize(new_size, None);
+
+        #[cfg(feature = "stats")]
+        {
+            self.n_grows += 1;
+        }
+    }
+
+    /// The number of slots currently allocated in the buffer
+    ///
+    /// The buffer never shrinks, so this is also its high-water size
+    #[cfg(feature = "stats")]
+    pub fn size(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// The number of times the buffer has grown past its allocation
+    #[cfg(feature = "stats")]
+    pub fn n_grows(&self) -> usize {
+        self.n_grows
     }
 
     /// Get the element at the given index in the buffer, returns `None` if the
@@ -60,6 +86,11 @@ impl<T: Clone> GrowableBuffer<T> {
         unsafe { *self.buf.get_unchecked_mut(idx) = Some(val) };
     }
 
+    /// Iterate over the values set in the buffer
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.buf.iter().flatten()
+    }
+
     /// Take ownership of a value at a given index
     pub fn take(&mut self, idx: usize) -> Option<T> {
         unsafe { self.buf.get_unchecked_mut(idx).take() }
@@ -120,4 +151,18 @@ mod test {
         assert_eq!(buf.take(2), Some(2));
         assert_eq!(buf.get(2), None);
     }
+
+    /// Tests that the buffer counts the times it grows
+    #[cfg(feature = "stats")]
+    #[test]
+    fn test_grow_count() {
+        let mut buf: GrowableBuffer<u64> = GrowableBuffer::new(2);
+        buf.insert(1, 1);
+        assert_eq!(buf.n_grows(), 0);
+
+        buf.insert(2, 2);
+        buf.insert(10, 10);
+        assert_eq!(buf.n_grows(), 2);
+        assert_eq!(buf.size(), 11);
+    }
 }
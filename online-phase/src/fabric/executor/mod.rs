@@ -1,14 +1,19 @@
 //! Executor implementations
 
-use std::sync::Arc;
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+};
 
 use ark_ec::CurveGroup;
 use crossbeam::queue::SegQueue;
+use tracing::log;
 
 use super::result::{OpResult, ResultWaiter};
-use crate::fabric::Operation;
-#[cfg(feature = "multithreaded_executor")]
-use crate::fabric::ResultId;
+use crate::fabric::{Operation, OperationKind, ResultId};
 
 pub(crate) mod buffer;
 #[cfg(feature = "multithreaded_executor")]
@@ -52,6 +57,134 @@ pub enum ExecutorMessage<C: CurveGroup> {
     NewWaiter(ResultWaiter<C>),
     /// Indicates that the executor should shut down
     Shutdown,
+    /// Indicates that the executor should finish the jobs already queued, then
+    /// set the given cancellation flag, wake all outstanding waiters, and shut
+    /// down
+    ShutdownDrain(Arc<AtomicBool>),
+    /// Indicates that the executor should shut down once it is idle, i.e. no
+    /// operation is waiting on its inputs and no task is waiting on a result.
+    /// The executor then sets the given cancellation flag, so that results
+    /// awaited afterwards observe the shutdown, and signals the given channel
+    ///
+    /// A backend that cannot tell when it is idle holds the channel until it
+    /// shuts down for another reason
+    ShutdownWhenIdle(Arc<AtomicBool>, mpsc::Sender<()>),
+    /// Indicates that all pending computation should be cancelled, the
+    /// executor wakes all waiters, drains its queue, and stops processing
+    Cancel,
+    /// Enables garbage collection of results, the executor reports the number
+    /// of results it holds through the given counter. Only the serial executor
+    /// implements GC, other backends ignore this message
+    EnableResultGc(Arc<AtomicUsize>),
+    /// All handles to a result have been dropped, so the result may be
+    /// reclaimed once no in-flight operation depends on it
+    ReleaseResult(ResultId),
+    /// Sets whether the executor runs ready network ops ahead of ready local
+    /// ops
+    SetNetworkPriority(bool),
+    /// Sets or clears the hook invoked with the results of each operation
+    SetTraceHook(Option<TraceHook<C>>),
+}
+
+/// A scheduler that evaluates the operations of an MPC circuit
+///
+/// The fabric pushes results, operations, and waiters onto the job queue, and
+/// the backend consumes them on its own thread. Implementing this trait allows
+/// an alternative scheduler to be swapped in through
+/// `MpcFabric::with_executor`. Only the three handlers are required: the
+/// default `run` loop dispatches the core messages to them and logs and
+/// ignores the executor configuration messages
+pub trait ExecutorBackend<C: CurveGroup>: 'static + Send {
+    /// The queue the backend receives jobs on
+    fn job_queue(&self) -> &ExecutorJobQueue<C>;
+
+    /// Handle a new result, executing any operations it makes ready
+    fn handle_new_result(&mut self, result: OpResult<C>);
+
+    /// Handle a new operation, executing it if its arguments are ready
+    fn handle_new_operation(&mut self, op: Operation<C>);
+
+    /// Handle a new waiter, waking it if its result is already available
+    fn handle_new_waiter(&mut self, waiter: ResultWaiter<C>);
+
+    /// Handle a message from the job queue, returning whether the backend
+    /// should continue running
+    fn handle_message(&mut self, message: ExecutorMessage<C>) -> bool {
+        match message {
+            ExecutorMessage::Result(res) => self.handle_new_result(res),
+            ExecutorMessage::ResultBatch(res) => {
+                for result in res.into_iter() {
+                    self.handle_new_result(result);
+                }
+            },
+            ExecutorMessage::Op(operation) => self.handle_new_operation(operation),
+            ExecutorMessage::NewWaiter(waiter) => self.handle_new_waiter(waiter),
+            ExecutorMessage::Shutdown | ExecutorMessage::Cancel => return false,
+            ExecutorMessage::ShutdownDrain(cancelled) => {
+                cancelled.store(true, Ordering::Release);
+                return false;
+            },
+            // Releases are only sent once result GC is enabled, and the backend has
+            // already warned that it ignores that message
+            ExecutorMessage::ReleaseResult(_) => {},
+            message => log::warn!("executor backend ignoring unsupported message: {message:?}"),
+        }
+
+        true
+    }
+
+    /// Run the backend until a shutdown message is received
+    fn run(mut self)
+    where
+        Self: Sized,
+    {
+        loop {
+            if let Some(job) = self.job_queue().pop() {
+                if !self.handle_message(job) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Check that a registered operation produced one value per result it
+/// allocated
+///
+/// The function is resolved by opcode, so it may not match the arity the
+/// operation was allocated with, e.g. when a trace is replayed against a
+/// different registry. Zipping the values onto the result IDs would silently
+/// drop results that consumers are waiting on
+pub(crate) fn check_registered_arity(opcode: &str, n_values: usize, n_results: usize) {
+    assert_eq!(
+        n_values, n_results,
+        "registered operation `{opcode}` produced {n_values} values for {n_results} results"
+    );
+}
+
+/// A callback invoked by the executor with each result an operation produces
+#[allow(clippy::type_complexity)]
+pub struct TraceHook<C: CurveGroup>(Arc<dyn Fn(&OpResult<C>, OperationKind) + Send + Sync>);
+
+impl<C: CurveGroup> TraceHook<C> {
+    /// Constructor
+    pub fn new<F>(hook: F) -> Self
+    where
+        F: 'static + Fn(&OpResult<C>, OperationKind) + Send + Sync,
+    {
+        Self(Arc::new(hook))
+    }
+
+    /// Invoke the hook on a result
+    pub fn call(&self, result: &OpResult<C>, kind: OperationKind) {
+        (self.0)(result, kind)
+    }
+}
+
+impl<C: CurveGroup> Debug for TraceHook<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "TraceHook")
+    }
 }
 
 /// Size hints given to an executor to pre-allocate buffer space
@@ -68,3 +201,93 @@ impl Default for ExecutorSizeHints {
         Self { n_ops: DEFAULT_N_OPS, n_results: DEFAULT_N_RESULTS }
     }
 }
+
+/// A snapshot of the statistics collected by the executor
+#[cfg(feature = "stats")]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExecutorStats {
+    /// The total number of operations executed by the executor
+    pub n_ops: usize,
+    /// The total number of network ops executed by the executor
+    pub n_network_ops: usize,
+    /// The total number of results received by the executor
+    pub n_results: usize,
+    /// The average sampled length of the executor's work queue
+    pub avg_queue_length: f64,
+    /// The distribution of sampled lengths of the executor's work queue
+    pub queue_length_histogram: QueueLengthHistogram,
+    /// The maximum depth of any operation in the circuit, in network ops
+    pub max_depth: usize,
+    /// The amount of time spent executing operations
+    pub execution_time_ns: u128,
+    /// The amount of time spent looking up arguments
+    pub lookup_time_ns: u128,
+    /// The amount of time spent inserting results
+    pub insert_time_ns: u128,
+    /// Growth of the buffer holding in-flight operations
+    pub operations_buffer: BufferStats,
+    /// Growth of the buffer mapping results to their dependent operations
+    pub dependencies_buffer: BufferStats,
+    /// Growth of the buffer holding completed results
+    pub results_buffer: BufferStats,
+}
+
+/// The inclusive upper bounds of the buckets in a `QueueLengthHistogram`, a
+/// final bucket holds all longer samples
+#[cfg(feature = "stats")]
+pub const QUEUE_LENGTH_BUCKETS: [usize; 8] = [0, 1, 4, 16, 64, 256, 1024, 4096];
+
+/// A fixed-bucket histogram of the sampled lengths of the executor's work
+/// queue
+///
+/// Unlike the average queue length, this shows bursty backlogs that indicate
+/// the executor is a bottleneck
+#[cfg(feature = "stats")]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueueLengthHistogram {
+    /// The number of samples in each bucket, indexed as `QUEUE_LENGTH_BUCKETS`
+    /// followed by the overflow bucket
+    pub counts: [u64; QUEUE_LENGTH_BUCKETS.len() + 1],
+}
+
+#[cfg(feature = "stats")]
+impl QueueLengthHistogram {
+    /// Record a sampled queue length
+    pub(crate) fn record(&mut self, queue_length: usize) {
+        let bucket = QUEUE_LENGTH_BUCKETS
+            .iter()
+            .position(|bound| queue_length <= *bound)
+            .unwrap_or(QUEUE_LENGTH_BUCKETS.len());
+        self.counts[bucket] += 1;
+    }
+
+    /// Iterate over the buckets as inclusive ranges of queue lengths, paired
+    /// with the number of samples in each
+    pub fn buckets(&self) -> impl Iterator<Item = (std::ops::RangeInclusive<usize>, u64)> + '_ {
+        let lower_bounds =
+            std::iter::once(0).chain(QUEUE_LENGTH_BUCKETS.iter().map(|bound| bound + 1));
+        let upper_bounds = QUEUE_LENGTH_BUCKETS.iter().copied().chain(std::iter::once(usize::MAX));
+
+        lower_bounds.zip(upper_bounds).zip(self.counts.iter()).map(|((lo, hi), n)| (lo..=hi, *n))
+    }
+}
+
+/// Statistics on the growth of one of the executor's buffers
+///
+/// A buffer that grows many times was allocated with too small a size hint,
+/// see `ExecutorSizeHints`
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BufferStats {
+    /// The number of times the buffer reallocated to grow past its size
+    pub n_grows: usize,
+    /// The largest number of slots the buffer has allocated
+    pub high_water: usize,
+}
+
+#[cfg(feature = "stats")]
+impl<T: Clone> From<&buffer::GrowableBuffer<T>> for BufferStats {
+    fn from(buf: &buffer::GrowableBuffer<T>) -> Self {
+        Self { n_grows: buf.n_grows(), high_water: buf.size() }
+    }
+}
@@ -10,7 +10,10 @@
 //! - The coordinator is backed by a thread pool of worker threads, onto which
 //!   it spawns operations that are ready to execute
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, mpsc, Arc},
+};
 
 use ark_ec::CurveGroup;
 use crossbeam::queue::SegQueue;
@@ -20,7 +23,10 @@ use tracing::log;
 
 use crate::{
     fabric::{
-        executor::{buffer::GrowableBuffer, ExecutorJobQueue, ExecutorMessage, ExecutorSizeHints},
+        executor::{
+            buffer::GrowableBuffer, check_registered_arity, ExecutorBackend, ExecutorJobQueue,
+            ExecutorMessage, ExecutorSizeHints,
+        },
         result::{ResultWaiter, ERR_RESULT_BUFFER_POISONED},
         OpResult, Operation, OperationId, OperationType,
     },
@@ -55,6 +61,9 @@ pub struct ParallelExecutor<C: CurveGroup> {
     pool: ThreadPool,
     /// The network outbound queue
     network_outbound: KanalSender<NetworkOutbound<C>>,
+    /// The channel a shutdown on idle waits on, held until the executor shuts
+    /// down as the executor cannot tell when it is idle
+    idle_signal: Option<mpsc::Sender<()>>,
 }
 
 impl<C: CurveGroup> ParallelExecutor<C> {
@@ -74,6 +83,7 @@ impl<C: CurveGroup> ParallelExecutor<C> {
             waiters: HashMap::new(),
             pool,
             network_outbound,
+            idle_signal: None,
         }
     }
 
@@ -81,29 +91,62 @@ impl<C: CurveGroup> ParallelExecutor<C> {
     pub fn run(mut self) {
         loop {
             if let Some(job) = self.job_queue.pop() {
-                match job {
-                    ExecutorMessage::Result(res) => self.handle_new_result(res),
-                    ExecutorMessage::ResultBatch(res) => {
-                        for result in res.into_iter() {
-                            self.handle_new_result(result);
-                        }
-                    },
-                    ExecutorMessage::ResultsReady(ids) => {
-                        for result_id in ids.into_iter() {
-                            self.handle_new_result_by_id(result_id);
-                        }
-                    },
-                    ExecutorMessage::Op(operation) => self.handle_new_operation(operation),
-                    ExecutorMessage::NewWaiter(waiter) => self.handle_new_waiter(waiter),
-                    ExecutorMessage::Shutdown => {
-                        log::debug!("executor shutting down");
-                        break;
-                    },
+                if !self.handle_job(job) {
+                    break;
                 }
             }
         }
     }
 
+    /// Handle a job from the queue, returning whether the executor should
+    /// continue running
+    fn handle_job(&mut self, job: ExecutorMessage<C>) -> bool {
+        match job {
+            ExecutorMessage::Result(res) => self.handle_new_result(res),
+            ExecutorMessage::ResultBatch(res) => {
+                for result in res.into_iter() {
+                    self.handle_new_result(result);
+                }
+            },
+            ExecutorMessage::ResultsReady(ids) => {
+                for result_id in ids.into_iter() {
+                    self.handle_new_result_by_id(result_id);
+                }
+            },
+            ExecutorMessage::Op(operation) => self.handle_new_operation(operation),
+            ExecutorMessage::NewWaiter(waiter) => self.handle_new_waiter(waiter),
+            ExecutorMessage::Shutdown => {
+                log::debug!("executor shutting down");
+                return false;
+            },
+            ExecutorMessage::Cancel => {
+                log::debug!("executor cancelled");
+                self.cancel();
+                return false;
+            },
+            // Operations run on the thread pool may still be in flight, so the
+            // parallel executor does not drain, it cancels the pending computation
+            ExecutorMessage::ShutdownDrain(cancelled) => {
+                log::debug!("executor cancelled on drain");
+                cancelled.store(true, Ordering::Release);
+                self.cancel();
+                return false;
+            },
+            // Operations run on the thread pool may still be in flight, so the
+            // parallel executor cannot tell when it is idle and holds the signal
+            // until it shuts down
+            ExecutorMessage::ShutdownWhenIdle(_, signal) => self.idle_signal = Some(signal),
+            // Result garbage collection, network priority, and trace hooks are only
+            // supported by the serial executor
+            ExecutorMessage::EnableResultGc(_)
+            | ExecutorMessage::ReleaseResult(_)
+            | ExecutorMessage::SetNetworkPriority(_)
+            | ExecutorMessage::SetTraceHook(_) => {},
+        }
+
+        true
+    }
+
     // ----------------
     // | Job Handlers |
     // ----------------
@@ -245,6 +288,16 @@ impl<C: CurveGroup> ParallelExecutor<C> {
                     .collect()
             },
 
+            OperationType::Registered { opcode, function } => {
+                let output = (function)(input);
+                check_registered_arity(&opcode, output.len(), result_ids.len());
+                result_ids
+                    .into_iter()
+                    .zip(output)
+                    .map(|(id, value)| OpResult { id, value })
+                    .collect()
+            },
+
             OperationType::Network { function } => {
                 // Derive a network payload from the gate inputs and forward it to the outbound
                 // buffer
@@ -257,7 +310,8 @@ impl<C: CurveGroup> ParallelExecutor<C> {
                 // On a `send`, the local party receives a copy of the value placed as the
                 // result of the network operation, so we must re-enqueue the
                 // result
-                vec![OpResult { id: result_id, value: payload.into() }]
+                let value = payload.try_into().expect("network gate produced a non-value payload");
+                vec![OpResult { id: result_id, value }]
             },
         };
 
@@ -274,6 +328,16 @@ impl<C: CurveGroup> ParallelExecutor<C> {
         job_queue.push(ExecutorMessage::ResultsReady(ids));
     }
 
+    /// Cancel all pending computation, waking every waiter so that it may
+    /// observe the cancellation and draining the job queue
+    fn cancel(&mut self) {
+        for waiter in self.waiters.drain().flat_map(|(_, waiters)| waiters) {
+            waiter.waker.wake();
+        }
+
+        while self.job_queue.pop().is_some() {}
+    }
+
     /// Wake all the waiters for a given result
     pub fn wake_waiters_on_result(&mut self, result_id: ResultId) {
         // Wake all tasks awaiting this result
@@ -289,3 +353,29 @@ impl<C: CurveGroup> ParallelExecutor<C> {
         }
     }
 }
+
+impl<C: CurveGroup> ExecutorBackend<C> for ParallelExecutor<C> {
+    fn job_queue(&self) -> &ExecutorJobQueue<C> {
+        &self.job_queue
+    }
+
+    fn handle_new_result(&mut self, result: OpResult<C>) {
+        ParallelExecutor::handle_new_result(self, result)
+    }
+
+    fn handle_new_operation(&mut self, op: Operation<C>) {
+        ParallelExecutor::handle_new_operation(self, op)
+    }
+
+    fn handle_new_waiter(&mut self, waiter: ResultWaiter<C>) {
+        ParallelExecutor::handle_new_waiter(self, waiter)
+    }
+
+    fn handle_message(&mut self, message: ExecutorMessage<C>) -> bool {
+        self.handle_job(message)
+    }
+
+    fn run(self) {
+        ParallelExecutor::run(self)
+    }
+}
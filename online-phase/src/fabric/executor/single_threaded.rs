@@ -6,24 +6,36 @@
 
 #[cfg(feature = "multithreaded_executor")]
 use core::panic;
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+};
 
 #[cfg(feature = "stats")]
-use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::Mutex;
 
 use ark_ec::CurveGroup;
 use kanal::Sender as KanalSender;
 use tracing::log;
 
 use crate::fabric::{
-    executor::{buffer::GrowableBuffer, ExecutorJobQueue, ExecutorMessage},
+    executor::{
+        buffer::GrowableBuffer, check_registered_arity, ExecutorBackend, ExecutorJobQueue,
+        ExecutorMessage, TraceHook,
+    },
     result::{ResultWaiter, ERR_RESULT_BUFFER_POISONED},
-    OpResult, Operation, OperationId, OperationType,
+    OpResult, Operation, OperationId, OperationType, ResultValue,
 };
 use crate::network::NetworkOutbound;
 use crate::ResultId;
 
 use super::ExecutorSizeHints;
+#[cfg(feature = "stats")]
+use super::{ExecutorStats, QueueLengthHistogram};
 
 // ---------
 // | Stats |
@@ -32,7 +44,7 @@ use super::ExecutorSizeHints;
 /// Statistics tracked by the executor
 #[cfg(feature = "stats")]
 #[derive(Default)]
-struct ExecutorStats {
+struct StatsCollector {
     /// The total number of operations executed by the executor
     n_ops: usize,
     /// The total number of network ops executed by the executor
@@ -43,6 +55,8 @@ struct ExecutorStats {
     summed_queue_length: u64,
     /// The number of samples taken of the executor's work queue length
     queue_length_sample_count: usize,
+    /// The distribution of the sampled queue lengths
+    queue_length_histogram: QueueLengthHistogram,
     /// The amount of time spent executing operations
     execution_time_ns: u128,
     /// The amount of time spent looking up arguments
@@ -56,7 +70,7 @@ struct ExecutorStats {
 }
 
 #[cfg(feature = "stats")]
-impl ExecutorStats {
+impl StatsCollector {
     /// Increment the number of operations executed by the executor
     pub fn increment_n_ops(&mut self) {
         self.n_ops += 1;
@@ -71,10 +85,15 @@ impl ExecutorStats {
     pub fn add_queue_length_sample(&mut self, queue_length: usize) {
         self.summed_queue_length += queue_length as u64;
         self.queue_length_sample_count += 1;
+        self.queue_length_histogram.record(queue_length);
     }
 
     /// Get the average queue length over the execution of the executor
     pub fn avg_queue_length(&self) -> f64 {
+        if self.queue_length_sample_count == 0 {
+            return 0.;
+        }
+
         (self.summed_queue_length as f64) / (self.queue_length_sample_count as f64)
     }
 
@@ -103,23 +122,22 @@ impl ExecutorStats {
     pub fn max_depth(&self) -> usize {
         *self.result_depth_map.values().max().unwrap_or(&0)
     }
-}
 
-#[cfg(feature = "stats")]
-impl Debug for ExecutorStats {
-    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        let avg_queue_length = self.avg_queue_length();
-        let max_depth = self.max_depth();
-        f.debug_struct("ExecutorStats")
-            .field("n_ops", &self.n_ops)
-            .field("n_network_ops", &self.n_network_ops)
-            .field("n_results", &self.n_results)
-            .field("avg_queue_length", &avg_queue_length)
-            .field("max_depth", &max_depth)
-            .field("execution_time_ns", &self.execution_time_ns)
-            .field("lookup_time_ns", &self.lookup_time_ns)
-            .field("insert_time", &self.insert_time)
-            .finish()
+    /// Take a snapshot of the collected statistics
+    pub fn snapshot(&self) -> ExecutorStats {
+        ExecutorStats {
+            n_ops: self.n_ops,
+            n_network_ops: self.n_network_ops,
+            n_results: self.n_results,
+            avg_queue_length: self.avg_queue_length(),
+            queue_length_histogram: self.queue_length_histogram.clone(),
+            max_depth: self.max_depth(),
+            execution_time_ns: self.execution_time_ns,
+            lookup_time_ns: self.lookup_time_ns,
+            insert_time_ns: self.insert_time,
+            // The buffer stats are filled in by the executor, which owns the buffers
+            ..Default::default()
+        }
     }
 }
 
@@ -140,6 +158,74 @@ macro_rules! stats_timer {
     }};
 }
 
+// ----------------------
+// | Garbage Collection |
+// ----------------------
+
+/// The state used to reclaim results once they are no longer needed
+///
+/// A result is reclaimed once every handle to it has been dropped and every
+/// in-flight operation that consumes it has executed
+struct ResultGc {
+    /// The number of in-flight operations consuming each result
+    op_refs: HashMap<ResultId, usize>,
+    /// The results whose handles have all been dropped
+    released: HashSet<ResultId>,
+    /// The number of results held by the executor since GC was enabled
+    live_results: Arc<AtomicUsize>,
+}
+
+impl ResultGc {
+    /// Constructor
+    fn new(live_results: Arc<AtomicUsize>) -> Self {
+        Self { op_refs: HashMap::new(), released: HashSet::new(), live_results }
+    }
+
+    /// Whether the given result may be reclaimed
+    fn is_collectable(&self, id: ResultId) -> bool {
+        self.released.contains(&id) && !self.op_refs.contains_key(&id)
+    }
+}
+
+// --------------
+// | Scheduling |
+// --------------
+
+/// The operations that are ready for execution
+///
+/// When network ops are prioritized they are placed in a separate lane that is
+/// always drained first, so that values the peer is waiting on are sent as
+/// early as possible. Otherwise all ops share a single lane
+struct ReadyOps<C: CurveGroup> {
+    /// The ready network ops, empty unless network ops are prioritized
+    network: Vec<Operation<C>>,
+    /// The remaining ready ops
+    local: Vec<Operation<C>>,
+    /// Whether network ops are prioritized
+    prioritize_network: bool,
+}
+
+impl<C: CurveGroup> ReadyOps<C> {
+    /// Constructor
+    fn new(prioritize_network: bool) -> Self {
+        Self { network: Vec::new(), local: Vec::new(), prioritize_network }
+    }
+
+    /// Add a ready operation
+    fn push(&mut self, op: Operation<C>) {
+        if self.prioritize_network && matches!(op.op_type, OperationType::Network { .. }) {
+            self.network.push(op);
+        } else {
+            self.local.push(op);
+        }
+    }
+
+    /// Take the next operation to execute
+    fn pop(&mut self) -> Option<Operation<C>> {
+        self.network.pop().or_else(|| self.local.pop())
+    }
+}
+
 // ------------
 // | Executor |
 // ------------
@@ -157,13 +243,34 @@ pub struct SerialExecutor<C: CurveGroup> {
     dependencies: GrowableBuffer<Vec<ResultId>>,
     /// The completed results of operations
     results: GrowableBuffer<OpResult<C>>,
+    /// A buffer into which each executed operation places its results, reused
+    /// across operations to avoid an allocation per operation
+    result_buf: Vec<OpResult<C>>,
     /// An index of waiters for incomplete results
     waiters: HashMap<ResultId, Vec<ResultWaiter<C>>>,
+    /// The number of operations waiting on results that are not yet available
+    n_waiting_ops: usize,
+    /// The cancellation flag to set and the channel to signal once the
+    /// executor is idle, after which it shuts down, `None` unless a shutdown on
+    /// idle was requested
+    idle_signal: Option<(Arc<AtomicBool>, mpsc::Sender<()>)>,
     /// The network outbound queue
     network_outbound: KanalSender<NetworkOutbound<C>>,
+    /// The result garbage collection state, `None` if GC is disabled
+    gc: Option<ResultGc>,
+    /// Whether ready network ops are executed ahead of ready local ops
+    prioritize_network: bool,
+    /// The hook invoked with the results of each operation, if one is set
+    trace_hook: Option<TraceHook<C>>,
     /// The collected statistics of the executor
     #[cfg(feature = "stats")]
-    stats: ExecutorStats,
+    stats: StatsCollector,
+    /// Whether the stats have changed since they were last published
+    #[cfg(feature = "stats")]
+    stats_dirty: bool,
+    /// The location to which the executor publishes snapshots of its stats
+    #[cfg(feature = "stats")]
+    stats_sink: Arc<Mutex<ExecutorStats>>,
 }
 
 impl<C: CurveGroup> SerialExecutor<C> {
@@ -180,9 +287,17 @@ impl<C: CurveGroup> SerialExecutor<C> {
                 operations: GrowableBuffer::new(size_hints.n_ops),
                 dependencies: GrowableBuffer::new(size_hints.n_ops),
                 results: GrowableBuffer::new(size_hints.n_results),
+                result_buf: Vec::new(),
                 waiters: HashMap::new(),
+                n_waiting_ops: 0,
+                idle_signal: None,
                 network_outbound,
-                stats: ExecutorStats::default(),
+                gc: None,
+                prioritize_network: false,
+                trace_hook: None,
+                stats: StatsCollector::default(),
+                stats_dirty: false,
+                stats_sink: Arc::default(),
             }
         }
 
@@ -193,38 +308,44 @@ impl<C: CurveGroup> SerialExecutor<C> {
                 operations: GrowableBuffer::new(size_hints.n_ops),
                 dependencies: GrowableBuffer::new(size_hints.n_ops),
                 results: GrowableBuffer::new(size_hints.n_results),
+                result_buf: Vec::new(),
                 waiters: HashMap::new(),
+                n_waiting_ops: 0,
+                idle_signal: None,
                 network_outbound,
+                gc: None,
+                prioritize_network: false,
+                trace_hook: None,
             }
         }
     }
 
+    /// Set the location to which the executor publishes snapshots of its
+    /// stats
+    #[cfg(feature = "stats")]
+    pub fn with_stats_sink(mut self, sink: Arc<Mutex<ExecutorStats>>) -> Self {
+        self.stats_sink = sink;
+        self
+    }
+
     /// Run the executor until a shutdown message is received
     pub fn run(mut self) {
         loop {
+            // Publish the stats whenever the executor drains its queue, so that
+            // snapshots reflect all work submitted before the executor went idle
+            #[cfg(feature = "stats")]
+            if self.job_queue.is_empty() && self.stats_dirty {
+                self.publish_stats();
+            }
+
             if let Some(job) = self.job_queue.pop() {
-                match job {
-                    ExecutorMessage::Result(res) => self.handle_new_result(res),
-                    ExecutorMessage::ResultBatch(res) => {
-                        for result in res.into_iter() {
-                            self.handle_new_result(result);
-                        }
-                    },
-                    #[cfg(feature = "multithreaded_executor")]
-                    ExecutorMessage::ResultsReady(_) => {
-                        panic!("Results must be passed by value to single-threaded executor")
-                    },
-                    ExecutorMessage::Op(operation) => self.handle_new_operation(operation),
-                    ExecutorMessage::NewWaiter(waiter) => self.handle_new_waiter(waiter),
-                    ExecutorMessage::Shutdown => {
-                        log::debug!("executor shutting down");
-
-                        // In benchmarks print the average queue length
-                        #[cfg(feature = "stats")]
-                        println!("Executor stats: {:?}", self.stats);
-
-                        break;
-                    },
+                #[cfg(feature = "stats")]
+                {
+                    self.stats_dirty = true;
+                }
+
+                if !self.handle_job(job) {
+                    break;
                 }
             }
 
@@ -233,15 +354,83 @@ impl<C: CurveGroup> SerialExecutor<C> {
         }
     }
 
+    /// Handle a job from the queue, returning whether the executor should
+    /// continue running
+    fn handle_job(&mut self, job: ExecutorMessage<C>) -> bool {
+        match job {
+            ExecutorMessage::Result(res) => self.handle_new_result(res),
+            ExecutorMessage::ResultBatch(res) => {
+                for result in res.into_iter() {
+                    self.handle_new_result(result);
+                }
+            },
+            #[cfg(feature = "multithreaded_executor")]
+            ExecutorMessage::ResultsReady(_) => {
+                panic!("Results must be passed by value to single-threaded executor")
+            },
+            ExecutorMessage::Op(operation) => self.handle_new_operation(operation),
+            ExecutorMessage::NewWaiter(waiter) => self.handle_new_waiter(waiter),
+            ExecutorMessage::Shutdown => {
+                log::debug!("executor shutting down");
+
+                #[cfg(feature = "stats")]
+                self.publish_stats();
+
+                return false;
+            },
+            ExecutorMessage::ShutdownDrain(cancelled) => {
+                log::debug!("executor draining before shutdown");
+                self.drain(&cancelled);
+
+                #[cfg(feature = "stats")]
+                self.publish_stats();
+
+                return false;
+            },
+            ExecutorMessage::ShutdownWhenIdle(cancelled, signal) => {
+                self.idle_signal = Some((cancelled, signal))
+            },
+            ExecutorMessage::Cancel => {
+                log::debug!("executor cancelled");
+                self.cancel();
+                return false;
+            },
+            ExecutorMessage::EnableResultGc(live_results) => self.enable_gc(live_results),
+            ExecutorMessage::ReleaseResult(id) => self.handle_release(id),
+            ExecutorMessage::SetNetworkPriority(enabled) => self.prioritize_network = enabled,
+            ExecutorMessage::SetTraceHook(hook) => self.trace_hook = hook,
+        }
+
+        if self.idle_signal.is_some() && self.is_idle() {
+            log::debug!("executor idle, shutting down");
+
+            #[cfg(feature = "stats")]
+            self.publish_stats();
+
+            let (cancelled, signal) = self.idle_signal.take().unwrap();
+            cancelled.store(true, Ordering::Release);
+            let _ = signal.send(());
+            return false;
+        }
+
+        true
+    }
+
+    /// Whether the executor is idle, i.e. no queued job is left to handle, no
+    /// operation is waiting on its inputs, and no task is waiting on a result
+    fn is_idle(&self) -> bool {
+        self.job_queue.is_empty() && self.n_waiting_ops == 0 && self.waiters.is_empty()
+    }
+
     /// Handle a new result
     fn handle_new_result(&mut self, result: OpResult<C>) {
         let id = result.id;
         self.insert_result(result);
 
         // Execute all operations that are ready after committing this result
-        let mut ops_queue = Vec::new();
-        self.append_ready_ops(id, &mut ops_queue);
-        self.execute_operations(ops_queue);
+        let mut ready_ops = ReadyOps::new(self.prioritize_network);
+        self.append_ready_ops(id, &mut ready_ops);
+        self.execute_operations(ready_ops);
     }
 
     /// Insert a result into the buffer
@@ -253,10 +442,14 @@ impl<C: CurveGroup> SerialExecutor<C> {
         stats_timer!(&mut self.stats.insert_time, { self.results.insert(id, result) });
 
         self.wake_waiters_on_result(id);
+        if let Some(gc) = self.gc.as_ref() {
+            gc.live_results.fetch_add(1, Ordering::Relaxed);
+            self.maybe_collect(id);
+        }
     }
 
     /// Get the operations that are ready for execution after a result comes in
-    fn append_ready_ops(&mut self, id: OperationId, ready_ops: &mut Vec<Operation<C>>) {
+    fn append_ready_ops(&mut self, id: OperationId, ready_ops: &mut ReadyOps<C>) {
         if let Some(deps) = self.dependencies.get(id) {
             for op_id in deps.iter() {
                 let operation = self.operations.get_mut(*op_id).unwrap();
@@ -268,6 +461,7 @@ impl<C: CurveGroup> SerialExecutor<C> {
 
                 // Mark the operation as ready for execution
                 ready_ops.push(self.operations.take(*op_id).unwrap());
+                self.n_waiting_ops -= 1;
             }
         }
     }
@@ -275,6 +469,12 @@ impl<C: CurveGroup> SerialExecutor<C> {
     /// Handle a new operation
     #[inline(never)]
     fn handle_new_operation(&mut self, mut op: Operation<C>) {
+        if let Some(gc) = self.gc.as_mut() {
+            for arg in op.args.iter() {
+                *gc.op_refs.entry(*arg).or_default() += 1;
+            }
+        }
+
         #[cfg(feature = "stats")]
         {
             self.record_op_depth(&op);
@@ -292,7 +492,9 @@ impl<C: CurveGroup> SerialExecutor<C> {
 
         // If the operation is ready for execution, do so
         if inflight_args == 0 {
-            self.execute_operations(vec![op]);
+            let mut ready_ops = ReadyOps::new(self.prioritize_network);
+            ready_ops.push(op);
+            self.execute_operations(ready_ops);
             return;
         }
 
@@ -308,6 +510,20 @@ impl<C: CurveGroup> SerialExecutor<C> {
         }
 
         stats_timer!(&mut self.stats.insert_time, { self.operations.insert(op.id, op) });
+        self.n_waiting_ops += 1;
+    }
+
+    /// Publish a snapshot of the executor's stats to the stats sink
+    #[cfg(feature = "stats")]
+    fn publish_stats(&mut self) {
+        let mut snapshot = self.stats.snapshot();
+        snapshot.operations_buffer = (&self.operations).into();
+        snapshot.dependencies_buffer = (&self.dependencies).into();
+        snapshot.results_buffer = (&self.results).into();
+        log::debug!("executor stats: {snapshot:?}");
+
+        *self.stats_sink.lock().expect("stats sink poisoned") = snapshot;
+        self.stats_dirty = false;
     }
 
     /// Record the depth of an operation in the circuit
@@ -319,40 +535,73 @@ impl<C: CurveGroup> SerialExecutor<C> {
 
     /// Executes the operations in the buffer, recursively executing any
     /// dependencies that become ready
-    fn execute_operations(&mut self, mut ops: Vec<Operation<C>>) {
+    fn execute_operations(&mut self, mut ops: ReadyOps<C>) {
+        let mut res = mem::take(&mut self.result_buf);
         while let Some(op) = ops.pop() {
-            let res = self.compute_result(op);
+            let kind = self.trace_hook.as_ref().map(|_| op.op_type.kind());
+            self.compute_result(op, &mut res);
+            if let (Some(hook), Some(kind)) = (self.trace_hook.as_ref(), kind) {
+                res.iter().for_each(|result| hook.call(result, kind));
+            }
 
-            for result in res.into_iter() {
+            for result in res.drain(..) {
                 self.append_ready_ops(result.id, &mut ops);
                 self.insert_result(result);
             }
         }
+
+        self.result_buf = res;
     }
 
-    /// Compute the result of an operation
-    fn compute_result(&mut self, op: Operation<C>) -> Vec<OpResult<C>> {
+    /// Compute the result of an operation, placing its results in `out`
+    fn compute_result(&mut self, op: Operation<C>, out: &mut Vec<OpResult<C>>) {
+        let consumed_args = self.gc.is_some().then(|| op.args.clone());
+        self.compute_op(op, out);
+
+        if let Some(args) = consumed_args {
+            self.release_op_args(args);
+        }
+    }
+
+    /// Execute an operation on its arguments, placing its results in `out`
+    fn compute_op(&mut self, op: Operation<C>, out: &mut Vec<OpResult<C>>) {
         let result_ids = op.result_ids();
 
-        // Collect the inputs to the operation
+        // Collect the inputs to the operation, moving out the values this op is
+        // the last to consume rather than cloning them
         let input = stats_timer!(&mut self.stats.lookup_time_ns, {
-            let args = op.args.into_iter().map(|arg| self.results.get(arg).unwrap().value.clone());
+            let gc = self.gc.as_ref();
+            let results = &mut self.results;
+            let args = op.args.into_iter().map(move |arg| {
+                let result = results.get_mut(arg).unwrap();
+                if Self::is_last_use(gc, arg) {
+                    mem::replace(&mut result.value, ResultValue::Placeholder)
+                } else {
+                    result.value.clone()
+                }
+            });
             Box::new(args)
         });
 
         match op.op_type {
             OperationType::Gate { function } => {
                 let value = stats_timer!(&mut self.stats.execution_time_ns, { (function)(input) });
-                vec![OpResult { id: op.result_id, value }]
+                out.push(OpResult { id: op.result_id, value });
             },
 
             OperationType::GateBatch { function } => {
                 let output = stats_timer!(&mut self.stats.execution_time_ns, { (function)(input) });
-                result_ids
-                    .into_iter()
-                    .zip(output)
-                    .map(|(id, value)| OpResult { id, value })
-                    .collect()
+                out.extend(
+                    result_ids.into_iter().zip(output).map(|(id, value)| OpResult { id, value }),
+                );
+            },
+
+            OperationType::Registered { opcode, function } => {
+                let output = stats_timer!(&mut self.stats.execution_time_ns, { (function)(input) });
+                check_registered_arity(&opcode, output.len(), result_ids.len());
+                out.extend(
+                    result_ids.into_iter().zip(output).map(|(id, value)| OpResult { id, value }),
+                );
             },
 
             OperationType::Network { function } => {
@@ -367,11 +616,86 @@ impl<C: CurveGroup> SerialExecutor<C> {
                 // On a `send`, the local party receives a copy of the value placed as the
                 // result of the network operation, so we must re-enqueue the
                 // result
-                vec![OpResult { id: result_id, value: payload.into() }]
+                let value = payload.try_into().expect("network gate produced a non-value payload");
+                out.push(OpResult { id: result_id, value });
             },
         }
     }
 
+    /// Determine whether an executing operation is the last to consume the
+    /// given argument
+    ///
+    /// An argument is consumed for the last time if every handle to it has
+    /// been released and the operation holds the only reference to it, in
+    /// which case it is reclaimed once the operation executes and its value
+    /// need not be cloned. Without GC no argument is ever reclaimed
+    fn is_last_use(gc: Option<&ResultGc>, arg: ResultId) -> bool {
+        gc.is_some_and(|gc| gc.released.contains(&arg) && gc.op_refs.get(&arg) == Some(&1))
+    }
+
+    /// Enable result garbage collection
+    ///
+    /// The operations already waiting on their inputs are counted as
+    /// references to their arguments, so that no argument is moved out or
+    /// reclaimed before every operation consuming it has executed
+    fn enable_gc(&mut self, live_results: Arc<AtomicUsize>) {
+        let mut gc = ResultGc::new(live_results);
+        for arg in self.operations.iter().flat_map(|op| op.args.iter()) {
+            *gc.op_refs.entry(*arg).or_default() += 1;
+        }
+
+        self.gc = Some(gc);
+    }
+
+    /// Handle the release of all handles to a result
+    fn handle_release(&mut self, id: ResultId) {
+        if let Some(gc) = self.gc.as_mut() {
+            gc.released.insert(id);
+            self.maybe_collect(id);
+        }
+    }
+
+    /// Drop the references an executed operation held on its arguments
+    fn release_op_args(&mut self, args: Vec<ResultId>) {
+        let gc = self.gc.as_mut().unwrap();
+        for arg in args.iter() {
+            let count = gc.op_refs.get_mut(arg).expect("operation arguments must be counted");
+            *count -= 1;
+            if *count == 0 {
+                gc.op_refs.remove(arg);
+            }
+        }
+
+        for arg in args.into_iter() {
+            self.maybe_collect(arg);
+        }
+    }
+
+    /// Reclaim a result if it is no longer referenced by any handle or
+    /// in-flight operation
+    fn maybe_collect(&mut self, id: ResultId) {
+        let gc = match self.gc.as_mut() {
+            Some(gc) if gc.is_collectable(id) => gc,
+            _ => return,
+        };
+
+        // A released result that has not yet been computed is reclaimed once it is
+        // inserted
+        if self.results.get(id).is_none() {
+            return;
+        }
+
+        gc.released.remove(&id);
+        let _ = gc
+            .live_results
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| Some(n.saturating_sub(1)));
+        self.results.take(id);
+        self.waiters.remove(&id);
+        if self.dependencies.get(id).is_some() {
+            self.dependencies.take(id);
+        }
+    }
+
     /// Handle a new waiter for a result
     pub fn handle_new_waiter(&mut self, waiter: ResultWaiter<C>) {
         let id = waiter.result_id;
@@ -385,18 +709,74 @@ impl<C: CurveGroup> SerialExecutor<C> {
         }
     }
 
+    /// Cancel all pending computation, waking every waiter so that it may
+    /// observe the cancellation and draining the job queue
+    fn cancel(&mut self) {
+        for waiter in self.waiters.drain().flat_map(|(_, waiters)| waiters) {
+            waiter.waker.wake();
+        }
+
+        while self.job_queue.pop().is_some() {}
+    }
+
+    /// Process every job already in the queue, then flag the computation as
+    /// cancelled and wake the waiters on results that can no longer be
+    /// produced
+    ///
+    /// Operations made ready by the queued jobs run to completion, so their
+    /// waiters resolve. Waiters on results that depend on values that never
+    /// arrived observe the cancellation instead of hanging
+    fn drain(&mut self, cancelled: &AtomicBool) {
+        while let Some(job) = self.job_queue.pop() {
+            if !self.handle_job(job) {
+                return;
+            }
+        }
+
+        cancelled.store(true, Ordering::Release);
+        for waiter in self.waiters.drain().flat_map(|(_, waiters)| waiters) {
+            waiter.waker.wake();
+        }
+    }
+
     /// Wake all the waiters for a given result
     pub fn wake_waiters_on_result(&mut self, result_id: ResultId) {
         // Wake all tasks awaiting this result
-        if let Some(waiters) = self.waiters.get(&result_id) {
+        if let Some(waiters) = self.waiters.remove(&result_id) {
             let result = &self.results.get(result_id).unwrap().value;
             for waiter in waiters {
                 // Place the result in the waiter's buffer and wake up the waiting thread
                 let mut buffer = waiter.result_buffer.write().expect(ERR_RESULT_BUFFER_POISONED);
 
                 *buffer = result.clone();
-                waiter.waker.wake_by_ref();
+                waiter.waker.wake();
             }
         }
     }
 }
+
+impl<C: CurveGroup> ExecutorBackend<C> for SerialExecutor<C> {
+    fn job_queue(&self) -> &ExecutorJobQueue<C> {
+        &self.job_queue
+    }
+
+    fn handle_new_result(&mut self, result: OpResult<C>) {
+        SerialExecutor::handle_new_result(self, result)
+    }
+
+    fn handle_new_operation(&mut self, op: Operation<C>) {
+        SerialExecutor::handle_new_operation(self, op)
+    }
+
+    fn handle_new_waiter(&mut self, waiter: ResultWaiter<C>) {
+        SerialExecutor::handle_new_waiter(self, waiter)
+    }
+
+    fn handle_message(&mut self, message: ExecutorMessage<C>) -> bool {
+        self.handle_job(message)
+    }
+
+    fn run(self) {
+        SerialExecutor::run(self)
+    }
+}
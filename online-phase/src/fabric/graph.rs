@@ -0,0 +1,67 @@
+//! Defines a record of the operation dependency graph of a circuit, which may
+//! be rendered in Graphviz DOT format for visualization
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use super::{OperationId, OperationKind, ResultId};
+
+/// An operation recorded in the dependency graph
+#[derive(Clone, Debug)]
+pub(crate) struct GraphOp {
+    /// The ID of the operation
+    pub id: OperationId,
+    /// The kind of the operation
+    pub kind: OperationKind,
+    /// The IDs of the operation's arguments
+    pub args: Vec<ResultId>,
+    /// The IDs of the operation's results
+    pub outputs: Vec<ResultId>,
+}
+
+impl OperationKind {
+    /// The label of the operation kind in a rendered graph
+    fn label(&self) -> &'static str {
+        match self {
+            OperationKind::Gate => "gate",
+            OperationKind::GateBatch => "batch",
+            OperationKind::Network => "network",
+            OperationKind::Registered => "registered",
+        }
+    }
+}
+
+/// Render the given operations as a DOT digraph
+///
+/// Each operation is a node labeled with its kind, and each argument is an
+/// edge from the operation that produced it, labeled with the result ID.
+/// Arguments not produced by a recorded operation, e.g. allocated values, get
+/// a node of their own
+pub(crate) fn render_dot(ops: &[GraphOp]) -> String {
+    let producers: HashMap<ResultId, OperationId> =
+        ops.iter().flat_map(|op| op.outputs.iter().map(move |id| (*id, op.id))).collect();
+
+    let mut out = String::from("digraph circuit {\n");
+    let mut inputs = HashSet::new();
+    for op in ops.iter() {
+        writeln!(out, "    op{} [label=\"{}\"];", op.id, op.kind.label()).unwrap();
+        for arg in op.args.iter() {
+            let source = match producers.get(arg) {
+                Some(producer) => format!("op{producer}"),
+                None => {
+                    if inputs.insert(*arg) {
+                        writeln!(out, "    r{arg} [label=\"r{arg}\", shape=box];").unwrap();
+                    }
+                    format!("r{arg}")
+                },
+            };
+
+            writeln!(out, "    {source} -> op{} [label=\"{arg}\"];", op.id).unwrap();
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
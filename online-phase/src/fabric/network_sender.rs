@@ -2,33 +2,71 @@
 //! the network and re-enqueues them in the result buffer for dependent
 //! instructions
 
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
-use std::sync::atomic::AtomicUsize;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use ark_ec::CurveGroup;
 use crossbeam::queue::SegQueue;
+use futures::future::pending;
 use futures::stream::SplitSink;
 use futures::SinkExt;
 use futures::{stream::SplitStream, StreamExt};
-use kanal::AsyncReceiver as KanalReceiver;
+use kanal::{AsyncReceiver as KanalReceiver, Sender as KanalSender};
 use tokio::sync::broadcast::Receiver as BroadcastReceiver;
+use tokio::sync::watch::Receiver as WatchReceiver;
+use tokio::time::Instant;
 use tracing::log;
 
-use crate::error::MpcNetworkError;
-use crate::network::{MpcNetwork, NetworkOutbound};
+use crate::error::{MpcError, MpcNetworkError};
+use crate::network::{MpcNetwork, NetworkOutbound, NetworkPayload, PartyId};
 
 use super::executor::ExecutorMessage;
 use super::result::OpResult;
+use super::FabricInner;
 
 /// Error message emitted when a stream closes early
 const ERR_STREAM_FINISHED_EARLY: &str = "stream finished early";
 
+/// Error message emitted when a peer's frames arrive too far out of order
+const ERR_REORDER_OVERFLOW: &str = "too many frames received out of order";
+
+/// The maximum number of outbound messages coalesced into one transmission
+const MAX_COALESCED_MESSAGES: usize = 1024;
+/// The maximum number of frames from a peer buffered while awaiting an
+/// earlier frame
+const MAX_BUFFERED_FRAMES: usize = 1 << 16;
+
+// -------------
+// | Heartbeat |
+// -------------
+
+/// The configuration of the keepalive messages exchanged with the peer
+#[derive(Clone, Copy, Debug)]
+pub struct HeartbeatConfig {
+    /// The interval at which heartbeats are sent
+    pub heartbeat_interval: Duration,
+    /// The number of heartbeat intervals the peer may stay silent for before it
+    /// is considered unreachable
+    pub max_missed: u32,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self { heartbeat_interval: Duration::from_secs(1), max_missed: 3 }
+    }
+}
+
 // ---------
 // | Stats |
 // ---------
 
-/// The network stats structs
+/// Counters of the traffic a party has sent and received over the network
+///
+/// Byte counts require serializing each message a second time, so they are
+/// only collected with the `stats` feature; message counts are always collected
 #[derive(Debug, Default)]
 pub struct NetworkStats {
     /// The number of bytes sent
@@ -39,28 +77,132 @@ pub struct NetworkStats {
     pub messages_sent: AtomicUsize,
     /// The number of messages received
     pub messages_received: AtomicUsize,
+    /// The number of rounds of communication, i.e. the maximum circuit depth
+    /// of any network op executed, published by the executor
+    pub(crate) rounds: Arc<AtomicUsize>,
 }
 
-#[allow(unused)]
 impl NetworkStats {
     /// Increment the number of bytes sent
     pub fn increment_bytes_sent(&self, bytes: usize) {
-        self.bytes_sent.fetch_add(bytes, std::sync::atomic::Ordering::SeqCst);
+        self.bytes_sent.fetch_add(bytes, Ordering::SeqCst);
     }
 
     /// Increment the number of bytes received
     pub fn increment_bytes_received(&self, bytes: usize) {
-        self.bytes_received.fetch_add(bytes, std::sync::atomic::Ordering::SeqCst);
+        self.bytes_received.fetch_add(bytes, Ordering::SeqCst);
     }
 
     /// Increment the number of messages sent
     pub fn increment_messages_sent(&self) {
-        self.messages_sent.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        self.messages_sent.fetch_add(1, Ordering::SeqCst);
     }
 
     /// Increment the number of messages received
     pub fn increment_messages_received(&self) {
-        self.messages_received.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        self.messages_received.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// The number of bytes sent, measured as the size of the serialized
+    /// messages
+    ///
+    /// Always zero unless the `stats` feature is enabled
+    pub fn bytes_sent(&self) -> usize {
+        self.bytes_sent.load(Ordering::SeqCst)
+    }
+
+    /// The number of bytes received, measured as the size of the serialized
+    /// messages
+    ///
+    /// Always zero unless the `stats` feature is enabled
+    pub fn bytes_received(&self) -> usize {
+        self.bytes_received.load(Ordering::SeqCst)
+    }
+
+    /// The number of messages sent
+    pub fn messages_sent(&self) -> usize {
+        self.messages_sent.load(Ordering::SeqCst)
+    }
+
+    /// The number of messages received
+    pub fn messages_received(&self) -> usize {
+        self.messages_received.load(Ordering::SeqCst)
+    }
+
+    /// The number of rounds of communication the local party has sent in
+    ///
+    /// A message's round is the number of network ops on the longest path
+    /// through the circuit to it, so this is the portion of
+    /// `MpcFabric::circuit_depth` that has been executed
+    pub fn num_rounds(&self) -> usize {
+        self.rounds.load(Ordering::Acquire)
+    }
+
+    /// Record a message sent onto the network
+    fn record_sent<C: CurveGroup>(&self, #[allow(unused)] msg: &NetworkOutbound<C>) {
+        #[cfg(feature = "stats")]
+        self.increment_bytes_sent(msg.serialized_size());
+        self.increment_messages_sent();
+    }
+
+    /// Record a message received from the network
+    fn record_received<C: CurveGroup>(&self, #[allow(unused)] msg: &NetworkOutbound<C>) {
+        #[cfg(feature = "stats")]
+        self.increment_bytes_received(msg.serialized_size());
+        self.increment_messages_received();
+    }
+}
+
+// --------------
+// | Reordering |
+// --------------
+
+/// Restores the order in which each peer sent its frames
+///
+/// A frame that arrives ahead of an earlier one is buffered until the gap is
+/// filled, and a frame that has already been received is dropped
+struct ReorderBuffer<C: CurveGroup> {
+    /// The sequence number of the next frame to deliver from each peer
+    next_seq: HashMap<PartyId, u64>,
+    /// The frames received ahead of their turn from each peer, by sequence
+    /// number
+    pending: HashMap<PartyId, BTreeMap<u64, Vec<NetworkOutbound<C>>>>,
+}
+
+impl<C: CurveGroup> Default for ReorderBuffer<C> {
+    fn default() -> Self {
+        Self { next_seq: HashMap::new(), pending: HashMap::new() }
+    }
+}
+
+impl<C: CurveGroup> ReorderBuffer<C> {
+    /// Accept a frame from the given peer, returning the messages that are
+    /// now deliverable in order
+    fn push(
+        &mut self,
+        sender: PartyId,
+        seq: u64,
+        msgs: Vec<NetworkOutbound<C>>,
+    ) -> Result<Vec<NetworkOutbound<C>>, MpcNetworkError> {
+        let next_seq = self.next_seq.entry(sender).or_default();
+        let pending = self.pending.entry(sender).or_default();
+        if seq < *next_seq || pending.contains_key(&seq) {
+            log::warn!("dropping duplicate frame {seq} from party {sender}");
+            return Ok(Vec::new());
+        }
+
+        if pending.len() >= MAX_BUFFERED_FRAMES {
+            return Err(MpcNetworkError::RecvError(ERR_REORDER_OVERFLOW.to_string()));
+        }
+
+        pending.insert(seq, msgs);
+        let mut deliverable = Vec::new();
+        while let Some(msgs) = pending.remove(next_seq) {
+            deliverable.extend(msgs);
+            *next_seq += 1;
+        }
+
+        Ok(deliverable)
     }
 }
 
@@ -74,37 +216,54 @@ impl NetworkStats {
 pub(crate) struct NetworkSender<C: CurveGroup, N: MpcNetwork<C>> {
     /// The outbound queue of messages to send
     outbound: KanalReceiver<NetworkOutbound<C>>,
-    /// The queue of completed results
-    result_queue: Arc<SegQueue<ExecutorMessage<C>>>,
     /// The underlying network connection
     network: N,
     /// The broadcast channel on which shutdown signals are sent
     shutdown: BroadcastReceiver<()>,
+    /// The fabric the sender serves, used to enqueue results, read the
+    /// sender's configuration, and cancel the fabric if the peer is lost
+    fabric: FabricInner<C>,
 }
 
 impl<C: CurveGroup, N: MpcNetwork<C> + 'static> NetworkSender<C, N> {
     /// Creates a new network sender
     pub fn new(
         outbound: KanalReceiver<NetworkOutbound<C>>,
-        result_queue: Arc<SegQueue<ExecutorMessage<C>>>,
         network: N,
         shutdown: BroadcastReceiver<()>,
+        fabric: &FabricInner<C>,
     ) -> Self {
-        NetworkSender { outbound, result_queue, network, shutdown }
+        NetworkSender { outbound, network, shutdown, fabric: fabric.clone() }
     }
 
     /// A helper for the `run` method that allows error handling in the caller
     pub async fn run(self) {
         // Destructure `self` to take ownership of each field
-        let NetworkSender { outbound, result_queue, network, mut shutdown } = self;
-
-        // Setup the stats for the network
-        let stats = Arc::new(NetworkStats::default());
+        let NetworkSender { outbound, network, mut shutdown, fabric } = self;
+        let stats = fabric.network_stats.clone();
+        let last_received = Arc::new(Mutex::new(Instant::now()));
 
         // Start a read and write loop separately
         let (send, recv): (SplitSink<N, NetworkOutbound<C>>, SplitStream<N>) = network.split();
-        let read_loop_fut = tokio::spawn(Self::read_loop(recv, result_queue, stats.clone()));
-        let write_loop_fut = tokio::spawn(Self::write_loop(outbound, send, stats.clone()));
+        let read_loop_fut = tokio::spawn(Self::read_loop(
+            recv,
+            fabric.execution_queue.clone(),
+            stats.clone(),
+            last_received.clone(),
+        ));
+        let write_loop_fut = tokio::spawn(Self::write_loop(
+            outbound,
+            send,
+            fabric.party_id,
+            stats.clone(),
+            fabric.coalescing_window.clone(),
+        ));
+        let heartbeat_fut = tokio::spawn(Self::heartbeat_loop(
+            fabric.heartbeat.subscribe(),
+            fabric.outbound_queue.clone(),
+            last_received,
+        ));
+        let heartbeat_abort = heartbeat_fut.abort_handle();
 
         // Await either of the loops to finish or the shutdown signal
         tokio::select! {
@@ -114,35 +273,64 @@ impl<C: CurveGroup, N: MpcNetwork<C> + 'static> NetworkSender<C, N> {
             err = write_loop_fut => {
                 log::error!("error in `NetworkSender::write_loop`: {err:?}")
             },
+            err = heartbeat_fut => {
+                log::error!("error in `NetworkSender::heartbeat_loop`: {err:?}");
+                if let Ok(err) = err {
+                    fabric.cancel_with_error(err);
+                }
+            },
             _ = shutdown.recv() => {
                 log::info!("received shutdown signal")
             },
         }
 
+        // Stop sending heartbeats so that the peer sees the local party go silent
+        heartbeat_abort.abort();
+
         // Log the stats after execution finishes
         #[cfg(feature = "stats")]
-        println!("Network stats: {:#?}", stats);
+        log::debug!("network stats: {stats:?}");
     }
 
     /// The read loop for the network, reads messages from the network and
     /// re-enqueues them with the executor
+    ///
+    /// Frames are delivered in the order each peer sent them, regardless of
+    /// the order in which the transport delivers them
     async fn read_loop(
         mut network_stream: SplitStream<N>,
         result_queue: Arc<SegQueue<ExecutorMessage<C>>>,
-        #[allow(unused)] stats: Arc<NetworkStats>,
+        stats: Arc<NetworkStats>,
+        last_received: Arc<Mutex<Instant>>,
     ) -> MpcNetworkError {
+        let mut reorder_buffer = ReorderBuffer::default();
         while let Some(Ok(msg)) = network_stream.next().await {
-            #[cfg(feature = "stats")]
-            {
-                let n_bytes = serde_json::to_vec(&msg).unwrap().len();
-                stats.increment_bytes_received(n_bytes);
-                stats.increment_messages_received();
-            }
+            *last_received.lock().expect("last received poisoned") = Instant::now();
+
+            // Unpack frames, each message carries its own result ID
+            let msgs = match msg.payload {
+                NetworkPayload::Frame { sender, seq, msgs } => {
+                    match reorder_buffer.push(sender, seq, msgs) {
+                        Ok(msgs) => msgs,
+                        Err(e) => return e,
+                    }
+                },
+                _ => vec![msg],
+            };
+
+            for msg in msgs {
+                if matches!(msg.payload, NetworkPayload::Heartbeat) {
+                    continue;
+                }
 
-            result_queue.push(ExecutorMessage::Result(OpResult {
-                id: msg.result_id,
-                value: msg.payload.into(),
-            }));
+                stats.record_received(&msg);
+
+                let value = match msg.payload.try_into() {
+                    Ok(value) => value,
+                    Err(e) => return e,
+                };
+                result_queue.push(ExecutorMessage::Result(OpResult { id: msg.result_id, value }));
+            }
         }
 
         MpcNetworkError::RecvError(ERR_STREAM_FINISHED_EARLY.to_string())
@@ -150,19 +338,32 @@ impl<C: CurveGroup, N: MpcNetwork<C> + 'static> NetworkSender<C, N> {
 
     /// The write loop for the network, reads messages from the outbound queue
     /// and sends them onto the network
+    ///
+    /// Messages already queued when one is sent, or queued within the
+    /// coalescing window after it, are coalesced into a single transmission
     async fn write_loop(
         outbound_stream: KanalReceiver<NetworkOutbound<C>>,
         mut network: SplitSink<N, NetworkOutbound<C>>,
-        #[allow(unused)] stats: Arc<NetworkStats>,
+        party_id: PartyId,
+        stats: Arc<NetworkStats>,
+        coalescing_window: Arc<AtomicU64>,
     ) -> MpcNetworkError {
+        let mut seq = 0;
         while let Ok(msg) = outbound_stream.recv().await {
-            #[cfg(feature = "stats")]
+            let window = Duration::from_nanos(coalescing_window.load(Ordering::Acquire));
+            let batch = Self::coalesce(msg, &outbound_stream, window).await;
+
+            for msg in batch.iter().filter(|msg| !matches!(msg.payload, NetworkPayload::Heartbeat))
             {
-                let n_bytes = serde_json::to_vec(&msg).unwrap().len();
-                stats.increment_bytes_sent(n_bytes);
-                stats.increment_messages_sent();
+                stats.record_sent(msg);
             }
 
+            let msg = NetworkOutbound {
+                result_id: batch[0].result_id,
+                payload: NetworkPayload::Frame { sender: party_id, seq, msgs: batch },
+            };
+            seq += 1;
+
             if let Err(e) = network.send(msg).await {
                 log::error!("error sending outbound: {e:?}");
                 return e;
@@ -171,4 +372,183 @@ impl<C: CurveGroup, N: MpcNetwork<C> + 'static> NetworkSender<C, N> {
 
         MpcNetworkError::RecvError(ERR_STREAM_FINISHED_EARLY.to_string())
     }
+
+    /// The heartbeat loop, once heartbeats are enabled sends a keepalive
+    /// message every interval and checks that the peer has sent a message
+    /// recently
+    ///
+    /// Returns `MpcError::PeerUnreachable` once the peer has been silent for
+    /// `max_missed` intervals, never returns if heartbeats are not enabled
+    async fn heartbeat_loop(
+        mut config: WatchReceiver<Option<HeartbeatConfig>>,
+        outbound: KanalSender<NetworkOutbound<C>>,
+        last_received: Arc<Mutex<Instant>>,
+    ) -> MpcError {
+        let config = config
+            .wait_for(Option::is_some)
+            .await
+            .ok()
+            .map(|config| config.expect("heartbeat config is set"));
+        let Some(config) = config else {
+            return pending().await;
+        };
+
+        // The peer's silence is measured from when heartbeats are enabled
+        *last_received.lock().expect("last received poisoned") = Instant::now();
+        let timeout = config.heartbeat_interval * config.max_missed;
+
+        let mut ticker = tokio::time::interval(config.heartbeat_interval);
+        loop {
+            ticker.tick().await;
+            let heartbeat = NetworkOutbound { result_id: 0, payload: NetworkPayload::Heartbeat };
+            if outbound.send(heartbeat).is_err() {
+                return pending().await;
+            }
+
+            let last_received = *last_received.lock().expect("last received poisoned");
+            if last_received.elapsed() > timeout {
+                return MpcError::PeerUnreachable;
+            }
+        }
+    }
+
+    /// Collect the outbound messages to send along with the given message
+    async fn coalesce(
+        msg: NetworkOutbound<C>,
+        outbound_stream: &KanalReceiver<NetworkOutbound<C>>,
+        window: Duration,
+    ) -> Vec<NetworkOutbound<C>> {
+        let deadline = Instant::now() + window;
+        let mut batch = vec![msg];
+        while batch.len() < MAX_COALESCED_MESSAGES {
+            match outbound_stream.try_recv() {
+                Ok(Some(msg)) => batch.push(msg),
+                Ok(None) if !window.is_zero() => {
+                    match tokio::time::timeout_at(deadline, outbound_stream.recv()).await {
+                        Ok(Ok(msg)) => batch.push(msg),
+                        _ => break,
+                    }
+                },
+                _ => break,
+            }
+        }
+
+        batch
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use crossbeam::queue::SegQueue;
+    use futures::{future, StreamExt};
+    use itertools::Itertools;
+    use rand::{seq::SliceRandom, thread_rng};
+
+    use crate::{
+        algebra::{Scalar, ScalarResult},
+        error::MpcNetworkError,
+        network::{MockNetwork, NetworkOutbound, NetworkPayload, UnboundedDuplexStream},
+        offline_prep::PartyIDBeaverSource,
+        test_helpers::TestCurve,
+        MpcFabric, PARTY0, PARTY1,
+    };
+
+    use super::{NetworkSender, NetworkStats};
+
+    /// The network sender over a mock network
+    type MockSender = NetworkSender<TestCurve, MockNetwork<TestCurve>>;
+
+    /// Tests that frames delivered out of order, including a duplicate, are
+    /// assigned to the correct results
+    #[tokio::test]
+    async fn test_shuffled_frames() {
+        const N: usize = 50;
+
+        let (stream, mut peer) = UnboundedDuplexStream::new_duplex_pair();
+        let fabric =
+            MpcFabric::new(MockNetwork::new(PARTY0, stream), PartyIDBeaverSource::new(PARTY0));
+        let results: Vec<ScalarResult<TestCurve>> =
+            (0..N).map(|_| fabric.receive_value()).collect();
+
+        // Frame `i` carries the value `i` for the `i`th result
+        let frame = |seq: usize, value: u64| NetworkOutbound {
+            result_id: results[seq].id(),
+            payload: NetworkPayload::Frame {
+                sender: PARTY1,
+                seq: seq as u64,
+                msgs: vec![NetworkOutbound {
+                    result_id: results[seq].id(),
+                    payload: NetworkPayload::Scalar(Scalar::from(value)),
+                }],
+            },
+        };
+
+        // Send the frames in a random order, following each with a duplicate
+        // that carries the wrong value
+        let mut order = (0..N).collect_vec();
+        order.shuffle(&mut thread_rng());
+        for seq in order {
+            peer.send(frame(seq, seq as u64));
+            peer.send(frame(seq, u64::MAX));
+        }
+
+        let values = future::join_all(results).await;
+        let expected = (0..N as u64).map(Scalar::from).collect_vec();
+        assert_eq!(values, expected);
+
+        fabric.shutdown();
+    }
+
+    /// Tests that messages queued within the coalescing window join the
+    /// transmission, and messages queued after it do not
+    #[tokio::test(start_paused = true)]
+    async fn test_coalescing_window() {
+        const WINDOW: Duration = Duration::from_millis(10);
+
+        let msg = |result_id| NetworkOutbound::<TestCurve> {
+            result_id,
+            payload: NetworkPayload::Heartbeat,
+        };
+        let (send, recv) = kanal::unbounded_async();
+        send.send(msg(1)).await.unwrap();
+
+        let sender = tokio::spawn(async move {
+            tokio::time::sleep(WINDOW / 2).await;
+            send.send(msg(2)).await.unwrap();
+            tokio::time::sleep(WINDOW).await;
+            send.send(msg(3)).await.unwrap();
+        });
+
+        let batch = MockSender::coalesce(msg(0), &recv, WINDOW).await;
+        assert_eq!(batch.iter().map(|msg| msg.result_id).collect_vec(), [0, 1, 2]);
+
+        sender.await.unwrap();
+        assert_eq!(recv.try_recv().unwrap().unwrap().result_id, 3);
+    }
+
+    /// Tests that a payload carrying no result value ends the read loop with
+    /// an error
+    #[tokio::test]
+    async fn test_read_loop_rejects_non_value() {
+        let (stream, mut peer) = UnboundedDuplexStream::new_duplex_pair();
+        let (_, network_stream) = MockNetwork::new(PARTY0, stream).split();
+        let result_queue = Arc::new(SegQueue::new());
+
+        peer.send(NetworkOutbound { result_id: 0, payload: NetworkPayload::Ack { received: 1 } });
+        let err = MockSender::read_loop(
+            network_stream,
+            result_queue.clone(),
+            Arc::new(NetworkStats::default()),
+            Arc::new(Mutex::new(tokio::time::Instant::now())),
+        )
+        .await;
+
+        assert!(matches!(err, MpcNetworkError::RecvError(_)));
+        assert!(result_queue.is_empty());
+    }
 }
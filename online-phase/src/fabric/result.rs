@@ -15,16 +15,26 @@ use futures::Future;
 
 use crate::{
     algebra::{CurvePoint, PointShare, Scalar, ScalarShare},
+    error::{MpcError, MpcNetworkError},
     network::{NetworkPayload, PartyId},
 };
 
-use super::MpcFabric;
+use super::{executor::ExecutorJobQueue, ExecutorMessage, MpcFabric, N_CONSTANT_RESULTS};
 
 /// A type alias representing a shared reference to a value
 pub(crate) type Shared<T> = Arc<RwLock<T>>;
 
 /// Error message when a result buffer lock is poisoned
 pub(crate) const ERR_RESULT_BUFFER_POISONED: &str = "result buffer lock poisoned";
+/// Error message emitted when a result is awaited directly on a cancelled
+/// fabric
+const ERR_RESULT_CANCELLED: &str = "fabric cancelled before the result was available";
+/// Error message emitted when an operation mixes results from different
+/// fabrics
+pub(crate) const ERR_MIXED_FABRICS: &str =
+    "cannot operate on results allocated in different fabrics";
+/// Error message emitted when a network payload carries no result value
+const ERR_NOT_A_VALUE: &str = "network payload does not carry a result value";
 
 // ---------------------
 // | Result Value Type |
@@ -80,9 +90,13 @@ impl<C: CurveGroup> Debug for ResultValue<C> {
     }
 }
 
-impl<C: CurveGroup> From<NetworkPayload<C>> for ResultValue<C> {
-    fn from(value: NetworkPayload<C>) -> Self {
-        match value {
+/// Frames, heartbeats, and reliable link messages are consumed by the
+/// transport and carry no result value, converting them is an error
+impl<C: CurveGroup> TryFrom<NetworkPayload<C>> for ResultValue<C> {
+    type Error = MpcNetworkError;
+
+    fn try_from(value: NetworkPayload<C>) -> Result<Self, Self::Error> {
+        Ok(match value {
             NetworkPayload::Bytes(bytes) => ResultValue::Bytes(bytes),
             NetworkPayload::Scalar(scalar) => ResultValue::Scalar(scalar),
             NetworkPayload::ScalarBatch(scalars) => ResultValue::ScalarBatch(scalars),
@@ -90,7 +104,13 @@ impl<C: CurveGroup> From<NetworkPayload<C>> for ResultValue<C> {
             NetworkPayload::Point(point) => ResultValue::Point(point),
             NetworkPayload::PointBatch(points) => ResultValue::PointBatch(points),
             NetworkPayload::PointShare(share) => ResultValue::PointShare(share),
-        }
+            NetworkPayload::Frame { .. }
+            | NetworkPayload::Heartbeat
+            | NetworkPayload::Sequenced { .. }
+            | NetworkPayload::Ack { .. } => {
+                return Err(MpcNetworkError::RecvError(ERR_NOT_A_VALUE.to_string()))
+            },
+        })
     }
 }
 
@@ -245,6 +265,11 @@ impl<C: CurveGroup> From<PointShare<C>> for ResultValue<C> {
 /// This allows for construction of the graph concurrently with execution,
 /// giving the fabric the opportunity to schedule all results onto the network
 /// optimistically
+///
+/// If the fabric is cancelled before the result is available, e.g. because the
+/// peer is unreachable, `await`-ing the handle panics with the cancellation
+/// error. Code that must survive a cancellation should await through
+/// `ResultHandle::cancellable`, which resolves to the error instead
 #[derive(Debug)]
 pub struct ResultHandle<C: CurveGroup, T: From<ResultValue<C>>> {
     /// The id of the result
@@ -253,6 +278,9 @@ pub struct ResultHandle<C: CurveGroup, T: From<ResultValue<C>>> {
     pub(crate) result_buffer: Option<Shared<ResultValue<C>>>,
     /// The underlying fabric
     pub(crate) fabric: MpcFabric<C>,
+    /// A guard shared between clones of the handle, releasing the result to
+    /// the garbage collector when the last clone is dropped
+    gc_guard: Option<Arc<ResultGuard<C>>>,
     /// A phantom for the type of the result
     phantom: PhantomData<T>,
 }
@@ -261,7 +289,28 @@ impl<C: CurveGroup, T: From<ResultValue<C>>> Unpin for ResultHandle<C, T> {}
 
 impl<C: CurveGroup, T: From<ResultValue<C>>> Clone for ResultHandle<C, T> {
     fn clone(&self) -> Self {
-        Self { id: self.id, result_buffer: None, fabric: self.fabric.clone(), phantom: PhantomData }
+        Self {
+            id: self.id,
+            result_buffer: None,
+            fabric: self.fabric.clone(),
+            gc_guard: self.gc_guard.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Notifies the executor that a result may be reclaimed when dropped
+#[derive(Debug)]
+pub(crate) struct ResultGuard<C: CurveGroup> {
+    /// The id of the guarded result
+    id: ResultId,
+    /// The executor's job queue
+    execution_queue: ExecutorJobQueue<C>,
+}
+
+impl<C: CurveGroup> Drop for ResultGuard<C> {
+    fn drop(&mut self) {
+        self.execution_queue.push(ExecutorMessage::ReleaseResult(self.id));
     }
 }
 
@@ -280,12 +329,53 @@ impl<C: CurveGroup, T: From<ResultValue<C>>> ResultHandle<C, T> {
     pub fn fabric(&self) -> &MpcFabric<C> {
         &self.fabric
     }
+
+    /// Assert that another result was allocated in the same fabric as this one
+    ///
+    /// Result IDs index into a single fabric's results, so an operation mixing
+    /// fabrics would compute on unrelated values and MAC them under another
+    /// fabric's key
+    pub(crate) fn assert_same_fabric<U: From<ResultValue<C>>>(&self, other: &ResultHandle<C, U>) {
+        assert!(self.fabric.same_fabric(&other.fabric), "{ERR_MIXED_FABRICS}");
+    }
 }
 
 impl<C: CurveGroup, T: From<ResultValue<C>>> ResultHandle<C, T> {
     /// Constructor
+    ///
+    /// The handle owns the result's GC guard, so this must be called once per
+    /// result. Further handles to the result are clones of this one, or are
+    /// built with `with_gc_guard` from its guard, otherwise the first of them
+    /// to be dropped releases the result while the others still refer to it
     pub(crate) fn new(id: ResultId, fabric: MpcFabric<C>) -> Self {
-        Self { id, result_buffer: None, fabric, phantom: PhantomData }
+        // The constant results are shared by the whole fabric and never reclaimed
+        let gc_guard = (fabric.result_gc_enabled() && id >= N_CONSTANT_RESULTS).then(|| {
+            let execution_queue = fabric.inner.execution_queue.clone();
+            Arc::new(ResultGuard { id, execution_queue })
+        });
+
+        Self { id, result_buffer: None, fabric, gc_guard, phantom: PhantomData }
+    }
+
+    /// Construct a handle to a result that is never reclaimed by the result GC
+    pub(crate) fn new_unguarded(id: ResultId, fabric: MpcFabric<C>) -> Self {
+        Self { id, result_buffer: None, fabric, gc_guard: None, phantom: PhantomData }
+    }
+
+    /// Construct a handle sharing the GC guard of an existing handle to the
+    /// same result
+    pub(crate) fn with_gc_guard(
+        id: ResultId,
+        fabric: MpcFabric<C>,
+        gc_guard: Option<Arc<ResultGuard<C>>>,
+    ) -> Self {
+        Self { id, result_buffer: None, fabric, gc_guard, phantom: PhantomData }
+    }
+
+    /// Get the GC guard shared by the handles to the result, `None` if the
+    /// result is never reclaimed
+    pub(crate) fn gc_guard(&self) -> Option<Arc<ResultGuard<C>>> {
+        self.gc_guard.clone()
     }
 
     /// Get the ids that this result represents, awaiting these IDs is awaiting
@@ -293,6 +383,13 @@ impl<C: CurveGroup, T: From<ResultValue<C>>> ResultHandle<C, T> {
     pub fn op_ids(&self) -> Vec<ResultId> {
         vec![self.id]
     }
+
+    /// Await the result, resolving to `MpcError::Cancelled` if the fabric is
+    /// cancelled before the result is available, or to the error that caused
+    /// the cancellation, e.g. `MpcError::PeerUnreachable`
+    pub fn cancellable(self) -> CancellableResult<C, T> {
+        CancellableResult { handle: self }
+    }
 }
 
 /// A struct describing an async task that is waiting on a result
@@ -311,10 +408,10 @@ impl<C: CurveGroup> Debug for ResultWaiter<C> {
     }
 }
 
-impl<C: CurveGroup, T: From<ResultValue<C>> + Unpin> Future for ResultHandle<C, T> {
-    type Output = T;
-
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+impl<C: CurveGroup, T: From<ResultValue<C>> + Unpin> ResultHandle<C, T> {
+    /// Poll the result, resolving to the cancellation error if the fabric is
+    /// cancelled before the result is available
+    fn poll_result(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, MpcError>> {
         // If the result buffer is not yet initialized, initialize it and alert the
         // executor that the polling task is waiting on it
         if self.result_buffer.is_none() {
@@ -330,9 +427,46 @@ impl<C: CurveGroup, T: From<ResultValue<C>> + Unpin> Future for ResultHandle<C,
         // waker with the `Executor`
         let locked_result =
             self.result_buffer.as_ref().unwrap().read().expect(ERR_RESULT_BUFFER_POISONED);
-        match locked_result.clone() {
-            ResultValue::Placeholder => Poll::Pending,
-            _ => Poll::Ready(locked_result.clone().into()),
+        if !matches!(*locked_result, ResultValue::Placeholder) {
+            return Poll::Ready(Ok(locked_result.clone().into()));
+        }
+
+        // The executor wakes all waiters on cancellation, so checking the flag
+        // after registering the waiter above cannot miss a cancellation
+        match self.fabric.cancellation_error() {
+            Some(err) => Poll::Ready(Err(err)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// Awaiting a result directly panics with the cancellation error if the fabric
+/// is cancelled before the result is available, as there is no value to
+/// resolve to. Await through `ResultHandle::cancellable` to handle the error
+/// instead
+impl<C: CurveGroup, T: From<ResultValue<C>> + Unpin> Future for ResultHandle<C, T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.poll_result(cx) {
+            Poll::Ready(Ok(value)) => Poll::Ready(value),
+            Poll::Ready(Err(err)) => panic!("{ERR_RESULT_CANCELLED}: {err}"),
+            Poll::Pending => Poll::Pending,
         }
     }
 }
+
+/// A wrapper around a `ResultHandle` that resolves to an error if the fabric
+/// is cancelled before the result is available
+pub struct CancellableResult<C: CurveGroup, T: From<ResultValue<C>>> {
+    /// The underlying result handle
+    handle: ResultHandle<C, T>,
+}
+
+impl<C: CurveGroup, T: From<ResultValue<C>> + Unpin> Future for CancellableResult<C, T> {
+    type Output = Result<T, MpcError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.handle.poll_result(cx)
+    }
+}
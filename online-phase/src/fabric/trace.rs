@@ -0,0 +1,267 @@
+//! Defines circuit traces, serializable records of the operations in a circuit
+//! that may be replayed against fresh inputs
+//!
+//! Gate closures cannot be serialized, so only operations allocated through
+//! `MpcFabric::new_registered_op` are traced. These operations reference
+//! their function by an opcode registered with the fabric, and a replaying
+//! fabric resolves the opcodes against its own registry
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::Result as IoResult,
+    path::Path,
+    sync::Arc,
+};
+
+use ark_ec::CurveGroup;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::error::MpcError;
+
+use super::{BoxedResultIter, MpcFabric, ResultHandle, ResultId, ResultValue, N_CONSTANT_RESULTS};
+
+/// The identifier of a registered operation
+pub type Opcode = String;
+
+/// The function implementing a registered operation
+#[allow(type_alias_bounds)]
+pub type OpcodeFn<C: CurveGroup> =
+    Arc<dyn for<'a> Fn(BoxedResultIter<'a, C>) -> Vec<ResultValue<C>> + Send + Sync>;
+
+/// A registry mapping opcodes to the functions they implement
+#[derive(Clone)]
+pub(crate) struct OpcodeRegistry<C: CurveGroup> {
+    /// The registered functions
+    functions: HashMap<Opcode, OpcodeFn<C>>,
+}
+
+impl<C: CurveGroup> Default for OpcodeRegistry<C> {
+    fn default() -> Self {
+        Self { functions: HashMap::new() }
+    }
+}
+
+impl<C: CurveGroup> OpcodeRegistry<C> {
+    /// Register a function under the given opcode, replacing any existing
+    /// registration
+    pub fn register(&mut self, opcode: Opcode, function: OpcodeFn<C>) {
+        self.functions.insert(opcode, function);
+    }
+
+    /// Get the function registered under the given opcode
+    pub fn get(&self, opcode: &str) -> Option<OpcodeFn<C>> {
+        self.functions.get(opcode).cloned()
+    }
+}
+
+// ---------
+// | Trace |
+// ---------
+
+/// A traced operation
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TracedOp {
+    /// The opcode of the operation
+    pub opcode: Opcode,
+    /// The IDs of the operation's arguments in the recorded circuit
+    pub args: Vec<ResultId>,
+    /// The IDs of the operation's results in the recorded circuit
+    pub outputs: Vec<ResultId>,
+}
+
+/// A serializable record of the registered operations in a circuit
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitTrace {
+    /// The IDs of the values the circuit consumes but does not compute, in the
+    /// order in which they were first used
+    pub inputs: Vec<ResultId>,
+    /// The operations in the circuit, in the order they were allocated
+    pub ops: Vec<TracedOp>,
+    /// The IDs of the results the circuit computes but does not consume
+    pub outputs: Vec<ResultId>,
+}
+
+impl CircuitTrace {
+    /// Build a trace from a sequence of recorded operations
+    pub(crate) fn from_ops(ops: Vec<TracedOp>) -> Self {
+        let produced: HashSet<ResultId> = ops.iter().flat_map(|op| op.outputs.clone()).collect();
+        let consumed: HashSet<ResultId> = ops.iter().flat_map(|op| op.args.clone()).collect();
+
+        // The constant results have the same IDs in every fabric and are not inputs
+        let inputs = ops
+            .iter()
+            .flat_map(|op| op.args.iter().copied())
+            .filter(|id| *id >= N_CONSTANT_RESULTS && !produced.contains(id))
+            .unique()
+            .collect_vec();
+        let outputs = ops
+            .iter()
+            .flat_map(|op| op.outputs.iter().copied())
+            .filter(|id| !consumed.contains(id))
+            .collect_vec();
+
+        Self { inputs, ops, outputs }
+    }
+
+    /// Serialize the trace to bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("trace serialization cannot fail")
+    }
+
+    /// Deserialize a trace from bytes
+    pub fn from_bytes(bytes: &[u8]) -> IoResult<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    /// Write the trace to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> IoResult<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    /// Read a trace from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> IoResult<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Replay the trace in the given fabric on the given inputs
+    ///
+    /// The inputs correspond positionally to `self.inputs`, and the returned
+    /// handles correspond positionally to `self.outputs`. Every opcode in the
+    /// trace must be registered with the fabric
+    ///
+    /// Returns an error if the number of inputs does not match the trace, or
+    /// if the trace uses an opcode the fabric does not know
+    pub fn replay<C: CurveGroup>(
+        &self,
+        fabric: &MpcFabric<C>,
+        inputs: &[ResultId],
+    ) -> Result<Vec<ResultHandle<C, ResultValue<C>>>, MpcError> {
+        if inputs.len() != self.inputs.len() {
+            return Err(MpcError::TraceInputMismatch {
+                expected: self.inputs.len(),
+                actual: inputs.len(),
+            });
+        }
+
+        if let Some(op) = self.ops.iter().find(|op| !fabric.has_opcode(&op.opcode)) {
+            return Err(MpcError::UnknownOpcode(op.opcode.clone()));
+        }
+
+        // Map the recorded IDs onto the IDs allocated in the replay
+        let mut id_map: HashMap<ResultId, ResultId> =
+            self.inputs.iter().copied().zip(inputs.iter().copied()).collect();
+        let mut results = HashMap::new();
+        for op in self.ops.iter() {
+            let args = op.args.iter().map(|id| *id_map.get(id).unwrap_or(id)).collect_vec();
+            let outputs = fabric.new_registered_op(&op.opcode, args, op.outputs.len());
+
+            for (recorded_id, output) in op.outputs.iter().zip(outputs) {
+                id_map.insert(*recorded_id, output.id());
+                results.insert(*recorded_id, output);
+            }
+        }
+
+        Ok(self.outputs.iter().map(|id| results[id].clone()).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algebra::{Scalar, ScalarResult},
+        error::MpcError,
+        test_helpers::{mock_fabric, TestCurve},
+        MpcFabric, ResultValue,
+    };
+
+    use super::CircuitTrace;
+
+    /// Register the opcodes used in the tests
+    fn register_opcodes(fabric: &MpcFabric<TestCurve>) {
+        fabric.register_opcode("add", |mut args| {
+            let a: Scalar<TestCurve> = args.next().unwrap().into();
+            let b: Scalar<TestCurve> = args.next().unwrap().into();
+            vec![ResultValue::Scalar(a + b)]
+        });
+        fabric.register_opcode("mul", |mut args| {
+            let a: Scalar<TestCurve> = args.next().unwrap().into();
+            let b: Scalar<TestCurve> = args.next().unwrap().into();
+            vec![ResultValue::Scalar(a * b)]
+        });
+    }
+
+    /// Tests recording a circuit, round tripping it through a file, and
+    /// replaying it
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let fabric = mock_fabric();
+        register_opcodes(&fabric);
+
+        // Record `(a + b) * c`
+        let inputs = fabric.allocate_scalars(vec![2u8, 3u8, 4u8]);
+        let ids = inputs.iter().map(|x| x.id()).collect::<Vec<_>>();
+
+        fabric.start_trace();
+        let sum: ScalarResult<TestCurve> =
+            fabric.new_registered_op("add", ids[..2].to_vec(), 1).remove(0);
+        let prod: ScalarResult<TestCurve> =
+            fabric.new_registered_op("mul", vec![sum.id(), ids[2]], 1).remove(0);
+        let trace = fabric.finish_trace().unwrap();
+
+        assert_eq!(trace.inputs, ids);
+        assert_eq!(trace.outputs, vec![prod.id()]);
+        let recorded = prod.await;
+
+        // Round trip the trace through a file
+        let path = std::env::temp_dir().join(format!("trace-{}.json", std::process::id()));
+        trace.save(&path).unwrap();
+        let loaded = CircuitTrace::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, trace);
+
+        // Replay on the same inputs, then on fresh inputs
+        let replayed = loaded.replay(&fabric, &ids).unwrap().remove(0).await;
+        let fresh = fabric.allocate_scalars(vec![5u8, 6u8, 7u8]);
+        let fresh_ids = fresh.iter().map(|x| x.id()).collect::<Vec<_>>();
+        let fresh_res = loaded.replay(&fabric, &fresh_ids).unwrap().remove(0).await;
+
+        assert_eq!(Scalar::from(replayed), recorded);
+        assert_eq!(Scalar::from(fresh_res), Scalar::from(77u8));
+    }
+
+    /// Tests that replaying a trace with an unregistered opcode fails
+    #[tokio::test]
+    async fn test_replay_unknown_opcode() {
+        let fabric = mock_fabric();
+        register_opcodes(&fabric);
+
+        let inputs = fabric.allocate_scalars(vec![1u8, 2u8]);
+        let ids = inputs.iter().map(|x| x.id()).collect::<Vec<_>>();
+        fabric.start_trace();
+        let _sum: Vec<ScalarResult<TestCurve>> = fabric.new_registered_op("add", ids.clone(), 1);
+        let trace = fabric.finish_trace().unwrap();
+
+        let other_fabric = mock_fabric();
+        let res = trace.replay(&other_fabric, &ids);
+        assert_eq!(res.err(), Some(MpcError::UnknownOpcode("add".to_string())));
+    }
+
+    /// Tests that replaying a trace on the wrong number of inputs fails
+    #[tokio::test]
+    async fn test_replay_input_mismatch() {
+        let fabric = mock_fabric();
+        register_opcodes(&fabric);
+
+        let inputs = fabric.allocate_scalars(vec![1u8, 2u8]);
+        let ids = inputs.iter().map(|x| x.id()).collect::<Vec<_>>();
+        fabric.start_trace();
+        let _sum: Vec<ScalarResult<TestCurve>> = fabric.new_registered_op("add", ids.clone(), 1);
+        let trace = fabric.finish_trace().unwrap();
+
+        let res = trace.replay(&fabric, &ids[..1]);
+        assert_eq!(res.err(), Some(MpcError::TraceInputMismatch { expected: 2, actual: 1 }));
+        fabric.shutdown();
+    }
+}
@@ -5,12 +5,20 @@ use std::iter;
 
 use ark_ec::CurveGroup;
 use itertools::Itertools;
+use num_bigint::BigUint;
 
 use crate::{
     algebra::{AuthenticatedScalarResult, Scalar, ScalarResult, ScalarShare},
     MpcFabric, ResultValue,
 };
 
+/// The statistical security parameter, in bits, of the masks that hide a
+/// shared integer when the integer gadgets open it
+pub const STATISTICAL_SECURITY: usize = 40;
+/// The default bound on the integers accepted by the integer gadgets, each
+/// value `x` is assumed to satisfy `|x| < 2^INTEGER_BITS`
+pub const INTEGER_BITS: usize = 64;
+
 /// Single bit xor, assumes that `a` and `b` are scalars representing bits
 ///
 /// xor(a, b) = a + b - 2ab
@@ -59,6 +67,7 @@ pub fn bit_xor_public<C: CurveGroup>(
     a: &ScalarResult<C>,
     b: &AuthenticatedScalarResult<C>,
 ) -> AuthenticatedScalarResult<C> {
+    b.assert_same_fabric(a);
     let fabric = a.fabric();
     let party_id = fabric.party_id();
     let mac_key = fabric.mac_key();
@@ -93,6 +102,289 @@ pub fn bit_xor_public_batch<C: CurveGroup>(
     AuthenticatedScalarResult::batch_sub(&a_plus_b, &twos_times_a_times_b)
 }
 
+/// OR a batch of bits
+///
+/// or(a, b) = a + b - ab
+pub fn bit_or_batch<C: CurveGroup>(
+    a: &[AuthenticatedScalarResult<C>],
+    b: &[AuthenticatedScalarResult<C>],
+) -> Vec<AuthenticatedScalarResult<C>> {
+    assert_eq!(a.len(), b.len(), "bit_or takes bit representations of equal length");
+    if a.is_empty() {
+        return Vec::new();
+    }
+
+    let a_plus_b = AuthenticatedScalarResult::batch_add(a, b);
+    let a_times_b = AuthenticatedScalarResult::batch_mul(a, b);
+    AuthenticatedScalarResult::batch_sub(&a_plus_b, &a_times_b)
+}
+
+/// Recompose a little-endian shared bit vector into the integer it represents
+///
+/// The bits are weighted by powers of two in a single gate, so no network
+/// round is required
+pub fn bit_compose<C: CurveGroup>(
+    bits: &[AuthenticatedScalarResult<C>],
+) -> AuthenticatedScalarResult<C> {
+    assert!(!bits.is_empty(), "bit_compose requires a non-empty bit vector");
+
+    let fabric = bits[0].fabric();
+    fabric.assert_same_fabric(bits);
+    let ids = bits.iter().map(|bit| bit.id()).collect_vec();
+    fabric.new_gate_op(ids, move |args| {
+        let two = Scalar::from(2u64);
+        let bits = args.map(ScalarShare::from).collect_vec();
+        let value = bits.into_iter().rev().fold(ScalarShare::default(), |acc, bit| acc * two + bit);
+
+        ResultValue::ScalarShare(value)
+    })
+}
+
+/// Compute the prefix ORs of each of a batch of shared bit vectors, where the
+/// `i`th prefix OR of a vector is `x0 | x1 | ... | xi`
+///
+/// The prefixes are computed with a Kogge-Stone network, so a vector of
+/// length `n` takes `log(n)` rounds. The multiplications of each round are
+/// batched across all the vectors
+pub fn prefix_or_batch<C: CurveGroup>(
+    bits: &[Vec<AuthenticatedScalarResult<C>>],
+) -> Vec<Vec<AuthenticatedScalarResult<C>>> {
+    let mut prefixes = bits.to_vec();
+    let max_len = prefixes.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut step = 1;
+    while step < max_len {
+        // OR each element with the element `step` places before it
+        let lhs = prefixes.iter().flat_map(|p| p.iter().skip(step).cloned()).collect_vec();
+        let rhs = prefixes
+            .iter()
+            .flat_map(|p| p.iter().take(p.len().saturating_sub(step)).cloned())
+            .collect_vec();
+
+        let mut combined = bit_or_batch(&lhs, &rhs).into_iter();
+        for prefix in prefixes.iter_mut() {
+            for x in prefix.iter_mut().skip(step) {
+                *x = combined.next().unwrap();
+            }
+        }
+
+        step *= 2;
+    }
+
+    prefixes
+}
+
+/// Compute the OR of all bits in each of a batch of non-empty shared bit
+/// vectors
+///
+/// The bits are ORed pairwise in a tree, so a vector of length `n` takes
+/// `log(n)` rounds and `n - 1` multiplications
+fn or_all_batch<C: CurveGroup>(
+    bits: Vec<Vec<AuthenticatedScalarResult<C>>>,
+) -> Vec<AuthenticatedScalarResult<C>> {
+    let mut levels = bits;
+    while levels.iter().any(|level| level.len() > 1) {
+        // Pair the first half of each level with the second, carrying any odd
+        // element up to the next level
+        let halves = levels.iter().map(|level| level.len() / 2).collect_vec();
+        let mut lhs = Vec::new();
+        let mut rhs = Vec::new();
+        for (level, half) in levels.iter().zip(halves.iter()) {
+            lhs.extend_from_slice(&level[..*half]);
+            rhs.extend_from_slice(&level[*half..2 * half]);
+        }
+
+        let mut combined = bit_or_batch(&lhs, &rhs).into_iter();
+        for (level, half) in levels.iter_mut().zip(halves) {
+            let carry = level.drain(2 * half..).collect_vec();
+            *level = combined.by_ref().take(half).chain(carry).collect_vec();
+        }
+    }
+
+    levels.into_iter().map(|mut level| level.pop().expect("empty bit vector")).collect_vec()
+}
+
+/// Compare each public bit vector `a` with a shared bit vector `b`, both
+/// little-endian, returning a sharing of the bit `a < b`
+///
+/// `a < b` iff the most significant bit in which the two differ is unset in
+/// `a`. The differing bits are found with a prefix OR from the most
+/// significant bit, which is the only step that requires communication
+pub fn bit_lt_public_batch<C: CurveGroup>(
+    a: &[Vec<ScalarResult<C>>],
+    b: &[Vec<AuthenticatedScalarResult<C>>],
+) -> Vec<AuthenticatedScalarResult<C>> {
+    assert_eq!(a.len(), b.len(), "bit_lt takes batches of equal length");
+
+    // The bits in which each pair differs, most significant first
+    let diffs = a
+        .iter()
+        .zip(b.iter())
+        .map(|(a, b)| {
+            assert!(!a.is_empty(), "bit_lt requires non-empty bit vectors");
+            let mut diff = bit_xor_public_batch(a, b);
+            diff.reverse();
+            diff
+        })
+        .collect_vec();
+
+    prefix_or_batch(&diffs)
+        .into_iter()
+        .zip(a.iter())
+        .map(|(prefix, a)| {
+            // The prefix OR steps from zero to one exactly at the first differing bit
+            let first_diff = iter::once(prefix[0].clone())
+                .chain(prefix.windows(2).map(|pair| &pair[1] - &pair[0]))
+                .collect_vec();
+            let a_msb_first = a.iter().rev().cloned().collect_vec();
+            let a_at_diff: AuthenticatedScalarResult<C> =
+                AuthenticatedScalarResult::batch_mul_public(&first_diff, &a_msb_first)
+                    .into_iter()
+                    .sum();
+
+            // The last prefix is one iff the vectors differ at all
+            prefix.last().unwrap() - a_at_diff
+        })
+        .collect_vec()
+}
+
+/// A shared integer opened under a random mask, alongside the shared bits of
+/// the low part of the mask
+struct MaskedOpening<C: CurveGroup> {
+    /// The low `k` bits of the opened value, little-endian
+    opened_bits: Vec<ScalarResult<C>>,
+    /// The opened value reduced modulo `2^k`
+    opened_low: ScalarResult<C>,
+    /// The low `k` bits of the mask, little-endian
+    mask_bits: Vec<AuthenticatedScalarResult<C>>,
+}
+
+/// Open each shared integer under a random mask whose low `k` bits are
+/// sampled as shared bits
+///
+/// Each value `x` must satisfy `|x| < 2^bits`. The opened value is
+/// `c = x + 2^bits + r` for a random `r` of `bits + STATISTICAL_SECURITY + 1`
+/// bits, so `c` is positive, does not wrap the field modulus, and is within
+/// statistical distance `2^-STATISTICAL_SECURITY` of a value independent of
+/// `x`. As `k <= bits`, `c mod 2^k = (x + r) mod 2^k`
+fn open_masked<C: CurveGroup>(
+    values: &[AuthenticatedScalarResult<C>],
+    k: usize,
+    bits: usize,
+) -> Vec<MaskedOpening<C>> {
+    assert!(k <= bits, "cannot take {k} low bits of a {bits} bit integer");
+    assert!(
+        bits + STATISTICAL_SECURITY + 3 <= Scalar::<C>::bit_length(),
+        "masked {bits} bit integers do not fit in the scalar field"
+    );
+
+    let fabric = values[0].fabric();
+    fabric.assert_same_fabric(values);
+    let mask_len = bits + STATISTICAL_SECURITY + 1;
+    let mask_bits = fabric.random_shared_bits(values.len() * mask_len);
+
+    let offset = Scalar::from(2u64).pow(bits as u64);
+    let masked = values
+        .iter()
+        .zip(mask_bits.chunks(mask_len))
+        .map(|(x, mask)| x + bit_compose(mask) + offset)
+        .collect_vec();
+    let opened = AuthenticatedScalarResult::open_authenticated_batch(&masked);
+
+    opened
+        .into_iter()
+        .zip(mask_bits.chunks(mask_len))
+        .map(|(c, mask)| {
+            let mut low = fabric.new_batch_gate_op(vec![c.value.id()], k + 1, move |mut args| {
+                let c = Scalar::from(args.next().unwrap()).to_biguint();
+                let low = c % (BigUint::from(1u8) << k);
+                let bits = (0..k as u64).map(|i| Scalar::from(low.bit(i)));
+
+                iter::once(Scalar::from_biguint(&low))
+                    .chain(bits)
+                    .map(ResultValue::Scalar)
+                    .collect_vec()
+            });
+
+            let opened_low = low.remove(0);
+            MaskedOpening { opened_bits: low, opened_low, mask_bits: mask[..k].to_vec() }
+        })
+        .collect_vec()
+}
+
+/// Reduce each shared integer modulo `2^k`
+///
+/// Each value `x` must satisfy `|x| < 2^bits` with `k <= bits`. The result is
+/// the residue in `[0, 2^k)`, so negative values wrap. Following Catrina and
+/// de Hoogh, the value is opened under a mask `r` and the residue is
+/// `(c mod 2^k) - (r mod 2^k)`, plus `2^k` if the subtraction borrows
+pub fn mod_power_of_two_batch<C: CurveGroup>(
+    values: &[AuthenticatedScalarResult<C>],
+    k: usize,
+    bits: usize,
+) -> Vec<AuthenticatedScalarResult<C>> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+    if k == 0 {
+        return values.iter().map(|x| x.fabric().zero_authenticated()).collect_vec();
+    }
+
+    let openings = open_masked(values, k, bits);
+    let opened_bits = openings.iter().map(|o| o.opened_bits.clone()).collect_vec();
+    let mask_bits = openings.iter().map(|o| o.mask_bits.clone()).collect_vec();
+    let borrows = bit_lt_public_batch(&opened_bits, &mask_bits);
+
+    let two_k = Scalar::from(2u64).pow(k as u64);
+    openings
+        .into_iter()
+        .zip(borrows)
+        .map(|(o, borrow)| borrow * two_k - bit_compose(&o.mask_bits) + o.opened_low)
+        .collect_vec()
+}
+
+/// Truncate each shared integer by `k` bits, i.e. compute `floor(x / 2^k)`
+///
+/// Each value `x` must satisfy `|x| < 2^bits` with `k <= bits`. The residue
+/// modulo `2^k` is subtracted so that the division by `2^k` is exact
+pub fn truncate_batch<C: CurveGroup>(
+    values: &[AuthenticatedScalarResult<C>],
+    k: usize,
+    bits: usize,
+) -> Vec<AuthenticatedScalarResult<C>> {
+    if k == 0 {
+        return values.to_vec();
+    }
+
+    let residues = mod_power_of_two_batch(values, k, bits);
+    let shift = Scalar::from(2u64).pow(k as u64).inverse();
+
+    values.iter().zip(residues).map(|(x, residue)| (x - residue) * shift).collect_vec()
+}
+
+/// Test each shared integer for zero, returning a sharing of one for zero
+/// values and of zero otherwise
+///
+/// Each value `x` must satisfy `|x| < 2^bits`, so that `x` is zero iff its
+/// low `bits` bits are. These are compared against the mask's bits after a
+/// masked opening, and the comparison takes `log(bits)` rounds
+pub fn is_zero_batch<C: CurveGroup>(
+    values: &[AuthenticatedScalarResult<C>],
+    bits: usize,
+) -> Vec<AuthenticatedScalarResult<C>> {
+    assert!(bits > 0, "is_zero requires a non-zero bit length");
+    if values.is_empty() {
+        return Vec::new();
+    }
+
+    let diffs = open_masked(values, bits, bits)
+        .into_iter()
+        .map(|o| bit_xor_public_batch(&o.opened_bits, &o.mask_bits))
+        .collect_vec();
+
+    or_all_batch(diffs).into_iter().map(|any_diff| Scalar::one() - any_diff).collect_vec()
+}
+
 /// A prefix product gadget, computes the prefix products of a vector of values,
 /// where for `n` values, the `i`th prefix product is defined as:
 ///     x0 * x1 * ... * xi
@@ -28,7 +28,12 @@ pub mod offline_prep;
 pub use fabric::*;
 
 #[cfg(all(not(feature = "benchmarks"), feature = "fabric"))]
-pub use fabric::{ExecutorSizeHints, FabricInner, MpcFabric, ResultHandle, ResultId, ResultValue};
+pub use fabric::{
+    CancellableResult, CircuitTrace, ExecutorBackend, ExecutorJobQueue, ExecutorMessage,
+    ExecutorSizeHints, FabricInner, HeartbeatConfig, MpcFabric, NetworkStats, OpResult, Opcode,
+    OpcodeFn, Operation, OperationKind, ResultHandle, ResultId, ResultValue, ResultWaiter,
+    SerialExecutor, TraceHook, TracedOp,
+};
 
 #[cfg(feature = "network")]
 pub mod network;
@@ -61,13 +66,18 @@ pub fn random_point<C: ark_ec::CurveGroup>() -> algebra::CurvePoint<C> {
 pub mod test_helpers {
     //! Defines test helpers for use in unit and integration tests, as well as
     //! benchmarks
+    use std::{sync::Arc, time::Duration};
+
     use ark_ec::CurveGroup;
     use futures::{future, Future};
 
     use crate::{
         algebra::{AuthenticatedPointResult, AuthenticatedScalarResult, CurvePoint, Scalar},
         fabric::ExecutorSizeHints,
-        network::{MockNetwork, NoRecvNetwork, UnboundedDuplexStream},
+        network::{
+            LatencyMpcNetwork, MockNetwork, MultiPartyMockNetwork, NoRecvNetwork,
+            UnboundedDuplexStream,
+        },
         offline_prep::{PartyIDBeaverSource, PreprocessingPhase},
         MpcFabric, PARTY0, PARTY1,
     };
@@ -108,6 +118,29 @@ pub mod test_helpers {
         MpcFabric::new(network, beaver_source)
     }
 
+    /// Create a pair of mock fabrics connected by a duplex stream
+    ///
+    /// Both parties' fabrics are returned to the caller, so that a single task
+    /// may drive both sides of the MPC
+    pub fn mock_fabric_pair() -> (MpcFabric<TestCurve>, MpcFabric<TestCurve>) {
+        let (party0_stream, party1_stream) = UnboundedDuplexStream::new_duplex_pair();
+        let party0_fabric = MpcFabric::new(
+            MockNetwork::new(PARTY0, party0_stream),
+            PartyIDBeaverSource::new(PARTY0),
+        );
+        let party1_fabric = MpcFabric::new(
+            MockNetwork::new(PARTY1, party1_stream),
+            PartyIDBeaverSource::new(PARTY1),
+        );
+
+        // Publish both parties' shares so that tests may open values directly
+        let shares = Arc::default();
+        party0_fabric.publish_shares(Arc::clone(&shares));
+        party1_fabric.publish_shares(shares);
+
+        (party0_fabric, party1_fabric)
+    }
+
     /// Run a mock MPC connected by a duplex stream as the mock network
     ///
     /// This will spawn two tasks to execute either side of the MPC
@@ -153,6 +186,26 @@ pub mod test_helpers {
         execute_mock_mpc_with_fabrics(f, party0_fabric, party1_fabric).await
     }
 
+    /// Execute a mock MPC over a simulated link that delays each message by
+    /// the given latency
+    pub async fn execute_mock_mpc_with_latency<T, S, F>(f: F, latency: Duration) -> (T, T)
+    where
+        T: Send + 'static,
+        S: Future<Output = T> + Send + 'static,
+        F: FnMut(MpcFabric<TestCurve>) -> S,
+    {
+        let (party0_stream, party1_stream) = UnboundedDuplexStream::new_duplex_pair();
+        let party0_network =
+            LatencyMpcNetwork::new(MockNetwork::new(PARTY0, party0_stream)).with_latency(latency);
+        let party1_network =
+            LatencyMpcNetwork::new(MockNetwork::new(PARTY1, party1_stream)).with_latency(latency);
+
+        let party0_fabric = MpcFabric::new(party0_network, PartyIDBeaverSource::new(PARTY0));
+        let party1_fabric = MpcFabric::new(party1_network, PartyIDBeaverSource::new(PARTY1));
+
+        execute_mock_mpc_with_fabrics(f, party0_fabric, party1_fabric).await
+    }
+
     /// Execute a mock MPC by specifying a beaver source for party 0 and 1
     pub async fn execute_mock_mpc_with_beaver_source<B, T, S, F>(
         f: F,
@@ -173,6 +226,34 @@ pub mod test_helpers {
         execute_mock_mpc_with_fabrics(f, party0_fabric, party1_fabric).await
     }
 
+    /// Run a mock MPC between `n` parties connected by a broadcast mesh
+    ///
+    /// Returns the outputs of all parties, in order of party ID
+    pub async fn execute_mock_mpc_n_party<T, S, F>(n: usize, mut f: F) -> Vec<T>
+    where
+        T: Send + 'static,
+        S: Future<Output = T> + Send + 'static,
+        F: FnMut(MpcFabric<TestCurve>) -> S,
+    {
+        let fabrics = MultiPartyMockNetwork::new_mesh(n)
+            .into_iter()
+            .enumerate()
+            .map(|(party_id, network)| {
+                MpcFabric::new(network, PartyIDBeaverSource::new_multi_party(party_id as u64, n))
+            })
+            .collect::<Vec<_>>();
+
+        let tasks =
+            fabrics.iter().map(|fabric| tokio::spawn(f(fabric.clone()))).collect::<Vec<_>>();
+        let outputs = future::join_all(tasks).await.into_iter().map(|res| res.unwrap()).collect();
+
+        for fabric in fabrics {
+            fabric.shutdown();
+        }
+
+        outputs
+    }
+
     /// Execute a mock in the given fabrics
     async fn execute_mock_mpc_with_fabrics<T, S, F>(
         mut f: F,
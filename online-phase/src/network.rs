@@ -1,17 +1,33 @@
 //! The `network` module defines abstractions of the transport used to
 //! communicate during the course of an MPC
 mod cert_verifier;
+mod compression;
 mod config;
+#[cfg(feature = "encryption")]
+mod encrypted;
+#[cfg(any(feature = "test_helpers", feature = "benchmarks", test))]
+mod latency;
 mod mock;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
 mod quic;
+mod reconnecting;
 mod stream_buffer;
+mod tcp;
 
 use ark_ec::CurveGroup;
+pub use compression::DEFAULT_COMPRESSION_THRESHOLD;
+#[cfg(feature = "encryption")]
+pub use encrypted::{EncryptedNetwork, IdentityKeypair};
 pub use quic::*;
+pub use reconnecting::{ReconnectingNetwork, DEFAULT_MAX_UNACKED};
+pub use tcp::*;
 
 use futures::{Sink, Stream};
 #[cfg(any(feature = "test_helpers", feature = "benchmarks", test))]
-pub use mock::{MockNetwork, NoRecvNetwork, UnboundedDuplexStream};
+pub use latency::LatencyMpcNetwork;
+#[cfg(any(feature = "test_helpers", feature = "benchmarks", test))]
+pub use mock::{MockNetwork, MultiPartyMockNetwork, NoRecvNetwork, UnboundedDuplexStream};
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -39,12 +55,38 @@ pub struct NetworkOutbound<C: CurveGroup> {
     pub payload: NetworkPayload<C>,
 }
 
+impl<C: CurveGroup> NetworkOutbound<C> {
+    /// Get the size of the message serialized for the network, without
+    /// buffering the serialized message
+    #[cfg(any(feature = "stats", feature = "test_helpers", feature = "benchmarks", test))]
+    pub(crate) fn serialized_size(&self) -> usize {
+        use std::io::{Result as IoResult, Write};
+
+        /// A writer that counts the bytes written to it
+        struct CountingWriter(usize);
+        impl Write for CountingWriter {
+            fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+                self.0 += buf.len();
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> IoResult<()> {
+                Ok(())
+            }
+        }
+
+        let mut writer = CountingWriter(0);
+        serde_json::to_writer(&mut writer, self).expect("message serialization cannot fail");
+        writer.0
+    }
+}
+
 /// The payload of an outbound message
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(bound(serialize = "C: CurveGroup", deserialize = "C: CurveGroup"))]
 pub enum NetworkPayload<C: CurveGroup> {
     /// A byte value
-    Bytes(Vec<u8>),
+    Bytes(#[serde(with = "byte_payload")] Vec<u8>),
     /// A scalar value
     Scalar(Scalar<C>),
     /// A batch of scalar values
@@ -57,6 +99,64 @@ pub enum NetworkPayload<C: CurveGroup> {
     PointBatch(Vec<CurvePoint<C>>),
     /// A share of a curve point value
     PointShare(PointShare<C>),
+    /// One or more messages sent as a single transmission, tagged with the
+    /// sender's sequence number so that the receiver can restore the order in
+    /// which transmissions were sent
+    ///
+    /// The fabric sends every transmission as a frame, so a party built
+    /// before framing cannot interoperate with one built after it
+    Frame {
+        /// The ID of the sending party
+        sender: PartyId,
+        /// The index of the frame among those sent by the sender
+        seq: u64,
+        /// The messages in the frame
+        msgs: Vec<NetworkOutbound<C>>,
+    },
+    /// A keepalive message, carrying no value
+    Heartbeat,
+    /// A message tagged with its sequence number on a reliable link, see
+    /// `ReconnectingNetwork`
+    Sequenced {
+        /// The index of the message among those sent on the link
+        seq: u64,
+        /// The number of the peer's messages received by the sender
+        ack: u64,
+        /// The message
+        msg: Box<NetworkOutbound<C>>,
+    },
+    /// Acknowledges the messages received on a reliable link, see
+    /// `ReconnectingNetwork`
+    Ack {
+        /// The number of the peer's messages received by the sender
+        received: u64,
+    },
+}
+
+/// Serializes byte payloads as a base64 string in human-readable formats,
+/// rather than as an array of numbers
+mod byte_payload {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Serialize a byte payload
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&STANDARD.encode(bytes))
+        } else {
+            bytes.serialize(serializer)
+        }
+    }
+
+    /// Deserialize a byte payload
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        if deserializer.is_human_readable() {
+            let encoded = String::deserialize(deserializer)?;
+            STANDARD.decode(encoded).map_err(D::Error::custom)
+        } else {
+            Vec::deserialize(deserializer)
+        }
+    }
 }
 
 // ---------------
@@ -139,11 +239,19 @@ impl<C: CurveGroup> From<NetworkPayload<C>> for Vec<CurvePoint<C>> {
 }
 
 /// The `MpcNetwork` trait defines shared functionality for a network
-/// implementing a connection between two parties in a 2PC
+/// implementing a connection between the parties in an MPC
 ///
 /// Values are sent as bytes, scalars, or curve points and always in batch form
 /// with the message length (measured in the number of elements sent) prepended
 /// to the message
+///
+/// A network connecting more than two parties broadcasts each outbound message
+/// to every peer, and yields the messages of all peers on its stream
+///
+/// A network need not deliver messages in order or exactly once. The fabric
+/// sends each transmission as a frame tagged with a per-sender sequence
+/// number, and delivers frames in the order they were sent, dropping
+/// duplicates. A network must not lose messages
 #[async_trait]
 pub trait MpcNetwork<C: CurveGroup>:
     Send
@@ -152,6 +260,11 @@ pub trait MpcNetwork<C: CurveGroup>:
 {
     /// Get the party ID of the local party in the MPC
     fn party_id(&self) -> PartyId;
+    /// Get the number of parties in the MPC, party IDs range over
+    /// `0..num_parties`
+    fn num_parties(&self) -> usize {
+        2
+    }
     /// Closes the connections opened in the handshake phase
     async fn close(&mut self) -> Result<(), MpcNetworkError>;
 }
@@ -1,8 +1,15 @@
-//! Implements a dummy certificate verifier that simply passes through the
-//! request We do not care about the authenticity of certificates during the
-//! course of a p2p MPC
+//! Implements the certificate verifiers used by the QUIC transport
+//!
+//! By default we do not care about the authenticity of certificates during the
+//! course of a p2p MPC, and a dummy verifier simply passes through the
+//! request. A party that configures the peer's certificate instead pins it
 
-use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use std::time::SystemTime;
+
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier},
+    Certificate, Error as TlsError, RootCertStore, ServerName,
+};
 
 /// Responds Ok() to any certificate verification request
 pub(crate) struct PassThroughCertVerifier;
@@ -27,3 +34,48 @@ impl ServerCertVerifier for PassThroughCertVerifier {
         Ok(ServerCertVerified::assertion())
     }
 }
+
+/// Accepts only the pinned certificate, or a certificate issued for the
+/// server name by the pinned certificate acting as a CA
+pub(crate) struct PinnedCertVerifier {
+    /// The pinned certificate
+    pinned: Certificate,
+    /// A verifier trusting the pinned certificate as its only root
+    ca_verifier: WebPkiVerifier,
+}
+
+impl PinnedCertVerifier {
+    /// Create a verifier pinning the given certificate, fails if the
+    /// certificate cannot be parsed
+    pub fn new(pinned: Certificate) -> Result<Self, TlsError> {
+        let mut roots = RootCertStore::empty();
+        roots.add(&pinned).map_err(|err| TlsError::InvalidCertificateData(format!("{err:?}")))?;
+
+        Ok(Self { pinned, ca_verifier: WebPkiVerifier::new(roots, None /* ct_policy */) })
+    }
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, TlsError> {
+        if *end_entity == self.pinned {
+            return Ok(ServerCertVerified::assertion());
+        }
+
+        self.ca_verifier.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )
+    }
+}
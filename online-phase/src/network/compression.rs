@@ -0,0 +1,175 @@
+//! Defines the serialization of messages sent over a transport, optionally
+//! compressing large messages
+//!
+//! When both parties enable compression, each serialized message is prefixed
+//! with a flag byte indicating whether the remainder of the message is
+//! compressed. Otherwise messages are sent as plain JSON, without the flag
+
+use ark_ec::CurveGroup;
+
+use crate::error::MpcNetworkError;
+
+use super::NetworkOutbound;
+
+/// The flag byte of a message sent uncompressed
+#[cfg(feature = "compression")]
+const FLAG_UNCOMPRESSED: u8 = 0;
+/// The flag byte of a message compressed with lz4
+#[cfg(feature = "compression")]
+const FLAG_LZ4: u8 = 1;
+/// The largest factor by which lz4 shrinks its input, an lz4 block
+/// decompresses to at most this many bytes per compressed byte
+#[cfg(feature = "compression")]
+const MAX_LZ4_RATIO: usize = 255;
+
+/// The default size in bytes below which a message is sent uncompressed
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 1024;
+
+/// Error message emitted when a message is empty
+const ERR_EMPTY_MESSAGE: &str = "empty message";
+/// Error message emitted when a message has an unknown compression flag
+#[cfg(feature = "compression")]
+const ERR_UNKNOWN_FLAG: &str = "unknown compression flag";
+/// Error message emitted when a compressed message claims a decompressed size
+/// it cannot have
+#[cfg(feature = "compression")]
+const ERR_DECOMPRESSED_SIZE: &str = "invalid decompressed size";
+
+/// Serialize a message
+///
+/// If a compression threshold is given, the message is prefixed with a flag
+/// byte and compressed if its serialization is at least the threshold in size
+pub(crate) fn serialize_message<C: CurveGroup>(
+    msg: &NetworkOutbound<C>,
+    #[cfg_attr(not(feature = "compression"), allow(unused_variables))]
+    compression_threshold: Option<usize>,
+) -> Result<Vec<u8>, MpcNetworkError> {
+    let bytes = serde_json::to_vec(msg)
+        .map_err(|err| MpcNetworkError::SerializationError(err.to_string()))?;
+
+    #[cfg(feature = "compression")]
+    if let Some(threshold) = compression_threshold {
+        if bytes.len() >= threshold {
+            let mut res = vec![FLAG_LZ4];
+            res.extend(lz4_flex::compress_prepend_size(&bytes));
+            return Ok(res);
+        }
+
+        let mut res = Vec::with_capacity(bytes.len() + 1);
+        res.push(FLAG_UNCOMPRESSED);
+        res.extend(bytes);
+        return Ok(res);
+    }
+
+    Ok(bytes)
+}
+
+/// Deserialize a message
+///
+/// If compression is enabled, the message is prefixed with a flag byte and
+/// decompressed if it was sent compressed
+pub(crate) fn deserialize_message<C: CurveGroup>(
+    bytes: &[u8],
+    #[cfg_attr(not(feature = "compression"), allow(unused_variables))] compression: bool,
+) -> Result<NetworkOutbound<C>, MpcNetworkError> {
+    if bytes.is_empty() {
+        return Err(MpcNetworkError::SerializationError(ERR_EMPTY_MESSAGE.to_string()));
+    }
+
+    #[cfg(feature = "compression")]
+    if compression {
+        let (flag, body) = bytes.split_first().unwrap();
+        return match *flag {
+            FLAG_UNCOMPRESSED => deserialize_json(body),
+            FLAG_LZ4 => deserialize_json(&decompress(body)?),
+            _ => Err(MpcNetworkError::SerializationError(ERR_UNKNOWN_FLAG.to_string())),
+        };
+    }
+
+    deserialize_json(bytes)
+}
+
+/// Decompress an lz4 block prefixed with its decompressed size
+///
+/// The size prefix is sent by the peer, so it is checked against the most the
+/// block can decompress to before the output buffer is allocated
+#[cfg(feature = "compression")]
+fn decompress(body: &[u8]) -> Result<Vec<u8>, MpcNetworkError> {
+    let (size, block) = lz4_flex::block::uncompressed_size(body)
+        .map_err(|err| MpcNetworkError::SerializationError(err.to_string()))?;
+    if size > block.len().saturating_mul(MAX_LZ4_RATIO) {
+        return Err(MpcNetworkError::SerializationError(ERR_DECOMPRESSED_SIZE.to_string()));
+    }
+
+    lz4_flex::decompress(block, size)
+        .map_err(|err| MpcNetworkError::SerializationError(err.to_string()))
+}
+
+/// Deserialize an uncompressed message body
+fn deserialize_json<C: CurveGroup>(bytes: &[u8]) -> Result<NetworkOutbound<C>, MpcNetworkError> {
+    serde_json::from_slice(bytes)
+        .map_err(|err| MpcNetworkError::SerializationError(err.to_string()))
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod test {
+    use crate::{
+        algebra::Scalar,
+        network::{NetworkOutbound, NetworkPayload},
+        test_helpers::TestCurve,
+    };
+
+    use super::{
+        deserialize_message, serialize_message, DEFAULT_COMPRESSION_THRESHOLD, FLAG_UNCOMPRESSED,
+    };
+
+    /// Tests that a large batch round trips through compression and is
+    /// compressed to a smaller size
+    #[test]
+    fn test_compressed_batch() {
+        let batch = (0..10_000u64).map(Scalar::<TestCurve>::from).collect::<Vec<_>>();
+        let msg = NetworkOutbound { result_id: 1, payload: NetworkPayload::ScalarBatch(batch) };
+
+        let uncompressed = serialize_message(&msg, None).unwrap();
+        let compressed = serialize_message(&msg, Some(DEFAULT_COMPRESSION_THRESHOLD)).unwrap();
+        assert!(compressed.len() < uncompressed.len());
+
+        let recovered: NetworkOutbound<TestCurve> = deserialize_message(&compressed, true).unwrap();
+        let (NetworkPayload::ScalarBatch(expected), NetworkPayload::ScalarBatch(recovered)) =
+            (msg.payload, recovered.payload)
+        else {
+            panic!("expected a scalar batch")
+        };
+        assert_eq!(recovered, expected);
+    }
+
+    /// Tests that messages below the threshold are sent uncompressed, and that
+    /// the flag byte is only sent when compression is enabled
+    #[test]
+    fn test_small_message_uncompressed() {
+        let payload = NetworkPayload::Scalar(Scalar::<TestCurve>::from(1u8));
+        let msg = NetworkOutbound { result_id: 1, payload };
+
+        let plain = serialize_message(&msg, None).unwrap();
+        assert_eq!(plain, serde_json::to_vec(&msg).unwrap());
+
+        let res = serialize_message(&msg, Some(DEFAULT_COMPRESSION_THRESHOLD)).unwrap();
+        assert_eq!(res[0], FLAG_UNCOMPRESSED);
+        assert_eq!(res[1..], plain);
+
+        let recovered: NetworkOutbound<TestCurve> = deserialize_message(&plain, false).unwrap();
+        assert_eq!(recovered.result_id, msg.result_id);
+    }
+
+    /// Tests that a compressed message claiming a larger decompressed size than
+    /// its body can hold is rejected
+    #[test]
+    fn test_forged_size_rejected() {
+        let batch = (0..10_000u64).map(Scalar::<TestCurve>::from).collect::<Vec<_>>();
+        let msg = NetworkOutbound { result_id: 1, payload: NetworkPayload::ScalarBatch(batch) };
+
+        let mut compressed = serialize_message(&msg, Some(0)).unwrap();
+        compressed[1..5].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(deserialize_message::<TestCurve>(&compressed, true).is_err());
+    }
+}
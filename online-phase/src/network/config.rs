@@ -4,10 +4,12 @@ use std::{sync::Arc, time::Duration};
 
 use quinn::{ClientConfig, IdleTimeout, ServerConfig, TransportConfig, VarInt};
 use rcgen::RcgenError;
-use rustls::{Certificate, ClientConfig as CryptoClientConfig};
+use rustls::{
+    client::ServerCertVerifier, Certificate, ClientConfig as CryptoClientConfig, PrivateKey,
+};
 
 use crate::error::SetupError;
-use crate::network::cert_verifier::PassThroughCertVerifier;
+use crate::network::cert_verifier::{PassThroughCertVerifier, PinnedCertVerifier};
 
 /// The maximum amount of time to wait for a response from the counterparty
 /// before
@@ -21,7 +23,15 @@ const KEEP_ALIVE_INTERVAL: u64 = 3_000; // milliseconds
 pub(crate) const SERVER_NAME: &str = "otter.cash"; // dummy value
 
 /// Builds the configs for quinn p2p communication
-pub fn build_configs() -> Result<(ClientConfig, ServerConfig), SetupError> {
+///
+/// The server presents the given certificate and private key if provided, and
+/// a freshly generated self-signed certificate otherwise. The client accepts
+/// only the given peer certificate, or certificates it issued, if provided,
+/// and any certificate otherwise
+pub fn build_configs(
+    certificate: Option<(Certificate, PrivateKey)>,
+    peer_certificate: Option<Certificate>,
+) -> Result<(ClientConfig, ServerConfig), SetupError> {
     // 1. Transport config
     let mut transport_config = TransportConfig::default();
     transport_config.max_idle_timeout(Some(IdleTimeout::from(VarInt::from_u32(MAX_IDLE_TIMEOUT))));
@@ -31,22 +41,31 @@ pub fn build_configs() -> Result<(ClientConfig, ServerConfig), SetupError> {
     let transport: Arc<TransportConfig> = Arc::new(transport_config);
 
     // 2. Cryptography setup
-    // Generate a self-signed server certificate for the QUIC connection
-    let (cert, key) = generate_cert().map_err(|_| SetupError::KeygenError)?;
+    // Generate a self-signed server certificate for the QUIC connection if none is
+    // configured
+    let (cert, key) = match certificate {
+        Some(certificate) => certificate,
+        None => generate_cert().map_err(|_| SetupError::KeygenError)?,
+    };
 
     // Setup the certificate root
     let mut roots = rustls::RootCertStore::empty();
     roots.add(&cert).map_err(|_| SetupError::ServerSetupError)?;
 
-    // Pass the self-signed cert to the client, and disable auth; p2p auth should
-    // happen at a higher layer
+    // Pin the peer's certificate if one is configured. Otherwise pass the
+    // self-signed cert to the client and disable auth; p2p auth should happen at
+    // a higher layer
     let mut client_crypto_config = CryptoClientConfig::builder()
         .with_safe_defaults()
         .with_root_certificates(roots)
         .with_no_client_auth();
-    client_crypto_config
-        .dangerous()
-        .set_certificate_verifier(Arc::new(PassThroughCertVerifier::new()));
+    let verifier: Arc<dyn ServerCertVerifier> = match peer_certificate {
+        Some(peer_cert) => {
+            Arc::new(PinnedCertVerifier::new(peer_cert).map_err(|_| SetupError::ServerSetupError)?)
+        },
+        None => Arc::new(PassThroughCertVerifier::new()),
+    };
+    client_crypto_config.dangerous().set_certificate_verifier(verifier);
 
     // 3. Client and server setup
     let mut client_config = ClientConfig::new(Arc::new(client_crypto_config));
@@ -61,13 +80,13 @@ pub fn build_configs() -> Result<(ClientConfig, ServerConfig), SetupError> {
 
 /// Generates a self-signed certificate to construct TLS 1.3 connections with
 /// borrowed from https://github.com/maidsafe/qp2p/blob/main/src/config.rs#L317
-fn generate_cert() -> Result<(Certificate, rustls::PrivateKey), RcgenError> {
+pub(crate) fn generate_cert() -> Result<(Certificate, PrivateKey), RcgenError> {
     let cert = rcgen::generate_simple_self_signed(vec![SERVER_NAME.to_string()])?;
 
     let key = cert.serialize_private_key_der();
     let cert = cert.serialize_der().unwrap();
 
-    let key = rustls::PrivateKey(key);
+    let key = PrivateKey(key);
     let cert = Certificate(cert);
     Ok((cert, key))
 }
@@ -0,0 +1,479 @@
+//! Defines a network wrapper that authenticates the peer and encrypts all
+//! traffic over an underlying network
+//!
+//! Each party holds a long-term Ed25519 identity key and knows the public key
+//! of its peer. In the handshake the parties exchange fresh X25519 keys, then
+//! each signs both ephemeral keys with its identity key, binding its signature
+//! to the session. Both parties derive a ChaCha20-Poly1305 key for either
+//! direction from the ephemeral Diffie-Hellman secret. Messages are then
+//! sealed under a counter nonce, so a replayed, reordered, or modified message
+//! fails to decrypt
+
+use std::{
+    marker::PhantomData,
+    mem::size_of,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::error::{MpcNetworkError, SetupError};
+use ark_ec::CurveGroup;
+use async_trait::async_trait;
+use futures::{ready, Sink, SinkExt, Stream, StreamExt};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN},
+    agreement::{self, EphemeralPrivateKey, X25519},
+    error::Unspecified,
+    hkdf::{Salt, HKDF_SHA256},
+    rand::SystemRandom,
+    signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519},
+};
+
+use super::{MpcNetwork, NetworkOutbound, NetworkPayload, PartyId};
+
+/// The domain separator of the handshake transcript signed by each party
+const HANDSHAKE_DOMAIN: &[u8] = b"ark-mpc encrypted transport handshake";
+/// The salt used to derive session keys from the Diffie-Hellman secret
+const KDF_SALT: &[u8] = b"ark-mpc encrypted transport";
+/// The info string used to derive the key of a party's outbound messages
+const KDF_INFO: &[u8] = b"ark-mpc transport key";
+/// The length of an X25519 public key
+const EPHEMERAL_KEY_LEN: usize = 32;
+/// The length of a party's first handshake message, its party ID followed by
+/// its ephemeral key
+const HELLO_LEN: usize = size_of::<PartyId>() + EPHEMERAL_KEY_LEN;
+
+/// Error message emitted when the peer closes the stream during the handshake
+const ERR_NO_HELLO: &str = "peer closed the stream during the handshake";
+/// Error message emitted when the peer's handshake message is malformed
+const ERR_INVALID_HELLO: &str = "invalid handshake message";
+/// Error message emitted when the peer's signature does not verify
+const ERR_INVALID_SIGNATURE: &str = "peer signature does not match the peer key";
+/// Error message emitted when the peer claims the local party's ID
+const ERR_PARTY_ID: &str = "peer claims the local party id";
+/// Error message emitted when key agreement fails
+const ERR_KEY_AGREEMENT: &str = "key agreement failed";
+/// Error message emitted when a message fails to encrypt
+const ERR_ENCRYPT: &str = "failed to encrypt message";
+/// Error message emitted when a message fails to decrypt
+const ERR_DECRYPT: &str = "failed to decrypt message";
+/// Error message emitted when a message is not encoded as bytes
+const ERR_EXPECTED_BYTES: &str = "expected a byte payload";
+
+/// Construct a handshake error
+fn handshake_error(msg: &str) -> MpcNetworkError {
+    MpcNetworkError::ConnectionSetupError(SetupError::HandshakeError(msg.to_string()))
+}
+
+/// The long-term identity key with which a party authenticates itself to its
+/// peer
+pub struct IdentityKeypair(Ed25519KeyPair);
+
+impl IdentityKeypair {
+    /// Generate a new identity key, returning its PKCS#8 encoding for
+    /// persisting and loading with `from_pkcs8`
+    pub fn generate_pkcs8() -> Result<Vec<u8>, SetupError> {
+        Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map(|doc| doc.as_ref().to_vec())
+            .map_err(|_| SetupError::KeygenError)
+    }
+
+    /// Generate a new identity key
+    pub fn generate() -> Result<Self, SetupError> {
+        Self::from_pkcs8(&Self::generate_pkcs8()?)
+    }
+
+    /// Load an identity key from its PKCS#8 encoding
+    pub fn from_pkcs8(pkcs8: &[u8]) -> Result<Self, SetupError> {
+        Ed25519KeyPair::from_pkcs8(pkcs8).map(Self).map_err(|_| SetupError::KeygenError)
+    }
+
+    /// The public key that the peer uses to authenticate the local party
+    pub fn public_key(&self) -> Vec<u8> {
+        self.0.public_key().as_ref().to_vec()
+    }
+}
+
+/// The transcript a party signs in the handshake, binding its signature to
+/// the ephemeral keys of both parties
+fn handshake_transcript(
+    signer: PartyId,
+    signer_key: &[u8],
+    peer: PartyId,
+    peer_key: &[u8],
+) -> Vec<u8> {
+    [HANDSHAKE_DOMAIN, &signer.to_le_bytes(), signer_key, &peer.to_le_bytes(), peer_key].concat()
+}
+
+/// The keys of an established session
+struct SessionKeys {
+    /// The key sealing outbound messages
+    send_key: LessSafeKey,
+    /// The key opening inbound messages
+    recv_key: LessSafeKey,
+    /// The number of messages sent, used as the nonce of the next
+    send_counter: u64,
+    /// The number of messages received, used as the nonce of the next
+    recv_counter: u64,
+}
+
+impl SessionKeys {
+    /// Derive the session keys from the Diffie-Hellman secret
+    fn derive(secret: &[u8], party_id: PartyId, peer_id: PartyId) -> Result<Self, Unspecified> {
+        let prk = Salt::new(HKDF_SHA256, KDF_SALT).extract(secret);
+        let key_for = |sender: PartyId| -> Result<LessSafeKey, Unspecified> {
+            let sender_bytes = sender.to_le_bytes();
+            let info = [KDF_INFO, &sender_bytes];
+            let okm = prk.expand(&info, &CHACHA20_POLY1305)?;
+            Ok(LessSafeKey::new(UnboundKey::from(okm)))
+        };
+
+        Ok(Self {
+            send_key: key_for(party_id)?,
+            recv_key: key_for(peer_id)?,
+            send_counter: 0,
+            recv_counter: 0,
+        })
+    }
+
+    /// Build the nonce for the given message counter, incrementing the counter
+    fn next_nonce(counter: &mut u64) -> Nonce {
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[NONCE_LEN - 8..].copy_from_slice(&counter.to_be_bytes());
+        *counter += 1;
+
+        Nonce::assume_unique_for_key(nonce)
+    }
+}
+
+/// A network wrapper that authenticates the peer and encrypts every message
+/// sent over the underlying network
+///
+/// `handshake` must complete before the network is given to a fabric
+///
+/// Unlike an arbitrary `MpcNetwork`, the underlying network must be reliable
+/// and deliver messages in order, exactly once. Each message is sealed under
+/// the count of messages sent before it, so the receiver opens the next
+/// message under its own count; a message that is reordered, duplicated, or
+/// lost desyncs the counters and every later message fails to decrypt. Wrap a
+/// lossy connection in a `ReconnectingNetwork` beneath this one
+pub struct EncryptedNetwork<C: CurveGroup, N: MpcNetwork<C>> {
+    /// The underlying network
+    network: N,
+    /// The local party's identity key
+    identity: IdentityKeypair,
+    /// The peer's identity public key
+    peer_public_key: Vec<u8>,
+    /// The keys of the session, `None` until the handshake completes
+    keys: Option<SessionKeys>,
+    /// The curve of the messages sent over the network
+    _phantom: PhantomData<C>,
+}
+
+// The wrapper never pins its fields
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin> Unpin for EncryptedNetwork<C, N> {}
+
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin> EncryptedNetwork<C, N> {
+    /// Wrap the given network, authenticating as `identity` to a peer holding
+    /// the identity key `peer_public_key`
+    pub fn new(network: N, identity: IdentityKeypair, peer_public_key: Vec<u8>) -> Self {
+        Self { network, identity, peer_public_key, keys: None, _phantom: PhantomData }
+    }
+
+    /// Whether the handshake has completed
+    pub fn is_established(&self) -> bool {
+        self.keys.is_some()
+    }
+
+    /// Authenticate the peer and agree on session keys
+    ///
+    /// Both parties must call this on their connected networks before
+    /// starting a fabric. Fails if the peer does not hold the expected
+    /// identity key
+    pub async fn handshake(&mut self) -> Result<(), MpcNetworkError> {
+        let party_id = self.network.party_id();
+        let ephemeral_key = EphemeralPrivateKey::generate(&X25519, &SystemRandom::new())
+            .map_err(|_| handshake_error(ERR_KEY_AGREEMENT))?;
+        let ephemeral_public_key = ephemeral_key
+            .compute_public_key()
+            .map_err(|_| handshake_error(ERR_KEY_AGREEMENT))?
+            .as_ref()
+            .to_vec();
+
+        // Exchange ephemeral keys with the peer
+        let hello = [&party_id.to_le_bytes()[..], &ephemeral_public_key].concat();
+        let peer_hello = self.exchange_handshake_message(hello).await?;
+        if peer_hello.len() != HELLO_LEN {
+            return Err(handshake_error(ERR_INVALID_HELLO));
+        }
+
+        let (peer_id, peer_ephemeral_key) = peer_hello.split_at(size_of::<PartyId>());
+        let peer_id = PartyId::from_le_bytes(peer_id.try_into().unwrap());
+        if peer_id == party_id {
+            return Err(handshake_error(ERR_PARTY_ID));
+        }
+
+        // Sign both ephemeral keys, so that a signature from one session cannot
+        // be replayed into another
+        let transcript =
+            handshake_transcript(party_id, &ephemeral_public_key, peer_id, peer_ephemeral_key);
+        let signature = self.identity.0.sign(&transcript).as_ref().to_vec();
+        let peer_signature = self.exchange_handshake_message(signature).await?;
+
+        let peer_transcript =
+            handshake_transcript(peer_id, peer_ephemeral_key, party_id, &ephemeral_public_key);
+        UnparsedPublicKey::new(&ED25519, &self.peer_public_key)
+            .verify(&peer_transcript, &peer_signature)
+            .map_err(|_| handshake_error(ERR_INVALID_SIGNATURE))?;
+
+        // Derive the session keys
+        let peer_ephemeral_key = agreement::UnparsedPublicKey::new(&X25519, peer_ephemeral_key);
+        let keys =
+            agreement::agree_ephemeral(ephemeral_key, &peer_ephemeral_key, Unspecified, |secret| {
+                SessionKeys::derive(secret, party_id, peer_id)
+            })
+            .map_err(|_| handshake_error(ERR_KEY_AGREEMENT))?;
+
+        self.keys = Some(keys);
+        Ok(())
+    }
+
+    /// Send a handshake message to the peer and receive the peer's message
+    async fn exchange_handshake_message(
+        &mut self,
+        msg: Vec<u8>,
+    ) -> Result<Vec<u8>, MpcNetworkError> {
+        self.network
+            .send(NetworkOutbound { result_id: 0, payload: NetworkPayload::Bytes(msg) })
+            .await?;
+
+        match self.network.next().await {
+            Some(Ok(NetworkOutbound { payload: NetworkPayload::Bytes(bytes), .. })) => Ok(bytes),
+            Some(Ok(_)) => Err(handshake_error(ERR_INVALID_HELLO)),
+            Some(Err(err)) => Err(err),
+            None => Err(handshake_error(ERR_NO_HELLO)),
+        }
+    }
+
+    /// Encrypt an outbound message
+    fn encrypt(&mut self, msg: &NetworkOutbound<C>) -> Result<NetworkOutbound<C>, MpcNetworkError> {
+        let keys = self.keys.as_mut().ok_or(MpcNetworkError::NetworkUninitialized)?;
+        let mut bytes = serde_json::to_vec(msg)
+            .map_err(|err| MpcNetworkError::SerializationError(err.to_string()))?;
+
+        let nonce = SessionKeys::next_nonce(&mut keys.send_counter);
+        keys.send_key
+            .seal_in_place_append_tag(nonce, Aad::empty(), &mut bytes)
+            .map_err(|_| MpcNetworkError::SendError(ERR_ENCRYPT.to_string()))?;
+
+        // The result ID is sent inside the ciphertext
+        Ok(NetworkOutbound { result_id: 0, payload: NetworkPayload::Bytes(bytes) })
+    }
+
+    /// Decrypt an inbound message
+    fn decrypt(&mut self, msg: NetworkOutbound<C>) -> Result<NetworkOutbound<C>, MpcNetworkError> {
+        let keys = self.keys.as_mut().ok_or(MpcNetworkError::NetworkUninitialized)?;
+        let mut bytes = match msg.payload {
+            NetworkPayload::Bytes(bytes) => bytes,
+            _ => return Err(MpcNetworkError::RecvError(ERR_EXPECTED_BYTES.to_string())),
+        };
+
+        let nonce = SessionKeys::next_nonce(&mut keys.recv_counter);
+        let plaintext = keys
+            .recv_key
+            .open_in_place(nonce, Aad::empty(), &mut bytes)
+            .map_err(|_| MpcNetworkError::RecvError(ERR_DECRYPT.to_string()))?;
+
+        serde_json::from_slice(plaintext)
+            .map_err(|err| MpcNetworkError::SerializationError(err.to_string()))
+    }
+}
+
+#[async_trait]
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin> MpcNetwork<C> for EncryptedNetwork<C, N> {
+    fn party_id(&self) -> PartyId {
+        self.network.party_id()
+    }
+
+    fn num_parties(&self) -> usize {
+        self.network.num_parties()
+    }
+
+    async fn close(&mut self) -> Result<(), MpcNetworkError> {
+        MpcNetwork::close(&mut self.network).await
+    }
+}
+
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin> Stream for EncryptedNetwork<C, N> {
+    type Item = Result<NetworkOutbound<C>, MpcNetworkError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match ready!(self.network.poll_next_unpin(cx)) {
+            Some(Ok(msg)) => Poll::Ready(Some(self.decrypt(msg))),
+            res => Poll::Ready(res),
+        }
+    }
+}
+
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin> Sink<NetworkOutbound<C>> for EncryptedNetwork<C, N> {
+    type Error = MpcNetworkError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.network.poll_ready_unpin(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: NetworkOutbound<C>) -> Result<(), Self::Error> {
+        let msg = self.encrypt(&item)?;
+        self.network.start_send_unpin(msg)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.network.poll_flush_unpin(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.network.poll_close_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{SocketAddr, TcpListener};
+
+    use rand::{thread_rng, RngCore};
+
+    use crate::{
+        algebra::Scalar,
+        error::{MpcNetworkError, SetupError},
+        network::{
+            MockNetwork, NetworkOutbound, NetworkPayload, TcpMpcNetwork, UnboundedDuplexStream,
+        },
+        offline_prep::PartyIDBeaverSource,
+        test_helpers::TestCurve,
+        MpcFabric, PARTY0, PARTY1,
+    };
+
+    use super::{EncryptedNetwork, IdentityKeypair, EPHEMERAL_KEY_LEN};
+
+    /// An encrypted network over localhost TCP
+    type EncryptedTcpNetwork = EncryptedNetwork<TestCurve, TcpMpcNetwork<TestCurve>>;
+
+    /// Find a free local address to listen on
+    fn free_addr() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+    }
+
+    /// Build a handshake message carrying the given bytes
+    fn handshake_message(bytes: Vec<u8>) -> NetworkOutbound<TestCurve> {
+        NetworkOutbound { result_id: 0, payload: NetworkPayload::Bytes(bytes) }
+    }
+
+    /// Connect to the peer over TCP and run the encrypted handshake
+    async fn connect_encrypted(
+        party_id: u64,
+        local_addr: SocketAddr,
+        peer_addr: SocketAddr,
+        identity: IdentityKeypair,
+        peer_public_key: Vec<u8>,
+    ) -> Result<EncryptedTcpNetwork, MpcNetworkError> {
+        let mut network = TcpMpcNetwork::new(party_id, local_addr, peer_addr);
+        network.connect().await?;
+
+        let mut network = EncryptedNetwork::new(network, identity, peer_public_key);
+        network.handshake().await?;
+        Ok(network)
+    }
+
+    /// Tests a multiplication between two fabrics over an encrypted localhost
+    /// connection
+    #[tokio::test]
+    async fn test_encrypted_multiplication() {
+        let (addr0, addr1) = (free_addr(), free_addr());
+        let (identity0, identity1) =
+            (IdentityKeypair::generate().unwrap(), IdentityKeypair::generate().unwrap());
+        let (public_key0, public_key1) = (identity0.public_key(), identity1.public_key());
+
+        let run = |party_id, local_addr, peer_addr, identity, peer_public_key| async move {
+            let network =
+                connect_encrypted(party_id, local_addr, peer_addr, identity, peer_public_key)
+                    .await
+                    .unwrap();
+            assert!(network.is_established());
+            let fabric = MpcFabric::new(network, PartyIDBeaverSource::new(party_id));
+
+            let a = fabric.share_scalar(2u8, PARTY0 /* sender */);
+            let b = fabric.share_scalar(3u8, PARTY1 /* sender */);
+            let res = (a * b).open_authenticated().await.unwrap();
+
+            fabric.shutdown();
+            res
+        };
+
+        let party0 = tokio::spawn(run(PARTY0, addr0, addr1, identity0, public_key1));
+        let party1 = tokio::spawn(run(PARTY1, addr1, addr0, identity1, public_key0));
+
+        let expected = Scalar::<TestCurve>::from(6u8);
+        assert_eq!(party0.await.unwrap(), expected);
+        assert_eq!(party1.await.unwrap(), expected);
+    }
+
+    /// Tests that the handshake fails when the peer does not hold the expected
+    /// identity key
+    #[tokio::test]
+    async fn test_handshake_wrong_peer_key() {
+        let (addr0, addr1) = (free_addr(), free_addr());
+        let (identity0, identity1) =
+            (IdentityKeypair::generate().unwrap(), IdentityKeypair::generate().unwrap());
+        let impostor_key = IdentityKeypair::generate().unwrap().public_key();
+        let public_key0 = identity0.public_key();
+
+        let party0 = tokio::spawn(connect_encrypted(PARTY0, addr0, addr1, identity0, impostor_key));
+        let party1 = tokio::spawn(connect_encrypted(PARTY1, addr1, addr0, identity1, public_key0));
+
+        let res0 = party0.await.unwrap();
+        assert!(matches!(
+            res0,
+            Err(MpcNetworkError::ConnectionSetupError(SetupError::HandshakeError(_)))
+        ));
+
+        // The honest party authenticates its peer
+        assert!(party1.await.unwrap().is_ok());
+    }
+
+    /// Tests that a signature captured from one handshake cannot be replayed
+    /// to impersonate its signer in another
+    #[tokio::test]
+    async fn test_handshake_replayed_signature() {
+        let (identity0, identity1) =
+            (IdentityKeypair::generate().unwrap(), IdentityKeypair::generate().unwrap());
+        let (public_key0, public_key1) = (identity0.public_key(), identity1.public_key());
+
+        // Pose as party 1 to capture party 0's hello and signature
+        let (conn, mut attacker) = UnboundedDuplexStream::new_duplex_pair();
+        let mut party0 =
+            EncryptedNetwork::new(MockNetwork::new(PARTY0, conn), identity0, public_key1);
+
+        let mut ephemeral_key = vec![0u8; EPHEMERAL_KEY_LEN];
+        thread_rng().fill_bytes(&mut ephemeral_key);
+        let hello = [&PARTY1.to_le_bytes()[..], &ephemeral_key].concat();
+        attacker.send(handshake_message(hello));
+        attacker.send(handshake_message(vec![0u8; 64]));
+        assert!(party0.handshake().await.is_err());
+
+        let captured_hello = attacker.recv().await;
+        let captured_signature = attacker.recv().await;
+
+        // Replay the captured messages to party 1
+        let (conn, mut attacker) = UnboundedDuplexStream::new_duplex_pair();
+        let mut party1 =
+            EncryptedNetwork::new(MockNetwork::new(PARTY1, conn), identity1, public_key0);
+        attacker.send(captured_hello);
+        attacker.send(captured_signature);
+
+        let res = party1.handshake().await;
+        assert!(matches!(
+            res,
+            Err(MpcNetworkError::ConnectionSetupError(SetupError::HandshakeError(_)))
+        ));
+    }
+}
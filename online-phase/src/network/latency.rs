@@ -0,0 +1,292 @@
+//! Defines a network wrapper that simulates the latency and bandwidth of a
+//! real link, for measuring the round complexity of a circuit in tests and
+//! benchmarks
+//!
+//! Delays are applied to inbound messages, so wrapping both parties'
+//! networks simulates a link with the configured latency in each direction
+
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use ark_ec::CurveGroup;
+use async_trait::async_trait;
+use futures::{Future, Sink, SinkExt, Stream, StreamExt};
+use rand::{thread_rng, Rng};
+use tokio::time::{sleep_until, Instant, Sleep};
+
+use crate::error::MpcNetworkError;
+
+use super::{MpcNetwork, NetworkOutbound, PartyId};
+
+/// A network wrapper that delays each inbound message by a fixed latency, an
+/// optional random jitter, and the time taken to transmit the message at an
+/// optional bandwidth
+pub struct LatencyMpcNetwork<C: CurveGroup, N: MpcNetwork<C>> {
+    /// The underlying network
+    network: N,
+    /// The delay added to each message
+    latency: Duration,
+    /// The maximum random delay added to each message on top of the latency
+    jitter: Duration,
+    /// The bandwidth of the simulated link in bytes per second, if capped
+    bandwidth: Option<usize>,
+    /// Messages received from the underlying network along with the time at
+    /// which they are delivered, in order of delivery
+    in_flight: VecDeque<(Instant, NetworkOutbound<C>)>,
+    /// The time at which the simulated link finishes transmitting the
+    /// messages received so far
+    link_free_at: Instant,
+    /// A timer that fires when the next message in flight is delivered
+    timer: Option<Pin<Box<Sleep>>>,
+    /// Set once the underlying network ends, to the error that ended it if
+    /// any, and returned once the messages in flight have been delivered
+    end: Option<Option<MpcNetworkError>>,
+}
+
+// `NetworkOutbound` is only held by value, so the wrapper may be moved freely
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin> Unpin for LatencyMpcNetwork<C, N> {}
+
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin> LatencyMpcNetwork<C, N> {
+    /// Wrap the given network, with no delay until one is configured
+    pub fn new(network: N) -> Self {
+        Self {
+            network,
+            latency: Duration::ZERO,
+            jitter: Duration::ZERO,
+            bandwidth: None,
+            in_flight: VecDeque::new(),
+            link_free_at: Instant::now(),
+            timer: None,
+            end: None,
+        }
+    }
+
+    /// Delay each message by the given latency
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Delay each message by an additional random duration of at most
+    /// `jitter`
+    ///
+    /// Messages are still delivered in the order they were sent
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Cap the bandwidth of the link at the given number of bytes per second,
+    /// measured in the serialized size of each message
+    pub fn with_bandwidth(mut self, bytes_per_sec: usize) -> Self {
+        assert!(bytes_per_sec > 0, "bandwidth must be non-zero");
+        self.bandwidth = Some(bytes_per_sec);
+        self
+    }
+
+    /// Schedule the delivery of a message received from the underlying network
+    fn enqueue(&mut self, msg: NetworkOutbound<C>) {
+        let now = Instant::now();
+
+        // Messages share the link, so a message is transmitted only after those
+        // before it
+        let transmitted_at = match self.bandwidth {
+            Some(bytes_per_sec) => {
+                let transmit_time =
+                    Duration::from_secs_f64(msg.serialized_size() as f64 / bytes_per_sec as f64);
+                let transmitted_at = Instant::max(now, self.link_free_at) + transmit_time;
+                self.link_free_at = transmitted_at;
+                transmitted_at
+            },
+            None => now,
+        };
+
+        let jitter = if self.jitter.is_zero() {
+            Duration::ZERO
+        } else {
+            thread_rng().gen_range(Duration::ZERO..=self.jitter)
+        };
+
+        // Never deliver a message before one received ahead of it
+        let mut deliver_at = transmitted_at + self.latency + jitter;
+        if let Some((last_deliver_at, _)) = self.in_flight.back() {
+            deliver_at = Instant::max(deliver_at, *last_deliver_at);
+        }
+
+        self.in_flight.push_back((deliver_at, msg));
+    }
+}
+
+#[async_trait]
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin> MpcNetwork<C> for LatencyMpcNetwork<C, N> {
+    fn party_id(&self) -> PartyId {
+        self.network.party_id()
+    }
+
+    fn num_parties(&self) -> usize {
+        self.network.num_parties()
+    }
+
+    async fn close(&mut self) -> Result<(), MpcNetworkError> {
+        self.network.close().await
+    }
+}
+
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin> Stream for LatencyMpcNetwork<C, N> {
+    type Item = Result<NetworkOutbound<C>, MpcNetworkError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // Pull all available messages off the underlying network so that their
+        // delays run concurrently
+        while self.end.is_none() {
+            match self.network.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(msg))) => self.enqueue(msg),
+                Poll::Ready(Some(Err(err))) => self.end = Some(Some(err)),
+                Poll::Ready(None) => self.end = Some(None),
+                Poll::Pending => break,
+            }
+        }
+
+        // The end of the underlying network is only surfaced once the messages
+        // received before it are delivered
+        let deliver_at = match self.in_flight.front() {
+            Some((deliver_at, _)) => *deliver_at,
+            None => {
+                return match self.end.take() {
+                    Some(end) => Poll::Ready(end.map(Err)),
+                    None => Poll::Pending,
+                };
+            },
+        };
+
+        let timer = self.timer.get_or_insert_with(|| Box::pin(sleep_until(deliver_at)));
+        if timer.deadline() != deliver_at {
+            timer.as_mut().reset(deliver_at);
+        }
+
+        match timer.as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                let (_, msg) = self.in_flight.pop_front().unwrap();
+                Poll::Ready(Some(Ok(msg)))
+            },
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin> Sink<NetworkOutbound<C>> for LatencyMpcNetwork<C, N> {
+    type Error = MpcNetworkError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.network.poll_ready_unpin(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: NetworkOutbound<C>) -> Result<(), Self::Error> {
+        self.network.start_send_unpin(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.network.poll_flush_unpin(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.network.poll_close_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use futures::{SinkExt, StreamExt};
+    use tokio::time::Instant;
+
+    use crate::{
+        algebra::Scalar,
+        network::{MultiPartyMockNetwork, NetworkOutbound, NetworkPayload},
+        test_helpers::{execute_mock_mpc_with_latency, TestCurve},
+    };
+
+    use super::LatencyMpcNetwork;
+
+    /// The latency of the simulated link
+    const LATENCY: Duration = Duration::from_millis(20);
+
+    /// A network wrapped in a simulated link
+    type TestLatencyNetwork = LatencyMpcNetwork<TestCurve, MultiPartyMockNetwork<TestCurve>>;
+
+    /// Build a pair of networks connected by a simulated link
+    fn latency_pair() -> (TestLatencyNetwork, TestLatencyNetwork) {
+        let mut mesh = MultiPartyMockNetwork::new_mesh(2).into_iter();
+        let mut wrap = || LatencyMpcNetwork::new(mesh.next().unwrap()).with_latency(LATENCY);
+
+        (wrap(), wrap())
+    }
+
+    /// Build a message carrying a scalar
+    fn message(result_id: usize) -> NetworkOutbound<TestCurve> {
+        NetworkOutbound { result_id, payload: NetworkPayload::Scalar(Scalar::from(1u8)) }
+    }
+
+    /// Tests that the runtime of a sequence of round trips is the number of
+    /// rounds times the latency of the link
+    #[tokio::test(start_paused = true)]
+    async fn test_runtime_scales_with_rounds() {
+        for n_rounds in [1, 6] {
+            let (mut net0, mut net1) = latency_pair();
+            let start = Instant::now();
+            for i in 0..n_rounds {
+                net0.send(message(i)).await.unwrap();
+                let msg = net1.next().await.unwrap().unwrap();
+                net1.send(msg).await.unwrap();
+                net0.next().await.unwrap().unwrap();
+            }
+
+            assert_eq!(start.elapsed(), LATENCY * 2 * n_rounds as u32);
+        }
+    }
+
+    /// Tests that messages in flight when the underlying network closes are
+    /// delivered before the end of the stream
+    #[tokio::test(start_paused = true)]
+    async fn test_in_flight_delivered_on_close() {
+        const N: usize = 3;
+        let (mut net0, mut net1) = latency_pair();
+
+        for i in 0..N {
+            net0.send(message(i)).await.unwrap();
+        }
+        drop(net0);
+
+        for i in 0..N {
+            assert_eq!(net1.next().await.unwrap().unwrap().result_id, i);
+        }
+        assert!(net1.next().await.is_none());
+    }
+
+    /// Tests that a circuit takes at least its depth times the latency of the
+    /// link to run
+    #[tokio::test]
+    async fn test_circuit_runtime_bounded_by_depth() {
+        const N: usize = 6;
+        let ((depth, elapsed), _) = execute_mock_mpc_with_latency(
+            move |fabric| async move {
+                let start = Instant::now();
+                let values = fabric.random_shared_scalars(N + 1);
+                let product = values.iter().skip(1).fold(values[0].clone(), |acc, x| acc * x);
+                product.open().await;
+
+                (fabric.circuit_depth(), start.elapsed())
+            },
+            LATENCY,
+        )
+        .await;
+
+        assert_eq!(depth, N + 1);
+        assert!(elapsed >= LATENCY * depth as u32, "{elapsed:?} < {:?}", LATENCY * depth as u32);
+    }
+}
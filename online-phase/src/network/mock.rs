@@ -141,3 +141,84 @@ impl<C: CurveGroup> Sink<NetworkOutbound<C>> for MockNetwork<C> {
         Poll::Ready(Ok(()))
     }
 }
+
+/// A dummy network connecting any number of parties over in-memory channels,
+/// used for unit testing protocols with more than two parties
+///
+/// Each outbound message is broadcast to every other party
+pub struct MultiPartyMockNetwork<C: CurveGroup> {
+    /// The ID of the local party
+    party_id: PartyId,
+    /// The senders to each party's inbound channel, `None` for the local party
+    peers: Vec<Option<UnboundedSender<NetworkOutbound<C>>>>,
+    /// The inbound channel on which all peers' messages arrive
+    recv: UnboundedReceiver<NetworkOutbound<C>>,
+}
+
+impl<C: CurveGroup> MultiPartyMockNetwork<C> {
+    /// Create a fully connected mesh of `n` networks, indexed by party ID
+    pub fn new_mesh(n: usize) -> Vec<Self> {
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..n).map(|_| unbounded_channel()).unzip();
+
+        receivers
+            .into_iter()
+            .enumerate()
+            .map(|(party_id, recv)| {
+                let peers = senders
+                    .iter()
+                    .enumerate()
+                    .map(|(peer_id, send)| (peer_id != party_id).then(|| send.clone()))
+                    .collect();
+
+                Self { party_id: party_id as PartyId, peers, recv }
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<C: CurveGroup> MpcNetwork<C> for MultiPartyMockNetwork<C> {
+    fn party_id(&self) -> PartyId {
+        self.party_id
+    }
+
+    fn num_parties(&self) -> usize {
+        self.peers.len()
+    }
+
+    async fn close(&mut self) -> Result<(), MpcNetworkError> {
+        Ok(())
+    }
+}
+
+impl<C: CurveGroup> Stream for MultiPartyMockNetwork<C> {
+    type Item = Result<NetworkOutbound<C>, MpcNetworkError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.recv.poll_recv(cx).map(|value| value.map(Ok))
+    }
+}
+
+impl<C: CurveGroup> Sink<NetworkOutbound<C>> for MultiPartyMockNetwork<C> {
+    type Error = MpcNetworkError;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: NetworkOutbound<C>) -> Result<(), Self::Error> {
+        for peer in self.peers.iter().flatten() {
+            peer.send(item.clone()).map_err(|e| MpcNetworkError::SendError(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,328 @@
+//! Defines the protobuf representation of network messages, allowing peers
+//! implemented in other languages to interoperate with a Rust party
+//!
+//! The schema is given in `proto/network.proto`; the message types below
+//! mirror it field for field
+
+use ark_ec::CurveGroup;
+use itertools::Itertools;
+use prost::Message;
+
+use crate::{
+    algebra::{CurvePoint, PointShare as PointShareValue, Scalar, ScalarShare as ScalarShareValue},
+    error::MpcNetworkError,
+};
+
+use super::{NetworkOutbound as Outbound, NetworkPayload as Payload};
+
+/// Error message emitted when a message has no payload
+const ERR_MISSING_PAYLOAD: &str = "protobuf message has no payload";
+
+/// A message sent to the peer
+#[derive(Clone, PartialEq, Message)]
+pub struct NetworkOutbound {
+    /// The ID of the result the message carries
+    #[prost(uint64, tag = "1")]
+    pub result_id: u64,
+    /// The body of the message
+    #[prost(message, optional, tag = "2")]
+    pub payload: Option<NetworkPayload>,
+}
+
+/// The body of a message
+#[derive(Clone, PartialEq, Message)]
+pub struct NetworkPayload {
+    /// The value carried by the message
+    #[prost(oneof = "PayloadValue", tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11")]
+    pub value: Option<PayloadValue>,
+}
+
+/// The value carried by a message
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum PayloadValue {
+    /// A byte value
+    #[prost(bytes, tag = "1")]
+    Bytes(Vec<u8>),
+    /// A scalar value
+    #[prost(bytes, tag = "2")]
+    Scalar(Vec<u8>),
+    /// A batch of scalar values
+    #[prost(message, tag = "3")]
+    ScalarBatch(ScalarBatch),
+    /// A share of a scalar value
+    #[prost(message, tag = "4")]
+    ScalarShare(ScalarShare),
+    /// A point on the curve
+    #[prost(bytes, tag = "5")]
+    Point(Vec<u8>),
+    /// A batch of points on the curve
+    #[prost(message, tag = "6")]
+    PointBatch(PointBatch),
+    /// A share of a curve point value
+    #[prost(message, tag = "7")]
+    PointShare(PointShare),
+    /// One or more messages sent as a single transmission
+    #[prost(message, tag = "8")]
+    Frame(Frame),
+    /// A keepalive message
+    #[prost(message, tag = "9")]
+    Heartbeat(Heartbeat),
+    /// A message tagged with its sequence number on a reliable link
+    #[prost(message, tag = "10")]
+    Sequenced(Box<Sequenced>),
+    /// Acknowledges the messages received on a reliable link
+    #[prost(message, tag = "11")]
+    Ack(Ack),
+}
+
+/// A batch of scalars
+#[derive(Clone, PartialEq, Message)]
+pub struct ScalarBatch {
+    /// The encoded scalars
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub values: Vec<Vec<u8>>,
+}
+
+/// A share of a scalar and its MAC
+#[derive(Clone, PartialEq, Message)]
+pub struct ScalarShare {
+    /// The encoded share
+    #[prost(bytes, tag = "1")]
+    pub share: Vec<u8>,
+    /// The encoded MAC share
+    #[prost(bytes, tag = "2")]
+    pub mac: Vec<u8>,
+}
+
+/// A batch of points
+#[derive(Clone, PartialEq, Message)]
+pub struct PointBatch {
+    /// The encoded points
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub values: Vec<Vec<u8>>,
+}
+
+/// A share of a point and its MAC
+#[derive(Clone, PartialEq, Message)]
+pub struct PointShare {
+    /// The encoded share
+    #[prost(bytes, tag = "1")]
+    pub share: Vec<u8>,
+    /// The encoded MAC share
+    #[prost(bytes, tag = "2")]
+    pub mac: Vec<u8>,
+}
+
+/// One or more messages sent as a single transmission
+#[derive(Clone, PartialEq, Message)]
+pub struct Frame {
+    /// The ID of the sending party
+    #[prost(uint64, tag = "1")]
+    pub sender: u64,
+    /// The index of the frame among those sent by the sender
+    #[prost(uint64, tag = "2")]
+    pub seq: u64,
+    /// The messages in the frame
+    #[prost(message, repeated, tag = "3")]
+    pub msgs: Vec<NetworkOutbound>,
+}
+
+/// A keepalive message, carrying no value
+#[derive(Clone, PartialEq, Message)]
+pub struct Heartbeat {}
+
+/// A message tagged with its sequence number on a reliable link
+#[derive(Clone, PartialEq, Message)]
+pub struct Sequenced {
+    /// The index of the message among those sent on the link
+    #[prost(uint64, tag = "1")]
+    pub seq: u64,
+    /// The number of the peer's messages received by the sender
+    #[prost(uint64, tag = "2")]
+    pub ack: u64,
+    /// The message
+    #[prost(message, optional, boxed, tag = "3")]
+    pub msg: Option<Box<NetworkOutbound>>,
+}
+
+/// Acknowledges the messages received on a reliable link
+#[derive(Clone, PartialEq, Message)]
+pub struct Ack {
+    /// The number of the peer's messages received by the sender
+    #[prost(uint64, tag = "1")]
+    pub received: u64,
+}
+
+// ------------
+// | Encoding |
+// ------------
+
+/// Encode a message in its protobuf representation
+pub fn encode_message<C: CurveGroup>(msg: &Outbound<C>) -> Vec<u8> {
+    NetworkOutbound::from(msg).encode_to_vec()
+}
+
+/// Decode a message from its protobuf representation
+pub fn decode_message<C: CurveGroup>(bytes: &[u8]) -> Result<Outbound<C>, MpcNetworkError> {
+    let msg = NetworkOutbound::decode(bytes)
+        .map_err(|err| MpcNetworkError::SerializationError(err.to_string()))?;
+    Outbound::try_from(msg)
+}
+
+impl<C: CurveGroup> From<&Outbound<C>> for NetworkOutbound {
+    fn from(msg: &Outbound<C>) -> Self {
+        let value = match &msg.payload {
+            Payload::Bytes(bytes) => PayloadValue::Bytes(bytes.clone()),
+            Payload::Scalar(scalar) => PayloadValue::Scalar(scalar.to_bytes_be()),
+            Payload::ScalarBatch(scalars) => PayloadValue::ScalarBatch(ScalarBatch {
+                values: scalars.iter().map(Scalar::to_bytes_be).collect(),
+            }),
+            Payload::ScalarShare(share) => PayloadValue::ScalarShare(ScalarShare {
+                share: share.share().to_bytes_be(),
+                mac: share.mac().to_bytes_be(),
+            }),
+            Payload::Point(point) => PayloadValue::Point(point.to_bytes()),
+            Payload::PointBatch(points) => PayloadValue::PointBatch(PointBatch {
+                values: points.iter().map(CurvePoint::to_bytes).collect(),
+            }),
+            Payload::PointShare(share) => PayloadValue::PointShare(PointShare {
+                share: share.share().to_bytes(),
+                mac: share.mac().to_bytes(),
+            }),
+            Payload::Frame { sender, seq, msgs } => PayloadValue::Frame(Frame {
+                sender: *sender,
+                seq: *seq,
+                msgs: msgs.iter().map(NetworkOutbound::from).collect(),
+            }),
+            Payload::Heartbeat => PayloadValue::Heartbeat(Heartbeat {}),
+            Payload::Sequenced { seq, ack, msg } => PayloadValue::Sequenced(Box::new(Sequenced {
+                seq: *seq,
+                ack: *ack,
+                msg: Some(Box::new(NetworkOutbound::from(msg.as_ref()))),
+            })),
+            Payload::Ack { received } => PayloadValue::Ack(Ack { received: *received }),
+        };
+
+        NetworkOutbound {
+            result_id: msg.result_id as u64,
+            payload: Some(NetworkPayload { value: Some(value) }),
+        }
+    }
+}
+
+impl<C: CurveGroup> TryFrom<NetworkOutbound> for Outbound<C> {
+    type Error = MpcNetworkError;
+
+    fn try_from(msg: NetworkOutbound) -> Result<Self, Self::Error> {
+        let value = msg
+            .payload
+            .and_then(|payload| payload.value)
+            .ok_or_else(|| MpcNetworkError::SerializationError(ERR_MISSING_PAYLOAD.to_string()))?;
+
+        let payload = match value {
+            PayloadValue::Bytes(bytes) => Payload::Bytes(bytes),
+            PayloadValue::Scalar(bytes) => Payload::Scalar(decode_scalar(&bytes)?),
+            PayloadValue::ScalarBatch(batch) => Payload::ScalarBatch(
+                batch.values.iter().map(|bytes| decode_scalar(bytes)).try_collect()?,
+            ),
+            PayloadValue::ScalarShare(share) => Payload::ScalarShare(ScalarShareValue::new(
+                decode_scalar(&share.share)?,
+                decode_scalar(&share.mac)?,
+            )),
+            PayloadValue::Point(bytes) => Payload::Point(decode_point(&bytes)?),
+            PayloadValue::PointBatch(batch) => Payload::PointBatch(
+                batch.values.iter().map(|bytes| decode_point(bytes)).try_collect()?,
+            ),
+            PayloadValue::PointShare(share) => Payload::PointShare(PointShareValue::new(
+                decode_point(&share.share)?,
+                decode_point(&share.mac)?,
+            )),
+            PayloadValue::Frame(frame) => Payload::Frame {
+                sender: frame.sender,
+                seq: frame.seq,
+                msgs: frame.msgs.into_iter().map(Outbound::try_from).try_collect()?,
+            },
+            PayloadValue::Heartbeat(_) => Payload::Heartbeat,
+            PayloadValue::Sequenced(sequenced) => {
+                let msg = sequenced.msg.ok_or_else(|| {
+                    MpcNetworkError::SerializationError(ERR_MISSING_PAYLOAD.to_string())
+                })?;
+
+                Payload::Sequenced {
+                    seq: sequenced.seq,
+                    ack: sequenced.ack,
+                    msg: Box::new(Outbound::try_from(*msg)?),
+                }
+            },
+            PayloadValue::Ack(ack) => Payload::Ack { received: ack.received },
+        };
+
+        Ok(Outbound { result_id: msg.result_id as usize, payload })
+    }
+}
+
+/// Decode a scalar from its big-endian encoding
+fn decode_scalar<C: CurveGroup>(bytes: &[u8]) -> Result<Scalar<C>, MpcNetworkError> {
+    Scalar::try_from(bytes).map_err(|err| MpcNetworkError::SerializationError(err.to_string()))
+}
+
+/// Decode a point from its compressed encoding
+fn decode_point<C: CurveGroup>(bytes: &[u8]) -> Result<CurvePoint<C>, MpcNetworkError> {
+    CurvePoint::from_bytes(bytes)
+        .map_err(|err| MpcNetworkError::SerializationError(err.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use rand::thread_rng;
+
+    use crate::{
+        algebra::{CurvePoint, Scalar, ScalarShare},
+        network::{NetworkOutbound, NetworkPayload},
+        test_helpers::TestCurve,
+    };
+
+    use super::{decode_message, encode_message};
+
+    /// Tests encoding and decoding a frame of messages through the protobuf
+    /// representation
+    #[test]
+    fn test_protobuf_round_trip() {
+        let mut rng = thread_rng();
+        let scalar = Scalar::<TestCurve>::random(&mut rng);
+        let point = CurvePoint::<TestCurve>::generator() * scalar;
+
+        let msgs = vec![
+            NetworkOutbound { result_id: 1, payload: NetworkPayload::Scalar(scalar) },
+            NetworkOutbound { result_id: 2, payload: NetworkPayload::PointBatch(vec![point; 3]) },
+            NetworkOutbound {
+                result_id: 3,
+                payload: NetworkPayload::ScalarShare(ScalarShare::new(scalar, -scalar)),
+            },
+            NetworkOutbound { result_id: 4, payload: NetworkPayload::Heartbeat },
+            NetworkOutbound {
+                result_id: 5,
+                payload: NetworkPayload::Sequenced {
+                    seq: 3,
+                    ack: 2,
+                    msg: Box::new(NetworkOutbound {
+                        result_id: 5,
+                        payload: NetworkPayload::Scalar(scalar),
+                    }),
+                },
+            },
+            NetworkOutbound { result_id: 0, payload: NetworkPayload::Ack { received: 9 } },
+        ];
+        let frame = NetworkOutbound {
+            result_id: 0,
+            payload: NetworkPayload::Frame { sender: 1, seq: 7, msgs: msgs.clone() },
+        };
+
+        let bytes = encode_message(&frame);
+        let decoded = decode_message::<TestCurve>(&bytes).unwrap();
+
+        // Compare through the JSON representation, as payloads are not `Eq`
+        assert_eq!(serde_json::to_vec(&decoded).unwrap(), serde_json::to_vec(&frame).unwrap());
+        assert!(decode_message::<TestCurve>(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -5,6 +5,7 @@ use ark_ec::CurveGroup;
 use async_trait::async_trait;
 use futures::{Future, Sink, Stream};
 use quinn::{Endpoint, RecvStream, SendStream};
+use rustls::{Certificate, PrivateKey};
 use std::{
     marker::PhantomData,
     net::SocketAddr,
@@ -18,7 +19,9 @@ use crate::{
     PARTY0,
 };
 
-use super::{config, stream_buffer::BufferWithCursor, MpcNetwork, NetworkOutbound, PartyId};
+use super::{
+    compression, config, stream_buffer::BufferWithCursor, MpcNetwork, NetworkOutbound, PartyId,
+};
 
 // -------------
 // | Constants |
@@ -64,6 +67,15 @@ pub struct QuicTwoPartyNet<C: CurveGroup> {
     buffered_inbound: Option<BufferWithCursor>,
     /// A buffered partial message written to the stream
     buffered_outbound: Option<BufferWithCursor>,
+    /// The size in bytes at or above which outbound messages are compressed,
+    /// compression is disabled if `None`
+    compression_threshold: Option<usize>,
+    /// The certificate and private key the local server presents, a
+    /// self-signed certificate is generated if none is configured
+    certificate: Option<(Certificate, PrivateKey)>,
+    /// The certificate the peer's server must present, or the CA that issued
+    /// it, any certificate is accepted if none is configured
+    peer_certificate: Option<Certificate>,
     /// The send side of the bidirectional stream
     send_stream: Option<SendStream>,
     /// The receive side of the bidirectional stream
@@ -85,12 +97,46 @@ impl<'a, C: CurveGroup> QuicTwoPartyNet<C> {
             buffered_message_length: None,
             buffered_inbound: None,
             buffered_outbound: None,
+            compression_threshold: None,
+            certificate: None,
+            peer_certificate: None,
             send_stream: None,
             recv_stream: None,
             _phantom: PhantomData,
         }
     }
 
+    /// Create a new network whose local server presents the given DER encoded
+    /// certificate and private key, and that pins the peer's DER encoded
+    /// certificate, do not connect the network yet
+    ///
+    /// The peer certificate may be the certificate the peer's server presents
+    /// or a CA that issued it for `otter.cash`. Only party 0 dials the peer and
+    /// verifies its certificate, so party 1 should authenticate party 0 at a
+    /// higher layer, e.g. with an `EncryptedNetwork`
+    pub fn new_with_certificate(
+        party_id: PartyId,
+        local_addr: SocketAddr,
+        peer_addr: SocketAddr,
+        cert_der: Vec<u8>,
+        key_der: Vec<u8>,
+        peer_cert_der: Vec<u8>,
+    ) -> Self {
+        let mut net = Self::new(party_id, local_addr, peer_addr);
+        net.certificate = Some((Certificate(cert_der), PrivateKey(key_der)));
+        net.peer_certificate = Some(Certificate(peer_cert_der));
+        net
+    }
+
+    /// Compress outbound messages of at least `threshold` bytes
+    ///
+    /// Both parties must enable compression, as the flag marking compressed
+    /// messages is only part of the wire format when it is enabled
+    #[cfg(feature = "compression")]
+    pub fn enable_compression(&mut self, threshold: usize) {
+        self.compression_threshold = Some(threshold);
+    }
+
     /// Returns true if the local party is party 0
     fn local_party0(&self) -> bool {
         self.party_id == PARTY0
@@ -109,7 +155,8 @@ impl<'a, C: CurveGroup> QuicTwoPartyNet<C> {
     pub async fn connect(&mut self) -> Result<(), MpcNetworkError> {
         // Build the client and server configs
         let (client_config, server_config) =
-            config::build_configs().map_err(|err| MpcNetworkError::ConnectionSetupError(err))?;
+            config::build_configs(self.certificate.clone(), self.peer_certificate.clone())
+                .map_err(|err| MpcNetworkError::ConnectionSetupError(err))?;
 
         // Create a quinn server
         let mut local_endpoint = Endpoint::server(server_config, self.local_addr).map_err(|e| {
@@ -246,8 +293,7 @@ impl<'a, C: CurveGroup> QuicTwoPartyNet<C> {
         self.buffered_message_length = None;
 
         // Deserialize the message
-        serde_json::from_slice(&bytes)
-            .map_err(|err| MpcNetworkError::SerializationError(err.to_string()))
+        compression::deserialize_message(&bytes, self.compression_threshold.is_some())
     }
 }
 
@@ -300,8 +346,7 @@ where
         }
 
         // Serialize the message and buffer it for writing
-        let bytes = serde_json::to_vec(&msg)
-            .map_err(|err| MpcNetworkError::SerializationError(err.to_string()))?;
+        let bytes = compression::serialize_message(&msg, self.compression_threshold)?;
         let mut payload = (bytes.len() as u64).to_le_bytes().to_vec();
         payload.extend_from_slice(&bytes);
 
@@ -324,3 +369,86 @@ where
         self.poll_flush(cx)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::net::{SocketAddr, UdpSocket};
+
+    use crate::{
+        algebra::Scalar, offline_prep::PartyIDBeaverSource, test_helpers::TestCurve, MpcFabric,
+        PARTY0, PARTY1,
+    };
+
+    use super::{config::generate_cert, QuicTwoPartyNet};
+
+    /// Find a free local address to bind a QUIC endpoint to
+    fn free_addr() -> SocketAddr {
+        UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+    }
+
+    /// Run one party of a multiplication over a QUIC network
+    async fn multiply_over_quic(
+        party_id: u64,
+        local_addr: SocketAddr,
+        peer_addr: SocketAddr,
+    ) -> Scalar<TestCurve> {
+        let mut network = QuicTwoPartyNet::new(party_id, local_addr, peer_addr);
+        network.connect().await.unwrap();
+        let fabric = MpcFabric::new(network, PartyIDBeaverSource::new(party_id));
+
+        let a = fabric.share_scalar(2u8, PARTY0 /* sender */);
+        let b = fabric.share_scalar(3u8, PARTY1 /* sender */);
+        let res = (a * b).open_authenticated().await.unwrap();
+
+        fabric.shutdown();
+        res
+    }
+
+    /// Tests a multiplication between two fabrics connected over localhost QUIC
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_quic_multiplication() {
+        let (addr0, addr1) = (free_addr(), free_addr());
+        let party0 = tokio::spawn(multiply_over_quic(PARTY0, addr0, addr1));
+        let party1 = tokio::spawn(multiply_over_quic(PARTY1, addr1, addr0));
+
+        let expected = Scalar::from(6u8);
+        assert_eq!(party0.await.unwrap(), expected);
+        assert_eq!(party1.await.unwrap(), expected);
+    }
+
+    /// Connect a pair of networks configured with certificates, returning
+    /// whether party 0 connects
+    ///
+    /// Party 0 pins party 1's certificate if `pin_peer` is set, and an
+    /// unrelated certificate otherwise
+    async fn connect_pinned(pin_peer: bool) -> bool {
+        let (addr0, addr1) = (free_addr(), free_addr());
+        let (cert0, key0) = generate_cert().unwrap();
+        let (cert1, key1) = generate_cert().unwrap();
+        let pinned = if pin_peer { cert1.clone() } else { generate_cert().unwrap().0 };
+
+        let mut net0: QuicTwoPartyNet<TestCurve> = QuicTwoPartyNet::new_with_certificate(
+            PARTY0,
+            addr0,
+            addr1,
+            cert0.0.clone(),
+            key0.0,
+            pinned.0,
+        );
+        let mut net1: QuicTwoPartyNet<TestCurve> =
+            QuicTwoPartyNet::new_with_certificate(PARTY1, addr1, addr0, cert1.0, key1.0, cert0.0);
+        let party1 = tokio::spawn(async move { net1.connect().await });
+
+        let connected = net0.connect().await.is_ok();
+        party1.abort();
+        connected
+    }
+
+    /// Tests that party 0 connects to a peer presenting the pinned certificate
+    /// and refuses a peer presenting any other
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_quic_pinned_certificate() {
+        assert!(connect_pinned(true /* pin_peer */).await);
+        assert!(!connect_pinned(false /* pin_peer */).await);
+    }
+}
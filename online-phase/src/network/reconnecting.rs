@@ -0,0 +1,602 @@
+//! Defines a network wrapper that survives transient connection drops by
+//! reconnecting to the peer and replaying unacknowledged messages
+//!
+//! Each message sent through the wrapper is tagged with a sequence number and
+//! the number of messages received from the peer so far, which acknowledges
+//! the peer's messages. A party that receives messages without sending any
+//! acknowledges them explicitly. After a reconnect both parties exchange the
+//! number of messages they have received, and each replays the messages its
+//! peer is missing
+
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use ark_ec::CurveGroup;
+use async_trait::async_trait;
+use futures::{future::BoxFuture, ready, Future, Sink, SinkExt, Stream, StreamExt};
+use tracing::log;
+
+use crate::error::MpcNetworkError;
+
+use super::{MpcNetwork, NetworkOutbound, NetworkPayload, PartyId};
+
+/// Error message emitted when a message from the peer skips a sequence number
+const ERR_SEQUENCE_GAP: &str = "message sequence gap";
+/// Error message emitted when a message is not sent by a `ReconnectingNetwork`
+const ERR_UNSEQUENCED: &str = "expected a sequenced message";
+/// Error message emitted when the peer does not resume a reconnected session
+const ERR_NO_RESUME: &str = "peer did not resume session";
+
+/// The default number of sent messages that may await acknowledgement before
+/// sending blocks
+pub const DEFAULT_MAX_UNACKED: usize = 1024;
+/// The number of received messages after which the receiver acknowledges them
+/// even if it has nothing to send
+const ACK_INTERVAL: u64 = 32;
+
+/// A message sent between two `ReconnectingNetwork`s
+enum ReliableMessage<C: CurveGroup> {
+    /// A message from the fabric
+    Data {
+        /// The sequence number of the message
+        seq: u64,
+        /// The number of the peer's messages received by the sender
+        ack: u64,
+        /// The message
+        msg: NetworkOutbound<C>,
+    },
+    /// Acknowledges the peer's messages, sent first on a reconnected
+    /// connection and whenever the sender has received `ACK_INTERVAL`
+    /// messages without sending any
+    Ack {
+        /// The number of the peer's messages received by the sender
+        received: u64,
+    },
+}
+
+impl<C: CurveGroup> ReliableMessage<C> {
+    /// Encode the message for the underlying network
+    fn encode(self) -> NetworkOutbound<C> {
+        match self {
+            Self::Data { seq, ack, msg } => NetworkOutbound {
+                result_id: msg.result_id,
+                payload: NetworkPayload::Sequenced { seq, ack, msg: Box::new(msg) },
+            },
+            Self::Ack { received } => {
+                NetworkOutbound { result_id: 0, payload: NetworkPayload::Ack { received } }
+            },
+        }
+    }
+
+    /// Decode a message from the underlying network
+    fn decode(msg: NetworkOutbound<C>) -> Result<Self, MpcNetworkError> {
+        match msg.payload {
+            NetworkPayload::Sequenced { seq, ack, msg } => Ok(Self::Data { seq, ack, msg: *msg }),
+            NetworkPayload::Ack { received } => Ok(Self::Ack { received }),
+            _ => Err(MpcNetworkError::SerializationError(ERR_UNSEQUENCED.to_string())),
+        }
+    }
+}
+
+/// A function establishing a new connection to the peer
+type ConnectFn<N> = Box<dyn Fn() -> BoxFuture<'static, Result<N, MpcNetworkError>> + Send>;
+
+/// A network that reconnects to its peer when the underlying connection drops,
+/// replaying the messages the peer did not receive
+///
+/// Messages are delivered exactly once and in order, duplicates replayed
+/// across a reconnect are dropped. Sending blocks while too many sent messages
+/// await acknowledgement from the peer, bounding the replay buffer
+pub struct ReconnectingNetwork<C: CurveGroup, N: MpcNetwork<C>> {
+    /// The ID of the local party
+    party_id: PartyId,
+    /// The number of parties in the MPC
+    num_parties: usize,
+    /// The underlying connection, `None` while reconnecting
+    inner: Option<N>,
+    /// Establishes a new connection to the peer
+    connect: ConnectFn<N>,
+    /// The in-progress reconnection, resolves to the new connection and the
+    /// number of local messages the peer has received
+    reconnecting: Option<BoxFuture<'static, Result<(N, u64), MpcNetworkError>>>,
+    /// The sequence number of the next outbound message
+    next_seq: u64,
+    /// The number of messages received from the peer
+    n_received: u64,
+    /// The number of messages received from the peer as of the last
+    /// acknowledgement sent
+    n_acked: u64,
+    /// The sent messages the peer has not acknowledged, in order
+    unacked: VecDeque<(u64, NetworkOutbound<C>)>,
+    /// The number of sent messages that may await acknowledgement before
+    /// sending blocks
+    max_unacked: usize,
+    /// The index into `unacked` of the next message to replay after a
+    /// reconnect
+    replay_index: usize,
+    /// The waker of the last task to poll the receive side
+    read_waker: Option<Waker>,
+    /// The waker of the last task to poll the send side
+    write_waker: Option<Waker>,
+}
+
+// The wrapper never pins its fields, buffered messages are stored on the heap
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin> Unpin for ReconnectingNetwork<C, N> {}
+
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin + 'static> ReconnectingNetwork<C, N> {
+    /// Wrap a connected network, using `connect` to establish a new connection
+    /// to the same peer when the connection drops
+    ///
+    /// Both parties must wrap their networks, and `connect` must resolve to a
+    /// connection to the same peer on both sides
+    pub fn new<F, Fut>(network: N, connect: F) -> Self
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<N, MpcNetworkError>> + Send + 'static,
+    {
+        Self {
+            party_id: network.party_id(),
+            num_parties: network.num_parties(),
+            inner: Some(network),
+            connect: Box::new(move || Box::pin(connect())),
+            reconnecting: None,
+            next_seq: 0,
+            n_received: 0,
+            n_acked: 0,
+            unacked: VecDeque::new(),
+            max_unacked: DEFAULT_MAX_UNACKED,
+            replay_index: 0,
+            read_waker: None,
+            write_waker: None,
+        }
+    }
+
+    /// Set the number of sent messages that may await acknowledgement before
+    /// sending blocks
+    pub fn set_max_unacked(&mut self, max_unacked: usize) {
+        assert!(
+            max_unacked >= ACK_INTERVAL as usize,
+            "at least {ACK_INTERVAL} messages must be allowed to await acknowledgement"
+        );
+        self.max_unacked = max_unacked;
+    }
+
+    /// Drop the current connection and begin reconnecting to the peer
+    fn start_reconnect(&mut self, err: MpcNetworkError) {
+        if self.reconnecting.is_some() {
+            return;
+        }
+
+        log::warn!("connection to peer dropped, reconnecting: {err:?}");
+        self.inner = None;
+
+        let connect = (self.connect)();
+        let received = self.n_received;
+        self.reconnecting = Some(Box::pin(async move {
+            let mut network = connect.await?;
+            network.send(ReliableMessage::<C>::Ack { received }.encode()).await?;
+
+            // The peer sends its resume message before any replayed messages
+            match network.next().await {
+                Some(Ok(msg)) => match ReliableMessage::<C>::decode(msg)? {
+                    ReliableMessage::Ack { received } => Ok((network, received)),
+                    ReliableMessage::Data { .. } => {
+                        Err(MpcNetworkError::RecvError(ERR_NO_RESUME.to_string()))
+                    },
+                },
+                Some(Err(err)) => Err(err),
+                None => Err(MpcNetworkError::RecvError(ERR_NO_RESUME.to_string())),
+            }
+        }));
+
+        // Wake both sides so that each polls the reconnection
+        self.wake_all();
+    }
+
+    /// Wake the tasks polling either side of the network
+    fn wake_all(&mut self) {
+        self.read_waker.take().into_iter().chain(self.write_waker.take()).for_each(Waker::wake);
+    }
+
+    /// Drive any in-progress reconnection to completion
+    fn poll_connected(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), MpcNetworkError>> {
+        let Some(reconnecting) = self.reconnecting.as_mut() else {
+            return Poll::Ready(Ok(()));
+        };
+
+        let res = ready!(reconnecting.as_mut().poll(cx));
+        self.reconnecting = None;
+        let (network, peer_received) = res?;
+
+        // Replay the messages the peer has not received
+        self.acknowledge(peer_received);
+        self.replay_index = 0;
+        self.inner = Some(network);
+        self.wake_all();
+
+        Poll::Ready(Ok(()))
+    }
+
+    /// Discard the sent messages the peer has acknowledged receiving, waking
+    /// the send side if it is blocked on a full replay buffer
+    fn acknowledge(&mut self, peer_received: u64) {
+        let n_unacked = self.unacked.len();
+        while self.unacked.front().is_some_and(|(seq, _)| *seq < peer_received) {
+            self.unacked.pop_front();
+            self.replay_index = self.replay_index.saturating_sub(1);
+        }
+
+        if self.unacked.len() < n_unacked {
+            self.write_waker.take().into_iter().for_each(Waker::wake);
+        }
+    }
+
+    /// Send a message on the underlying connection, reconnecting on failure
+    ///
+    /// A data message must already be recorded in `unacked`, it is replayed
+    /// after the reconnect if sending fails
+    fn send_inner(&mut self, msg: ReliableMessage<C>) {
+        // Every message acknowledges the peer's messages received so far
+        self.n_acked = self.n_received;
+
+        let inner = self.inner.as_mut().expect("connection not established");
+        if let Err(err) = Pin::new(inner).start_send(msg.encode()) {
+            self.start_reconnect(err);
+        }
+    }
+
+    /// Replay unacknowledged messages after a reconnect, acknowledge the
+    /// peer's messages if due, and flush the underlying connection
+    fn poll_replay_and_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), MpcNetworkError>> {
+        loop {
+            ready!(self.poll_connected(cx))?;
+            let inner = Pin::new(self.inner.as_mut().expect("connection not established"));
+
+            let replay_due = self.replay_index < self.unacked.len();
+            let ack_due = self.n_received - self.n_acked >= ACK_INTERVAL;
+            let res = if replay_due || ack_due {
+                match ready!(inner.poll_ready(cx)) {
+                    Ok(()) if replay_due => {
+                        let (seq, msg) = self.unacked[self.replay_index].clone();
+                        self.replay_index += 1;
+                        self.send_inner(ReliableMessage::Data { seq, ack: self.n_received, msg });
+                        continue;
+                    },
+                    Ok(()) => {
+                        self.send_inner(ReliableMessage::Ack { received: self.n_received });
+                        continue;
+                    },
+                    Err(err) => Err(err),
+                }
+            } else {
+                ready!(inner.poll_flush(cx))
+            };
+
+            match res {
+                Ok(()) => return Poll::Ready(Ok(())),
+                Err(err) => self.start_reconnect(err),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<C, N> MpcNetwork<C> for ReconnectingNetwork<C, N>
+where
+    C: CurveGroup,
+    N: MpcNetwork<C> + Unpin + 'static,
+{
+    fn party_id(&self) -> PartyId {
+        self.party_id
+    }
+
+    fn num_parties(&self) -> usize {
+        self.num_parties
+    }
+
+    async fn close(&mut self) -> Result<(), MpcNetworkError> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.close().await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl<C, N> Stream for ReconnectingNetwork<C, N>
+where
+    C: CurveGroup,
+    N: MpcNetwork<C> + Unpin + 'static,
+{
+    type Item = Result<NetworkOutbound<C>, MpcNetworkError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.read_waker = Some(cx.waker().clone());
+
+        loop {
+            if let Err(err) = ready!(this.poll_connected(cx)) {
+                return Poll::Ready(Some(Err(err)));
+            }
+
+            // Replay any messages the peer is missing and acknowledge the peer's
+            // messages, the send side may be idle
+            if let Poll::Ready(Err(err)) = this.poll_replay_and_flush(cx) {
+                return Poll::Ready(Some(Err(err)));
+            }
+            if this.reconnecting.is_some() {
+                continue;
+            }
+
+            let inner = Pin::new(this.inner.as_mut().expect("connection not established"));
+            let msg = match ready!(inner.poll_next(cx)) {
+                Some(Ok(msg)) => ReliableMessage::decode(msg)?,
+                Some(Err(err)) => {
+                    this.start_reconnect(err);
+                    continue;
+                },
+                None => {
+                    this.start_reconnect(MpcNetworkError::RecvError("stream closed".to_string()));
+                    continue;
+                },
+            };
+
+            match msg {
+                ReliableMessage::Data { seq, ack, msg } => {
+                    this.acknowledge(ack);
+
+                    // Drop messages replayed by the peer that were already received
+                    if seq < this.n_received {
+                        continue;
+                    } else if seq > this.n_received {
+                        let err = MpcNetworkError::RecvError(ERR_SEQUENCE_GAP.to_string());
+                        return Poll::Ready(Some(Err(err)));
+                    }
+
+                    this.n_received += 1;
+                    return Poll::Ready(Some(Ok(msg)));
+                },
+                ReliableMessage::Ack { received } => this.acknowledge(received),
+            }
+        }
+    }
+}
+
+impl<C, N> Sink<NetworkOutbound<C>> for ReconnectingNetwork<C, N>
+where
+    C: CurveGroup,
+    N: MpcNetwork<C> + Unpin + 'static,
+{
+    type Error = MpcNetworkError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.write_waker = Some(cx.waker().clone());
+
+        // Finish replaying before sending new messages to preserve their order
+        ready!(this.poll_replay_and_flush(cx))?;
+
+        // Wait for the peer to acknowledge messages if the replay buffer is full,
+        // the receive side wakes this task when acknowledgements arrive
+        if this.unacked.len() >= this.max_unacked {
+            return Poll::Pending;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, msg: NetworkOutbound<C>) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        let seq = this.next_seq;
+        this.next_seq += 1;
+        this.unacked.push_back((seq, msg.clone()));
+
+        // If a reconnect is in progress the message is replayed once it completes
+        if this.reconnecting.is_some() {
+            return Ok(());
+        }
+
+        this.replay_index = this.unacked.len();
+        this.send_inner(ReliableMessage::Data { seq, ack: this.n_received, msg });
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.write_waker = Some(cx.waker().clone());
+        this.poll_replay_and_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::VecDeque,
+        pin::Pin,
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+    };
+
+    use async_trait::async_trait;
+    use futures::{FutureExt, Sink, SinkExt, Stream, StreamExt};
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+    use crate::{
+        algebra::Scalar,
+        error::MpcNetworkError,
+        network::{MpcNetwork, NetworkOutbound, NetworkPayload, PartyId},
+        offline_prep::PartyIDBeaverSource,
+        test_helpers::TestCurve,
+        MpcFabric, PARTY0, PARTY1,
+    };
+
+    use super::{ReconnectingNetwork, ACK_INTERVAL};
+
+    /// A mock connection that drops after a given number of sends, losing the
+    /// message it fails on
+    struct FlakyNetwork {
+        /// The ID of the local party
+        party_id: PartyId,
+        /// The send side of the connection, `None` once dropped
+        send: Option<UnboundedSender<NetworkOutbound<TestCurve>>>,
+        /// The receive side of the connection
+        recv: UnboundedReceiver<NetworkOutbound<TestCurve>>,
+        /// The number of sends before the connection drops
+        sends_until_drop: Option<usize>,
+    }
+
+    impl FlakyNetwork {
+        /// Create a connected pair, party 0's side drops after the given
+        /// number of sends
+        fn new_pair(sends_until_drop: Option<usize>) -> (Self, Self) {
+            let (send0, recv1) = unbounded_channel();
+            let (send1, recv0) = unbounded_channel();
+
+            (
+                Self { party_id: PARTY0, send: Some(send0), recv: recv0, sends_until_drop },
+                Self { party_id: PARTY1, send: Some(send1), recv: recv1, sends_until_drop: None },
+            )
+        }
+    }
+
+    #[async_trait]
+    impl MpcNetwork<TestCurve> for FlakyNetwork {
+        fn party_id(&self) -> PartyId {
+            self.party_id
+        }
+
+        async fn close(&mut self) -> Result<(), MpcNetworkError> {
+            Ok(())
+        }
+    }
+
+    impl Stream for FlakyNetwork {
+        type Item = Result<NetworkOutbound<TestCurve>, MpcNetworkError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.recv.poll_recv(cx).map(|msg| msg.map(Ok))
+        }
+    }
+
+    impl Sink<NetworkOutbound<TestCurve>> for FlakyNetwork {
+        type Error = MpcNetworkError;
+
+        fn poll_ready(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(
+            mut self: Pin<&mut Self>,
+            msg: NetworkOutbound<TestCurve>,
+        ) -> Result<(), Self::Error> {
+            if self.sends_until_drop == Some(0) {
+                // Drop the connection, closing the peer's receive side
+                self.send = None;
+                self.recv.close();
+            }
+            self.sends_until_drop = self.sends_until_drop.map(|n| n.saturating_sub(1));
+
+            let send = self.send.as_ref().ok_or(MpcNetworkError::ConnectionTeardownError)?;
+            send.send(msg).map_err(|err| MpcNetworkError::SendError(err.to_string()))
+        }
+
+        fn poll_flush(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Build a reconnecting network that pops its connections from a queue
+    fn reconnecting_network(
+        mut conns: VecDeque<FlakyNetwork>,
+    ) -> ReconnectingNetwork<TestCurve, FlakyNetwork> {
+        let first = conns.pop_front().unwrap();
+        let conns = Arc::new(Mutex::new(conns));
+
+        ReconnectingNetwork::new(first, move || {
+            let conn = conns.lock().unwrap().pop_front();
+            async move { conn.ok_or(MpcNetworkError::NetworkUninitialized) }
+        })
+    }
+
+    /// Tests a circuit that completes across a dropped connection
+    #[tokio::test]
+    async fn test_reconnect_mid_circuit() {
+        const N_MULS: usize = 20;
+
+        // The first connection drops partway through the circuit
+        let (mut conns0, mut conns1) = (VecDeque::new(), VecDeque::new());
+        for sends_until_drop in [Some(5), None] {
+            let (conn0, conn1) = FlakyNetwork::new_pair(sends_until_drop);
+            conns0.push_back(conn0);
+            conns1.push_back(conn1);
+        }
+
+        let run = |network: ReconnectingNetwork<TestCurve, FlakyNetwork>| async move {
+            let party_id = network.party_id();
+            let fabric = MpcFabric::new(network, PartyIDBeaverSource::new(party_id));
+
+            let mut res = fabric.share_scalar(2u8, PARTY0 /* sender */);
+            let b = fabric.share_scalar(1u8, PARTY1 /* sender */);
+            for _ in 0..N_MULS {
+                res = &res * &b;
+            }
+
+            let res = res.open_authenticated().await;
+            fabric.shutdown();
+            res
+        };
+
+        let party0 = tokio::spawn(run(reconnecting_network(conns0)));
+        let party1 = tokio::spawn(run(reconnecting_network(conns1)));
+
+        let expected = Scalar::from(2u8);
+        assert_eq!(party0.await.unwrap().unwrap(), expected);
+        assert_eq!(party1.await.unwrap().unwrap(), expected);
+    }
+
+    /// Tests that sending blocks while the replay buffer is full, and resumes
+    /// once the receiver acknowledges the buffered messages
+    #[tokio::test]
+    async fn test_unacked_backpressure() {
+        let (conn0, conn1) = FlakyNetwork::new_pair(None /* sends_until_drop */);
+        let mut net0 = reconnecting_network(VecDeque::from([conn0]));
+        let mut net1 = reconnecting_network(VecDeque::from([conn1]));
+        net0.set_max_unacked(ACK_INTERVAL as usize);
+
+        let msg = NetworkOutbound { result_id: 1, payload: NetworkPayload::Bytes(vec![1]) };
+        for _ in 0..ACK_INTERVAL {
+            net0.feed(msg.clone()).await.unwrap();
+        }
+        assert!(net0.feed(msg.clone()).now_or_never().is_none());
+
+        // Receiving the messages acknowledges them, which frees the buffer once
+        // the sender reads the acknowledgement
+        for _ in 0..ACK_INTERVAL {
+            net1.next().await.unwrap().unwrap();
+        }
+        assert!(net1.next().now_or_never().is_none());
+        assert!(net0.next().now_or_never().is_none());
+
+        assert_eq!(net0.unacked.len(), 0);
+        net0.feed(msg).await.unwrap();
+    }
+}
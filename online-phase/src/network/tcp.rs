@@ -0,0 +1,377 @@
+//! Defines an implementation of an `MpcNetwork` over a TCP connection
+//!
+//! Messages are framed on the stream by prepending their length as a
+//! little-endian `u64`
+
+use ark_ec::CurveGroup;
+use async_trait::async_trait;
+use futures::{Future, Sink, Stream};
+use std::{
+    marker::PhantomData,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpListener, TcpStream,
+    },
+};
+use tracing::log;
+
+use crate::{
+    error::{MpcNetworkError, SetupError},
+    PARTY0,
+};
+
+use super::{compression, stream_buffer::BufferWithCursor, MpcNetwork, NetworkOutbound, PartyId};
+
+// -------------
+// | Constants |
+// -------------
+
+/// The number of bytes in a u64
+const BYTES_PER_U64: usize = 8;
+/// The number of times the dialing party attempts to connect to its peer
+const CONNECT_ATTEMPTS: usize = 50;
+/// The delay between connection attempts
+const CONNECT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Error thrown when a stream finishes early
+const ERR_STREAM_FINISHED_EARLY: &str = "stream finished early";
+/// Error message emitted when reading a message length from the stream fails
+const ERR_READ_MESSAGE_LENGTH: &str = "error reading message length from stream";
+/// Error message emitted when the the send `Sink` is not ready
+const ERR_SEND_BUFFER_FULL: &str = "send buffer full";
+
+// ----------------------
+// | TCP Implementation |
+// ----------------------
+
+/// Implements an MpcNetwork on top of a TCP connection
+pub struct TcpMpcNetwork<C: CurveGroup> {
+    /// The index of the local party in the participants
+    party_id: PartyId,
+    /// Whether the network has been bootstrapped yet
+    connected: bool,
+    /// The address of the local peer, the second party listens on this
+    /// address for a connection
+    local_addr: SocketAddr,
+    /// The address of the counterparty in the MPC
+    peer_addr: SocketAddr,
+    /// A buffered message length read from the stream
+    ///
+    /// Reads may be cancelled between the length and the body of a message,
+    /// so the length is buffered to avoid re-reading it from the stream
+    buffered_message_length: Option<u64>,
+    /// A buffered partial message read from the stream
+    buffered_inbound: Option<BufferWithCursor>,
+    /// A buffered partial message written to the stream
+    buffered_outbound: Option<BufferWithCursor>,
+    /// The size in bytes at or above which outbound messages are compressed,
+    /// compression is disabled if `None`
+    compression_threshold: Option<usize>,
+    /// The write half of the TCP stream
+    write_half: Option<OwnedWriteHalf>,
+    /// The read half of the TCP stream
+    read_half: Option<OwnedReadHalf>,
+    /// The phantom on the curve group
+    _phantom: PhantomData<C>,
+}
+
+impl<C: CurveGroup> TcpMpcNetwork<C> {
+    /// Create a new network, do not connect the network yet
+    pub fn new(party_id: PartyId, local_addr: SocketAddr, peer_addr: SocketAddr) -> Self {
+        Self {
+            party_id,
+            local_addr,
+            peer_addr,
+            connected: false,
+            buffered_message_length: None,
+            buffered_inbound: None,
+            buffered_outbound: None,
+            compression_threshold: None,
+            write_half: None,
+            read_half: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Compress outbound messages of at least `threshold` bytes
+    ///
+    /// Both parties must enable compression, as the flag marking compressed
+    /// messages is only part of the wire format when it is enabled
+    #[cfg(feature = "compression")]
+    pub fn enable_compression(&mut self, threshold: usize) {
+        self.compression_threshold = Some(threshold);
+    }
+
+    /// Returns true if the local party is party 0
+    fn local_party0(&self) -> bool {
+        self.party_id == PARTY0
+    }
+
+    /// Returns an error if the network is not connected
+    fn assert_connected(&self) -> Result<(), MpcNetworkError> {
+        if self.connected {
+            Ok(())
+        } else {
+            Err(MpcNetworkError::NetworkUninitialized)
+        }
+    }
+
+    /// Establishes a connection to the peer
+    ///
+    /// The king dials the peer, retrying while the peer's listener comes up,
+    /// and the peer accepts a single connection on its local address
+    pub async fn connect(&mut self) -> Result<(), MpcNetworkError> {
+        let stream = if self.local_party0() { self.dial().await? } else { self.listen().await? };
+        stream.set_nodelay(true).map_err(|err| {
+            MpcNetworkError::ConnectionSetupError(SetupError::TcpError(err.to_string()))
+        })?;
+
+        // Update MpcNet state
+        let (read_half, write_half) = stream.into_split();
+        self.connected = true;
+        self.read_half = Some(read_half);
+        self.write_half = Some(write_half);
+
+        Ok(())
+    }
+
+    /// Dial the peer
+    async fn dial(&self) -> Result<TcpStream, MpcNetworkError> {
+        let mut last_err = None;
+        for _ in 0..CONNECT_ATTEMPTS {
+            match TcpStream::connect(self.peer_addr).await {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_err = Some(err),
+            }
+
+            tokio::time::sleep(CONNECT_RETRY_DELAY).await;
+        }
+
+        let err = last_err.unwrap();
+        log::error!("error connecting to the remote tcp peer: {err}");
+        Err(MpcNetworkError::ConnectionSetupError(SetupError::TcpError(err.to_string())))
+    }
+
+    /// Await a connection from the peer
+    async fn listen(&self) -> Result<TcpStream, MpcNetworkError> {
+        let listener = TcpListener::bind(self.local_addr).await.map_err(|err| {
+            log::error!("error binding tcp listener: {err}");
+            MpcNetworkError::ConnectionSetupError(SetupError::ServerSetupError)
+        })?;
+
+        let (stream, _) = listener.accept().await.map_err(|err| {
+            log::error!("error accepting tcp connection: {err}");
+            MpcNetworkError::ConnectionSetupError(SetupError::NoIncomingConnection)
+        })?;
+
+        Ok(stream)
+    }
+
+    /// Write the current buffer to the stream
+    async fn write_bytes(&mut self) -> Result<(), MpcNetworkError> {
+        // If no pending writes are available, return
+        if self.buffered_outbound.is_none() {
+            return Ok(());
+        }
+
+        // While the outbound buffer has elements remaining, write them
+        let buf = self.buffered_outbound.as_mut().unwrap();
+        while !buf.is_depleted() {
+            let bytes_written = self
+                .write_half
+                .as_mut()
+                .unwrap()
+                .write(buf.get_remaining())
+                .await
+                .map_err(|e| MpcNetworkError::SendError(e.to_string()))?;
+
+            buf.advance_cursor(bytes_written);
+        }
+
+        self.buffered_outbound = None;
+        Ok(())
+    }
+
+    /// Read exactly `n` bytes from the stream
+    async fn read_bytes(&mut self, num_bytes: usize) -> Result<Vec<u8>, MpcNetworkError> {
+        // Allocate a buffer for the next message if one does not already exist
+        if self.buffered_inbound.is_none() {
+            self.buffered_inbound = Some(BufferWithCursor::new(vec![0u8; num_bytes]));
+        }
+
+        // Read until the buffer is full
+        let read_buffer = self.buffered_inbound.as_mut().unwrap();
+        while !read_buffer.is_depleted() {
+            let bytes_read = self
+                .read_half
+                .as_mut()
+                .unwrap()
+                .read(read_buffer.get_remaining())
+                .await
+                .map_err(|e| MpcNetworkError::RecvError(e.to_string()))?;
+
+            // A zero length read indicates the peer closed the stream
+            if bytes_read == 0 {
+                return Err(MpcNetworkError::RecvError(ERR_STREAM_FINISHED_EARLY.to_string()));
+            }
+
+            read_buffer.advance_cursor(bytes_read);
+        }
+
+        // Take ownership of the buffer, and reset the buffered message to `None`
+        Ok(self.buffered_inbound.take().unwrap().into_vec())
+    }
+
+    /// Read a message length from the stream
+    async fn read_message_length(&mut self) -> Result<u64, MpcNetworkError> {
+        let read_buffer = self.read_bytes(BYTES_PER_U64).await?;
+        Ok(u64::from_le_bytes(read_buffer.try_into().map_err(|_| {
+            MpcNetworkError::SerializationError(ERR_READ_MESSAGE_LENGTH.to_string())
+        })?))
+    }
+
+    /// Receive a message from the peer
+    async fn receive_message(&mut self) -> Result<NetworkOutbound<C>, MpcNetworkError> {
+        // Read the message length from the buffer if available
+        if self.buffered_message_length.is_none() {
+            self.buffered_message_length = Some(self.read_message_length().await?);
+        }
+
+        // Read the data from the stream
+        let len = self.buffered_message_length.unwrap();
+        let bytes = self.read_bytes(len as usize).await?;
+
+        // Reset the message length buffer after the data has been pulled from the
+        // stream
+        self.buffered_message_length = None;
+
+        // Deserialize the message
+        compression::deserialize_message(&bytes, self.compression_threshold.is_some())
+    }
+}
+
+#[async_trait]
+impl<C: CurveGroup> MpcNetwork<C> for TcpMpcNetwork<C>
+where
+    C: Unpin,
+{
+    fn party_id(&self) -> PartyId {
+        self.party_id
+    }
+
+    async fn close(&mut self) -> Result<(), MpcNetworkError> {
+        self.assert_connected()?;
+
+        self.write_half
+            .as_mut()
+            .unwrap()
+            .shutdown()
+            .await
+            .map_err(|_| MpcNetworkError::ConnectionTeardownError)
+    }
+}
+
+impl<C: CurveGroup> Stream for TcpMpcNetwork<C>
+where
+    C: Unpin,
+{
+    type Item = Result<NetworkOutbound<C>, MpcNetworkError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Box::pin(self.get_mut().receive_message()).as_mut().poll(cx).map(Some)
+    }
+}
+
+impl<C: CurveGroup> Sink<NetworkOutbound<C>> for TcpMpcNetwork<C>
+where
+    C: Unpin,
+{
+    type Error = MpcNetworkError;
+
+    fn start_send(self: Pin<&mut Self>, msg: NetworkOutbound<C>) -> Result<(), Self::Error> {
+        if !self.connected {
+            return Err(MpcNetworkError::NetworkUninitialized);
+        }
+
+        // Must call `poll_flush` before calling `start_send` again
+        if self.buffered_outbound.is_some() {
+            return Err(MpcNetworkError::SendError(ERR_SEND_BUFFER_FULL.to_string()));
+        }
+
+        // Serialize the message and buffer it for writing
+        let bytes = compression::serialize_message(&msg, self.compression_threshold)?;
+        let mut payload = (bytes.len() as u64).to_le_bytes().to_vec();
+        payload.extend_from_slice(&bytes);
+
+        self.get_mut().buffered_outbound = Some(BufferWithCursor::new(payload));
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Poll the write future
+        Box::pin(self.write_bytes()).as_mut().poll(cx)
+    }
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The network is ready to send once the previous message is flushed
+        self.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{SocketAddr, TcpListener};
+
+    use crate::{
+        algebra::Scalar, offline_prep::PartyIDBeaverSource, test_helpers::TestCurve, MpcFabric,
+        PARTY0, PARTY1,
+    };
+
+    use super::TcpMpcNetwork;
+
+    /// Find a free local address to listen on
+    fn free_addr() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+    }
+
+    /// Run one party of a multiplication over a TCP network
+    async fn multiply_over_tcp(
+        party_id: u64,
+        local_addr: SocketAddr,
+        peer_addr: SocketAddr,
+    ) -> Scalar<TestCurve> {
+        let mut network = TcpMpcNetwork::new(party_id, local_addr, peer_addr);
+        network.connect().await.unwrap();
+        let fabric = MpcFabric::new(network, PartyIDBeaverSource::new(party_id));
+
+        let a = fabric.share_scalar(2u8, PARTY0 /* sender */);
+        let b = fabric.share_scalar(3u8, PARTY1 /* sender */);
+        let res = (a * b).open_authenticated().await.unwrap();
+
+        fabric.shutdown();
+        res
+    }
+
+    /// Tests a multiplication between two fabrics connected over localhost TCP
+    #[tokio::test]
+    async fn test_tcp_multiplication() {
+        let (addr0, addr1) = (free_addr(), free_addr());
+        let party0 = tokio::spawn(multiply_over_tcp(PARTY0, addr0, addr1));
+        let party1 = tokio::spawn(multiply_over_tcp(PARTY1, addr1, addr0));
+
+        let expected = Scalar::from(6u8);
+        assert_eq!(party0.await.unwrap(), expected);
+        assert_eq!(party1.await.unwrap(), expected);
+    }
+}
@@ -2,9 +2,26 @@
 //! as well as a dummy beaver interface for testing
 
 use ark_ec::CurveGroup;
+use async_trait::async_trait;
 use itertools::Itertools;
 
-use crate::algebra::{Scalar, ScalarShare};
+use crate::{
+    algebra::{Scalar, ScalarShare},
+    error::PreprocessingError,
+};
+
+mod buffered;
+mod file;
+mod ot;
+#[cfg(any(feature = "test_helpers", test))]
+mod seeded;
+mod stats;
+pub use buffered::{BufferedBeaverSource, PrefetchConfig};
+pub use file::{FileBeaverSource, PreprocessingValues};
+pub use ot::OtTripleSource;
+#[cfg(any(feature = "test_helpers", test))]
+pub use seeded::SeededBeaverSource;
+pub use stats::{PreprocessingCounters, PreprocessingStats, StatsBeaverSource};
 
 /// PreprocessingPhase implements both the functionality for:
 ///     1. Input authentication and sharing
@@ -48,6 +65,17 @@ pub trait PreprocessingPhase<C: CurveGroup>: Send + Sync {
     fn next_shared_value_batch(&mut self, num_values: usize) -> Vec<ScalarShare<C>> {
         (0..num_values).map(|_| self.next_shared_value()).collect_vec()
     }
+    /// Fetch a batch of shared single values, or an error if the source
+    /// cannot supply the whole batch
+    ///
+    /// Sources with a finite supply of values should override this method so
+    /// that no values are consumed when the batch cannot be supplied
+    fn try_next_shared_value_batch(
+        &mut self,
+        num_values: usize,
+    ) -> Result<Vec<ScalarShare<C>>, PreprocessingError> {
+        Ok(self.next_shared_value_batch(num_values))
+    }
     /// Fetch the next pair of values that are multiplicative inverses of one
     /// another
     fn next_shared_inverse_pair(&mut self) -> (ScalarShare<C>, ScalarShare<C>);
@@ -58,6 +86,25 @@ pub trait PreprocessingPhase<C: CurveGroup>: Send + Sync {
     ) -> (Vec<ScalarShare<C>>, Vec<ScalarShare<C>>) {
         (0..num_pairs).map(|_| self.next_shared_inverse_pair()).unzip()
     }
+    /// Fetch the next random value and its square
+    ///
+    /// Returns `None` if the source does not supply square pairs, in which
+    /// case the fabric derives each pair from a beaver triplet, at the cost of
+    /// one opening
+    fn next_shared_square_pair(&mut self) -> Option<(ScalarShare<C>, ScalarShare<C>)> {
+        None
+    }
+    /// Fetch the next batch of random values and their squares
+    #[allow(clippy::type_complexity)]
+    fn next_shared_square_pair_batch(
+        &mut self,
+        num_pairs: usize,
+    ) -> Option<(Vec<ScalarShare<C>>, Vec<ScalarShare<C>>)> {
+        (0..num_pairs)
+            .map(|_| self.next_shared_square_pair())
+            .collect::<Option<Vec<_>>>()
+            .map(|pairs| pairs.into_iter().unzip())
+    }
     /// Fetch the next beaver triplet
     fn next_triplet(&mut self) -> (ScalarShare<C>, ScalarShare<C>, ScalarShare<C>);
     /// Fetch a batch of beaver triplets
@@ -79,6 +126,58 @@ pub trait PreprocessingPhase<C: CurveGroup>: Send + Sync {
 
         (a_vals, b_vals, c_vals)
     }
+    /// Fetch the next beaver triplet, or an error if the source is exhausted
+    ///
+    /// Sources with a finite supply of triplets should override this method,
+    /// the default never fails
+    #[allow(clippy::type_complexity)]
+    fn try_next_triplet(
+        &mut self,
+    ) -> Result<(ScalarShare<C>, ScalarShare<C>, ScalarShare<C>), PreprocessingError> {
+        Ok(self.next_triplet())
+    }
+    /// Fetch a batch of beaver triplets, or an error if the source cannot
+    /// supply the whole batch
+    ///
+    /// Sources with a finite supply of triplets should override this method
+    /// so that no triplets are consumed when the batch cannot be supplied
+    #[allow(clippy::type_complexity)]
+    fn try_next_triplet_batch(
+        &mut self,
+        num_triplets: usize,
+    ) -> Result<(Vec<ScalarShare<C>>, Vec<ScalarShare<C>>, Vec<ScalarShare<C>>), PreprocessingError>
+    {
+        Ok(self.next_triplet_batch(num_triplets))
+    }
+}
+
+/// An asynchronous source of beaver triples, for sources that wait on the
+/// network or on disk to produce them
+///
+/// A fabric given an asynchronous source requests triples from it in a
+/// background task, so that fetching triples does not stall the circuit
+#[async_trait]
+pub trait AsyncPreprocessingPhase<C: CurveGroup>: Send {
+    /// Fetch a batch of beaver triplets, or an error if the source cannot
+    /// supply the whole batch
+    #[allow(clippy::type_complexity)]
+    async fn fetch_triplet_batch(
+        &mut self,
+        num_triplets: usize,
+    ) -> Result<(Vec<ScalarShare<C>>, Vec<ScalarShare<C>>, Vec<ScalarShare<C>>), PreprocessingError>;
+}
+
+/// Any synchronous source is an asynchronous source whose futures resolve
+/// immediately
+#[async_trait]
+impl<C: CurveGroup, S: PreprocessingPhase<C>> AsyncPreprocessingPhase<C> for S {
+    async fn fetch_triplet_batch(
+        &mut self,
+        num_triplets: usize,
+    ) -> Result<(Vec<ScalarShare<C>>, Vec<ScalarShare<C>>, Vec<ScalarShare<C>>), PreprocessingError>
+    {
+        self.try_next_triplet_batch(num_triplets)
+    }
 }
 
 /// An implementation of a beaver value source that returns
@@ -97,6 +196,24 @@ impl PartyIDBeaverSource {
         assert!(party_id == 0 || party_id == 1);
         Self { party_id }
     }
+
+    /// Create a new beaver source for one of `num_parties` parties
+    ///
+    /// Only the MAC key and the input masks are valid sharings for more than
+    /// two parties, the other preprocessed values assume a 2PC
+    pub fn new_multi_party(party_id: u64, num_parties: usize) -> Self {
+        assert!((party_id as usize) < num_parties);
+        Self { party_id }
+    }
+
+    /// Share an input mask, party 1 holds the whole mask and each party holds
+    /// its party ID times the mask as a MAC share
+    fn input_mask_share<C: CurveGroup>(&self, mask: Scalar<C>) -> ScalarShare<C> {
+        let share = if self.party_id == 1 { mask } else { Scalar::zero() };
+        let mac = Scalar::from(self.party_id) * mask;
+
+        ScalarShare::new(share, mac)
+    }
 }
 
 /// The PartyIDBeaverSource returns beaver triplets split statically between the
@@ -112,20 +229,12 @@ impl<C: CurveGroup> PreprocessingPhase<C> for PartyIDBeaverSource {
     }
 
     fn next_local_input_mask(&mut self) -> (Scalar<C>, ScalarShare<C>) {
-        let party = Scalar::from(self.party_id);
         let value = Scalar::from(3u8);
-        let share = party * value;
-        let mac = party * value;
-
-        (value, ScalarShare::new(share, mac))
+        (value, self.input_mask_share(value))
     }
 
     fn next_counterparty_input_mask(&mut self) -> ScalarShare<C> {
-        let party = Scalar::from(self.party_id);
-        let value = Scalar::from(3u8) * party;
-        let mac = party * value;
-
-        ScalarShare::new(value, mac)
+        self.input_mask_share(Scalar::from(3u8))
     }
 
     fn next_shared_bit(&mut self) -> ScalarShare<C> {
@@ -167,4 +276,9 @@ impl<C: CurveGroup> PreprocessingPhase<C> for PartyIDBeaverSource {
     fn next_shared_value(&mut self) -> ScalarShare<C> {
         ScalarShare::new(Scalar::from(self.party_id), Scalar::from(self.party_id))
     }
+
+    fn next_shared_square_pair(&mut self) -> Option<(ScalarShare<C>, ScalarShare<C>)> {
+        let one = ScalarShare::new(Scalar::from(self.party_id), Scalar::from(self.party_id));
+        Some((one, one))
+    }
 }
@@ -0,0 +1,455 @@
+//! Defines a preprocessing source that prefetches values from another source
+//! on a background thread
+//!
+//! Triples and shared values are fetched from the underlying source in
+//! batches and served from in-memory buffers, so that a slow source, e.g. one
+//! generating values over the network, does not stall the circuit each time a
+//! value is consumed. Other kinds of values are read from the underlying
+//! source directly
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
+};
+
+use ark_ec::CurveGroup;
+use itertools::{multiunzip, multizip, Itertools};
+
+use crate::{
+    algebra::{Scalar, ScalarShare},
+    error::PreprocessingError,
+};
+
+use super::PreprocessingPhase;
+
+/// A beaver triple
+type Triple<C> = (ScalarShare<C>, ScalarShare<C>, ScalarShare<C>);
+
+/// Error message emitted when the underlying source is poisoned
+const ERR_SOURCE_POISONED: &str = "underlying source poisoned";
+/// Error message emitted when the prefetch thread stops while a value is
+/// awaited, i.e. the underlying source panicked
+const ERR_WORKER_STOPPED: &str = "prefetch thread stopped";
+
+/// The configuration of the prefetching of a `BufferedBeaverSource`
+#[derive(Clone, Copy, Debug)]
+pub struct PrefetchConfig {
+    /// The number of values fetched from the underlying source at once
+    pub batch_size: usize,
+    /// The number of buffered values of a kind below which the buffer is
+    /// refilled
+    pub low_water: usize,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self { batch_size: 1024, low_water: 256 }
+    }
+}
+
+/// A buffer of prefetched values of one kind
+struct Buffer<T> {
+    /// The prefetched values, in the order the underlying source served them
+    values: VecDeque<T>,
+    /// The number of values a consumer is waiting for
+    demand: usize,
+    /// The error the underlying source returned when it ran out of values
+    error: Option<PreprocessingError>,
+}
+
+impl<T> Buffer<T> {
+    /// Create an empty buffer
+    fn new() -> Self {
+        Self { values: VecDeque::new(), demand: 0, error: None }
+    }
+
+    /// The number of values to fetch to refill the buffer, zero if it need
+    /// not be refilled
+    fn refill_size(&self, config: &PrefetchConfig) -> usize {
+        let target = usize::max(config.low_water, self.demand);
+        if self.error.is_some() || self.values.len() >= target {
+            return 0;
+        }
+
+        usize::max(config.batch_size, target - self.values.len())
+    }
+}
+
+/// The buffers shared between the source and its prefetch thread
+struct Buffers<C: CurveGroup> {
+    /// The prefetched triples
+    triples: Buffer<Triple<C>>,
+    /// The prefetched shared values
+    shared_values: Buffer<ScalarShare<C>>,
+    /// Whether the source has been dropped and the prefetch thread should exit
+    shutdown: bool,
+    /// Whether the prefetch thread has exited
+    worker_stopped: bool,
+}
+
+/// The state shared between the source and its prefetch thread
+struct SharedState<C: CurveGroup> {
+    /// The prefetched values
+    buffers: Mutex<Buffers<C>>,
+    /// Notifies the prefetch thread that a buffer may need refilling
+    refill: Condvar,
+    /// Notifies consumers that values have been added to a buffer
+    filled: Condvar,
+}
+
+impl<C: CurveGroup> SharedState<C> {
+    /// Lock the buffers
+    ///
+    /// The buffers are never left inconsistent by a panic, so poisoning is
+    /// ignored
+    fn lock(&self) -> MutexGuard<'_, Buffers<C>> {
+        self.buffers.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Marks the prefetch thread as stopped when it exits, including by a panic
+/// in the underlying source, so that consumers do not wait on it forever
+struct WorkerGuard<C: CurveGroup>(Arc<SharedState<C>>);
+
+impl<C: CurveGroup> Drop for WorkerGuard<C> {
+    fn drop(&mut self) {
+        self.0.lock().worker_stopped = true;
+        self.0.filled.notify_all();
+    }
+}
+
+/// A preprocessing source that prefetches triples and shared values from an
+/// underlying source on a background thread
+///
+/// Values are served in the same order as the underlying source serves them
+pub struct BufferedBeaverSource<C: CurveGroup, S: PreprocessingPhase<C>> {
+    /// The underlying source
+    source: Arc<Mutex<S>>,
+    /// The local party's share of the MAC key
+    mac_key_share: Scalar<C>,
+    /// The prefetch configuration
+    config: PrefetchConfig,
+    /// The state shared with the prefetch thread
+    state: Arc<SharedState<C>>,
+    /// The prefetch thread
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<C: CurveGroup, S: 'static + PreprocessingPhase<C>> BufferedBeaverSource<C, S> {
+    /// Wrap the given source with the default prefetch configuration
+    pub fn new(source: S) -> Self {
+        Self::new_with_config(source, PrefetchConfig::default())
+    }
+
+    /// Wrap the given source with the given prefetch configuration
+    pub fn new_with_config(source: S, config: PrefetchConfig) -> Self {
+        assert!(config.batch_size > 0, "prefetch batch size must be non-zero");
+
+        let mac_key_share = source.get_mac_key_share();
+        let source = Arc::new(Mutex::new(source));
+        let state = Arc::new(SharedState {
+            buffers: Mutex::new(Buffers {
+                triples: Buffer::new(),
+                shared_values: Buffer::new(),
+                shutdown: false,
+                worker_stopped: false,
+            }),
+            refill: Condvar::new(),
+            filled: Condvar::new(),
+        });
+
+        let worker_source = source.clone();
+        let worker_state = state.clone();
+        let worker = thread::spawn(move || prefetch_loop(worker_source, worker_state, config));
+
+        Self { source, mac_key_share, config, state, worker: Some(worker) }
+    }
+
+    /// Lock the underlying source
+    fn source(&self) -> MutexGuard<'_, S> {
+        self.source.lock().expect(ERR_SOURCE_POISONED)
+    }
+
+    /// Take `n` values from the buffer selected by `buffer`, waiting for the
+    /// prefetch thread to fill it if necessary
+    ///
+    /// No values are taken if the underlying source cannot supply all `n`
+    fn take<T>(
+        &self,
+        n: usize,
+        buffer: impl Fn(&mut Buffers<C>) -> &mut Buffer<T>,
+    ) -> Result<Vec<T>, PreprocessingError> {
+        let mut buffers = self.state.lock();
+        loop {
+            let buf = buffer(&mut buffers);
+            if buf.values.len() >= n {
+                buf.demand = 0;
+                let values = buf.values.drain(..n).collect_vec();
+                if buf.values.len() < self.config.low_water {
+                    self.state.refill.notify_one();
+                }
+
+                return Ok(values);
+            }
+
+            if let Some(err) = &buf.error {
+                return Err(err.clone());
+            }
+
+            buf.demand = n;
+            if buffers.worker_stopped {
+                panic!("{ERR_WORKER_STOPPED}");
+            }
+
+            self.state.refill.notify_one();
+            buffers = self.state.filled.wait(buffers).unwrap_or_else(|err| err.into_inner());
+        }
+    }
+
+    /// Take `n` triples from the buffer
+    fn take_triples(&self, n: usize) -> Result<Vec<Triple<C>>, PreprocessingError> {
+        self.take(n, |buffers| &mut buffers.triples)
+    }
+
+    /// Take `n` shared values from the buffer
+    fn take_shared_values(&self, n: usize) -> Result<Vec<ScalarShare<C>>, PreprocessingError> {
+        self.take(n, |buffers| &mut buffers.shared_values)
+    }
+}
+
+impl<C: CurveGroup, S: PreprocessingPhase<C>> Drop for BufferedBeaverSource<C, S> {
+    fn drop(&mut self) {
+        self.state.lock().shutdown = true;
+        self.state.refill.notify_all();
+        if let Some(worker) = self.worker.take() {
+            // A panic in the prefetch thread has already been reported
+            let _ = worker.join();
+        }
+    }
+}
+
+/// The body of the prefetch thread, refilling buffers that fall below the low
+/// water mark until the source is dropped
+fn prefetch_loop<C: CurveGroup, S: PreprocessingPhase<C>>(
+    source: Arc<Mutex<S>>,
+    state: Arc<SharedState<C>>,
+    config: PrefetchConfig,
+) {
+    let _guard = WorkerGuard(state.clone());
+    loop {
+        // Wait for a buffer to need refilling
+        let (n_triples, n_values) = {
+            let mut buffers = state.lock();
+            loop {
+                if buffers.shutdown {
+                    return;
+                }
+
+                let n_triples = buffers.triples.refill_size(&config);
+                let n_values = buffers.shared_values.refill_size(&config);
+                if n_triples > 0 || n_values > 0 {
+                    break (n_triples, n_values);
+                }
+
+                buffers = state.refill.wait(buffers).unwrap_or_else(|err| err.into_inner());
+            }
+        };
+
+        // Fetch without holding the buffers so that consumers are not blocked
+        let mut source = source.lock().expect(ERR_SOURCE_POISONED);
+        let triples = fetch(n_triples, |n| {
+            source.try_next_triplet_batch(n).map(|(a, b, c)| multizip((a, b, c)).collect())
+        });
+        let values = fetch(n_values, |n| source.try_next_shared_value_batch(n));
+        drop(source);
+
+        let mut buffers = state.lock();
+        buffers.triples.extend(triples);
+        buffers.shared_values.extend(values);
+        state.filled.notify_all();
+    }
+}
+
+impl<T> Buffer<T> {
+    /// Add the result of a fetch to the buffer
+    fn extend(&mut self, (values, error): (Vec<T>, Option<PreprocessingError>)) {
+        self.values.extend(values);
+        if error.is_some() {
+            self.error = error;
+        }
+    }
+}
+
+/// Fetch `n` values with the given batch fetch, returning as many values as
+/// the source can supply and the error it returned if it ran out
+fn fetch<T>(
+    n: usize,
+    mut fetch_batch: impl FnMut(usize) -> Result<Vec<T>, PreprocessingError>,
+) -> (Vec<T>, Option<PreprocessingError>) {
+    if n == 0 {
+        return (Vec::new(), None);
+    }
+
+    match fetch_batch(n) {
+        Ok(batch) => (batch, None),
+        // Fall back to fetching values one at a time to drain the source
+        Err(_) => {
+            let mut values = Vec::new();
+            loop {
+                match fetch_batch(1) {
+                    Ok(batch) => values.extend(batch),
+                    Err(err) => return (values, Some(err)),
+                }
+            }
+        },
+    }
+}
+
+impl<C: CurveGroup, S: 'static + PreprocessingPhase<C>> PreprocessingPhase<C>
+    for BufferedBeaverSource<C, S>
+{
+    fn get_mac_key_share(&self) -> Scalar<C> {
+        self.mac_key_share
+    }
+
+    fn next_local_input_mask(&mut self) -> (Scalar<C>, ScalarShare<C>) {
+        self.source().next_local_input_mask()
+    }
+
+    fn next_local_input_mask_batch(
+        &mut self,
+        num_values: usize,
+    ) -> (Vec<Scalar<C>>, Vec<ScalarShare<C>>) {
+        self.source().next_local_input_mask_batch(num_values)
+    }
+
+    fn next_counterparty_input_mask(&mut self) -> ScalarShare<C> {
+        self.source().next_counterparty_input_mask()
+    }
+
+    fn next_counterparty_input_mask_batch(&mut self, num_values: usize) -> Vec<ScalarShare<C>> {
+        self.source().next_counterparty_input_mask_batch(num_values)
+    }
+
+    fn next_shared_bit(&mut self) -> ScalarShare<C> {
+        self.source().next_shared_bit()
+    }
+
+    fn next_shared_bit_batch(&mut self, num_values: usize) -> Vec<ScalarShare<C>> {
+        self.source().next_shared_bit_batch(num_values)
+    }
+
+    fn next_shared_value(&mut self) -> ScalarShare<C> {
+        self.next_shared_value_batch(1).pop().unwrap()
+    }
+
+    fn next_shared_value_batch(&mut self, num_values: usize) -> Vec<ScalarShare<C>> {
+        self.take_shared_values(num_values).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    fn try_next_shared_value_batch(
+        &mut self,
+        num_values: usize,
+    ) -> Result<Vec<ScalarShare<C>>, PreprocessingError> {
+        self.take_shared_values(num_values)
+    }
+
+    fn next_shared_inverse_pair(&mut self) -> (ScalarShare<C>, ScalarShare<C>) {
+        self.source().next_shared_inverse_pair()
+    }
+
+    fn next_shared_inverse_pair_batch(
+        &mut self,
+        num_pairs: usize,
+    ) -> (Vec<ScalarShare<C>>, Vec<ScalarShare<C>>) {
+        self.source().next_shared_inverse_pair_batch(num_pairs)
+    }
+
+    fn next_shared_square_pair(&mut self) -> Option<(ScalarShare<C>, ScalarShare<C>)> {
+        self.source().next_shared_square_pair()
+    }
+
+    fn next_shared_square_pair_batch(
+        &mut self,
+        num_pairs: usize,
+    ) -> Option<(Vec<ScalarShare<C>>, Vec<ScalarShare<C>>)> {
+        self.source().next_shared_square_pair_batch(num_pairs)
+    }
+
+    fn next_triplet(&mut self) -> Triple<C> {
+        self.try_next_triplet().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    fn next_triplet_batch(
+        &mut self,
+        num_triplets: usize,
+    ) -> (Vec<ScalarShare<C>>, Vec<ScalarShare<C>>, Vec<ScalarShare<C>>) {
+        self.try_next_triplet_batch(num_triplets).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    fn try_next_triplet(&mut self) -> Result<Triple<C>, PreprocessingError> {
+        self.take_triples(1).map(|mut triples| triples.pop().unwrap())
+    }
+
+    fn try_next_triplet_batch(
+        &mut self,
+        num_triplets: usize,
+    ) -> Result<(Vec<ScalarShare<C>>, Vec<ScalarShare<C>>, Vec<ScalarShare<C>>), PreprocessingError>
+    {
+        self.take_triples(num_triplets).map(multiunzip)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use itertools::multizip;
+    use rand::thread_rng;
+
+    use crate::{
+        algebra::{Scalar, ScalarShare},
+        error::PreprocessingError,
+        offline_prep::{FileBeaverSource, PreprocessingPhase, PreprocessingValues},
+        test_helpers::TestCurve,
+    };
+
+    use super::{BufferedBeaverSource, PrefetchConfig};
+
+    /// Sample a random share
+    fn random_share() -> ScalarShare<TestCurve> {
+        let mut rng = thread_rng();
+        ScalarShare::new(Scalar::random(&mut rng), Scalar::random(&mut rng))
+    }
+
+    /// Tests that the buffered source serves the same values as the underlying
+    /// source, in order, and reports its exhaustion
+    #[test]
+    fn test_buffered_values_in_order() {
+        const N: usize = 50;
+        let mut values = PreprocessingValues::new(Scalar::random(&mut thread_rng()));
+        values.shared_values = (0..N).map(|_| random_share()).collect();
+        values.triples = (0..N).map(|_| (random_share(), random_share(), random_share())).collect();
+
+        let config = PrefetchConfig { batch_size: 8, low_water: 4 };
+        let underlying = FileBeaverSource::from_values(values.clone());
+        let mut source = BufferedBeaverSource::new_with_config(underlying, config);
+        assert_eq!(source.get_mac_key_share(), values.mac_key_share);
+
+        // Consume singly and in batches larger than the prefetch batch
+        let mut triples = vec![source.next_triplet()];
+        let (a, b, c) = source.next_triplet_batch(20);
+        triples.extend(multizip((a, b, c)));
+        while triples.len() < N {
+            triples.push(source.next_triplet());
+        }
+        assert_eq!(triples, values.triples);
+
+        let mut shared_values = source.next_shared_value_batch(N - 1);
+        shared_values.push(source.next_shared_value());
+        assert_eq!(shared_values, values.shared_values);
+
+        let exhausted = |kind: &str| PreprocessingError::Exhausted(kind.to_string());
+        assert_eq!(source.try_next_triplet().unwrap_err(), exhausted("triples"));
+        assert_eq!(source.try_next_shared_value_batch(1).unwrap_err(), exhausted("shared values"));
+    }
+}
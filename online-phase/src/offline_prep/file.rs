@@ -0,0 +1,503 @@
+//! Defines a preprocessing source that serves values precomputed in the offline
+//! phase and stored in a file
+//!
+//! A file holds a header followed by the values of each kind in turn.
+//! Integers are encoded little endian and scalars big endian, padded to the
+//! width of the scalar field:
+//!
+//! ```text
+//! magic (8 bytes) | version (u8) | scalar width (u32) | mac key share
+//! | number of each kind of value (6 x u64)
+//! | local input masks | counterparty input masks | shared bits
+//! | shared values | inverse pairs | triples
+//! ```
+
+use std::{fs, path::Path, vec::IntoIter};
+
+use ark_ec::CurveGroup;
+
+use crate::{
+    algebra::{n_bytes_field, Scalar, ScalarShare},
+    error::PreprocessingError,
+};
+
+use super::PreprocessingPhase;
+
+/// The magic bytes at the start of a preprocessing file
+const MAGIC: &[u8; 8] = b"ARKMPCPP";
+/// The version of the file format
+const VERSION: u8 = 1;
+
+/// Error message emitted when a file does not begin with the magic bytes
+const ERR_BAD_MAGIC: &str = "not a preprocessing file";
+/// Error message emitted when a file has an unsupported version
+const ERR_BAD_VERSION: &str = "unsupported preprocessing file version";
+/// Error message emitted when a file's scalars are not of the curve's width
+const ERR_SCALAR_WIDTH: &str = "scalar width does not match the curve";
+/// Error message emitted when a file ends before all its values are read
+const ERR_TRUNCATED: &str = "file is shorter than its value counts";
+/// Error message emitted when a file has bytes after its values
+const ERR_TRAILING_BYTES: &str = "file is longer than its value counts";
+/// Error message emitted when a file's value counts overflow its length
+const ERR_COUNT_OVERFLOW: &str = "value counts overflow the file length";
+
+/// The name of the triples in a preprocessing file, used in exhaustion errors
+const TRIPLES: &str = "triples";
+/// The name of the shared values in a preprocessing file, used in exhaustion
+/// errors
+const SHARED_VALUES: &str = "shared values";
+
+/// A set of values generated in the offline phase, in the form written to and
+/// read from a preprocessing file
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::type_complexity)]
+pub struct PreprocessingValues<C: CurveGroup> {
+    /// The local party's share of the MAC key
+    pub mac_key_share: Scalar<C>,
+    /// Input masks held in the clear by the local party, with the local
+    /// party's share of each
+    pub local_input_masks: Vec<(Scalar<C>, ScalarShare<C>)>,
+    /// Shares of the input masks held in the clear by the counterparty
+    pub counterparty_input_masks: Vec<ScalarShare<C>>,
+    /// Shares of random bits
+    pub shared_bits: Vec<ScalarShare<C>>,
+    /// Shares of random values
+    pub shared_values: Vec<ScalarShare<C>>,
+    /// Shares of random values and their multiplicative inverses
+    pub inverse_pairs: Vec<(ScalarShare<C>, ScalarShare<C>)>,
+    /// Shares of Beaver triples
+    pub triples: Vec<(ScalarShare<C>, ScalarShare<C>, ScalarShare<C>)>,
+}
+
+impl<C: CurveGroup> PreprocessingValues<C> {
+    /// Create an empty set of values under the given MAC key share
+    pub fn new(mac_key_share: Scalar<C>) -> Self {
+        Self {
+            mac_key_share,
+            local_input_masks: Vec::new(),
+            counterparty_input_masks: Vec::new(),
+            shared_bits: Vec::new(),
+            shared_values: Vec::new(),
+            inverse_pairs: Vec::new(),
+            triples: Vec::new(),
+        }
+    }
+
+    /// Serialize the values in the preprocessing file format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.bytes.push(VERSION);
+        writer.bytes.extend_from_slice(&(n_bytes_field::<C::ScalarField>() as u32).to_le_bytes());
+        writer.scalar(&self.mac_key_share);
+
+        for count in [
+            self.local_input_masks.len(),
+            self.counterparty_input_masks.len(),
+            self.shared_bits.len(),
+            self.shared_values.len(),
+            self.inverse_pairs.len(),
+            self.triples.len(),
+        ] {
+            writer.bytes.extend_from_slice(&(count as u64).to_le_bytes());
+        }
+
+        for (mask, share) in self.local_input_masks.iter() {
+            writer.scalar(mask);
+            writer.share(share);
+        }
+        self.counterparty_input_masks.iter().for_each(|share| writer.share(share));
+        self.shared_bits.iter().for_each(|share| writer.share(share));
+        self.shared_values.iter().for_each(|share| writer.share(share));
+        for (value, inverse) in self.inverse_pairs.iter() {
+            writer.share(value);
+            writer.share(inverse);
+        }
+        for (a, b, c) in self.triples.iter() {
+            writer.share(a);
+            writer.share(b);
+            writer.share(c);
+        }
+
+        writer.bytes
+    }
+
+    /// Deserialize values in the preprocessing file format, validating that
+    /// the file holds exactly the number of values its header declares
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PreprocessingError> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid_format(ERR_BAD_MAGIC));
+        }
+        if reader.take(1)?[0] != VERSION {
+            return Err(invalid_format(ERR_BAD_VERSION));
+        }
+
+        let width = u32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+        if width as usize != n_bytes_field::<C::ScalarField>() {
+            return Err(invalid_format(ERR_SCALAR_WIDTH));
+        }
+
+        let mac_key_share = reader.scalar()?;
+        let mut counts = [0usize; 6];
+        for count in counts.iter_mut() {
+            *count =
+                usize::try_from(reader.u64()?).map_err(|_| invalid_format(ERR_COUNT_OVERFLOW))?;
+        }
+        let [n_local, n_counterparty, n_bits, n_values, n_inverses, n_triples] = counts;
+
+        // Check the length up front so that a corrupt count cannot trigger a
+        // huge allocation
+        let expected_len = Self::expected_len(counts, width as usize)
+            .ok_or_else(|| invalid_format(ERR_COUNT_OVERFLOW))?;
+        match reader.bytes.len().cmp(&expected_len) {
+            std::cmp::Ordering::Less => return Err(invalid_format(ERR_TRUNCATED)),
+            std::cmp::Ordering::Greater => return Err(invalid_format(ERR_TRAILING_BYTES)),
+            std::cmp::Ordering::Equal => {},
+        }
+
+        let mut values = Self::new(mac_key_share);
+        for _ in 0..n_local {
+            values.local_input_masks.push((reader.scalar()?, reader.share()?));
+        }
+        for _ in 0..n_counterparty {
+            values.counterparty_input_masks.push(reader.share()?);
+        }
+        for _ in 0..n_bits {
+            values.shared_bits.push(reader.share()?);
+        }
+        for _ in 0..n_values {
+            values.shared_values.push(reader.share()?);
+        }
+        for _ in 0..n_inverses {
+            values.inverse_pairs.push((reader.share()?, reader.share()?));
+        }
+        for _ in 0..n_triples {
+            values.triples.push((reader.share()?, reader.share()?, reader.share()?));
+        }
+
+        Ok(values)
+    }
+
+    /// The length of the values section of a file with the given counts and
+    /// scalar width, or `None` if the length overflows
+    fn expected_len(counts: [usize; 6], width: usize) -> Option<usize> {
+        // The number of scalars in each value of the given kinds, in file order
+        const SCALARS_PER_VALUE: [usize; 6] = [3, 2, 2, 2, 4, 6];
+
+        let mut n_scalars = 0usize;
+        for (count, n) in counts.into_iter().zip(SCALARS_PER_VALUE) {
+            n_scalars = n_scalars.checked_add(count.checked_mul(n)?)?;
+        }
+
+        n_scalars.checked_mul(width)
+    }
+
+    /// Write the values to a preprocessing file
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> Result<(), PreprocessingError> {
+        fs::write(path, self.to_bytes()).map_err(|err| PreprocessingError::IoError(err.to_string()))
+    }
+
+    /// Read the values from a preprocessing file
+    pub fn read_file<P: AsRef<Path>>(path: P) -> Result<Self, PreprocessingError> {
+        let bytes = fs::read(path).map_err(|err| PreprocessingError::IoError(err.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Construct an error for a malformed file
+fn invalid_format(msg: &str) -> PreprocessingError {
+    PreprocessingError::InvalidFormat(msg.to_string())
+}
+
+/// Serializes values into the file format
+#[derive(Default)]
+struct Writer {
+    /// The bytes written so far
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    /// Write a scalar
+    fn scalar<C: CurveGroup>(&mut self, scalar: &Scalar<C>) {
+        self.bytes.extend(scalar.to_bytes_be());
+    }
+
+    /// Write a share and its MAC
+    fn share<C: CurveGroup>(&mut self, share: &ScalarShare<C>) {
+        self.scalar(&share.share());
+        self.scalar(&share.mac());
+    }
+}
+
+/// Deserializes values from the file format
+struct Reader<'a> {
+    /// The bytes remaining to be read
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Read the given number of bytes
+    fn take(&mut self, n: usize) -> Result<&'a [u8], PreprocessingError> {
+        if self.bytes.len() < n {
+            return Err(invalid_format(ERR_TRUNCATED));
+        }
+
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    /// Read a `u64`
+    fn u64(&mut self) -> Result<u64, PreprocessingError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Read a scalar
+    fn scalar<C: CurveGroup>(&mut self) -> Result<Scalar<C>, PreprocessingError> {
+        let bytes = self.take(n_bytes_field::<C::ScalarField>())?;
+        Ok(Scalar::from_be_bytes_mod_order(bytes))
+    }
+
+    /// Read a share and its MAC
+    fn share<C: CurveGroup>(&mut self) -> Result<ScalarShare<C>, PreprocessingError> {
+        Ok(ScalarShare::new(self.scalar()?, self.scalar()?))
+    }
+}
+
+/// A preprocessing source that serves the values of a preprocessing file in
+/// order
+///
+/// The whole file is read into memory when the source is opened. Running out
+/// of triples or shared values is reported as a `PreprocessingError::Exhausted`
+/// through the fallible methods, the source panics if a circuit consumes more
+/// of any other kind of value than the file holds
+pub struct FileBeaverSource<C: CurveGroup> {
+    /// The local party's share of the MAC key
+    mac_key_share: Scalar<C>,
+    /// The remaining local input masks
+    local_input_masks: IntoIter<(Scalar<C>, ScalarShare<C>)>,
+    /// The remaining counterparty input masks
+    counterparty_input_masks: IntoIter<ScalarShare<C>>,
+    /// The remaining shared bits
+    shared_bits: IntoIter<ScalarShare<C>>,
+    /// The remaining shared values
+    shared_values: IntoIter<ScalarShare<C>>,
+    /// The remaining inverse pairs
+    inverse_pairs: IntoIter<(ScalarShare<C>, ScalarShare<C>)>,
+    /// The remaining triples
+    #[allow(clippy::type_complexity)]
+    triples: IntoIter<(ScalarShare<C>, ScalarShare<C>, ScalarShare<C>)>,
+}
+
+impl<C: CurveGroup> FileBeaverSource<C> {
+    /// Open a preprocessing file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, PreprocessingError> {
+        PreprocessingValues::read_file(path).map(Self::from_values)
+    }
+
+    /// Serve the given values
+    pub fn from_values(values: PreprocessingValues<C>) -> Self {
+        Self {
+            mac_key_share: values.mac_key_share,
+            local_input_masks: values.local_input_masks.into_iter(),
+            counterparty_input_masks: values.counterparty_input_masks.into_iter(),
+            shared_bits: values.shared_bits.into_iter(),
+            shared_values: values.shared_values.into_iter(),
+            inverse_pairs: values.inverse_pairs.into_iter(),
+            triples: values.triples.into_iter(),
+        }
+    }
+
+    /// The number of triples remaining
+    pub fn remaining_triples(&self) -> usize {
+        self.triples.len()
+    }
+}
+
+/// Take the next value of the given kind, or an error if none remain
+fn try_next_value<T>(values: &mut IntoIter<T>, kind: &str) -> Result<T, PreprocessingError> {
+    values.next().ok_or_else(|| PreprocessingError::Exhausted(kind.to_string()))
+}
+
+/// Take the next value of the given kind, panicking if the file is exhausted
+fn next_value<T>(values: &mut IntoIter<T>, kind: &str) -> T {
+    try_next_value(values, kind)
+        .unwrap_or_else(|_| panic!("preprocessing file exhausted: no {kind} remaining"))
+}
+
+impl<C: CurveGroup> PreprocessingPhase<C> for FileBeaverSource<C> {
+    fn get_mac_key_share(&self) -> Scalar<C> {
+        self.mac_key_share
+    }
+
+    fn next_local_input_mask(&mut self) -> (Scalar<C>, ScalarShare<C>) {
+        next_value(&mut self.local_input_masks, "local input masks")
+    }
+
+    fn next_counterparty_input_mask(&mut self) -> ScalarShare<C> {
+        next_value(&mut self.counterparty_input_masks, "counterparty input masks")
+    }
+
+    fn next_shared_bit(&mut self) -> ScalarShare<C> {
+        next_value(&mut self.shared_bits, "shared bits")
+    }
+
+    fn next_shared_value(&mut self) -> ScalarShare<C> {
+        next_value(&mut self.shared_values, SHARED_VALUES)
+    }
+
+    fn try_next_shared_value_batch(
+        &mut self,
+        num_values: usize,
+    ) -> Result<Vec<ScalarShare<C>>, PreprocessingError> {
+        if self.shared_values.len() < num_values {
+            return Err(PreprocessingError::Exhausted(SHARED_VALUES.to_string()));
+        }
+
+        Ok(self.next_shared_value_batch(num_values))
+    }
+
+    fn next_shared_inverse_pair(&mut self) -> (ScalarShare<C>, ScalarShare<C>) {
+        next_value(&mut self.inverse_pairs, "inverse pairs")
+    }
+
+    fn next_triplet(&mut self) -> (ScalarShare<C>, ScalarShare<C>, ScalarShare<C>) {
+        next_value(&mut self.triples, TRIPLES)
+    }
+
+    fn try_next_triplet(
+        &mut self,
+    ) -> Result<(ScalarShare<C>, ScalarShare<C>, ScalarShare<C>), PreprocessingError> {
+        try_next_value(&mut self.triples, TRIPLES)
+    }
+
+    fn try_next_triplet_batch(
+        &mut self,
+        num_triplets: usize,
+    ) -> Result<(Vec<ScalarShare<C>>, Vec<ScalarShare<C>>, Vec<ScalarShare<C>>), PreprocessingError>
+    {
+        if self.triples.len() < num_triplets {
+            return Err(PreprocessingError::Exhausted(TRIPLES.to_string()));
+        }
+
+        Ok(self.next_triplet_batch(num_triplets))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, process};
+
+    use ark_ec::Group;
+    use rand::thread_rng;
+
+    use crate::{
+        algebra::{n_bytes_field, Scalar, ScalarShare},
+        error::PreprocessingError,
+        offline_prep::PreprocessingPhase,
+        test_helpers::TestCurve,
+    };
+
+    use super::{FileBeaverSource, PreprocessingValues, MAGIC};
+
+    /// Sample a random share
+    fn random_share() -> ScalarShare<TestCurve> {
+        let mut rng = thread_rng();
+        ScalarShare::new(Scalar::random(&mut rng), Scalar::random(&mut rng))
+    }
+
+    /// Build a small set of random values
+    fn random_values() -> PreprocessingValues<TestCurve> {
+        let mut values = PreprocessingValues::new(Scalar::random(&mut thread_rng()));
+        values.local_input_masks =
+            (0..2).map(|i| (Scalar::from(i as u64), random_share())).collect();
+        values.counterparty_input_masks = (0..2).map(|_| random_share()).collect();
+        values.shared_bits = (0..3).map(|_| random_share()).collect();
+        values.shared_values = (0..4).map(|_| random_share()).collect();
+        values.inverse_pairs = (0..2).map(|_| (random_share(), random_share())).collect();
+        values.triples = (0..5).map(|_| (random_share(), random_share(), random_share())).collect();
+
+        values
+    }
+
+    /// Tests writing a preprocessing file and consuming every kind of value
+    /// back from it in order
+    #[test]
+    fn test_file_round_trip() {
+        let values = random_values();
+        let path = env::temp_dir().join(format!("ark-mpc-preprocessing-{}", process::id()));
+        values.write_file(&path).unwrap();
+        let mut source = FileBeaverSource::<TestCurve>::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(source.get_mac_key_share(), values.mac_key_share);
+        assert_eq!(source.remaining_triples(), values.triples.len());
+        for expected in values.local_input_masks.iter() {
+            assert_eq!(&source.next_local_input_mask(), expected);
+        }
+        for expected in values.counterparty_input_masks.iter() {
+            assert_eq!(&source.next_counterparty_input_mask(), expected);
+        }
+        for expected in values.shared_bits.iter() {
+            assert_eq!(&source.next_shared_bit(), expected);
+        }
+        for expected in values.shared_values.iter() {
+            assert_eq!(&source.next_shared_value(), expected);
+        }
+        for expected in values.inverse_pairs.iter() {
+            assert_eq!(&source.next_shared_inverse_pair(), expected);
+        }
+        for expected in values.triples.iter() {
+            assert_eq!(&source.next_triplet(), expected);
+        }
+        assert_eq!(source.remaining_triples(), 0);
+    }
+
+    /// Tests that a file whose counts do not match its contents is rejected
+    #[test]
+    fn test_invalid_counts() {
+        let bytes = random_values().to_bytes();
+
+        let truncated = PreprocessingValues::<TestCurve>::from_bytes(&bytes[..bytes.len() - 1]);
+        assert!(matches!(truncated, Err(PreprocessingError::InvalidFormat(_))));
+
+        let mut extended = bytes.clone();
+        extended.push(0);
+        let extended = PreprocessingValues::<TestCurve>::from_bytes(&extended);
+        assert!(matches!(extended, Err(PreprocessingError::InvalidFormat(_))));
+    }
+
+    /// Tests that a count large enough to overflow the file length is
+    /// rejected rather than wrapping or panicking
+    #[test]
+    fn test_overflowing_count() {
+        let mut bytes = random_values().to_bytes();
+
+        // The triple count is the last of the six counts in the header
+        let offset =
+            MAGIC.len() + 1 + 4 + n_bytes_field::<<TestCurve as Group>::ScalarField>() + 5 * 8;
+        bytes[offset..offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        let res = PreprocessingValues::<TestCurve>::from_bytes(&bytes);
+        assert!(matches!(res, Err(PreprocessingError::InvalidFormat(_))));
+    }
+
+    /// Tests that consuming triples past the end of the file returns an
+    /// error rather than panicking
+    #[test]
+    fn test_triples_exhausted() {
+        let values = random_values();
+        let n = values.triples.len();
+        let mut source = FileBeaverSource::from_values(values);
+
+        let err = source.try_next_triplet_batch(n + 1).unwrap_err();
+        assert_eq!(err, PreprocessingError::Exhausted("triples".to_string()));
+        assert_eq!(source.remaining_triples(), n);
+
+        source.try_next_triplet_batch(n - 1).unwrap();
+        source.try_next_triplet().unwrap();
+        let err = source.try_next_triplet().unwrap_err();
+        assert_eq!(err, PreprocessingError::Exhausted("triples".to_string()));
+    }
+}
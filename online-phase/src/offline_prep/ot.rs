@@ -0,0 +1,520 @@
+//! Defines a preprocessing source that generates its values with the peer
+//! using oblivious transfer, removing the need for a trusted dealer
+//!
+//! Products of the parties' shares are computed with Gilboa's OT-based
+//! multiplication: to share `x * y` where one party holds `x` and the other
+//! holds `y`, the parties run one OT per bit `y_i` of `y`, in which the holder
+//! of `x` sends a random pad `s_i` or `s_i + 2^i * x`. The receiver's sum of
+//! the received values and the sender's sum of `-s_i` are shares of `x * y`.
+//!
+//! Values are authenticated by multiplying them with the shared MAC key in
+//! the same way. The protocol is secure against semi-honest adversaries
+//! only, a malicious peer may corrupt the values undetected
+
+mod extension;
+
+use std::{collections::VecDeque, slice};
+
+use ark_ec::CurveGroup;
+use ark_ff::PrimeField;
+use futures::{SinkExt, StreamExt};
+use itertools::{izip, Itertools};
+use rand::{thread_rng, Rng};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+use crate::{
+    algebra::{Scalar, ScalarShare},
+    error::{MpcError, MpcNetworkError, PreprocessingError},
+    network::{MpcNetwork, NetworkOutbound, NetworkPayload, PartyId},
+};
+
+use self::extension::{
+    base_ot_receive, BaseOtSender, OtExtensionReceiver, OtExtensionSender, OtKey, N_BASE_OTS,
+};
+
+use super::PreprocessingPhase;
+
+/// A beaver triple
+type Triple<C> = (ScalarShare<C>, ScalarShare<C>, ScalarShare<C>);
+
+/// Error message emitted when the peer sends a message of the wrong type
+const ERR_UNEXPECTED_MESSAGE: &str = "unexpected message from peer";
+/// Error message emitted when the peer sends a message of the wrong length
+const ERR_MESSAGE_LENGTH: &str = "message from peer has the wrong length";
+/// Error message emitted when the network closes during generation
+const ERR_STREAM_CLOSED: &str = "network closed";
+
+/// The name of the triples, used in exhaustion errors
+const TRIPLES: &str = "triples";
+/// The name of the shared values, used in exhaustion errors
+const SHARED_VALUES: &str = "shared values";
+
+/// A preprocessing source that generates values with the peer over a
+/// dedicated network connection
+///
+/// Values are generated in batches ahead of time by the `generate_*` methods,
+/// which both parties must call in the same order with the same arguments,
+/// and are then served in order. Running out of triples or shared values is
+/// reported as a `PreprocessingError::Exhausted` through the fallible
+/// methods, the source panics if a circuit consumes more of any other kind of
+/// value than has been generated
+pub struct OtTripleSource<C: CurveGroup, N: MpcNetwork<C>> {
+    /// The connection to the peer
+    network: N,
+    /// The local party's share of the MAC key
+    mac_key_share: Scalar<C>,
+    /// The sender in the OTs in which the local party holds the multiplicand
+    ot_sender: OtExtensionSender,
+    /// The receiver in the OTs in which the local party holds the multiplier
+    ot_receiver: OtExtensionReceiver,
+    /// The generated local input masks
+    local_input_masks: VecDeque<(Scalar<C>, ScalarShare<C>)>,
+    /// The generated counterparty input masks
+    counterparty_input_masks: VecDeque<ScalarShare<C>>,
+    /// The generated shared bits
+    shared_bits: VecDeque<ScalarShare<C>>,
+    /// The generated shared values
+    shared_values: VecDeque<ScalarShare<C>>,
+    /// The generated inverse pairs
+    inverse_pairs: VecDeque<(ScalarShare<C>, ScalarShare<C>)>,
+    /// The generated triples
+    triples: VecDeque<Triple<C>>,
+}
+
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin> OtTripleSource<C, N> {
+    /// Set up a source over the given connection to the peer, sampling a
+    /// random MAC key share and running the base OTs
+    pub async fn new(network: N) -> Result<Self, MpcError> {
+        let mut rng = thread_rng();
+        let mut network = network;
+
+        // Each party plays the base OT sender for the OTs it receives in
+        let base_sender = BaseOtSender::<C>::new(&mut rng);
+        send(&mut network, NetworkPayload::Point(base_sender.public_key())).await?;
+        let peer_key = match recv(&mut network).await? {
+            NetworkPayload::Point(key) => key,
+            _ => return Err(unexpected_message()),
+        };
+
+        let delta: u128 = rng.gen();
+        let delta_bits = (0..N_BASE_OTS).map(|i| (delta >> i) & 1 == 1).collect_vec();
+        let (receiver_keys, chosen_keys) = base_ot_receive(peer_key, &delta_bits, &mut rng);
+        send(&mut network, NetworkPayload::PointBatch(receiver_keys)).await?;
+        let peer_receiver_keys = match recv(&mut network).await? {
+            NetworkPayload::PointBatch(keys) if keys.len() == N_BASE_OTS => keys,
+            NetworkPayload::PointBatch(_) => return Err(wrong_length()),
+            _ => return Err(unexpected_message()),
+        };
+        let base_keys = base_sender.finish(&peer_receiver_keys);
+
+        Ok(Self {
+            network,
+            mac_key_share: Scalar::random(&mut rng),
+            ot_sender: OtExtensionSender::new(delta, &chosen_keys),
+            ot_receiver: OtExtensionReceiver::new(&base_keys),
+            local_input_masks: VecDeque::new(),
+            counterparty_input_masks: VecDeque::new(),
+            shared_bits: VecDeque::new(),
+            shared_values: VecDeque::new(),
+            inverse_pairs: VecDeque::new(),
+            triples: VecDeque::new(),
+        })
+    }
+
+    /// The number of generated triples remaining
+    pub fn remaining_triples(&self) -> usize {
+        self.triples.len()
+    }
+
+    /// Generate `n` beaver triples
+    pub async fn generate_triples(&mut self, n: usize) -> Result<(), MpcError> {
+        let triples = self.new_triples(n).await?;
+        self.triples.extend(triples);
+        Ok(())
+    }
+
+    /// Generate `n` shared random values
+    pub async fn generate_shared_values(&mut self, n: usize) -> Result<(), MpcError> {
+        let mut rng = thread_rng();
+        let values = (0..n).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let shares = self.authenticate(values).await?;
+        self.shared_values.extend(shares);
+        Ok(())
+    }
+
+    /// Generate `n` shared random bits
+    pub async fn generate_shared_bits(&mut self, n: usize) -> Result<(), MpcError> {
+        // The bit is the xor of the parties' bits, `b_0 + b_1 - 2 * b_0 * b_1`
+        let mut rng = thread_rng();
+        let bits = (0..n).map(|_| Scalar::from(rng.gen_bool(0.5) as u8)).collect_vec();
+        let multiplicands = bits.iter().map(|bit| vec![*bit]).collect_vec();
+        let cross_terms = self.cross_products(&multiplicands, &bits, 1 /* width */).await?;
+
+        let values = izip!(bits, cross_terms).map(|(bit, cross)| bit - cross[0]).collect_vec();
+        let shares = self.authenticate(values).await?;
+        self.shared_bits.extend(shares);
+        Ok(())
+    }
+
+    /// Generate `n` pairs of shared values that are multiplicative inverses of
+    /// one another
+    pub async fn generate_inverse_pairs(&mut self, n: usize) -> Result<(), MpcError> {
+        // Open `c = a * b` of a triple, then `b / c` is the inverse of `a`
+        let triples = self.new_triples(n).await?;
+        let c_shares = triples.iter().map(|(_, _, c)| c.share()).collect_vec();
+        send(&mut self.network, NetworkPayload::ScalarBatch(c_shares.clone())).await?;
+        let peer_c_shares = recv_scalars(&mut self.network, n).await?;
+
+        let mut c_inv = izip!(c_shares, peer_c_shares).map(|(c0, c1)| c0 + c1).collect_vec();
+        Scalar::batch_inverse(&mut c_inv);
+        let pairs = izip!(triples, c_inv).map(|((a, b, _), c_inv)| (a, b * c_inv));
+        self.inverse_pairs.extend(pairs);
+        Ok(())
+    }
+
+    /// Generate `n` input masks, held in the clear by the given party
+    pub async fn generate_input_masks(&mut self, n: usize, owner: PartyId) -> Result<(), MpcError> {
+        let is_owner = self.network.party_id() == owner;
+        let mut rng = thread_rng();
+        let masks = (0..n)
+            .map(|_| if is_owner { Scalar::random(&mut rng) } else { Scalar::zero() })
+            .collect_vec();
+        let shares = self.authenticate(masks.clone()).await?;
+
+        if is_owner {
+            self.local_input_masks.extend(izip!(masks, shares));
+        } else {
+            self.counterparty_input_masks.extend(shares);
+        }
+        Ok(())
+    }
+
+    /// Generate `n` unauthenticated triples, then authenticate them
+    async fn new_triples(&mut self, n: usize) -> Result<Vec<Triple<C>>, MpcError> {
+        let mut rng = thread_rng();
+        let a = (0..n).map(|_| Scalar::random(&mut rng)).collect_vec();
+        let b = (0..n).map(|_| Scalar::random(&mut rng)).collect_vec();
+
+        // c = a_0 * b_0 + a_1 * b_1 + (a_0 * b_1 + a_1 * b_0)
+        let multiplicands = a.iter().map(|a| vec![*a]).collect_vec();
+        let cross_terms = self.cross_products(&multiplicands, &b, 1 /* width */).await?;
+        let c = izip!(&a, &b, cross_terms).map(|(a, b, cross)| a * b + cross[0]).collect_vec();
+
+        let values = a.into_iter().chain(b).chain(c).collect_vec();
+        let mut shares = self.authenticate(values).await?;
+        let c = shares.split_off(2 * n);
+        let b = shares.split_off(n);
+        Ok(izip!(shares, b, c).collect_vec())
+    }
+
+    /// Authenticate the local party's shares of a batch of values, computing
+    /// shares of each value's MAC `alpha * x`
+    async fn authenticate(
+        &mut self,
+        values: Vec<Scalar<C>>,
+    ) -> Result<Vec<ScalarShare<C>>, MpcError> {
+        if values.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // alpha * x = alpha_0 * x_0 + alpha_1 * x_1 + (alpha_0 * x_1 + alpha_1 * x_0)
+        let width = values.len();
+        let mac_key_share = self.mac_key_share;
+        let cross_terms =
+            self.cross_products(slice::from_ref(&values), &[mac_key_share], width).await?.remove(0);
+
+        Ok(izip!(values, cross_terms)
+            .map(|(value, cross)| ScalarShare::new(value, mac_key_share * value + cross))
+            .collect_vec())
+    }
+
+    /// Compute shares of the cross terms `x_0 * y_1 + x_1 * y_0` of the
+    /// products of the parties' values
+    ///
+    /// Each `x` is a vector of `width` values that are all multiplied by the
+    /// corresponding `y`, so that a single set of OTs on the bits of `y`
+    /// serves the whole vector
+    async fn cross_products(
+        &mut self,
+        multiplicands: &[Vec<Scalar<C>>],
+        multipliers: &[Scalar<C>],
+        width: usize,
+    ) -> Result<Vec<Vec<Scalar<C>>>, MpcError> {
+        assert_eq!(multiplicands.len(), multipliers.len());
+        let n = multipliers.len();
+        let n_bits = C::ScalarField::MODULUS_BIT_SIZE as usize;
+        let powers = (0..n_bits)
+            .scan(Scalar::one(), |power, _| {
+                let curr = *power;
+                *power = curr + curr;
+                Some(curr)
+            })
+            .collect_vec();
+
+        // Receive in the OTs on the bits of the local multipliers
+        let choices = multipliers
+            .iter()
+            .flat_map(|y| {
+                let y = y.to_biguint();
+                (0..n_bits as u64).map(move |i| y.bit(i))
+            })
+            .collect_vec();
+        let (extension_msg, received_keys) = self.ot_receiver.extend(&choices);
+        send(&mut self.network, NetworkPayload::Bytes(extension_msg)).await?;
+
+        // Send in the OTs on the bits of the peer's multipliers, the correction
+        // for each OT is `s_0 + 2^i * x - s_1` so that the receiver holding
+        // `s_1` recovers `s_0 + 2^i * x`
+        let peer_msg = match recv(&mut self.network).await? {
+            NetworkPayload::Bytes(msg) => msg,
+            _ => return Err(unexpected_message()),
+        };
+        let sent_keys =
+            self.ot_sender.extend(n * n_bits, &peer_msg).map_err(MpcError::NetworkError)?;
+
+        let mut shares = vec![vec![Scalar::zero(); width]; n];
+        let mut corrections = Vec::with_capacity(n * n_bits * width);
+        for (x, share, keys) in izip!(multiplicands, &mut shares, sent_keys.chunks(n_bits)) {
+            assert_eq!(x.len(), width);
+            for (power, (key0, key1)) in powers.iter().zip(keys) {
+                let pad0 = expand_pad::<C>(key0, width);
+                let pad1 = expand_pad::<C>(key1, width);
+                for (x, share, pad0, pad1) in izip!(x, share.iter_mut(), pad0, pad1) {
+                    corrections.push(pad0 + power * x - pad1);
+                    *share -= pad0;
+                }
+            }
+        }
+        send(&mut self.network, NetworkPayload::ScalarBatch(corrections)).await?;
+
+        // Recover the received values from the peer's corrections
+        let peer_corrections = recv_scalars(&mut self.network, n * n_bits * width).await?;
+        let received = izip!(&choices, &received_keys, peer_corrections.chunks(width));
+        for (ot_index, (choice, key, correction)) in received.enumerate() {
+            let share = &mut shares[ot_index / n_bits];
+            let pad = expand_pad::<C>(key, width);
+            for (share, pad, correction) in izip!(share.iter_mut(), pad, correction) {
+                *share += pad;
+                if *choice {
+                    *share += *correction;
+                }
+            }
+        }
+
+        Ok(shares)
+    }
+}
+
+/// Expand an OT key into a pad of `width` scalars
+fn expand_pad<C: CurveGroup>(key: &OtKey, width: usize) -> Vec<Scalar<C>> {
+    let mut prg = ChaCha20Rng::from_seed(*key);
+    (0..width).map(|_| Scalar::random(&mut prg)).collect_vec()
+}
+
+/// Send a payload to the peer
+async fn send<C: CurveGroup, N: MpcNetwork<C> + Unpin>(
+    network: &mut N,
+    payload: NetworkPayload<C>,
+) -> Result<(), MpcError> {
+    network.send(NetworkOutbound { result_id: 0, payload }).await.map_err(MpcError::NetworkError)
+}
+
+/// Receive a payload from the peer
+async fn recv<C: CurveGroup, N: MpcNetwork<C> + Unpin>(
+    network: &mut N,
+) -> Result<NetworkPayload<C>, MpcError> {
+    match network.next().await {
+        Some(Ok(msg)) => Ok(msg.payload),
+        Some(Err(err)) => Err(MpcError::NetworkError(err)),
+        None => {
+            Err(MpcError::NetworkError(MpcNetworkError::RecvError(ERR_STREAM_CLOSED.to_string())))
+        },
+    }
+}
+
+/// Receive a batch of `n` scalars from the peer
+async fn recv_scalars<C: CurveGroup, N: MpcNetwork<C> + Unpin>(
+    network: &mut N,
+    n: usize,
+) -> Result<Vec<Scalar<C>>, MpcError> {
+    match recv(network).await? {
+        NetworkPayload::ScalarBatch(scalars) if scalars.len() == n => Ok(scalars),
+        NetworkPayload::ScalarBatch(_) => Err(wrong_length()),
+        _ => Err(unexpected_message()),
+    }
+}
+
+/// The error returned when the peer sends a message of the wrong type
+fn unexpected_message() -> MpcError {
+    MpcError::NetworkError(MpcNetworkError::SerializationError(ERR_UNEXPECTED_MESSAGE.to_string()))
+}
+
+/// The error returned when the peer sends a message of the wrong length
+fn wrong_length() -> MpcError {
+    MpcError::NetworkError(MpcNetworkError::SerializationError(ERR_MESSAGE_LENGTH.to_string()))
+}
+
+/// Take the next value of the given kind, or an error if none remain
+fn try_next_value<T>(values: &mut VecDeque<T>, kind: &str) -> Result<T, PreprocessingError> {
+    values.pop_front().ok_or_else(|| PreprocessingError::Exhausted(kind.to_string()))
+}
+
+/// Take the next value of the given kind, panicking if none remain
+fn next_value<T>(values: &mut VecDeque<T>, kind: &str) -> T {
+    try_next_value(values, kind)
+        .unwrap_or_else(|_| panic!("OT source exhausted: no {kind} remaining"))
+}
+
+impl<C: CurveGroup, N: MpcNetwork<C> + Unpin + Sync> PreprocessingPhase<C>
+    for OtTripleSource<C, N>
+{
+    fn get_mac_key_share(&self) -> Scalar<C> {
+        self.mac_key_share
+    }
+
+    fn next_local_input_mask(&mut self) -> (Scalar<C>, ScalarShare<C>) {
+        next_value(&mut self.local_input_masks, "local input masks")
+    }
+
+    fn next_counterparty_input_mask(&mut self) -> ScalarShare<C> {
+        next_value(&mut self.counterparty_input_masks, "counterparty input masks")
+    }
+
+    fn next_shared_bit(&mut self) -> ScalarShare<C> {
+        next_value(&mut self.shared_bits, "shared bits")
+    }
+
+    fn next_shared_value(&mut self) -> ScalarShare<C> {
+        next_value(&mut self.shared_values, SHARED_VALUES)
+    }
+
+    fn try_next_shared_value_batch(
+        &mut self,
+        num_values: usize,
+    ) -> Result<Vec<ScalarShare<C>>, PreprocessingError> {
+        if self.shared_values.len() < num_values {
+            return Err(PreprocessingError::Exhausted(SHARED_VALUES.to_string()));
+        }
+
+        Ok(self.shared_values.drain(..num_values).collect_vec())
+    }
+
+    fn next_shared_inverse_pair(&mut self) -> (ScalarShare<C>, ScalarShare<C>) {
+        next_value(&mut self.inverse_pairs, "inverse pairs")
+    }
+
+    fn next_triplet(&mut self) -> Triple<C> {
+        next_value(&mut self.triples, TRIPLES)
+    }
+
+    fn try_next_triplet(&mut self) -> Result<Triple<C>, PreprocessingError> {
+        try_next_value(&mut self.triples, TRIPLES)
+    }
+
+    fn try_next_triplet_batch(
+        &mut self,
+        num_triplets: usize,
+    ) -> Result<(Vec<ScalarShare<C>>, Vec<ScalarShare<C>>, Vec<ScalarShare<C>>), PreprocessingError>
+    {
+        if self.triples.len() < num_triplets {
+            return Err(PreprocessingError::Exhausted(TRIPLES.to_string()));
+        }
+
+        Ok(self.next_triplet_batch(num_triplets))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::future;
+    use itertools::izip;
+
+    use crate::{
+        algebra::Scalar,
+        error::MpcError,
+        network::{MockNetwork, UnboundedDuplexStream},
+        offline_prep::PreprocessingPhase,
+        test_helpers::{execute_mock_mpc_with_beaver_source, TestCurve},
+        PARTY0, PARTY1,
+    };
+
+    use super::OtTripleSource;
+
+    /// An OT source over the mock network
+    type MockOtSource = OtTripleSource<TestCurve, MockNetwork<TestCurve>>;
+
+    /// Set up a pair of sources connected by a mock network
+    async fn setup_sources() -> (MockOtSource, MockOtSource) {
+        let (stream0, stream1) = UnboundedDuplexStream::new_duplex_pair();
+        let (source0, source1) = future::join(
+            OtTripleSource::new(MockNetwork::new(PARTY0, stream0)),
+            OtTripleSource::new(MockNetwork::new(PARTY1, stream1)),
+        )
+        .await;
+
+        (source0.unwrap(), source1.unwrap())
+    }
+
+    /// Generate the values consumed by the circuit in
+    /// `test_multiplication_with_ot_source`
+    async fn generate(source: &mut MockOtSource) -> Result<(), MpcError> {
+        source.generate_triples(3).await?;
+        source.generate_input_masks(1, PARTY0).await?;
+        source.generate_input_masks(1, PARTY1).await?;
+        source.generate_inverse_pairs(1).await?;
+        source.generate_shared_bits(2).await
+    }
+
+    /// Tests that the generated triples satisfy `a * b = c` and carry valid
+    /// MACs when the parties' shares are combined
+    #[tokio::test]
+    async fn test_generated_triples() {
+        const N: usize = 3;
+        let (mut source0, mut source1) = setup_sources().await;
+        let (res0, res1) =
+            future::join(source0.generate_triples(N), source1.generate_triples(N)).await;
+        res0.unwrap();
+        res1.unwrap();
+
+        let mac_key: Scalar<TestCurve> = source0.get_mac_key_share() + source1.get_mac_key_share();
+        let (triples0, triples1) = (source0.next_triplet_batch(N), source1.next_triplet_batch(N));
+        let shares0 = izip!(triples0.0, triples0.1, triples0.2);
+        let shares1 = izip!(triples1.0, triples1.1, triples1.2);
+        for ((a0, b0, c0), (a1, b1, c1)) in shares0.zip(shares1) {
+            let (a, b, c) = (a0 + a1, b0 + b1, c0 + c1);
+            assert_eq!(a.share() * b.share(), c.share());
+            for value in [a, b, c] {
+                assert_eq!(value.mac(), mac_key * value.share());
+            }
+        }
+        assert!(source0.try_next_triplet().is_err());
+    }
+
+    /// Tests a circuit run on values generated by the source, checking the
+    /// MACs of its output
+    #[tokio::test]
+    async fn test_multiplication_with_ot_source() {
+        let (mut source0, mut source1) = setup_sources().await;
+        let (res0, res1) = future::join(generate(&mut source0), generate(&mut source1)).await;
+        res0.unwrap();
+        res1.unwrap();
+
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| async move {
+                let a = fabric.share_scalar(2u8, PARTY0);
+                let b = fabric.share_scalar(3u8, PARTY1);
+                let bits = fabric.random_shared_bits(2);
+                let bit_check = &bits[0] * &bits[0] - &bits[0];
+                let (x, x_inv) = fabric.random_inverse_pair();
+
+                let product = &a * &b + bit_check + x * x_inv;
+                product.open_authenticated().await
+            },
+            source0,
+            source1,
+        )
+        .await;
+
+        assert_eq!(res, Ok(Scalar::from(7u8)));
+    }
+}
@@ -0,0 +1,298 @@
+//! Defines random oblivious transfer between the two parties
+//!
+//! A small number of base OTs are run once using the "simplest OT" protocol
+//! of Chou and Orlandi, and extended to arbitrarily many OTs with the IKNP
+//! protocol. In a random OT the sender learns two random keys and the
+//! receiver learns the key selected by its choice bit.
+//!
+//! Both protocols are secure against semi-honest adversaries only
+
+use ark_ec::CurveGroup;
+use itertools::Itertools;
+use rand::{CryptoRng, RngCore};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use sha3::{Digest, Sha3_256};
+
+use crate::{
+    algebra::{CurvePoint, Scalar},
+    error::MpcNetworkError,
+};
+
+/// The number of base OTs, i.e. the computational security parameter
+pub(crate) const N_BASE_OTS: usize = 128;
+
+/// A key output by a random OT
+pub(crate) type OtKey = [u8; 32];
+
+/// Error message emitted when an extension message has the wrong length
+const ERR_EXTENSION_LENGTH: &str = "OT extension message has the wrong length";
+
+/// Hash the given inputs into an OT key, domain separated by the index of
+/// the OT
+fn hash_key(index: u64, inputs: &[&[u8]]) -> OtKey {
+    let mut hasher = Sha3_256::new();
+    hasher.update(index.to_le_bytes());
+    for input in inputs {
+        hasher.update(input);
+    }
+
+    hasher.finalize().into()
+}
+
+// ------------
+// | Base OTs |
+// ------------
+
+/// The sender of a batch of base OTs
+pub(crate) struct BaseOtSender<C: CurveGroup> {
+    /// The sender's secret `y`
+    secret: Scalar<C>,
+    /// The sender's public key `Y = y * G`
+    public_key: CurvePoint<C>,
+}
+
+impl<C: CurveGroup> BaseOtSender<C> {
+    /// Sample a new sender
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let secret = Scalar::random(rng);
+        Self { secret, public_key: CurvePoint::generator() * secret }
+    }
+
+    /// The public key sent to the receiver
+    pub fn public_key(&self) -> CurvePoint<C> {
+        self.public_key
+    }
+
+    /// Derive both keys of each OT from the receiver's public keys
+    pub fn finish(&self, receiver_keys: &[CurvePoint<C>]) -> Vec<(OtKey, OtKey)> {
+        let public_bytes = self.public_key.to_bytes();
+        let offset = self.public_key * self.secret;
+
+        receiver_keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let key_bytes = key.to_bytes();
+                let shared0 = key * self.secret;
+                let shared1 = shared0 - offset;
+
+                let derive = |shared: CurvePoint<C>| {
+                    hash_key(i as u64, &[&public_bytes, &key_bytes, &shared.to_bytes()])
+                };
+                (derive(shared0), derive(shared1))
+            })
+            .collect_vec()
+    }
+}
+
+/// Receive a batch of base OTs with the given choice bits from a sender with
+/// the given public key
+///
+/// Returns the public keys to send to the sender and the key selected by each
+/// choice bit
+pub(crate) fn base_ot_receive<C: CurveGroup, R: RngCore + CryptoRng>(
+    sender_key: CurvePoint<C>,
+    choices: &[bool],
+    rng: &mut R,
+) -> (Vec<CurvePoint<C>>, Vec<OtKey>) {
+    let sender_bytes = sender_key.to_bytes();
+    choices
+        .iter()
+        .enumerate()
+        .map(|(i, choice)| {
+            let secret = Scalar::random(rng);
+            let mut key = CurvePoint::generator() * secret;
+            if *choice {
+                key += sender_key;
+            }
+
+            let shared = sender_key * secret;
+            let ot_key = hash_key(i as u64, &[&sender_bytes, &key.to_bytes(), &shared.to_bytes()]);
+            (key, ot_key)
+        })
+        .unzip()
+}
+
+// ----------------
+// | OT Extension |
+// ----------------
+
+/// Expand the next `n_bytes` bytes of a PRG
+fn expand(prg: &mut ChaCha20Rng, n_bytes: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; n_bytes];
+    prg.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Transpose `N_BASE_OTS` columns of `n` bits into `n` rows of
+/// `N_BASE_OTS` bits
+fn transpose(columns: &[Vec<u8>], n: usize) -> Vec<u128> {
+    (0..n)
+        .map(|j| {
+            columns.iter().enumerate().fold(0u128, |row, (i, column)| {
+                let bit = (column[j / 8] >> (j % 8)) & 1;
+                row | ((bit as u128) << i)
+            })
+        })
+        .collect_vec()
+}
+
+/// The sender of extended OTs, which plays the receiver in the base OTs
+pub(crate) struct OtExtensionSender {
+    /// The sender's choice bits in the base OTs, one per bit
+    delta: u128,
+    /// The PRGs seeded with the keys received in the base OTs
+    prgs: Vec<ChaCha20Rng>,
+    /// The number of OTs extended so far, used to domain separate the keys
+    counter: u64,
+}
+
+impl OtExtensionSender {
+    /// Create a sender from its choice bits and keys in the base OTs
+    pub fn new(delta: u128, base_keys: &[OtKey]) -> Self {
+        assert_eq!(base_keys.len(), N_BASE_OTS);
+        let prgs = base_keys.iter().map(|key| ChaCha20Rng::from_seed(*key)).collect_vec();
+        Self { delta, prgs, counter: 0 }
+    }
+
+    /// Extend `n` OTs from the receiver's extension message, returning both
+    /// keys of each OT
+    pub fn extend(&mut self, n: usize, msg: &[u8]) -> Result<Vec<(OtKey, OtKey)>, MpcNetworkError> {
+        let n_bytes = n.div_ceil(8);
+        if msg.len() != N_BASE_OTS * n_bytes {
+            return Err(MpcNetworkError::SerializationError(ERR_EXTENSION_LENGTH.to_string()));
+        }
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+
+        // q_i = G(k_i) ^ (delta_i * u_i) = t_i ^ (delta_i * r)
+        let columns = self
+            .prgs
+            .iter_mut()
+            .zip(msg.chunks(n_bytes))
+            .enumerate()
+            .map(|(i, (prg, u))| {
+                let mut q = expand(prg, n_bytes);
+                if (self.delta >> i) & 1 == 1 {
+                    q.iter_mut().zip(u).for_each(|(q, u)| *q ^= u);
+                }
+                q
+            })
+            .collect_vec();
+
+        let keys = transpose(&columns, n)
+            .into_iter()
+            .enumerate()
+            .map(|(j, row)| {
+                let index = self.counter + j as u64;
+                let key0 = hash_key(index, &[&row.to_le_bytes()]);
+                let key1 = hash_key(index, &[&(row ^ self.delta).to_le_bytes()]);
+                (key0, key1)
+            })
+            .collect_vec();
+        self.counter += n as u64;
+
+        Ok(keys)
+    }
+}
+
+/// The receiver of extended OTs, which plays the sender in the base OTs
+pub(crate) struct OtExtensionReceiver {
+    /// The PRGs seeded with both keys of each base OT
+    prgs: Vec<(ChaCha20Rng, ChaCha20Rng)>,
+    /// The number of OTs extended so far, used to domain separate the keys
+    counter: u64,
+}
+
+impl OtExtensionReceiver {
+    /// Create a receiver from its keys in the base OTs
+    pub fn new(base_keys: &[(OtKey, OtKey)]) -> Self {
+        assert_eq!(base_keys.len(), N_BASE_OTS);
+        let prgs = base_keys
+            .iter()
+            .map(|(key0, key1)| (ChaCha20Rng::from_seed(*key0), ChaCha20Rng::from_seed(*key1)))
+            .collect_vec();
+        Self { prgs, counter: 0 }
+    }
+
+    /// Extend OTs with the given choice bits, returning the message to send
+    /// to the sender and the key selected by each choice bit
+    pub fn extend(&mut self, choices: &[bool]) -> (Vec<u8>, Vec<OtKey>) {
+        let n = choices.len();
+        let n_bytes = n.div_ceil(8);
+        let mut packed_choices = vec![0u8; n_bytes];
+        for (j, choice) in choices.iter().enumerate() {
+            packed_choices[j / 8] |= (*choice as u8) << (j % 8);
+        }
+
+        // u_i = G(k_i^0) ^ G(k_i^1) ^ r
+        let mut msg = Vec::with_capacity(N_BASE_OTS * n_bytes);
+        let columns = self
+            .prgs
+            .iter_mut()
+            .map(|(prg0, prg1)| {
+                let t = expand(prg0, n_bytes);
+                let u = expand(prg1, n_bytes);
+                msg.extend(xor3(&t, &u, &packed_choices));
+                t
+            })
+            .collect_vec();
+
+        let keys = transpose(&columns, n)
+            .into_iter()
+            .enumerate()
+            .map(|(j, row)| hash_key(self.counter + j as u64, &[&row.to_le_bytes()]))
+            .collect_vec();
+        self.counter += n as u64;
+
+        (msg, keys)
+    }
+}
+
+/// Xor three byte strings of equal length
+fn xor3<'a>(a: &'a [u8], b: &'a [u8], c: &'a [u8]) -> impl Iterator<Item = u8> + 'a {
+    a.iter().zip(b).zip(c).map(|((a, b), c)| a ^ b ^ c)
+}
+
+#[cfg(test)]
+mod test {
+    use itertools::Itertools;
+    use rand::{thread_rng, Rng};
+
+    use crate::test_helpers::TestCurve;
+
+    use super::{
+        base_ot_receive, BaseOtSender, OtExtensionReceiver, OtExtensionSender, N_BASE_OTS,
+    };
+
+    /// Tests that the receiver of extended OTs learns exactly the keys selected
+    /// by its choice bits
+    #[test]
+    fn test_ot_extension() {
+        let mut rng = thread_rng();
+
+        // The extension receiver plays the base OT sender
+        let base_sender = BaseOtSender::<TestCurve>::new(&mut rng);
+        let delta: u128 = rng.gen();
+        let delta_bits = (0..N_BASE_OTS).map(|i| (delta >> i) & 1 == 1).collect_vec();
+        let (receiver_keys, chosen_keys) =
+            base_ot_receive(base_sender.public_key(), &delta_bits, &mut rng);
+        let base_keys = base_sender.finish(&receiver_keys);
+
+        let mut sender = OtExtensionSender::new(delta, &chosen_keys);
+        let mut receiver = OtExtensionReceiver::new(&base_keys);
+
+        // Extend twice to check that the PRGs stay in step
+        for n in [100, 37] {
+            let choices = (0..n).map(|_| rng.gen_bool(0.5)).collect_vec();
+            let (msg, received) = receiver.extend(&choices);
+            let sent = sender.extend(n, &msg).unwrap();
+
+            for ((key0, key1), (choice, key)) in sent.iter().zip(choices.iter().zip(received)) {
+                assert_ne!(key0, key1);
+                assert_eq!(&key, if *choice { key1 } else { key0 });
+            }
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Defines a dummy preprocessing source that derives random, consistent
+//! shares for both parties of a two party test from a shared seed
+
+use ark_ec::CurveGroup;
+use rand::Rng;
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+use crate::{
+    algebra::{Scalar, ScalarShare},
+    network::PartyId,
+    PARTY0,
+};
+
+use super::PreprocessingPhase;
+
+/// The PRG stream from which the MAC key is sampled
+const MAC_KEY_STREAM: u64 = 0;
+/// The PRG stream from which the input masks held by party 0 are sampled
+const PARTY0_MASK_STREAM: u64 = 1;
+/// The PRG stream from which the input masks held by party 1 are sampled
+const PARTY1_MASK_STREAM: u64 = 2;
+/// The PRG stream from which shared bits are sampled
+const BIT_STREAM: u64 = 3;
+/// The PRG stream from which shared values are sampled
+const VALUE_STREAM: u64 = 4;
+/// The PRG stream from which inverse pairs are sampled
+const INVERSE_PAIR_STREAM: u64 = 5;
+/// The PRG stream from which triples are sampled
+const TRIPLE_STREAM: u64 = 6;
+/// The PRG stream from which square pairs are sampled
+const SQUARE_PAIR_STREAM: u64 = 7;
+
+/// A beaver source for two party tests that samples random values and their
+/// sharings from a PRG seeded with a seed shared by both parties
+///
+/// Both parties sample the same values and the same sharings, and each keeps
+/// its own share, so the values are valid, authenticated sharings of random
+/// values. Each kind of value is sampled from its own PRG stream, so the
+/// parties stay consistent as long as each consumes values of a given kind in
+/// the same order
+#[derive(Clone, Debug)]
+pub struct SeededBeaverSource {
+    /// The ID of the local party
+    party_id: PartyId,
+    /// The seed shared by both parties
+    seed: [u8; 32],
+    /// The PRG from which the input masks held by the local party are sampled
+    local_masks: ChaCha20Rng,
+    /// The PRG from which the input masks held by the counterparty are
+    /// sampled
+    counterparty_masks: ChaCha20Rng,
+    /// The PRG from which shared bits are sampled
+    bits: ChaCha20Rng,
+    /// The PRG from which shared values are sampled
+    values: ChaCha20Rng,
+    /// The PRG from which inverse pairs are sampled
+    inverse_pairs: ChaCha20Rng,
+    /// The PRG from which triples are sampled
+    triples: ChaCha20Rng,
+    /// The PRG from which square pairs are sampled
+    square_pairs: ChaCha20Rng,
+}
+
+impl SeededBeaverSource {
+    /// Create a source for the given party from a seed shared with the peer
+    pub fn new(party_id: PartyId, seed: u64) -> Self {
+        assert!(party_id == 0 || party_id == 1);
+        let mut seed_bytes = [0u8; 32];
+        seed_bytes[..8].copy_from_slice(&seed.to_le_bytes());
+
+        let (local_stream, counterparty_stream) = if party_id == PARTY0 {
+            (PARTY0_MASK_STREAM, PARTY1_MASK_STREAM)
+        } else {
+            (PARTY1_MASK_STREAM, PARTY0_MASK_STREAM)
+        };
+
+        Self {
+            party_id,
+            seed: seed_bytes,
+            local_masks: Self::stream(seed_bytes, local_stream),
+            counterparty_masks: Self::stream(seed_bytes, counterparty_stream),
+            bits: Self::stream(seed_bytes, BIT_STREAM),
+            values: Self::stream(seed_bytes, VALUE_STREAM),
+            inverse_pairs: Self::stream(seed_bytes, INVERSE_PAIR_STREAM),
+            triples: Self::stream(seed_bytes, TRIPLE_STREAM),
+            square_pairs: Self::stream(seed_bytes, SQUARE_PAIR_STREAM),
+        }
+    }
+
+    /// Create the PRG for the given stream of the seed
+    fn stream(seed: [u8; 32], stream: u64) -> ChaCha20Rng {
+        let mut rng = ChaCha20Rng::from_seed(seed);
+        rng.set_stream(stream);
+        rng
+    }
+
+    /// Sample the MAC key and party 0's share of it
+    fn mac_key<C: CurveGroup>(&self) -> (Scalar<C>, Scalar<C>) {
+        let mut rng = Self::stream(self.seed, MAC_KEY_STREAM);
+        (Scalar::random(&mut rng), Scalar::random(&mut rng))
+    }
+}
+
+/// Sample a sharing of the given value under the given MAC key and return
+/// the given party's share
+fn share<C: CurveGroup>(
+    party_id: PartyId,
+    mac_key: Scalar<C>,
+    rng: &mut ChaCha20Rng,
+    value: Scalar<C>,
+) -> ScalarShare<C> {
+    let share0 = Scalar::random(rng);
+    let mac0 = Scalar::random(rng);
+
+    if party_id == PARTY0 {
+        ScalarShare::new(share0, mac0)
+    } else {
+        ScalarShare::new(value - share0, mac_key * value - mac0)
+    }
+}
+
+impl<C: CurveGroup> PreprocessingPhase<C> for SeededBeaverSource {
+    fn get_mac_key_share(&self) -> Scalar<C> {
+        let (mac_key, share0) = self.mac_key();
+        if self.party_id == PARTY0 {
+            share0
+        } else {
+            mac_key - share0
+        }
+    }
+
+    fn next_local_input_mask(&mut self) -> (Scalar<C>, ScalarShare<C>) {
+        let (mac_key, _) = self.mac_key();
+        let mask = Scalar::random(&mut self.local_masks);
+        (mask, share(self.party_id, mac_key, &mut self.local_masks, mask))
+    }
+
+    fn next_counterparty_input_mask(&mut self) -> ScalarShare<C> {
+        let (mac_key, _) = self.mac_key();
+        let mask = Scalar::random(&mut self.counterparty_masks);
+        share(self.party_id, mac_key, &mut self.counterparty_masks, mask)
+    }
+
+    fn next_shared_bit(&mut self) -> ScalarShare<C> {
+        let (mac_key, _) = self.mac_key();
+        let bit = Scalar::from(self.bits.gen_bool(0.5) as u8);
+        share(self.party_id, mac_key, &mut self.bits, bit)
+    }
+
+    fn next_shared_value(&mut self) -> ScalarShare<C> {
+        let (mac_key, _) = self.mac_key();
+        let value = Scalar::random(&mut self.values);
+        share(self.party_id, mac_key, &mut self.values, value)
+    }
+
+    fn next_shared_inverse_pair(&mut self) -> (ScalarShare<C>, ScalarShare<C>) {
+        let (mac_key, _) = self.mac_key();
+        let rng = &mut self.inverse_pairs;
+        let value = Scalar::random(rng);
+        let value_share = share(self.party_id, mac_key, rng, value);
+        let inverse_share = share(self.party_id, mac_key, rng, value.inverse());
+
+        (value_share, inverse_share)
+    }
+
+    fn next_shared_square_pair(&mut self) -> Option<(ScalarShare<C>, ScalarShare<C>)> {
+        let (mac_key, _) = self.mac_key();
+        let rng = &mut self.square_pairs;
+        let value = Scalar::random(rng);
+        let value_share = share(self.party_id, mac_key, rng, value);
+        let square_share = share(self.party_id, mac_key, rng, value * value);
+
+        Some((value_share, square_share))
+    }
+
+    fn next_triplet(&mut self) -> (ScalarShare<C>, ScalarShare<C>, ScalarShare<C>) {
+        let (mac_key, _) = self.mac_key();
+        let rng = &mut self.triples;
+        let a = Scalar::random(rng);
+        let b = Scalar::random(rng);
+
+        (
+            share(self.party_id, mac_key, rng, a),
+            share(self.party_id, mac_key, rng, b),
+            share(self.party_id, mac_key, rng, a * b),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::future;
+
+    use crate::{
+        algebra::{AuthenticatedScalarResult, Scalar},
+        test_helpers::{execute_mock_mpc_with_beaver_source, TestCurve},
+        PARTY0, PARTY1,
+    };
+
+    use super::SeededBeaverSource;
+
+    /// Tests multiplying random shared values with triples from the seeded
+    /// source
+    #[tokio::test]
+    async fn test_seeded_multiplication() {
+        const SEED: u64 = 42;
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| async move {
+                let values = fabric.random_shared_scalars(4);
+                let products = AuthenticatedScalarResult::batch_mul(&values[..2], &values[2..]);
+                let input = fabric.share_scalar(5u8, PARTY1);
+                let scaled = &products[0] * &input;
+
+                let opened = future::join_all(
+                    values.iter().chain(&products).chain([&scaled]).map(|v| v.open_authenticated()),
+                )
+                .await;
+                opened.into_iter().collect::<Result<Vec<Scalar<TestCurve>>, _>>()
+            },
+            SeededBeaverSource::new(PARTY0, SEED),
+            SeededBeaverSource::new(PARTY1, SEED),
+        )
+        .await;
+
+        let opened = res.unwrap();
+        let (values, products) = opened.split_at(4);
+        assert_eq!(products[0], values[0] * values[2]);
+        assert_eq!(products[1], values[1] * values[3]);
+        assert_eq!(products[2], products[0] * Scalar::from(5u8));
+        assert!(values.iter().all(|value| *value != Scalar::from(1u8)));
+    }
+
+    /// Tests that each square pair from the seeded source opens to a value and
+    /// its square
+    #[tokio::test]
+    async fn test_seeded_square_pairs() {
+        const N: usize = 5;
+        const SEED: u64 = 7;
+        let (res, _) = execute_mock_mpc_with_beaver_source(
+            |fabric| async move {
+                let (values, squares) = fabric.random_square_pairs(N);
+                let values = future::join_all(values.iter().map(|v| v.open_authenticated())).await;
+                let squares =
+                    future::join_all(squares.iter().map(|v| v.open_authenticated())).await;
+
+                (values, squares, fabric.num_triples_consumed())
+            },
+            SeededBeaverSource::new(PARTY0, SEED),
+            SeededBeaverSource::new(PARTY1, SEED),
+        )
+        .await;
+
+        let (values, squares, n_triples) = res;
+        assert_eq!(n_triples, 0);
+        for (value, square) in values.into_iter().zip(squares) {
+            let (first, second): (Scalar<TestCurve>, _) = (value.unwrap(), square.unwrap());
+            assert_eq!(second, first * first);
+        }
+    }
+}
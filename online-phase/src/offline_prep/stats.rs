@@ -0,0 +1,269 @@
+//! Defines a preprocessing source that counts the values consumed from
+//! another source, for sizing the offline phase of a circuit
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use ark_ec::CurveGroup;
+
+use crate::{
+    algebra::{Scalar, ScalarShare},
+    error::PreprocessingError,
+};
+
+use super::PreprocessingPhase;
+
+/// A snapshot of the number of values of each kind consumed from a source
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PreprocessingStats {
+    /// The number of input masks held in the clear by the local party
+    pub local_input_masks: usize,
+    /// The number of input masks held in the clear by the counterparty
+    pub counterparty_input_masks: usize,
+    /// The number of shared bits
+    pub shared_bits: usize,
+    /// The number of shared values
+    pub shared_values: usize,
+    /// The number of inverse pairs
+    pub inverse_pairs: usize,
+    /// The number of square pairs
+    pub square_pairs: usize,
+    /// The number of beaver triples
+    pub triples: usize,
+}
+
+/// The counters of a `StatsBeaverSource`
+#[derive(Debug, Default)]
+struct Counters {
+    /// The number of local input masks consumed
+    local_input_masks: AtomicUsize,
+    /// The number of counterparty input masks consumed
+    counterparty_input_masks: AtomicUsize,
+    /// The number of shared bits consumed
+    shared_bits: AtomicUsize,
+    /// The number of shared values consumed
+    shared_values: AtomicUsize,
+    /// The number of inverse pairs consumed
+    inverse_pairs: AtomicUsize,
+    /// The number of square pairs consumed
+    square_pairs: AtomicUsize,
+    /// The number of triples consumed
+    triples: AtomicUsize,
+}
+
+/// A handle to the counters of a `StatsBeaverSource`, which remains valid
+/// once the source is moved into a fabric
+#[derive(Clone, Debug, Default)]
+pub struct PreprocessingCounters(Arc<Counters>);
+
+impl PreprocessingCounters {
+    /// Take a snapshot of the number of values consumed so far
+    pub fn snapshot(&self) -> PreprocessingStats {
+        let counters = &self.0;
+        PreprocessingStats {
+            local_input_masks: counters.local_input_masks.load(Ordering::Acquire),
+            counterparty_input_masks: counters.counterparty_input_masks.load(Ordering::Acquire),
+            shared_bits: counters.shared_bits.load(Ordering::Acquire),
+            shared_values: counters.shared_values.load(Ordering::Acquire),
+            inverse_pairs: counters.inverse_pairs.load(Ordering::Acquire),
+            square_pairs: counters.square_pairs.load(Ordering::Acquire),
+            triples: counters.triples.load(Ordering::Acquire),
+        }
+    }
+}
+
+/// Add `n` to the given counter
+fn record(counter: &AtomicUsize, n: usize) {
+    counter.fetch_add(n, Ordering::AcqRel);
+}
+
+/// A preprocessing source that counts the values of each kind consumed from
+/// an underlying source
+pub struct StatsBeaverSource<S> {
+    /// The underlying source
+    source: S,
+    /// The consumption counters
+    counters: PreprocessingCounters,
+}
+
+impl<S> StatsBeaverSource<S> {
+    /// Wrap the given source
+    pub fn new(source: S) -> Self {
+        Self { source, counters: PreprocessingCounters::default() }
+    }
+
+    /// Get a handle to the source's counters
+    pub fn counters(&self) -> PreprocessingCounters {
+        self.counters.clone()
+    }
+
+    /// Take a snapshot of the number of values consumed so far
+    pub fn stats(&self) -> PreprocessingStats {
+        self.counters.snapshot()
+    }
+}
+
+impl<C: CurveGroup, S: PreprocessingPhase<C>> PreprocessingPhase<C> for StatsBeaverSource<S> {
+    fn get_mac_key_share(&self) -> Scalar<C> {
+        self.source.get_mac_key_share()
+    }
+
+    fn next_local_input_mask(&mut self) -> (Scalar<C>, ScalarShare<C>) {
+        record(&self.counters.0.local_input_masks, 1);
+        self.source.next_local_input_mask()
+    }
+
+    fn next_local_input_mask_batch(
+        &mut self,
+        num_values: usize,
+    ) -> (Vec<Scalar<C>>, Vec<ScalarShare<C>>) {
+        record(&self.counters.0.local_input_masks, num_values);
+        self.source.next_local_input_mask_batch(num_values)
+    }
+
+    fn next_counterparty_input_mask(&mut self) -> ScalarShare<C> {
+        record(&self.counters.0.counterparty_input_masks, 1);
+        self.source.next_counterparty_input_mask()
+    }
+
+    fn next_counterparty_input_mask_batch(&mut self, num_values: usize) -> Vec<ScalarShare<C>> {
+        record(&self.counters.0.counterparty_input_masks, num_values);
+        self.source.next_counterparty_input_mask_batch(num_values)
+    }
+
+    fn next_shared_bit(&mut self) -> ScalarShare<C> {
+        record(&self.counters.0.shared_bits, 1);
+        self.source.next_shared_bit()
+    }
+
+    fn next_shared_bit_batch(&mut self, num_values: usize) -> Vec<ScalarShare<C>> {
+        record(&self.counters.0.shared_bits, num_values);
+        self.source.next_shared_bit_batch(num_values)
+    }
+
+    fn next_shared_value(&mut self) -> ScalarShare<C> {
+        record(&self.counters.0.shared_values, 1);
+        self.source.next_shared_value()
+    }
+
+    fn next_shared_value_batch(&mut self, num_values: usize) -> Vec<ScalarShare<C>> {
+        record(&self.counters.0.shared_values, num_values);
+        self.source.next_shared_value_batch(num_values)
+    }
+
+    fn try_next_shared_value_batch(
+        &mut self,
+        num_values: usize,
+    ) -> Result<Vec<ScalarShare<C>>, PreprocessingError> {
+        let values = self.source.try_next_shared_value_batch(num_values)?;
+        record(&self.counters.0.shared_values, num_values);
+        Ok(values)
+    }
+
+    fn next_shared_inverse_pair(&mut self) -> (ScalarShare<C>, ScalarShare<C>) {
+        record(&self.counters.0.inverse_pairs, 1);
+        self.source.next_shared_inverse_pair()
+    }
+
+    fn next_shared_inverse_pair_batch(
+        &mut self,
+        num_pairs: usize,
+    ) -> (Vec<ScalarShare<C>>, Vec<ScalarShare<C>>) {
+        record(&self.counters.0.inverse_pairs, num_pairs);
+        self.source.next_shared_inverse_pair_batch(num_pairs)
+    }
+
+    fn next_shared_square_pair(&mut self) -> Option<(ScalarShare<C>, ScalarShare<C>)> {
+        let pair = self.source.next_shared_square_pair()?;
+        record(&self.counters.0.square_pairs, 1);
+        Some(pair)
+    }
+
+    fn next_shared_square_pair_batch(
+        &mut self,
+        num_pairs: usize,
+    ) -> Option<(Vec<ScalarShare<C>>, Vec<ScalarShare<C>>)> {
+        let pairs = self.source.next_shared_square_pair_batch(num_pairs)?;
+        record(&self.counters.0.square_pairs, num_pairs);
+        Some(pairs)
+    }
+
+    fn next_triplet(&mut self) -> (ScalarShare<C>, ScalarShare<C>, ScalarShare<C>) {
+        record(&self.counters.0.triples, 1);
+        self.source.next_triplet()
+    }
+
+    fn next_triplet_batch(
+        &mut self,
+        num_triplets: usize,
+    ) -> (Vec<ScalarShare<C>>, Vec<ScalarShare<C>>, Vec<ScalarShare<C>>) {
+        record(&self.counters.0.triples, num_triplets);
+        self.source.next_triplet_batch(num_triplets)
+    }
+
+    fn try_next_triplet(
+        &mut self,
+    ) -> Result<(ScalarShare<C>, ScalarShare<C>, ScalarShare<C>), PreprocessingError> {
+        let triple = self.source.try_next_triplet()?;
+        record(&self.counters.0.triples, 1);
+        Ok(triple)
+    }
+
+    fn try_next_triplet_batch(
+        &mut self,
+        num_triplets: usize,
+    ) -> Result<(Vec<ScalarShare<C>>, Vec<ScalarShare<C>>, Vec<ScalarShare<C>>), PreprocessingError>
+    {
+        let triples = self.source.try_next_triplet_batch(num_triplets)?;
+        record(&self.counters.0.triples, num_triplets);
+        Ok(triples)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::future;
+
+    use crate::{
+        algebra::AuthenticatedScalarResult, offline_prep::PartyIDBeaverSource,
+        test_helpers::execute_mock_mpc_with_beaver_source, PARTY0, PARTY1,
+    };
+
+    use super::StatsBeaverSource;
+
+    /// Tests that the triple count matches the number of multiplications of
+    /// two shared values in a circuit
+    #[tokio::test]
+    async fn test_triple_count() {
+        let source0 = StatsBeaverSource::new(PartyIDBeaverSource::new(PARTY0));
+        let source1 = StatsBeaverSource::new(PartyIDBeaverSource::new(PARTY1));
+        let (counters0, counters1) = (source0.counters(), source1.counters());
+
+        execute_mock_mpc_with_beaver_source(
+            |fabric| async move {
+                let values = fabric.random_shared_scalars(5);
+
+                // 1 + 4 + 5 shared-shared multiplications, the multiplication
+                // by a public value consumes no triple
+                let product = &values[0] * &values[1];
+                let chain = values.iter().skip(1).fold(product.clone(), |acc, x| acc * x);
+                let batch = AuthenticatedScalarResult::batch_mul(&values, &values);
+                let scaled = &chain * fabric.allocate_scalar(3u8);
+
+                scaled.open().await;
+                future::join_all(AuthenticatedScalarResult::open_batch(&batch)).await;
+            },
+            source0,
+            source1,
+        )
+        .await;
+
+        for counters in [counters0, counters1] {
+            let stats = counters.snapshot();
+            assert_eq!(stats.triples, 10);
+            assert_eq!(stats.shared_values, 5);
+        }
+    }
+}